@@ -0,0 +1,48 @@
+//! Times counting a coverage matrix into per-statement counters, word-wise
+//! against the scalar fallback. Run with `cargo bench --bench accumulate`;
+//! the size defaults to 20,000 tests by 50,000 statements and can be set
+//! with `FL_DSTAR_BENCH_TESTS` and `FL_DSTAR_BENCH_STATEMENTS`.
+
+use fl_dstar::scoring::CoverageMatrix;
+use std::time::Instant;
+
+fn size(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn main() {
+    let tests = size("FL_DSTAR_BENCH_TESTS", 20_000);
+    let statements = size("FL_DSTAR_BENCH_STATEMENTS", 50_000);
+    let mut matrix = CoverageMatrix::new(statements);
+    let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+    let mut words = vec![0u64; statements.div_ceil(64)];
+    for test in 0..tests {
+        for word in &mut words {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            *word = seed;
+        }
+        matrix.push_words(&words, test % 50 == 0);
+    }
+    println!("{} tests x {} statements", tests, statements);
+
+    let start = Instant::now();
+    let wordwise = matrix.counters_wordwise();
+    let wordwise_time = start.elapsed();
+    println!("word-wise: {:>10.3?}", wordwise_time);
+
+    let start = Instant::now();
+    let scalar = matrix.counters_scalar();
+    let scalar_time = start.elapsed();
+    println!("scalar:    {:>10.3?}", scalar_time);
+
+    assert_eq!(wordwise, scalar);
+    println!(
+        "speedup:   {:>9.1}x",
+        scalar_time.as_secs_f64() / wordwise_time.as_secs_f64()
+    );
+}
@@ -0,0 +1,44 @@
+//! Well-formed gcov built from structured input must survive conversion to
+//! lcov and back with the same lines and coverage.
+#![no_main]
+
+use fl_dstar::formats::{self, Format};
+use fl_dstar::Coverage;
+use libfuzzer_sys::arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Line {
+    /// `None` for a line that wasn't executed
+    count: Option<u32>,
+    statement: String,
+}
+
+fuzz_target!(|lines: Vec<Line>| {
+    let mut gcov = String::from("        -:    0:Source:fuzz.c\n");
+    for (i, line) in lines.iter().enumerate() {
+        let count = match line.count {
+            Some(count) => count.max(1).to_string(),
+            None => "#####".to_string(),
+        };
+        // keep each statement on its own line, and clear of suppression markers
+        let mut statement: String = line.statement.chars().filter(|c| !c.is_control()).collect();
+        if statement.contains("fl-dstar") {
+            statement.clear();
+        }
+        gcov.push_str(&format!("{:>9}:{:>5}:{}\n", count, i + 1, statement));
+    }
+    let coverage = formats::read(Format::Gcov, gcov.as_bytes());
+    assert_eq!(coverage[0].lines.len(), lines.len());
+    let mut lcov = Vec::new();
+    formats::write(Format::Lcov, &coverage, &mut lcov).unwrap();
+    let back = formats::read(Format::Lcov, lcov.as_slice());
+    let summary = |coverage: &[formats::SourceCoverage]| -> Vec<(u32, bool)> {
+        coverage[0]
+            .lines
+            .iter()
+            .map(|line| (line.line_number, line.coverage == Coverage::Covered))
+            .collect()
+    };
+    assert_eq!(summary(&coverage), summary(&back));
+});
@@ -0,0 +1,16 @@
+//! Every parser must accept arbitrary bytes without panicking.
+#![no_main]
+
+use fl_dstar::formats::{self, Format};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for format in [Format::Gcov, Format::Lcov, Format::LlvmCov, Format::Showmap] {
+        formats::read(format, data);
+    }
+    // the summaries `gcov -f` prints, for `fl_dstar functions`
+    fl_dstar::functions::parse_function_summaries(data);
+    // as the CLI reads files: decoded first, which may turn them into UTF-16
+    let text = fl_dstar::encoding::decode(data);
+    formats::read(Format::Gcov, text.as_bytes());
+});
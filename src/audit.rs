@@ -0,0 +1,198 @@
+use crate::formats::SourceCoverage;
+use crate::json::Json;
+use crate::Coverage;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// A kind of decision that changes what ends up in a ranking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// A test's coverage file was left out
+    DiscardedFile,
+    /// A line of a coverage file wasn't parsed
+    SkippedLine,
+    /// A statement was taken out of a test's coverage before scoring
+    DroppedStatement,
+    /// A covered statement was counted as not covered
+    Uncovered,
+    /// A statement a test didn't report was added to it as not covered
+    Aligned,
+    /// A ranked statement was hidden from the reports
+    Suppressed,
+}
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::DiscardedFile => "discarded_file",
+            Action::SkippedLine => "skipped_line",
+            Action::DroppedStatement => "dropped_statement",
+            Action::Uncovered => "uncovered",
+            Action::Aligned => "aligned",
+            Action::Suppressed => "suppressed",
+        }
+    }
+}
+
+/// One decision, with enough context to find what it was about.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub action: Action,
+    /// The coverage file the decision was made while reading
+    pub input: Option<String>,
+    pub source: Option<String>,
+    /// Line of the source, or of the coverage file for skipped lines
+    pub line: Option<usize>,
+    pub text: Option<String>,
+    pub reason: String,
+}
+
+impl Event {
+    pub fn new(action: Action, reason: impl Into<String>) -> Event {
+        Event {
+            action,
+            input: None,
+            source: None,
+            line: None,
+            text: None,
+            reason: reason.into(),
+        }
+    }
+
+    /// The event as a JSON object, leaving out the fields it doesn't have.
+    pub fn to_json(&self) -> Json {
+        let mut members = vec![("action".to_string(), Json::from(self.action.name()))];
+        let optional = [
+            ("input", self.input.clone().map(Json::from)),
+            ("source", self.source.clone().map(Json::from)),
+            ("line", self.line.map(|line| Json::Number(line as f64))),
+            ("text", self.text.clone().map(Json::from)),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                members.push((key.to_string(), value));
+            }
+        }
+        members.push(("reason".to_string(), Json::from(self.reason.as_str())));
+        Json::Object(members)
+    }
+}
+
+/// The decisions made during a run, collected as they happen and written
+/// out at the end so a reviewer can see why a statement did or didn't make
+/// it into the ranking.
+#[derive(Debug, Default)]
+pub struct Log {
+    events: RefCell<Vec<Event>>,
+}
+
+impl Log {
+    pub fn record(&self, event: Event) {
+        self.events.borrow_mut().push(event);
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.events.borrow().clone()
+    }
+
+    /// Write the events as JSON lines, one object per decision in the order
+    /// they were made.
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        for event in self.events.borrow().iter() {
+            writeln!(writer, "{}", event.to_json())?;
+        }
+        Ok(())
+    }
+}
+
+/// The executable statements of some coverage, taken before a step that
+/// may drop or uncover them.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    statements: BTreeMap<(Option<String>, u32), (Coverage, String)>,
+}
+
+impl Snapshot {
+    pub fn of(coverage: &[SourceCoverage]) -> Snapshot {
+        let statements = coverage
+            .iter()
+            .flat_map(|record| record.lines.iter().map(move |line| (record, line)))
+            .filter(|(_, line)| line.coverage != Coverage::NoExecutableCode)
+            .map(|(record, line)| {
+                let key = (record.source.clone(), line.line_number);
+                (key, (line.coverage, line.statement.clone()))
+            })
+            .collect();
+        Snapshot { statements }
+    }
+
+    /// What a step, described by `reason`, did to the statements since the
+    /// snapshot: those it removed or made non-code were dropped, and those
+    /// no longer covered were uncovered.
+    pub fn changes(&self, after: &[SourceCoverage], reason: &str) -> Vec<Event> {
+        let now = Snapshot::of(after);
+        self.statements
+            .iter()
+            .filter_map(|((source, line), (coverage, text))| {
+                let action = match now.statements.get(&(source.clone(), *line)) {
+                    None => Action::DroppedStatement,
+                    Some((Coverage::NotCovered, _)) if *coverage == Coverage::Covered => {
+                        Action::Uncovered
+                    }
+                    Some(_) => return None,
+                };
+                Some(Event {
+                    action,
+                    input: None,
+                    source: source.clone(),
+                    line: Some(*line as usize),
+                    text: Some(text.clone()),
+                    reason: reason.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats;
+
+    #[test]
+    fn test_changes() {
+        let gcov = "        -:    0:Source:a.c
+        1:    1:a();
+        1:    2:}
+        2:    3:b();
+";
+        let mut coverage = formats::read(formats::Format::Gcov, gcov.as_bytes());
+        let before = Snapshot::of(&coverage);
+        formats::drop_brace_lines(&mut coverage);
+        formats::apply_min_hits(&mut coverage, 2);
+        let changes: Vec<(Action, Option<usize>)> = before
+            .changes(&coverage, "brace line")
+            .iter()
+            .map(|e| (e.action, e.line))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (Action::Uncovered, Some(1)),
+                (Action::DroppedStatement, Some(2))
+            ]
+        );
+
+        let log = Log::default();
+        let mut event = Event::new(Action::DiscardedFile, "no coverage");
+        event.input = Some("fail/t1.gcov".to_string());
+        log.record(event);
+        let mut text = Vec::new();
+        log.write(&mut text).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "{\"action\":\"discarded_file\",\"input\":\"fail/t1.gcov\",\"reason\":\"no coverage\"}\n"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::StatementInfo;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// A `COMMIT=SNAPSHOT` pair: the coverage snapshot taken at a commit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub commit: String,
+    pub snapshot: PathBuf,
+}
+
+impl std::str::FromStr for Checkpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((commit, snapshot)) if !commit.is_empty() && !snapshot.is_empty() => {
+                Ok(Checkpoint {
+                    commit: commit.to_string(),
+                    snapshot: PathBuf::from(snapshot),
+                })
+            }
+            _ => Err(format!(
+                "invalid checkpoint '{}' (expected COMMIT=SNAPSHOT)",
+                s
+            )),
+        }
+    }
+}
+
+/// The ranking computed at one commit.
+#[derive(Debug, Clone)]
+pub struct Ranking {
+    pub commit: String,
+    pub failing_tests: usize,
+    /// Statements in rank order
+    pub statements: Vec<StatementInfo>,
+}
+
+/// How the top suspicious statements of the newest ranking fared at one
+/// commit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Step {
+    pub commit: String,
+    pub failing_tests: usize,
+    /// Top statements whose text exists at the commit
+    pub present: usize,
+    /// Top statements that are among the commit's own top statements
+    pub suspicious: usize,
+}
+
+/// Where the bug most likely came in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hint {
+    pub steps: Vec<Step>,
+    /// Newest commit that doesn't show the bug, if any does
+    pub good: Option<String>,
+    /// Oldest commit from which every ranking shows the bug
+    pub bad: String,
+}
+
+/// Hashes of the first `top` suspicious statements of a ranking.
+fn top_hashes(statements: &[StatementInfo], top: usize) -> Vec<String> {
+    statements
+        .iter()
+        .filter(|s| s.suspiciousness > 0.0)
+        .take(top)
+        .map(StatementInfo::hash)
+        .collect()
+}
+
+/// Follow the `top` most suspicious statements of the newest ranking back
+/// through older ones, matching statements by their text's hash so they are
+/// found when lines move. A commit shows the bug when its tests fail and
+/// more than half of those statements are among its own top `top`. `rankings`
+/// must be in commit order, oldest first; returns None when the newest has
+/// no failing tests.
+pub fn hint(rankings: &[Ranking], top: usize) -> Option<Hint> {
+    let newest = rankings.last().filter(|r| r.failing_tests > 0)?;
+    let target = top_hashes(&newest.statements, top);
+    let steps: Vec<Step> = rankings
+        .iter()
+        .map(|ranking| {
+            let all: HashSet<String> = ranking.statements.iter().map(StatementInfo::hash).collect();
+            let suspicious: HashSet<String> = match ranking.failing_tests {
+                0 => HashSet::new(),
+                _ => top_hashes(&ranking.statements, top).into_iter().collect(),
+            };
+            Step {
+                commit: ranking.commit.clone(),
+                failing_tests: ranking.failing_tests,
+                present: target.iter().filter(|h| all.contains(*h)).count(),
+                suspicious: target.iter().filter(|h| suspicious.contains(*h)).count(),
+            }
+        })
+        .collect();
+    let shows_bug = |step: &Step| step.failing_tests > 0 && step.suspicious * 2 > target.len();
+    let first_bad = steps
+        .iter()
+        .rposition(|step| !shows_bug(step))
+        .map_or(0, |i| i + 1)
+        // the newest commit is where the bug was seen, whatever its overlap
+        .min(steps.len() - 1);
+    Some(Hint {
+        good: first_bad.checked_sub(1).map(|i| steps[i].commit.clone()),
+        bad: steps[first_bad].commit.clone(),
+        steps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranking(commit: &str, failing_tests: usize, texts: &[&str]) -> Ranking {
+        let statements = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let mut statement = StatementInfo::new(i as u32 + 1, text.to_string(), 1);
+                statement.suspiciousness = (texts.len() - i) as f32;
+                statement
+            })
+            .collect();
+        Ranking {
+            commit: commit.to_string(),
+            failing_tests,
+            statements,
+        }
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let checkpoint: Checkpoint = "abc123=snaps/abc.fls".parse().unwrap();
+        assert_eq!(checkpoint.commit, "abc123");
+        assert!("abc123".parse::<Checkpoint>().is_err());
+    }
+
+    #[test]
+    fn test_hint() {
+        let rankings = [
+            ranking("a", 0, &["x = 1;", "y = 2;", "z = 3;"]),
+            ranking("b", 2, &["x = 1;", "y = 2;", "z = 3;"]),
+            ranking("c", 2, &["y = 2 + 1;", "x = 1;", "z = 3;"]),
+            ranking("d", 3, &["y = 2 + 1;", "x = 1;", "w = 4;"]),
+        ];
+        let hint = hint(&rankings, 2).unwrap();
+        assert_eq!((hint.good.as_deref(), hint.bad.as_str()), (Some("b"), "c"));
+        let present: Vec<usize> = hint.steps.iter().map(|s| s.present).collect();
+        assert_eq!(present, [1, 1, 2, 2]);
+        assert_eq!(hint.steps[1].suspicious, 1);
+
+        // suspicious from the start
+        let hint = super::hint(&rankings[2..], 2).unwrap();
+        assert_eq!((hint.good, hint.bad.as_str()), (None, "c"));
+        assert!(super::hint(&rankings[..1], 2).is_none());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::suppressions::Date;
+use crate::StatementInfo;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// What `git blame` reports for lines that aren't committed yet.
+const UNCOMMITTED: &str = "0000000000000000000000000000000000000000";
+
+/// A commit that last touched some of the blamed lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    pub summary: String,
+    pub date: Option<Date>,
+}
+
+/// The output of `git blame` for one file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Blame {
+    /// Commit that last touched each line
+    pub lines: HashMap<u32, String>,
+    pub commits: HashMap<String, Commit>,
+}
+
+/// Parse the output of `git blame --porcelain`. Each line starts with a
+/// header naming its commit and line number; the first line of every commit
+/// is followed by the commit's details.
+pub fn parse_porcelain(text: &str) -> Blame {
+    let mut blame = Blame::default();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        if line.starts_with('\t') {
+            continue;
+        }
+        let mut words = line.split(' ');
+        let first = words.next().unwrap_or("");
+        if first.len() == 40 && first.bytes().all(|b| b.is_ascii_hexdigit()) {
+            let final_line = words.nth(1).and_then(|n| n.parse().ok());
+            if let Some(final_line) = final_line {
+                blame.lines.insert(final_line, first.to_string());
+            }
+            blame
+                .commits
+                .entry(first.to_string())
+                .or_insert_with(|| Commit {
+                    summary: String::new(),
+                    date: None,
+                });
+            current = Some(first.to_string());
+            continue;
+        }
+        let Some(commit) = current.as_ref().and_then(|c| blame.commits.get_mut(c)) else {
+            continue;
+        };
+        match line.split_once(' ') {
+            Some(("summary", summary)) => commit.summary = summary.to_string(),
+            Some(("author-time", seconds)) => {
+                commit.date = seconds
+                    .parse::<i64>()
+                    .ok()
+                    .map(|seconds| Date::from_days(seconds.div_euclid(86400)))
+            }
+            _ => {}
+        }
+    }
+    blame
+}
+
+/// Run `git blame` (or `$GIT`) on `path` in the repository at `root`.
+pub fn blame(root: &Path, path: &Path) -> std::io::Result<Blame> {
+    let program = std::env::var("GIT").unwrap_or_else(|_| "git".to_string());
+    let output = Command::new(program)
+        .arg("-C")
+        .arg(root)
+        .args(["blame", "--porcelain", "--"])
+        .arg(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(parse_porcelain(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// How suspicious the lines a commit last touched are, taken together.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommitScore {
+    pub commit: String,
+    pub date: Option<String>,
+    pub summary: String,
+    /// Suspicious statements the commit last touched
+    pub statements: usize,
+    /// Rank of the most suspicious of them
+    pub best_rank: usize,
+    /// Their suspiciousness, summed
+    #[serde(serialize_with = "crate::round_serialize")]
+    pub suspiciousness: f32,
+}
+
+/// Rank commits by the summed suspiciousness of the statements they last
+/// touched, ties going to the commit with the best ranked statement.
+/// `statements` must be in rank order, and only suspicious ones count.
+/// `blames` holds the blame of each file named in `sources`; statements of
+/// other files are left out. Lines not committed yet are grouped as one
+/// commit of zeros, as `git blame` reports them.
+pub fn rank_commits(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    blames: &HashMap<String, Blame>,
+) -> Vec<CommitScore> {
+    let mut scores: Vec<CommitScore> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for (rank, statement) in statements.iter().enumerate() {
+        if statement.suspiciousness <= 0.0 {
+            continue;
+        }
+        let Some(blame) = sources
+            .get(&statement.file)
+            .and_then(|file| blames.get(file))
+        else {
+            continue;
+        };
+        let Some(sha) = blame.lines.get(&statement.line_number) else {
+            continue;
+        };
+        let i = *index.entry(sha.as_str()).or_insert_with(|| {
+            let commit = blame.commits.get(sha);
+            scores.push(CommitScore {
+                commit: sha.clone(),
+                date: commit.and_then(|c| c.date).map(|date| date.to_string()),
+                summary: match sha.as_str() {
+                    UNCOMMITTED => "Not committed yet".to_string(),
+                    _ => commit.map(|c| c.summary.clone()).unwrap_or_default(),
+                },
+                statements: 0,
+                best_rank: rank + 1,
+                suspiciousness: 0.0,
+            });
+            scores.len() - 1
+        });
+        scores[i].statements += 1;
+        scores[i].suspiciousness += statement.suspiciousness;
+    }
+    scores.sort_by(|a, b| {
+        b.suspiciousness
+            .total_cmp(&a.suspiciousness)
+            .then(a.best_rank.cmp(&b.best_rank))
+    });
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORCELAIN: &str = "\
+1111111111111111111111111111111111111111 1 1 2
+author Ada
+author-time 1760572800
+summary Add parser
+\tint a;
+1111111111111111111111111111111111111111 2 2
+\tint b;
+2222222222222222222222222222222222222222 5 3 1
+author Bob
+author-time 1760659200
+summary Fix bounds check
+\tif (a > b)
+";
+
+    #[test]
+    fn test_parse_porcelain() {
+        let blame = parse_porcelain(PORCELAIN);
+        assert_eq!(blame.lines.len(), 3);
+        assert_eq!(blame.lines[&3], "2".repeat(40));
+        let commit = &blame.commits[&"2".repeat(40)];
+        assert_eq!(commit.summary, "Fix bounds check");
+        assert_eq!(commit.date.unwrap().to_string(), "2025-10-17");
+    }
+
+    #[test]
+    fn test_rank_commits() {
+        let blames = HashMap::from([("a.c".to_string(), parse_porcelain(PORCELAIN))]);
+        let sources = HashMap::from([(1, "a.c".to_string())]);
+        let statements: Vec<StatementInfo> = [(3, 0.8), (1, 0.5), (2, 0.5), (9, 0.9)]
+            .iter()
+            .map(|&(line, score)| {
+                let mut statement = StatementInfo::new(line, String::new(), 1);
+                statement.file = (line <= 3) as u32;
+                statement.suspiciousness = score;
+                statement
+            })
+            .collect();
+        let commits = rank_commits(&statements, &sources, &blames);
+        let rows: Vec<(&str, usize, usize)> = commits
+            .iter()
+            .map(|c| (c.summary.as_str(), c.statements, c.best_rank))
+            .collect();
+        assert_eq!(rows, [("Add parser", 2, 2), ("Fix bounds check", 1, 1)]);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::StatementInfo;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Lines within this distance of an earlier pick count as the same block.
+const NEARBY_LINES: u32 = 10;
+
+/// Choose `budget` statements worth inspecting from a ranking, preferring
+/// high ranks but spreading picks over the code: a statement is worth the
+/// reciprocal of its rank, multiplied by `decay` for each earlier pick in
+/// the same file and again for each one within a few lines of it. The raw top
+/// of a ranking is often one block of code, which a single look covers.
+///
+/// `statements` must be in rank order. Returns indices into it, in the order
+/// picked.
+pub fn select(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    budget: usize,
+    decay: f64,
+) -> Vec<usize> {
+    let mut picked: Vec<usize> = Vec::new();
+    while picked.len() < budget.min(statements.len()) {
+        let worth = |i: usize| -> f64 {
+            let statement = &statements[i];
+            let file = sources.get(&statement.file);
+            let mut worth = 1.0 / (i + 1) as f64;
+            for &j in &picked {
+                let other = &statements[j];
+                if sources.get(&other.file) == file {
+                    worth *= decay;
+                    if statement.line_number.abs_diff(other.line_number) <= NEARBY_LINES {
+                        worth *= decay;
+                    }
+                }
+            }
+            worth
+        };
+        // max_by keeps the last of equals, so go from the bottom to favour rank
+        let best = (0..statements.len())
+            .rev()
+            .filter(|i| !picked.contains(i))
+            .max_by(|&a, &b| worth(a).total_cmp(&worth(b)));
+        match best {
+            Some(best) => picked.push(best),
+            None => break,
+        }
+    }
+    picked
+}
+
+/// Write the picked statements as CSV, with each one's rank in the full
+/// ranking.
+pub fn write_selection<W: Write>(
+    writer: W,
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    picked: &[usize],
+) -> csv::Result<()> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    wtr.write_record([
+        "pick",
+        "rank",
+        "file",
+        "line_number",
+        "statement",
+        "suspiciousness",
+    ])?;
+    for (pick, &i) in picked.iter().enumerate() {
+        let statement = &statements[i];
+        wtr.write_record([
+            (pick + 1).to_string(),
+            (i + 1).to_string(),
+            sources.get(&statement.file).cloned().unwrap_or_default(),
+            statement.line_number.to_string(),
+            statement.statement().to_string(),
+            format!("{:.2}", statement.suspiciousness),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        // one block of three at the top of a.c, then b.c, then further down a.c
+        let lines = [
+            (10, "a.c"),
+            (11, "a.c"),
+            (12, "a.c"),
+            (5, "b.c"),
+            (80, "a.c"),
+        ];
+        let statements: Vec<StatementInfo> = lines
+            .iter()
+            .map(|&(line, file)| {
+                let mut statement = StatementInfo::new(line, String::new(), 1);
+                statement.file = if file == "a.c" { 1 } else { 2 };
+                statement
+            })
+            .collect();
+        let sources = HashMap::from([(1, "a.c".to_string()), (2, "b.c".to_string())]);
+        assert_eq!(select(&statements, &sources, 3, 0.5), [0, 3, 1]);
+        assert_eq!(select(&statements, &sources, 3, 1.0), [0, 1, 2]);
+        assert_eq!(select(&statements, &sources, 9, 0.5).len(), 5);
+    }
+}
@@ -0,0 +1,304 @@
+use crate::json::Json;
+use crate::StatementInfo;
+use std::collections::HashMap;
+
+/// How strongly a suspicious statement is flagged in CI, from the score
+/// relative to the most suspicious statement.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Severity {
+    Info,
+    Minor,
+    Major,
+    Critical,
+}
+
+impl Severity {
+    pub fn from_score(score: f32, top: f32) -> Severity {
+        let relative = match (score.is_infinite(), top.is_infinite()) {
+            (true, _) => 1.0,
+            (false, true) => 0.5 * score / (1.0 + score),
+            (false, false) if top > 0.0 => score / top,
+            _ => 0.0,
+        };
+        match relative {
+            r if r >= 0.9 => Severity::Critical,
+            r if r >= 0.6 => Severity::Major,
+            r if r >= 0.3 => Severity::Minor,
+            _ => Severity::Info,
+        }
+    }
+
+    fn gitlab(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Minor => "minor",
+            Severity::Major => "major",
+            Severity::Critical => "critical",
+        }
+    }
+
+    fn sonar(&self) -> &'static str {
+        match self {
+            Severity::Info => "INFO",
+            Severity::Minor => "MINOR",
+            Severity::Major => "MAJOR",
+            Severity::Critical => "CRITICAL",
+        }
+    }
+
+    fn warnings_ng(&self) -> &'static str {
+        match self {
+            Severity::Info => "LOW",
+            Severity::Minor => "NORMAL",
+            Severity::Major => "HIGH",
+            Severity::Critical => "ERROR",
+        }
+    }
+}
+
+/// A suspicious statement as a CI issue.
+pub(crate) struct Issue<'a> {
+    pub rank: usize,
+    pub path: &'a str,
+    pub statement: &'a StatementInfo,
+    pub severity: Severity,
+}
+
+impl Issue<'_> {
+    pub fn message(&self) -> String {
+        format!(
+            "Suspicious statement #{} (score {:.2}, covered by {} failing and {} passing tests): {}",
+            self.rank,
+            self.statement.suspiciousness,
+            self.statement.failed_tests,
+            self.statement.passed_tests,
+            self.statement.statement()
+        )
+    }
+
+    /// Stable across runs, so CI can tell new findings from old ones.
+    fn fingerprint(&self) -> String {
+        let key = format!("{}:{}", self.path, self.statement.statement().trim());
+        format!("{:016x}", crate::redact::fnv1a(key.as_bytes()))
+    }
+}
+
+/// The first `limit` statements with a positive score; `statements` must be
+/// sorted by suspiciousness.
+pub(crate) fn issues<'a>(
+    statements: &'a [StatementInfo],
+    sources: &'a HashMap<u32, String>,
+    limit: usize,
+) -> Vec<Issue<'a>> {
+    let top = statements.first().map(|s| s.suspiciousness).unwrap_or(0.0);
+    statements
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.suspiciousness > 0.0)
+        .take(limit)
+        .map(|(i, statement)| Issue {
+            rank: i + 1,
+            path: sources
+                .get(&statement.file)
+                .map(|s| s.as_str())
+                .unwrap_or("unknown"),
+            statement,
+            severity: Severity::from_score(statement.suspiciousness, top),
+        })
+        .collect()
+}
+
+/// A GitLab Code Quality report (`artifacts:reports:codequality`).
+pub fn gitlab_code_quality(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    limit: usize,
+) -> Json {
+    let issues = issues(statements, sources, limit)
+        .iter()
+        .map(|issue| {
+            Json::Object(vec![
+                ("description".to_string(), Json::from(issue.message())),
+                ("check_name".to_string(), Json::from("fl_dstar")),
+                ("fingerprint".to_string(), Json::from(issue.fingerprint())),
+                ("severity".to_string(), Json::from(issue.severity.gitlab())),
+                (
+                    "location".to_string(),
+                    Json::Object(vec![
+                        ("path".to_string(), Json::from(issue.path)),
+                        (
+                            "lines".to_string(),
+                            Json::Object(vec![(
+                                "begin".to_string(),
+                                Json::from(issue.statement.line_number),
+                            )]),
+                        ),
+                    ]),
+                ),
+            ])
+        })
+        .collect();
+    Json::Array(issues)
+}
+
+/// A report in the native JSON format of the Jenkins Warnings Next
+/// Generation plugin.
+pub fn warnings_ng(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    limit: usize,
+) -> Json {
+    let issues = issues(statements, sources, limit)
+        .iter()
+        .map(|issue| {
+            Json::Object(vec![
+                ("fileName".to_string(), Json::from(issue.path)),
+                (
+                    "lineStart".to_string(),
+                    Json::from(issue.statement.line_number),
+                ),
+                (
+                    "lineEnd".to_string(),
+                    Json::from(issue.statement.line_number),
+                ),
+                (
+                    "severity".to_string(),
+                    Json::from(issue.severity.warnings_ng()),
+                ),
+                ("category".to_string(), Json::from("Fault localization")),
+                ("type".to_string(), Json::from("fl_dstar")),
+                ("message".to_string(), Json::from(issue.message())),
+                ("fingerprint".to_string(), Json::from(issue.fingerprint())),
+            ])
+        })
+        .collect();
+    Json::Object(vec![("issues".to_string(), Json::Array(issues))])
+}
+
+/// Minutes a developer is assumed to spend on each statement while working
+/// down the ranking.
+const MINUTES_PER_STATEMENT: usize = 2;
+
+/// A SonarQube generic external issues report
+/// (`sonar.externalIssuesReportPaths`). The effort of an issue is the time
+/// to inspect every statement ranked up to and including it.
+pub fn sonar_external_issues(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    limit: usize,
+) -> Json {
+    let issues = issues(statements, sources, limit)
+        .iter()
+        .map(|issue| {
+            Json::Object(vec![
+                ("engineId".to_string(), Json::from("fl_dstar")),
+                ("ruleId".to_string(), Json::from("suspicious-statement")),
+                ("severity".to_string(), Json::from(issue.severity.sonar())),
+                ("type".to_string(), Json::from("BUG")),
+                (
+                    "effortMinutes".to_string(),
+                    Json::from((issue.rank * MINUTES_PER_STATEMENT) as u32),
+                ),
+                (
+                    "primaryLocation".to_string(),
+                    Json::Object(vec![
+                        ("message".to_string(), Json::from(issue.message())),
+                        ("filePath".to_string(), Json::from(issue.path)),
+                        (
+                            "textRange".to_string(),
+                            Json::Object(vec![(
+                                "startLine".to_string(),
+                                Json::from(issue.statement.line_number),
+                            )]),
+                        ),
+                    ]),
+                ),
+            ])
+        })
+        .collect();
+    Json::Object(vec![("issues".to_string(), Json::Array(issues))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked() -> (Vec<StatementInfo>, HashMap<u32, String>) {
+        let statements = [(4, f32::INFINITY), (2, 0.5), (5, 0.0)]
+            .iter()
+            .map(|&(line, score)| {
+                let mut s = StatementInfo::new(line, format!("s{}();", line), 1);
+                s.file = (line == 4) as u32;
+                s.suspiciousness = score;
+                s
+            })
+            .collect();
+        (statements, HashMap::from([(1, "src/t.c".to_string())]))
+    }
+
+    #[test]
+    fn test_gitlab_code_quality() {
+        let (statements, sources) = ranked();
+        let report = gitlab_code_quality(&statements, &sources, 10);
+        let issues = report.as_array().unwrap();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(
+            issues[0].get("severity").unwrap().as_str(),
+            Some("critical")
+        );
+        let location = issues[0].get("location").unwrap();
+        assert_eq!(location.get("path").unwrap().as_str(), Some("src/t.c"));
+        assert_eq!(
+            location
+                .get("lines")
+                .unwrap()
+                .get("begin")
+                .unwrap()
+                .as_u32(),
+            Some(4)
+        );
+        assert_eq!(issues[1].get("severity").unwrap().as_str(), Some("info"));
+        assert_ne!(issues[0].get("fingerprint"), issues[1].get("fingerprint"));
+        assert_eq!(
+            gitlab_code_quality(&statements, &sources, 1)
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_warnings_ng() {
+        let (statements, sources) = ranked();
+        let report = warnings_ng(&statements, &sources, 10);
+        let issues = report.get("issues").unwrap().as_array().unwrap();
+        assert_eq!(issues[0].get("fileName").unwrap().as_str(), Some("src/t.c"));
+        assert_eq!(issues[0].get("severity").unwrap().as_str(), Some("ERROR"));
+        assert_eq!(issues[1].get("fileName").unwrap().as_str(), Some("unknown"));
+    }
+
+    #[test]
+    fn test_sonar_external_issues() {
+        let (statements, sources) = ranked();
+        let report = sonar_external_issues(&statements, &sources, 10);
+        let issues = report.get("issues").unwrap().as_array().unwrap();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(
+            issues[0].get("severity").unwrap().as_str(),
+            Some("CRITICAL")
+        );
+        assert_eq!(issues[1].get("effortMinutes").unwrap().as_u32(), Some(4));
+        let location = issues[0].get("primaryLocation").unwrap();
+        assert_eq!(location.get("filePath").unwrap().as_str(), Some("src/t.c"));
+        assert_eq!(
+            location
+                .get("textRange")
+                .unwrap()
+                .get("startLine")
+                .unwrap()
+                .as_u32(),
+            Some(4)
+        );
+    }
+}
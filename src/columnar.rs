@@ -0,0 +1,371 @@
+use crate::{Coverage, Spectrum};
+
+/// The type of a [`Column`], named by its Arrow C data interface format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    UInt32,
+    UInt64,
+    Float32,
+    Boolean,
+    Utf8,
+}
+
+impl DataType {
+    /// The format string Arrow's C data interface uses for the type.
+    pub fn format(&self) -> &'static str {
+        match self {
+            DataType::UInt32 => "I",
+            DataType::UInt64 => "L",
+            DataType::Float32 => "f",
+            DataType::Boolean => "b",
+            DataType::Utf8 => "u",
+        }
+    }
+}
+
+/// Bits packed least significant first, as Arrow lays out validity and
+/// boolean buffers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bitmap {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Bitmap {
+    pub fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            self.bytes[self.len / 8] |= 1 << (self.len % 8);
+        }
+        self.len += 1;
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        i < self.len && self.bytes[i / 8] & (1 << (i % 8)) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// One column in Arrow's memory layout: an optional validity bitmap, the
+/// offsets of variable-length values, and the values themselves,
+/// little-endian. The buffers can be handed to an Arrow implementation
+/// as they are, without converting each value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub data_type: DataType,
+    /// Which rows hold a value; `None` when all of them do
+    pub validity: Option<Bitmap>,
+    /// Where each row's value starts and ends in `values`, for Utf8
+    pub offsets: Vec<i32>,
+    pub values: Vec<u8>,
+    len: usize,
+}
+
+impl Column {
+    fn new(data_type: DataType) -> Column {
+        Column {
+            data_type,
+            validity: None,
+            offsets: Vec::new(),
+            values: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn from_u32(values: impl IntoIterator<Item = u32>) -> Column {
+        let mut column = Column::new(DataType::UInt32);
+        for value in values {
+            column.values.extend_from_slice(&value.to_le_bytes());
+            column.len += 1;
+        }
+        column
+    }
+
+    pub fn from_u64(values: impl IntoIterator<Item = u64>) -> Column {
+        let mut column = Column::new(DataType::UInt64);
+        for value in values {
+            column.values.extend_from_slice(&value.to_le_bytes());
+            column.len += 1;
+        }
+        column
+    }
+
+    pub fn from_f32(values: impl IntoIterator<Item = f32>) -> Column {
+        let mut column = Column::new(DataType::Float32);
+        for value in values {
+            column.values.extend_from_slice(&value.to_le_bytes());
+            column.len += 1;
+        }
+        column
+    }
+
+    pub fn from_bool(values: impl IntoIterator<Item = bool>) -> Column {
+        let mut bits = Bitmap::default();
+        for value in values {
+            bits.push(value);
+        }
+        let mut column = Column::new(DataType::Boolean);
+        column.len = bits.len();
+        column.values = bits.bytes;
+        column
+    }
+
+    /// A string column, null where a value is `None`.
+    pub fn from_strs<'a>(values: impl IntoIterator<Item = Option<&'a str>>) -> Column {
+        let mut column = Column::new(DataType::Utf8);
+        let mut validity = Bitmap::default();
+        column.offsets.push(0);
+        for value in values {
+            validity.push(value.is_some());
+            column
+                .values
+                .extend_from_slice(value.unwrap_or("").as_bytes());
+            column.offsets.push(column.values.len() as i32);
+            column.len += 1;
+        }
+        if (0..validity.len()).any(|i| !validity.get(i)) {
+            column.validity = Some(validity);
+        }
+        column
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_null(&self, i: usize) -> bool {
+        self.validity.as_ref().is_some_and(|v| !v.get(i))
+    }
+
+    /// The `N` bytes of row `i`, if the column holds fixed-width values
+    /// of that width.
+    fn fixed<const N: usize>(&self, data_type: DataType, i: usize) -> Option<[u8; N]> {
+        if self.data_type != data_type || i >= self.len || self.is_null(i) {
+            return None;
+        }
+        self.values[i * N..(i + 1) * N].try_into().ok()
+    }
+
+    pub fn u32(&self, i: usize) -> Option<u32> {
+        self.fixed(DataType::UInt32, i).map(u32::from_le_bytes)
+    }
+
+    pub fn u64(&self, i: usize) -> Option<u64> {
+        self.fixed(DataType::UInt64, i).map(u64::from_le_bytes)
+    }
+
+    pub fn f32(&self, i: usize) -> Option<f32> {
+        self.fixed(DataType::Float32, i).map(f32::from_le_bytes)
+    }
+
+    pub fn bool(&self, i: usize) -> Option<bool> {
+        match self.data_type == DataType::Boolean && i < self.len && !self.is_null(i) {
+            true => Some(self.values[i / 8] & (1 << (i % 8)) != 0),
+            false => None,
+        }
+    }
+
+    pub fn str(&self, i: usize) -> Option<&str> {
+        if self.data_type != DataType::Utf8 || i >= self.len || self.is_null(i) {
+            return None;
+        }
+        let (start, end) = (self.offsets[i] as usize, self.offsets[i + 1] as usize);
+        std::str::from_utf8(&self.values[start..end]).ok()
+    }
+}
+
+/// Named columns of equal length, like an Arrow record batch.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    pub fields: Vec<(String, Column)>,
+}
+
+impl Table {
+    fn with(mut self, name: &str, column: Column) -> Table {
+        self.fields.push((name.to_string(), column));
+        self
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, column)| column)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.fields.first().map_or(0, |(_, column)| column.len())
+    }
+}
+
+/// The spectrum's statements, one row each: `source` (null when the
+/// coverage didn't name one), `line_number`, `statement`, the counts `ef`,
+/// `ep`, `nf` and `np`, and `suspiciousness`.
+pub fn statements(spectrum: &Spectrum) -> Table {
+    let statements = &spectrum.statements;
+    let counts =
+        |count: fn(&crate::StatementInfo) -> u32| Column::from_u32(statements.iter().map(count));
+    Table::default()
+        .with(
+            "source",
+            Column::from_strs(
+                statements
+                    .iter()
+                    .map(|s| spectrum.sources.get(&s.file).map(String::as_str)),
+            ),
+        )
+        .with("line_number", counts(|s| s.line_number))
+        .with(
+            "statement",
+            Column::from_strs(statements.iter().map(|s| Some(s.statement()))),
+        )
+        .with("ef", counts(|s| s.failed_tests))
+        .with("ep", counts(|s| s.passed_tests))
+        .with(
+            "nf",
+            counts(|s| s.total_failed.saturating_sub(s.failed_tests)),
+        )
+        .with(
+            "np",
+            counts(|s| s.total_passed.saturating_sub(s.passed_tests)),
+        )
+        .with(
+            "suspiciousness",
+            Column::from_f32(statements.iter().map(|s| s.suspiciousness)),
+        )
+}
+
+/// The spectrum's tests, one row each: `test` (passing tests first, then
+/// failing, each numbered from 0), `name` (from `names`, null past its
+/// end), `failed` and `covered`, the number of statements the test ran.
+pub fn tests(spectrum: &Spectrum, names: &[String]) -> Table {
+    let tests: Vec<(&Vec<crate::LineInfo>, bool)> = spectrum
+        .passing
+        .iter()
+        .map(|test| (test, false))
+        .chain(spectrum.failing.iter().map(|test| (test, true)))
+        .collect();
+    let covered = |test: &Vec<crate::LineInfo>| {
+        test.iter()
+            .filter(|line| line.coverage == Coverage::Covered)
+            .count() as u32
+    };
+    Table::default()
+        .with("test", Column::from_u32(0..tests.len() as u32))
+        .with(
+            "name",
+            Column::from_strs((0..tests.len()).map(|i| names.get(i).map(String::as_str))),
+        )
+        .with("failed", Column::from_bool(tests.iter().map(|test| test.1)))
+        .with(
+            "covered",
+            Column::from_u32(tests.iter().map(|test| covered(test.0))),
+        )
+}
+
+/// The tests' coverage in long form, one row per executable line of each
+/// test: `test` (passing tests first, then failing, each numbered from 0),
+/// `failed`, `line_number`, `covered` and `execution_count`. Empty for
+/// spectra that only kept their counts.
+pub fn coverage(spectrum: &Spectrum) -> Table {
+    let rows: Vec<(u32, bool, &crate::LineInfo)> = spectrum
+        .passing
+        .iter()
+        .map(|test| (test, false))
+        .chain(spectrum.failing.iter().map(|test| (test, true)))
+        .enumerate()
+        .flat_map(|(i, (test, failed))| test.iter().map(move |line| (i as u32, failed, line)))
+        .filter(|(_, _, line)| line.coverage != Coverage::NoExecutableCode)
+        .collect();
+    Table::default()
+        .with("test", Column::from_u32(rows.iter().map(|row| row.0)))
+        .with("failed", Column::from_bool(rows.iter().map(|row| row.1)))
+        .with(
+            "line_number",
+            Column::from_u32(rows.iter().map(|row| row.2.line_number)),
+        )
+        .with(
+            "covered",
+            Column::from_bool(rows.iter().map(|row| row.2.coverage == Coverage::Covered)),
+        )
+        .with(
+            "execution_count",
+            Column::from_u64(rows.iter().map(|row| row.2.execution_count)),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LineInfo;
+
+    #[test]
+    fn test_tables() {
+        let line = |line_number, statement: &str, coverage, execution_count| LineInfo {
+            file: (line_number == 3) as u32,
+            line_number,
+            statement: statement.to_string(),
+            coverage,
+            execution_count,
+        };
+        let passing = vec![vec![
+            line(1, "a();", Coverage::Covered, 3),
+            line(2, "}", Coverage::NoExecutableCode, 0),
+            line(3, "b();", Coverage::NotCovered, 0),
+        ]];
+        let failing = vec![vec![
+            line(1, "a();", Coverage::Covered, 1),
+            line(2, "}", Coverage::NoExecutableCode, 0),
+            line(3, "b();", Coverage::Covered, 2),
+        ]];
+        let mut spectrum = Spectrum::new(passing, failing);
+        spectrum.sources.insert(1, "t.c".to_string());
+
+        let table = statements(&spectrum);
+        assert_eq!(table.rows(), 2);
+        let source = table.column("source").unwrap();
+        assert_eq!((source.str(0), source.str(1)), (None, Some("t.c")));
+        assert_eq!(table.column("statement").unwrap().str(1), Some("b();"));
+        assert_eq!(table.column("ef").unwrap().u32(1), Some(1));
+        assert_eq!(table.column("np").unwrap().u32(1), Some(1));
+        assert_eq!(
+            table.column("suspiciousness").unwrap().data_type.format(),
+            "f"
+        );
+
+        let table = tests(&spectrum, &["p1".to_string()]);
+        assert_eq!(table.rows(), 2);
+        assert_eq!(table.column("name").unwrap().str(1), None);
+        assert_eq!(table.column("covered").unwrap().u32(1), Some(2));
+
+        let table = coverage(&spectrum);
+        assert_eq!(table.rows(), 4);
+        let covered: Vec<Option<bool>> = (0..4)
+            .map(|i| table.column("covered").unwrap().bool(i))
+            .collect();
+        assert_eq!(covered, [Some(true), Some(false), Some(true), Some(true)]);
+        assert_eq!(table.column("failed").unwrap().bool(2), Some(true));
+        assert_eq!(table.column("execution_count").unwrap().u64(0), Some(3));
+        assert_eq!(table.column("test").unwrap().u32(3), Some(1));
+        // the wrong type or a row past the end has no value
+        assert_eq!(table.column("test").unwrap().u64(0), None);
+        assert_eq!(table.column("test").unwrap().u32(4), None);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::scoring::{Counters, Smoothing};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// One way a condition of a decision can evaluate, e.g. the second
+/// condition of `if (a && b)` being false.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Outcome {
+    pub line_number: u32,
+    /// Index of the condition within its decision, from 0
+    pub condition: u32,
+    pub value: bool,
+}
+
+/// The condition coverage of one test in one source file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConditionCoverage {
+    pub source: Option<String>,
+    /// Text of each line holding a decision
+    pub statements: HashMap<u32, String>,
+    /// Whether each outcome was seen
+    pub outcomes: BTreeMap<Outcome, bool>,
+}
+
+/// Read the condition coverage written by `gcov --conditions` (GCC 14 and
+/// later): after each line holding a decision, `condition outcomes covered
+/// N/M` and a `condition K not covered (true false)` line for each condition
+/// with outcomes that weren't seen.
+pub fn parse_gcov(text: &str) -> Result<ConditionCoverage, String> {
+    let mut coverage = ConditionCoverage::default();
+    let mut current: Option<(u32, &str)> = None;
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(counts) = trimmed.strip_prefix("condition outcomes covered ") {
+            let (line_number, statement) =
+                current.ok_or_else(|| format!("line {}: conditions before any line", i + 1))?;
+            let total = counts
+                .split_once('/')
+                .and_then(|(_, total)| total.trim().parse::<u32>().ok())
+                .ok_or_else(|| format!("line {}: expected N/M, found '{}'", i + 1, counts))?;
+            coverage
+                .statements
+                .insert(line_number, statement.trim().to_string());
+            for condition in 0..total / 2 {
+                for value in [true, false] {
+                    let outcome = Outcome {
+                        line_number,
+                        condition,
+                        value,
+                    };
+                    coverage.outcomes.insert(outcome, true);
+                }
+            }
+        } else if let Some(rest) = trimmed.strip_prefix("condition ") {
+            let (line_number, _) =
+                current.ok_or_else(|| format!("line {}: conditions before any line", i + 1))?;
+            let (condition, values) = rest
+                .split_once(" not covered (")
+                .and_then(|(condition, values)| {
+                    Some((condition.trim().parse::<u32>().ok()?, values))
+                })
+                .ok_or_else(|| format!("line {}: expected a condition, found '{}'", i + 1, line))?;
+            for value in values.trim_end_matches(')').split_whitespace() {
+                let outcome = Outcome {
+                    line_number,
+                    condition,
+                    value: value == "true",
+                };
+                coverage.outcomes.insert(outcome, false);
+            }
+        } else {
+            let mut parts = line.splitn(3, ':');
+            let (Some(_), Some(number), Some(statement)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            match number.trim().parse::<u32>() {
+                Ok(0) => {
+                    if let Some(source) = statement.strip_prefix("Source:") {
+                        coverage.source = Some(source.to_string());
+                    }
+                }
+                Ok(line_number) => current = Some((line_number, statement)),
+                Err(_) => {}
+            }
+        }
+    }
+    Ok(coverage)
+}
+
+/// A condition outcome and how suspicious it is.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RankedOutcome {
+    pub file: Option<String>,
+    pub line_number: u32,
+    pub condition: u32,
+    pub outcome: bool,
+    pub statement: String,
+    pub ef: u32,
+    pub ep: u32,
+    #[serde(serialize_with = "crate::round_serialize")]
+    pub suspiciousness: f32,
+}
+
+/// Rank every condition outcome by D*, treating each as a program element
+/// that a test covers when it saw the outcome. Each test is the coverage of
+/// all its files.
+pub fn rank(
+    passing: &[Vec<ConditionCoverage>],
+    failing: &[Vec<ConditionCoverage>],
+    smoothing: Smoothing,
+) -> Vec<RankedOutcome> {
+    let mut counters: BTreeMap<(Option<&str>, Outcome), Counters> = BTreeMap::new();
+    let mut statements: HashMap<(Option<&str>, u32), &str> = HashMap::new();
+    for test in passing.iter().chain(failing) {
+        for file in test {
+            let source = file.source.as_deref();
+            for outcome in file.outcomes.keys() {
+                counters.entry((source, *outcome)).or_default();
+            }
+            for (line_number, statement) in &file.statements {
+                statements.insert((source, *line_number), statement);
+            }
+        }
+    }
+    let tests = passing
+        .iter()
+        .map(|test| (test, false))
+        .chain(failing.iter().map(|test| (test, true)));
+    for (test, failed) in tests {
+        for ((source, outcome), counter) in counters.iter_mut() {
+            let covered = test
+                .iter()
+                .filter(|file| file.source.as_deref() == *source)
+                .any(|file| file.outcomes.get(outcome) == Some(&true));
+            counter.record(covered, failed);
+        }
+    }
+    let mut ranked: Vec<RankedOutcome> = counters
+        .into_iter()
+        .map(|((source, outcome), counter)| RankedOutcome {
+            file: source.map(str::to_string),
+            line_number: outcome.line_number,
+            condition: outcome.condition,
+            outcome: outcome.value,
+            statement: statements
+                .get(&(source, outcome.line_number))
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+            ef: counter.failed,
+            ep: counter.passed,
+            suspiciousness: counter.score(smoothing),
+        })
+        .collect();
+    // stable, so ties stay in file, line and condition order
+    ranked.sort_by(|a, b| b.suspiciousness.total_cmp(&a.suspiciousness));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // b is never false
+    const GCOV: &str = "\
+        -:    0:Source:t.c
+        2:    3:  if (a && b)
+condition outcomes covered 3/4
+condition  1 not covered (false)
+        1:    4:    x();
+";
+
+    #[test]
+    fn test_parse_gcov() {
+        let coverage = parse_gcov(GCOV).unwrap();
+        assert_eq!(coverage.source.as_deref(), Some("t.c"));
+        assert_eq!(coverage.statements[&3], "if (a && b)");
+        let seen: Vec<(u32, bool, bool)> = coverage
+            .outcomes
+            .iter()
+            .map(|(o, seen)| (o.condition, o.value, *seen))
+            .collect();
+        assert_eq!(
+            seen,
+            [
+                (0, false, true),
+                (0, true, true),
+                (1, false, false),
+                (1, true, true)
+            ]
+        );
+        assert!(parse_gcov("condition outcomes covered 1/2\n").is_err());
+    }
+
+    #[test]
+    fn test_rank() {
+        // the failing test is the only one where b is false
+        let passing = [vec![parse_gcov(GCOV).unwrap()]];
+        let mut failing_coverage = parse_gcov(GCOV).unwrap();
+        for seen in failing_coverage.outcomes.values_mut() {
+            *seen = true;
+        }
+        let failing = [vec![failing_coverage]];
+        let ranked = rank(&passing, &failing, Smoothing::None);
+        assert_eq!(ranked.len(), 4);
+        assert_eq!((ranked[0].condition, ranked[0].outcome), (1, false));
+        assert!(ranked[0].suspiciousness.is_infinite());
+        assert_eq!((ranked[0].ef, ranked[0].ep), (1, 0));
+        assert_eq!(ranked[1].suspiciousness, 1.0);
+    }
+}
@@ -0,0 +1,622 @@
+/// Demangle an Itanium C++ ABI symbol (the scheme used by gcc and clang) or a
+/// legacy Rust symbol. Symbols that are not mangled, or use constructs this
+/// demangler does not understand, are returned unchanged.
+pub fn demangle(symbol: &str) -> String {
+    demangle_rust_legacy(symbol)
+        .or_else(|| demangle_itanium(symbol))
+        .unwrap_or_else(|| symbol.to_string())
+}
+
+/// Rust's legacy mangling wraps the path in `_ZN ... E` and ends it with a
+/// `17h<16 hex digits>` hash, which is dropped. Escapes such as `$LT$` are
+/// decoded. The newer v0 scheme (`_R...`) is passed through untouched.
+fn demangle_rust_legacy(symbol: &str) -> Option<String> {
+    // macOS adds an extra leading underscore
+    let rest = symbol
+        .strip_prefix("_ZN")
+        .or_else(|| symbol.strip_prefix("__ZN"))?;
+    let mut rest = rest.strip_suffix('E')?;
+    let mut components = Vec::new();
+    while !rest.is_empty() {
+        let digits = rest.bytes().take_while(|c| c.is_ascii_digit()).count();
+        let len: usize = rest[..digits].parse().ok()?;
+        let component = rest.get(digits..digits + len)?;
+        rest = &rest[digits + len..];
+        components.push(component);
+    }
+    let hash = components.pop()?;
+    let is_hash = hash.len() == 17
+        && hash.starts_with('h')
+        && hash[1..].bytes().all(|c| c.is_ascii_hexdigit());
+    if !is_hash || components.is_empty() {
+        return None;
+    }
+    let decoded: Option<Vec<String>> = components
+        .iter()
+        .map(|c| decode_rust_component(c))
+        .collect();
+    Some(decoded?.join("::"))
+}
+
+fn decode_rust_component(component: &str) -> Option<String> {
+    // identifiers starting with '$' are prefixed with '_' to keep them valid
+    let component = match component.strip_prefix("_$") {
+        Some(rest) => format!("${}", rest),
+        None => component.to_string(),
+    };
+    let mut decoded = String::new();
+    let mut rest = component.as_str();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("..") {
+            decoded.push_str("::");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('$') {
+            let (escape, after) = after.split_once('$')?;
+            let c = match escape {
+                "SP" => '@',
+                "BP" => '*',
+                "RF" => '&',
+                "LT" => '<',
+                "GT" => '>',
+                "LP" => '(',
+                "RP" => ')',
+                "C" => ',',
+                _ => {
+                    let code = u32::from_str_radix(escape.strip_prefix('u')?, 16).ok()?;
+                    char::from_u32(code)?
+                }
+            };
+            decoded.push(c);
+            rest = after;
+        } else {
+            let c = rest.chars().next()?;
+            decoded.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Some(decoded)
+}
+
+/// The name used to group template instantiations of the same function:
+/// template arguments, the parameter list and the return type are dropped so
+/// `int max<int>(int, int)` and `double max<double>(double, double)` both
+/// become `max`. Names without template arguments are returned unchanged.
+pub fn template_group(demangled: &str) -> String {
+    let params_start = demangled.find('(').unwrap_or(demangled.len());
+    if !demangled[..params_start].contains('<') {
+        return demangled.to_string();
+    }
+    let mut name = String::new();
+    let mut depth = 0;
+    for c in demangled[..params_start].chars() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ if depth == 0 => name.push(c),
+            _ => {}
+        }
+    }
+    // template functions carry their return type in front of the name
+    match name.trim().rsplit_once(' ') {
+        Some((_, name)) => name.to_string(),
+        None => name.trim().to_string(),
+    }
+}
+
+fn demangle_itanium(symbol: &str) -> Option<String> {
+    let rest = symbol.strip_prefix("_Z")?;
+    // gcc appends clone suffixes such as `.constprop.0` or `.cold`
+    let (rest, clone) = match rest.find('.') {
+        Some(i) => (&rest[..i], Some(&rest[i..])),
+        None => (rest, None),
+    };
+    let mut parser = Parser {
+        input: rest.as_bytes(),
+        pos: 0,
+        subs: Vec::new(),
+        template_args: Vec::new(),
+    };
+    let name = parser.name()?;
+    let mut demangled = if parser.eof() {
+        // a data symbol such as a static variable
+        name.text
+    } else {
+        let return_type = match name.is_template {
+            true => Some(parser.ty()?),
+            false => None,
+        };
+        let params = parser.bare_function_type()?;
+        let mut text = String::new();
+        if let Some(return_type) = return_type {
+            text.push_str(&return_type);
+            text.push(' ');
+        }
+        text.push_str(&name.text);
+        text.push_str(&params);
+        if name.is_const {
+            text.push_str(" const");
+        }
+        text
+    };
+    if !parser.eof() {
+        return None;
+    }
+    if let Some(clone) = clone {
+        demangled.push_str(&format!(" [clone {}]", clone));
+    }
+    Some(demangled)
+}
+
+struct Name {
+    text: String,
+    is_template: bool,
+    is_const: bool,
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    /// Substitution candidates, referenced by `S_`, `S0_`, ...
+    subs: Vec<String>,
+    /// Arguments of the most recent template, referenced by `T_`, `T0_`, ...
+    template_args: Vec<String>,
+}
+
+impl Parser<'_> {
+    fn eof(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.input.get(self.pos + offset).copied()
+    }
+
+    fn consume(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.input[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// `<seq-id> _`, in base 36, where `_` alone means 0 and `0_` means 1.
+    fn seq_id(&mut self) -> Option<usize> {
+        if self.consume(b'_') {
+            return Some(0);
+        }
+        let mut value = 0;
+        loop {
+            let c = self.peek()?;
+            self.pos += 1;
+            match c {
+                b'0'..=b'9' => value = value * 36 + (c - b'0') as usize,
+                b'A'..=b'Z' => value = value * 36 + (c - b'A') as usize + 10,
+                b'_' => return Some(value + 1),
+                _ => return None,
+            }
+        }
+    }
+
+    fn name(&mut self) -> Option<Name> {
+        match self.peek()? {
+            b'N' => self.nested_name(),
+            b'S' if self.peek_at(1) == Some(b't') => {
+                self.pos += 2;
+                let text = format!("std::{}", self.unqualified_name(None)?);
+                self.maybe_template(text)
+            }
+            b'S' => {
+                // only valid here as an unscoped template name
+                let text = self.substitution()?;
+                if self.peek() != Some(b'I') {
+                    return None;
+                }
+                let args = self.template_args()?;
+                Some(Name {
+                    text: text + &args,
+                    is_template: true,
+                    is_const: false,
+                })
+            }
+            _ => {
+                let text = self.unqualified_name(None)?;
+                self.maybe_template(text)
+            }
+        }
+    }
+
+    fn maybe_template(&mut self, text: String) -> Option<Name> {
+        if self.peek() != Some(b'I') {
+            return Some(Name {
+                text,
+                is_template: false,
+                is_const: false,
+            });
+        }
+        self.subs.push(text.clone());
+        let args = self.template_args()?;
+        Some(Name {
+            text: text + &args,
+            is_template: true,
+            is_const: false,
+        })
+    }
+
+    fn nested_name(&mut self) -> Option<Name> {
+        self.consume(b'N');
+        let mut is_const = false;
+        loop {
+            match self.peek()? {
+                b'K' => is_const = true,
+                b'r' | b'V' | b'R' | b'O' => {}
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let mut text = String::new();
+        let mut last_name = String::new();
+        let mut is_template = false;
+        while !self.consume(b'E') {
+            match self.peek()? {
+                b'S' if self.peek_at(1) == Some(b't') => {
+                    self.pos += 2;
+                    text = "std".to_string();
+                    continue;
+                }
+                b'S' => {
+                    text = self.substitution()?;
+                    is_template = false;
+                    continue;
+                }
+                b'I' => {
+                    text.push_str(&self.template_args()?);
+                    is_template = true;
+                }
+                b'T' => {
+                    text = self.template_param()?;
+                    is_template = false;
+                }
+                _ => {
+                    let name = self.unqualified_name(Some(&last_name))?;
+                    last_name = name.clone();
+                    if !text.is_empty() {
+                        text.push_str("::");
+                    }
+                    text.push_str(&name);
+                    is_template = false;
+                }
+            }
+            if self.peek() != Some(b'E') {
+                self.subs.push(text.clone());
+            }
+        }
+        Some(Name {
+            text,
+            is_template,
+            is_const,
+        })
+    }
+
+    fn unqualified_name(&mut self, enclosing: Option<&str>) -> Option<String> {
+        match self.peek()? {
+            b'0'..=b'9' => self.source_name(),
+            b'C' | b'D' => {
+                let kind = self.peek()?;
+                let variant = self.peek_at(1)?;
+                if !variant.is_ascii_digit() {
+                    return None;
+                }
+                self.pos += 2;
+                let class = enclosing?;
+                Some(match kind {
+                    b'C' => class.to_string(),
+                    _ => format!("~{}", class),
+                })
+            }
+            b'a'..=b'z' => self.operator_name(),
+            _ => None,
+        }
+    }
+
+    fn source_name(&mut self) -> Option<String> {
+        let len = self.number()?;
+        let bytes = self.input.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        let name = std::str::from_utf8(bytes).ok()?;
+        if name.starts_with("_GLOBAL__N") {
+            return Some("(anonymous namespace)".to_string());
+        }
+        Some(name.to_string())
+    }
+
+    fn operator_name(&mut self) -> Option<String> {
+        let code = self.input.get(self.pos..self.pos + 2)?;
+        let op = match code {
+            b"nw" => " new",
+            b"na" => " new[]",
+            b"dl" => " delete",
+            b"da" => " delete[]",
+            b"ps" | b"pl" => "+",
+            b"ng" | b"mi" => "-",
+            b"ad" | b"an" => "&",
+            b"de" | b"ml" => "*",
+            b"co" => "~",
+            b"dv" => "/",
+            b"rm" => "%",
+            b"or" => "|",
+            b"eo" => "^",
+            b"aS" => "=",
+            b"pL" => "+=",
+            b"mI" => "-=",
+            b"mL" => "*=",
+            b"dV" => "/=",
+            b"rM" => "%=",
+            b"aN" => "&=",
+            b"oR" => "|=",
+            b"eO" => "^=",
+            b"ls" => "<<",
+            b"rs" => ">>",
+            b"lS" => "<<=",
+            b"rS" => ">>=",
+            b"eq" => "==",
+            b"ne" => "!=",
+            b"lt" => "<",
+            b"gt" => ">",
+            b"le" => "<=",
+            b"ge" => ">=",
+            b"nt" => "!",
+            b"aa" => "&&",
+            b"oo" => "||",
+            b"pp" => "++",
+            b"mm" => "--",
+            b"cm" => ",",
+            b"pm" => "->*",
+            b"pt" => "->",
+            b"cl" => "()",
+            b"ix" => "[]",
+            _ => return None,
+        };
+        self.pos += 2;
+        Some(format!("operator{}", op))
+    }
+
+    fn substitution(&mut self) -> Option<String> {
+        self.consume(b'S');
+        let abbreviation = match self.peek()? {
+            b'a' => Some("std::allocator"),
+            b'b' => Some("std::basic_string"),
+            b's' => Some("std::string"),
+            b'i' => Some("std::istream"),
+            b'o' => Some("std::ostream"),
+            b'd' => Some("std::iostream"),
+            _ => None,
+        };
+        if let Some(abbreviation) = abbreviation {
+            self.pos += 1;
+            return Some(abbreviation.to_string());
+        }
+        let index = self.seq_id()?;
+        self.subs.get(index).cloned()
+    }
+
+    fn template_param(&mut self) -> Option<String> {
+        self.consume(b'T');
+        let index = self.seq_id()?;
+        self.template_args.get(index).cloned()
+    }
+
+    fn template_args(&mut self) -> Option<String> {
+        self.consume(b'I');
+        let mut args = Vec::new();
+        while !self.consume(b'E') {
+            let arg = match self.peek()? {
+                b'L' => self.literal()?,
+                b'X' | b'J' => return None,
+                _ => self.ty()?,
+            };
+            args.push(arg);
+        }
+        let mut text = format!("<{}", args.join(", "));
+        // keep `> >` apart the way c++filt does
+        if text.ends_with('>') {
+            text.push(' ');
+        }
+        text.push('>');
+        self.template_args = args;
+        Some(text)
+    }
+
+    /// `L <type> <value> E`, e.g. `Li3E` for the integer 3.
+    fn literal(&mut self) -> Option<String> {
+        self.consume(b'L');
+        let ty = self.ty()?;
+        let negative = self.consume(b'n');
+        let value = self.number()?;
+        if !self.consume(b'E') {
+            return None;
+        }
+        Some(match ty.as_str() {
+            "bool" => (value != 0).to_string(),
+            _ if negative => format!("-{}", value),
+            _ => value.to_string(),
+        })
+    }
+
+    fn builtin_type(&mut self) -> Option<&'static str> {
+        let ty = match self.peek()? {
+            b'v' => "void",
+            b'w' => "wchar_t",
+            b'b' => "bool",
+            b'c' => "char",
+            b'a' => "signed char",
+            b'h' => "unsigned char",
+            b's' => "short",
+            b't' => "unsigned short",
+            b'i' => "int",
+            b'j' => "unsigned int",
+            b'l' => "long",
+            b'm' => "unsigned long",
+            b'x' => "long long",
+            b'y' => "unsigned long long",
+            b'n' => "__int128",
+            b'o' => "unsigned __int128",
+            b'f' => "float",
+            b'd' => "double",
+            b'e' => "long double",
+            b'g' => "__float128",
+            b'z' => "...",
+            b'D' => {
+                let ty = match self.peek_at(1)? {
+                    b'n' => "decltype(nullptr)",
+                    b'i' => "char32_t",
+                    b's' => "char16_t",
+                    b'u' => "char8_t",
+                    _ => return None,
+                };
+                self.pos += 1;
+                ty
+            }
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(ty)
+    }
+
+    fn ty(&mut self) -> Option<String> {
+        if let Some(builtin) = self.builtin_type() {
+            return Some(builtin.to_string());
+        }
+        let ty = match self.peek()? {
+            b'P' | b'R' | b'O' | b'K' | b'V' | b'r' => {
+                let qualifier = match self.peek()? {
+                    b'P' => "*",
+                    b'R' => "&",
+                    b'O' => "&&",
+                    b'K' => " const",
+                    b'V' => " volatile",
+                    _ => " restrict",
+                };
+                self.pos += 1;
+                self.ty()? + qualifier
+            }
+            b'S' => {
+                let text = if self.peek_at(1) == Some(b't') {
+                    self.pos += 2;
+                    let text = format!("std::{}", self.unqualified_name(None)?);
+                    self.subs.push(text.clone());
+                    text
+                } else {
+                    let text = self.substitution()?;
+                    if self.peek() != Some(b'I') {
+                        // a plain substitution is not a new candidate
+                        return Some(text);
+                    }
+                    text
+                };
+                match self.peek() {
+                    Some(b'I') => text + &self.template_args()?,
+                    _ => return Some(text),
+                }
+            }
+            b'T' => {
+                let text = self.template_param()?;
+                if self.peek() == Some(b'I') {
+                    self.subs.push(text.clone());
+                    text + &self.template_args()?
+                } else {
+                    text
+                }
+            }
+            b'N' => self.nested_name()?.text,
+            b'0'..=b'9' => {
+                let text = self.source_name()?;
+                if self.peek() == Some(b'I') {
+                    self.subs.push(text.clone());
+                    text + &self.template_args()?
+                } else {
+                    text
+                }
+            }
+            _ => return None,
+        };
+        self.subs.push(ty.clone());
+        Some(ty)
+    }
+
+    fn bare_function_type(&mut self) -> Option<String> {
+        let mut params = Vec::new();
+        while !self.eof() {
+            params.push(self.ty()?);
+        }
+        if params == ["void"] {
+            params.clear();
+        }
+        Some(format!("({})", params.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demangle() {
+        assert_eq!(demangle("_Z3fooi"), "foo(int)");
+        assert_eq!(demangle("_ZN2ns3barEPKc"), "ns::bar(char const*)");
+        assert_eq!(demangle("_ZNK3Foo3getEv"), "Foo::get() const");
+        assert_eq!(demangle("_ZN3FooC2Ev"), "Foo::Foo()");
+        assert_eq!(demangle("_Z3maxIiET_S0_S0_"), "int max<int>(int, int)");
+        assert_eq!(
+            demangle("_ZNSt6vectorIiSaIiEE9push_backERKi"),
+            "std::vector<int, std::allocator<int> >::push_back(int const&)"
+        );
+        assert_eq!(
+            demangle("_Z3fooi.constprop.0"),
+            "foo(int) [clone .constprop.0]"
+        );
+        // plain C names and anything unparseable pass through
+        assert_eq!(demangle("main"), "main");
+        assert_eq!(demangle("_Zbogus"), "_Zbogus");
+    }
+
+    #[test]
+    fn test_demangle_rust_legacy() {
+        assert_eq!(
+            demangle("_ZN4core3ptr13drop_in_place17h0123456789abcdefE"),
+            "core::ptr::drop_in_place"
+        );
+        assert_eq!(
+            demangle("_ZN60_$LT$alloc..vec..Vec$LT$T$GT$$u20$as$u20$core..ops..Drop$GT$4drop17h2f4bba1e3d1c95e8E"),
+            "<alloc::vec::Vec<T> as core::ops::Drop>::drop"
+        );
+        // a C++ nested name without a Rust hash still goes through the Itanium path
+        assert_eq!(demangle("_ZN2ns3barEPKc"), "ns::bar(char const*)");
+    }
+
+    #[test]
+    fn test_template_group() {
+        assert_eq!(template_group("int max<int>(int, int)"), "max");
+        assert_eq!(template_group("double max<double>(double, double)"), "max");
+        assert_eq!(
+            template_group("std::vector<int, std::allocator<int> >::push_back(int const&)"),
+            "std::vector::push_back"
+        );
+        assert_eq!(template_group("foo(int)"), "foo(int)");
+    }
+}
@@ -0,0 +1,136 @@
+use crate::json::{self, Json};
+use crate::StatementInfo;
+use std::collections::HashMap;
+
+/// A data or control dependency: the statement on line `from` depends on the
+/// one on line `to`, so a fault at `to` may only show up at `from`. Edges
+/// name no file, so they apply to those lines of every file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    pub from: u32,
+    pub to: u32,
+    pub kind: Option<String>,
+}
+
+/// Read a dependency edge list:
+///
+/// ```text
+/// {"edges": [{"from": 12, "to": 7, "kind": "data"}, ...]}
+/// ```
+///
+/// A bare array of edges is accepted as well. `kind` is optional.
+pub fn parse_dependencies(text: &str) -> Result<Vec<Dependency>, String> {
+    let value = json::parse(text).map_err(|e| e.to_string())?;
+    let edges = match &value {
+        Json::Array(edges) => edges.as_slice(),
+        _ => value
+            .get("edges")
+            .and_then(Json::as_array)
+            .ok_or("dependency file has no \"edges\" array")?,
+    };
+    edges
+        .iter()
+        .enumerate()
+        .map(|(i, edge)| {
+            let line = |key| {
+                edge.get(key)
+                    .and_then(Json::as_u32)
+                    .ok_or_else(|| format!("edge {} needs a numeric \"{}\"", i, key))
+            };
+            Ok(Dependency {
+                from: line("from")?,
+                to: line("to")?,
+                kind: edge.get("kind").and_then(Json::as_str).map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+pub fn parse_dependency_file(path: &std::path::Path) -> Result<Vec<Dependency>, String> {
+    let text = crate::encoding::read_to_string(path).map_err(|e| e.to_string())?;
+    parse_dependencies(&text)
+}
+
+/// Give each statement `factor` times the score of every statement that
+/// depends on it. Only one step is taken, using the scores from before
+/// propagation, so cycles in the dependency graph do not compound. Boosts
+/// that are zero or not finite are left out: an infinite score would tie
+/// everything it reaches with the statement only failing tests run, and a
+/// zero `factor` times it is NaN.
+pub fn propagate(statements: &mut [StatementInfo], dependencies: &[Dependency], factor: f32) {
+    let scores: HashMap<u32, f32> = statements
+        .iter()
+        .map(|s| (s.line_number, s.suspiciousness))
+        .collect();
+    let mut boost: HashMap<u32, f32> = HashMap::new();
+    for dependency in dependencies {
+        let Some(score) = scores.get(&dependency.from) else {
+            continue;
+        };
+        let extra = factor * score;
+        if extra.is_finite() && extra != 0.0 {
+            *boost.entry(dependency.to).or_insert(0.0) += extra;
+        }
+    }
+    for statement in statements {
+        if let Some(extra) = boost.get(&statement.line_number) {
+            statement.suspiciousness += extra;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dependencies() {
+        let deps =
+            parse_dependencies(r#"{"edges": [{"from": 12, "to": 7, "kind": "data"}]}"#).unwrap();
+        assert_eq!(
+            deps,
+            vec![Dependency {
+                from: 12,
+                to: 7,
+                kind: Some("data".to_string())
+            }]
+        );
+        assert_eq!(
+            parse_dependencies(r#"[{"from": 3, "to": 1}]"#).unwrap()[0].to,
+            1
+        );
+        assert!(parse_dependencies(r#"[{"from": 3}]"#).is_err());
+    }
+
+    #[test]
+    fn test_propagate() {
+        let mut statements: Vec<StatementInfo> = (1..=3)
+            .map(|n| StatementInfo::new(n, String::new(), 1))
+            .collect();
+        statements[2].suspiciousness = 2.0;
+        statements[1].suspiciousness = 1.0;
+        // 3 uses a value defined on 1, and 1 depends on 2
+        let deps = vec![
+            Dependency {
+                from: 3,
+                to: 1,
+                kind: None,
+            },
+            Dependency {
+                from: 1,
+                to: 2,
+                kind: None,
+            },
+        ];
+        propagate(&mut statements, &deps, 0.5);
+        let scores: Vec<f32> = statements.iter().map(|s| s.suspiciousness).collect();
+        assert_eq!(scores, vec![1.0, 1.0, 2.0]);
+
+        // An infinite score is not passed on, and can't turn into NaN
+        statements[2].suspiciousness = f32::INFINITY;
+        propagate(&mut statements, &deps, 0.5);
+        assert!(statements.iter().all(|s| !s.suspiciousness.is_nan()));
+        assert_eq!(statements[0].suspiciousness, 1.0);
+        assert_eq!(statements[1].suspiciousness, 1.5);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{covered_set, Coverage, LineInfo};
+
+/// Lines executed by exactly one of two tests.
+#[derive(Debug)]
+pub struct CoverageDiff<'a> {
+    pub only_first: Vec<&'a LineInfo>,
+    pub only_second: Vec<&'a LineInfo>,
+}
+
+/// Compare the coverage of two tests line by line. Lines are matched on their
+/// file and line number, so the two inputs do not need to list the same
+/// statements.
+pub fn coverage_diff<'a>(first: &'a [LineInfo], second: &'a [LineInfo]) -> CoverageDiff<'a> {
+    let first_covered = covered_set(first);
+    let second_covered = covered_set(second);
+    let only_first = first
+        .iter()
+        .filter(|line| line.coverage == Coverage::Covered)
+        .filter(|line| !second_covered.contains(&line.key()))
+        .collect();
+    let only_second = second
+        .iter()
+        .filter(|line| line.coverage == Coverage::Covered)
+        .filter(|line| !first_covered.contains(&line.key()))
+        .collect();
+    CoverageDiff {
+        only_first,
+        only_second,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(line_number: u32, coverage: Coverage) -> LineInfo {
+        LineInfo {
+            file: 0,
+            line_number,
+            statement: format!("s{}", line_number),
+            execution_count: (coverage == Coverage::Covered) as u64,
+            coverage,
+        }
+    }
+
+    #[test]
+    fn test_coverage_diff() {
+        let first = vec![
+            line(1, Coverage::Covered),
+            line(2, Coverage::Covered),
+            line(3, Coverage::NotCovered),
+        ];
+        let second = vec![
+            line(1, Coverage::Covered),
+            line(2, Coverage::NotCovered),
+            line(4, Coverage::Covered),
+        ];
+        let diff = coverage_diff(&first, &second);
+        let only_first: Vec<u32> = diff.only_first.iter().map(|l| l.line_number).collect();
+        let only_second: Vec<u32> = diff.only_second.iter().map(|l| l.line_number).collect();
+        assert_eq!(only_first, vec![2]);
+        assert_eq!(only_second, vec![4]);
+    }
+}
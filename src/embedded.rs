@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+/// Marker that starts a counter line in a dump captured from a target.
+const MARKER: &str = "@cov ";
+
+/// Where a coverage counter sits in the firmware image.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Probe {
+    Address(u64),
+    /// An offset into a function, for firmware that only knows its own
+    /// symbols; resolved with a linker map
+    Symbol {
+        name: String,
+        offset: u64,
+    },
+}
+
+fn parse_hex(word: &str) -> Option<u64> {
+    let digits = word
+        .strip_prefix("0x")
+        .or_else(|| word.strip_prefix("0X"))?;
+    u64::from_str_radix(&digits.replace('_', ""), 16).ok()
+}
+
+impl std::str::FromStr for Probe {
+    type Err = String;
+
+    /// `0x8000134`, `main+0x1a` or `main`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(address) = parse_hex(s) {
+            return Ok(Probe::Address(address));
+        }
+        let (name, offset) = match s.split_once('+') {
+            Some((name, offset)) => (name, parse_hex(offset)),
+            None => (s, Some(0)),
+        };
+        match offset {
+            Some(offset) if !name.is_empty() => Ok(Probe::Symbol {
+                name: name.to_string(),
+                offset,
+            }),
+            _ => Err(format!("invalid probe '{}'", s)),
+        }
+    }
+}
+
+/// Read the counters a target printed over its serial port: lines
+/// `@cov PROBE COUNT`, wherever they start, so boot logs and timestamps
+/// around them are ignored. Returns `None` if the text has no counter lines.
+pub fn parse_counter_dump(text: &str) -> Option<Result<Vec<(Probe, u64)>, String>> {
+    let lines: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| Some((i, &line[line.find(MARKER)? + MARKER.len()..])))
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    let counters = lines
+        .into_iter()
+        .map(|(i, counter)| {
+            let mut words = counter.split_whitespace();
+            let probe = words
+                .next()
+                .ok_or_else(|| format!("line {}: missing probe", i + 1))?
+                .parse::<Probe>();
+            let count = words.next().and_then(|count| count.parse::<u64>().ok());
+            match (probe, count) {
+                (Ok(probe), Some(count)) => Ok((probe, count)),
+                (Err(e), _) => Err(format!("line {}: {}", i + 1, e)),
+                (_, None) => Err(format!("line {}: expected a count", i + 1)),
+            }
+        })
+        .collect();
+    Some(counters)
+}
+
+/// Symbol addresses from a GNU ld map file (`-Wl,-Map=firmware.map`), whose
+/// symbol lines are an address and a name alone.
+pub fn parse_linker_map(text: &str) -> HashMap<String, u64> {
+    text.lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let (address, name) = (words.next()?, words.next()?);
+            let address = parse_hex(address)?;
+            let is_symbol = words.next().is_none()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '$' || c == '.');
+            is_symbol.then(|| (name.to_string(), address))
+        })
+        .collect()
+}
+
+/// The address of each counter, looking symbols up in `symbols`.
+pub fn resolve(
+    counters: &[(Probe, u64)],
+    symbols: Option<&HashMap<String, u64>>,
+) -> Result<Vec<(u64, u64)>, String> {
+    counters
+        .iter()
+        .map(|(probe, count)| match probe {
+            Probe::Address(address) => Ok((*address, *count)),
+            Probe::Symbol { name, offset } => {
+                let symbols = symbols
+                    .ok_or_else(|| format!("counter at {} needs a linker map to resolve", name))?;
+                match symbols.get(name) {
+                    Some(address) => Ok((address + offset, *count)),
+                    None => Err(format!("symbol {} is not in the linker map", name)),
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_counter_dump() {
+        let dump = "\
+[00:00.001] boot ok
+[00:00.120] @cov 0x0800_0134 3
+@cov main+0x1a 0
+@cov uart_init 1
+";
+        let counters = parse_counter_dump(dump).unwrap().unwrap();
+        assert_eq!(
+            counters,
+            [
+                (Probe::Address(0x8000134), 3),
+                (
+                    Probe::Symbol {
+                        name: "main".to_string(),
+                        offset: 0x1a
+                    },
+                    0
+                ),
+                (
+                    Probe::Symbol {
+                        name: "uart_init".to_string(),
+                        offset: 0
+                    },
+                    1
+                ),
+            ]
+        );
+        assert!(parse_counter_dump("boot ok\n").is_none());
+        assert_eq!(
+            parse_counter_dump("ok\n@cov 0x10 many\n").unwrap(),
+            Err("line 2: expected a count".to_string())
+        );
+    }
+
+    #[test]
+    fn test_linker_map() {
+        let map = "\
+ .text.main     0x08000120       0x40 build/main.o
+                0x08000120                main
+                0x20000000                _estack = 0x20000000
+ .text.uart_init
+                0x08000160       0x10 build/uart.o
+                0x08000160                uart_init
+";
+        let symbols = parse_linker_map(map);
+        assert_eq!(symbols.len(), 2);
+        let counters = parse_counter_dump("@cov main+0x14 2\n@cov 0x10 1\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resolve(&counters, Some(&symbols)),
+            Ok(vec![(0x8000134, 2), (0x10, 1)])
+        );
+        assert!(resolve(&counters, None).is_err());
+    }
+}
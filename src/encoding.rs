@@ -0,0 +1,128 @@
+use std::path::Path;
+
+/// Decode a text artifact whatever toolchain wrote it. MSVC-era tools and
+/// PowerShell redirection produce UTF-16 (with or without a byte order
+/// mark), and sources quoted in gcov output are often in a legacy 8-bit code
+/// page; none of these should stop a report.
+///
+/// UTF-8 (with an optional BOM) is used when valid. UTF-16 is recognized by
+/// its BOM or, without one, by every other byte being zero. Anything else is
+/// read as Latin-1, which never fails and keeps ASCII intact.
+pub fn decode(bytes: &[u8]) -> String {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return decode_utf8_or_latin1(rest);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return decode_utf16(rest, u16::from_le_bytes);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return decode_utf16(rest, u16::from_be_bytes);
+    }
+    match utf16_without_bom(bytes) {
+        Some(true) => decode_utf16(bytes, u16::from_le_bytes),
+        Some(false) => decode_utf16(bytes, u16::from_be_bytes),
+        None => decode_utf8_or_latin1(bytes),
+    }
+}
+
+/// Read a file with [`decode`].
+pub fn read_to_string(path: &Path) -> std::io::Result<String> {
+    std::fs::read(path).map(|bytes| decode(&bytes))
+}
+
+/// Standard base64 with padding, for embedding assets in reports.
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+fn decode_utf8_or_latin1(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Whether BOM-less text looks like UTF-16: `Some(true)` for little endian,
+/// `Some(false)` for big endian. Coverage files are mostly ASCII, so one
+/// byte of every pair is zero.
+fn utf16_without_bom(bytes: &[u8]) -> Option<bool> {
+    if bytes.len() < 4 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = bytes.len() / 2;
+    let zeros_at = |offset: usize| {
+        bytes
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|&&b| b == 0)
+            .count()
+    };
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    if odd * 10 >= pairs * 9 && even == 0 {
+        Some(true)
+    } else if even * 10 >= pairs * 9 && odd == 0 {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+        let mut bytes = match bom {
+            true => vec![0xFF, 0xFE],
+            false => vec![],
+        };
+        bytes.extend(text.encode_utf16().flat_map(|u| u.to_le_bytes()));
+        bytes
+    }
+
+    #[test]
+    fn test_decode() {
+        let gcov = "        -:    0:Source:C:\\proj\\a.c\r\n        1:    2:int a;\r\n";
+        assert_eq!(decode(gcov.as_bytes()), gcov);
+        assert_eq!(decode(&utf16le(gcov, true)), gcov);
+        assert_eq!(decode(&utf16le(gcov, false)), gcov);
+        let mut big_endian = vec![0xFE, 0xFF];
+        big_endian.extend(gcov.encode_utf16().flat_map(|u| u.to_be_bytes()));
+        assert_eq!(decode(&big_endian), gcov);
+        assert_eq!(decode(b"\xEF\xBB\xBFSF:a.c"), "SF:a.c");
+        // a Latin-1 comment in quoted source
+        assert_eq!(decode(b"1:    3:// caf\xE9"), "1:    3:// café");
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -0,0 +1,83 @@
+use crate::StatementInfo;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Statements that failing tests execute and no passing test does. When
+/// there are any they are often the fault itself, whatever the formula.
+pub fn failing_only(statements: &[StatementInfo]) -> Vec<&StatementInfo> {
+    statements
+        .iter()
+        .filter(|s| s.failed_tests > 0 && s.passed_tests == 0)
+        .collect()
+}
+
+/// Statements that passing tests execute and no failing test does.
+pub fn passing_only(statements: &[StatementInfo]) -> Vec<&StatementInfo> {
+    statements
+        .iter()
+        .filter(|s| s.passed_tests > 0 && s.failed_tests == 0)
+        .collect()
+}
+
+/// Write both lists as plain text, each statement with the number of tests
+/// that execute it.
+pub fn write_section<W: Write>(
+    mut writer: W,
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+) -> std::io::Result<()> {
+    let lists = [
+        ("failing", failing_only(statements)),
+        ("passing", passing_only(statements)),
+    ];
+    for (kind, list) in lists {
+        writeln!(writer, "Executed only by {} tests ({}):", kind, list.len())?;
+        for s in list {
+            let tests = match kind {
+                "failing" => s.failed_tests,
+                _ => s.passed_tests,
+            };
+            let location = match sources.get(&s.file) {
+                Some(file) => format!("{}:{}", file, s.line_number),
+                None => s.line_number.to_string(),
+            };
+            writeln!(
+                writer,
+                "  {}  {}  ({} {})",
+                location,
+                s.statement().trim(),
+                tests,
+                kind
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_section() {
+        let mut statements: Vec<StatementInfo> = ["a();", "b();", "c();"]
+            .iter()
+            .enumerate()
+            .map(|(i, text)| StatementInfo::new(i as u32 + 1, text.to_string(), 2))
+            .collect();
+        statements[0].add_failing_coverage();
+        statements[0].add_passing_coverage();
+        statements[1].add_failing_coverage();
+        statements[2].add_passing_coverage();
+        statements[2].add_passing_coverage();
+        statements[1].file = 1;
+        let sources = HashMap::from([(1, "a.c".to_string())]);
+        let mut text = Vec::new();
+        write_section(&mut text, &statements, &sources).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "Executed only by failing tests (1):\n  a.c:2  b();  (1 failing)\n\
+             Executed only by passing tests (1):\n  3  c();  (2 passing)\n"
+        );
+    }
+}
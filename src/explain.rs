@@ -0,0 +1,184 @@
+use crate::locale::NumberFormat;
+use crate::smoothing::Smoothing;
+use crate::StatementInfo;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// A smoothing as it is written in traces and reports.
+pub fn describe(smoothing: Smoothing) -> String {
+    match smoothing {
+        Smoothing::None => "none".to_string(),
+        Smoothing::AddOne => "add-one".to_string(),
+        Smoothing::Beta { alpha, beta } => format!("Beta({}, {}) prior", alpha, beta),
+    }
+}
+
+/// A step-by-step trace of how every statement was scored, in rank order:
+/// its counters, the counts after smoothing, the terms of the formula and
+/// the score. Meant to be read by people checking the computation by hand.
+///
+/// `formula` is the smoothing the scores were computed with, or `None` when
+/// they came from another technique, in which case only the counters and
+/// scores are listed.
+pub fn trace(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    formula: Option<Smoothing>,
+    numbers: &NumberFormat,
+) -> String {
+    let mut out = String::new();
+    let (total_failed, total_passed) = statements
+        .first()
+        .map(|s| (s.total_failed, s.total_passed))
+        .unwrap_or((0, 0));
+    writeln!(
+        out,
+        "fl_dstar {} computation trace",
+        env!("CARGO_PKG_VERSION")
+    )
+    .unwrap();
+    match formula {
+        Some(smoothing) => writeln!(
+            out,
+            "technique: D* formula, ef² / (ep + nf); smoothing: {}",
+            describe(smoothing)
+        ),
+        None => writeln!(
+            out,
+            "technique: not a formula; scores are listed as computed"
+        ),
+    }
+    .unwrap();
+    writeln!(
+        out,
+        "failing tests F = {}, passing tests P = {}",
+        total_failed, total_passed
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "ef/ep: failing/passing tests that ran the statement; nf/np: those that didn't"
+    )
+    .unwrap();
+    for (i, statement) in statements.iter().enumerate() {
+        let location = match sources.get(&statement.file) {
+            Some(file) => format!("{}:{}", file, statement.line_number),
+            None => format!("line {}", statement.line_number),
+        };
+        writeln!(
+            out,
+            "\n#{} {} `{}`",
+            i + 1,
+            location,
+            statement.statement().trim()
+        )
+        .unwrap();
+        out.push_str(&steps(statement, formula, numbers));
+    }
+    out
+}
+
+/// The steps of one statement's score in [`trace`], each on a line of its
+/// own and indented by two spaces: its counters, the smoothed counts and the
+/// terms of the formula when there is one, and the score.
+pub fn steps(
+    statement: &StatementInfo,
+    formula: Option<Smoothing>,
+    numbers: &NumberFormat,
+) -> String {
+    let count = |x: f32| match x.fract() == 0.0 {
+        true => numbers.format(x as f64, 0),
+        false => numbers.format(x as f64, 4),
+    };
+    let mut out = String::new();
+    writeln!(
+        out,
+        "  counters: ef = {}, ep = {}, nf = F - ef = {}, np = P - ep = {}",
+        statement.failed_tests,
+        statement.passed_tests,
+        statement
+            .total_failed
+            .saturating_sub(statement.failed_tests),
+        statement
+            .total_passed
+            .saturating_sub(statement.passed_tests)
+    )
+    .unwrap();
+    if let Some(smoothing) = formula {
+        let counts = smoothing.counts(
+            statement.failed_tests,
+            statement.passed_tests,
+            statement.total_failed,
+            statement.total_passed,
+        );
+        if smoothing != Smoothing::None {
+            writeln!(
+                out,
+                "  smoothed: ef = {}, ep = {}, nf = {}",
+                count(counts.failed),
+                count(counts.passed),
+                count(counts.not_failed)
+            )
+            .unwrap();
+        }
+        let (numerator, denominator) = counts.dstar_terms();
+        writeln!(
+            out,
+            "  numerator: ef² = {}² = {}",
+            count(counts.failed),
+            count(numerator)
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  denominator: ep + nf = {} + {} = {}",
+            count(counts.passed),
+            count(counts.not_failed),
+            count(denominator)
+        )
+        .unwrap();
+        if denominator == 0.0 {
+            writeln!(
+                out,
+                "  no passing test ran it and no failing test missed it"
+            )
+            .unwrap();
+        }
+    }
+    writeln!(
+        out,
+        "  score: {}",
+        numbers.format(statement.suspiciousness as f64, 4)
+    )
+    .unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace() {
+        let mut statement = StatementInfo::new(4, "  b();".to_string(), 2);
+        statement.total_passed = 3;
+        statement.add_failing_coverage();
+        statement.add_passing_coverage();
+        statement.calculate_smoothed_suspiciousness(Smoothing::AddOne);
+        statement.file = 1;
+        let sources = HashMap::from([(1, "t.c".to_string())]);
+        let trace = trace(
+            &[statement],
+            &sources,
+            Some(Smoothing::AddOne),
+            &NumberFormat::default(),
+        );
+        assert!(trace.contains("failing tests F = 2, passing tests P = 3"));
+        assert!(trace.contains("\n#1 t.c:4 `b();`\n"));
+        assert!(trace.contains("  counters: ef = 1, ep = 1, nf = F - ef = 1, np = P - ep = 2\n"));
+        assert!(trace.contains("  smoothed: ef = 2, ep = 2, nf = 2\n"));
+        assert!(trace.contains("  numerator: ef² = 2² = 4\n"));
+        assert!(trace.contains("  denominator: ep + nf = 2 + 2 = 4\n"));
+        assert!(trace.ends_with("  score: 1.0000\n"));
+    }
+}
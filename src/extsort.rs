@@ -0,0 +1,257 @@
+use crate::StatementInfo;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Rank order with every field as a tie-break, so duplicates end up next
+/// to each other.
+fn rank_order(a: &StatementInfo, b: &StatementInfo) -> Ordering {
+    b.suspiciousness
+        .total_cmp(&a.suspiciousness)
+        .then(a.key().cmp(&b.key()))
+        .then_with(|| a.statement().cmp(b.statement()))
+        .then(a.failed_tests.cmp(&b.failed_tests))
+        .then(a.passed_tests.cmp(&b.passed_tests))
+}
+
+/// Sorts a ranking too large to hold twice in memory: statements are
+/// gathered into runs of at most `run_len`, each sorted and written to a
+/// file in a scratch directory, and the runs are merged as they are read
+/// back. Identical statements are kept once. The scratch directory is
+/// removed when the sort is dropped.
+pub struct ExternalSort {
+    dir: PathBuf,
+    run_len: usize,
+    buffer: Vec<StatementInfo>,
+    runs: Vec<PathBuf>,
+}
+
+impl ExternalSort {
+    /// Sort in a new directory under `parent`.
+    pub fn new(parent: &Path, run_len: usize) -> io::Result<ExternalSort> {
+        let dir = parent.join(format!("fl_dstar-sort-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        Ok(ExternalSort {
+            dir,
+            run_len: run_len.max(1),
+            buffer: Vec::new(),
+            runs: Vec::new(),
+        })
+    }
+
+    pub fn push(&mut self, statement: StatementInfo) -> io::Result<()> {
+        self.buffer.push(statement);
+        if self.buffer.len() >= self.run_len {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Runs written so far.
+    pub fn runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let mut run = std::mem::take(&mut self.buffer);
+        run.sort_by(rank_order);
+        run.dedup_by(|a, b| rank_order(a, b) == Ordering::Equal);
+        let path = self.dir.join(format!("run-{}", self.runs.len()));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for statement in &run {
+            write_statement(&mut writer, statement)?;
+        }
+        writer.flush()?;
+        self.runs.push(path);
+        Ok(())
+    }
+
+    /// The statements pushed so far, in rank order. Can be called more
+    /// than once, for callers that need a pass over the scores first.
+    pub fn merge(&mut self) -> io::Result<Merge> {
+        self.spill()?;
+        let mut readers = Vec::new();
+        let mut heap = BinaryHeap::new();
+        for (run, path) in self.runs.iter().enumerate() {
+            let mut reader = BufReader::new(File::open(path)?);
+            if let Some(statement) = read_statement(&mut reader)? {
+                heap.push(Head { statement, run });
+            }
+            readers.push(reader);
+        }
+        Ok(Merge {
+            readers,
+            heap,
+            last: None,
+        })
+    }
+}
+
+impl Drop for ExternalSort {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// The next statement of one run, ordered so the heap's top is the most
+/// suspicious.
+struct Head {
+    statement: StatementInfo,
+    run: usize,
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        rank_order(&other.statement, &self.statement).then(other.run.cmp(&self.run))
+    }
+}
+
+/// The merged runs of an [`ExternalSort`].
+pub struct Merge {
+    readers: Vec<BufReader<File>>,
+    heap: BinaryHeap<Head>,
+    last: Option<StatementInfo>,
+}
+
+impl Merge {
+    fn pop(&mut self) -> io::Result<Option<StatementInfo>> {
+        let Some(Head { statement, run }) = self.heap.pop() else {
+            return Ok(None);
+        };
+        if let Some(next) = read_statement(&mut self.readers[run])? {
+            self.heap.push(Head {
+                statement: next,
+                run,
+            });
+        }
+        Ok(Some(statement))
+    }
+}
+
+impl Iterator for Merge {
+    type Item = io::Result<StatementInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let statement = match self.pop() {
+                Ok(statement) => statement?,
+                Err(e) => return Some(Err(e)),
+            };
+            let duplicate = self
+                .last
+                .as_ref()
+                .is_some_and(|last| rank_order(last, &statement) == Ordering::Equal);
+            if !duplicate {
+                self.last = Some(statement.clone());
+                return Some(Ok(statement));
+            }
+        }
+    }
+}
+
+fn write_statement<W: Write>(writer: &mut W, statement: &StatementInfo) -> io::Result<()> {
+    let text = statement.statement().as_bytes();
+    let fields = [
+        statement.file,
+        statement.line_number,
+        statement.failed_tests,
+        statement.passed_tests,
+        statement.total_failed,
+        statement.total_passed,
+        statement.suspiciousness.to_bits(),
+        text.len() as u32,
+    ];
+    for field in fields {
+        writer.write_all(&field.to_le_bytes())?;
+    }
+    writer.write_all(text)
+}
+
+/// The next statement of a run, or `None` at its end.
+fn read_statement<R: Read>(reader: &mut R) -> io::Result<Option<StatementInfo>> {
+    let mut fields = [0u32; 8];
+    for (i, field) in fields.iter_mut().enumerate() {
+        let mut bytes = [0u8; 4];
+        match reader.read_exact(&mut bytes) {
+            Err(e) if i == 0 && e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        *field = u32::from_le_bytes(bytes);
+    }
+    let [file, line_number, failed_tests, passed_tests, total_failed, total_passed, score, len] =
+        fields;
+    let mut text = vec![0u8; len as usize];
+    reader.read_exact(&mut text)?;
+    let text =
+        String::from_utf8(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut statement = StatementInfo::new(line_number, text, total_failed);
+    statement.file = file;
+    statement.failed_tests = failed_tests;
+    statement.passed_tests = passed_tests;
+    statement.total_passed = total_passed;
+    statement.suspiciousness = f32::from_bits(score);
+    Ok(Some(statement))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external_sort() {
+        let parent = std::env::temp_dir().join(format!("fl_dstar_extsort_{}", std::process::id()));
+        let scores = [0.5, 2.0, 0.5, f32::INFINITY, 0.0, 2.0, 1.0];
+        let statements: Vec<StatementInfo> = scores
+            .iter()
+            .enumerate()
+            .map(|(i, &score)| {
+                let mut s = StatementInfo::new(i as u32 + 1, format!("s{}();", i + 1), 1);
+                s.suspiciousness = score;
+                s
+            })
+            .collect();
+        let mut expected = statements.clone();
+        crate::sort_by_suspiciousness(&mut expected);
+
+        let mut sort = ExternalSort::new(&parent, 3).unwrap();
+        // the duplicate of line 2 lands in another run and is merged away
+        for statement in statements.iter().chain([&statements[1]]) {
+            sort.push(statement.clone()).unwrap();
+        }
+        let sorted: Vec<StatementInfo> = sort.merge().unwrap().map(Result::unwrap).collect();
+        assert_eq!(sort.runs(), 3);
+        let lines = |statements: &[StatementInfo]| -> Vec<(u32, String)> {
+            statements
+                .iter()
+                .map(|s| (s.line_number, s.statement().to_string()))
+                .collect()
+        };
+        assert_eq!(lines(&sorted), lines(&expected));
+        assert!(sorted[0].suspiciousness.is_infinite());
+        // a second pass reads the same runs
+        assert_eq!(sort.merge().unwrap().count(), scores.len());
+        let dir = sort.dir.clone();
+        drop(sort);
+        assert!(!dir.exists());
+        fs::remove_dir_all(&parent).unwrap();
+    }
+}
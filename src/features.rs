@@ -0,0 +1,203 @@
+use crate::metrics::Metric;
+use crate::smoothing::Smoothing;
+use crate::techniques::Technique;
+use crate::{LineInfo, Spectrum, StatementInfo, SuspiciousnessMetric};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// Everything known about one statement, for learning-to-rank pipelines.
+/// Scores are raw, not rounded; infinite D* scores are written as `inf`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatementFeatures {
+    pub file: Option<String>,
+    pub line_number: u32,
+    /// Hash of the statement's text, see [`crate::integrity`]
+    pub hash: String,
+    pub ef: u32,
+    pub ep: u32,
+    pub nf: u32,
+    pub np: u32,
+    /// Times the failing tests executed the statement, summed
+    pub failing_hits: u64,
+    /// Times the passing tests executed the statement, summed
+    pub passing_hits: u64,
+    pub dstar: f32,
+    pub ochiai: f32,
+    pub tarantula: f32,
+    pub jaccard: f32,
+    pub op2: f32,
+    pub nearest_neighbor: f32,
+    pub union: f32,
+    pub intersection: f32,
+    pub barinel_bayes: f32,
+    /// Commits that touched the statement's file, when its history is known
+    pub churn: Option<u32>,
+    /// Leading whitespace, a rough nesting depth
+    pub indent: u32,
+    /// Branching operators and keywords in the statement
+    pub branches: u32,
+    pub length: u32,
+}
+
+/// Count the tokens that make a statement branch.
+fn branches(statement: &str) -> u32 {
+    let operators = ["&&", "||", "?"]
+        .iter()
+        .map(|op| statement.matches(op).count())
+        .sum::<usize>();
+    let keywords = statement
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| ["if", "for", "while", "case", "catch", "match"].contains(word))
+        .count();
+    (operators + keywords) as u32
+}
+
+/// Summed execution counts of each line over `tests`.
+fn hits(tests: &[Vec<LineInfo>]) -> HashMap<u64, u64> {
+    let mut hits = HashMap::new();
+    for line in tests.iter().flatten() {
+        *hits.entry(line.key()).or_insert(0u64) += line.execution_count;
+    }
+    hits
+}
+
+/// Extract the features of every statement of `spectrum`, in line order.
+/// `smoothing` applies to the D* score; `churn` maps source files to the
+/// number of commits that touched them (see [`file_churn`]).
+pub fn extract(
+    spectrum: &Spectrum,
+    smoothing: Smoothing,
+    churn: &HashMap<String, u32>,
+) -> Vec<StatementFeatures> {
+    let techniques = [
+        Technique::NearestNeighbor,
+        Technique::Union,
+        Technique::Intersection,
+        Technique::BarinelBayes,
+    ];
+    let scores: Vec<HashMap<u64, f32>> = techniques
+        .iter()
+        .map(|&technique| {
+            let mut scored = spectrum.clone();
+            scored.score(technique, smoothing);
+            scored
+                .statements
+                .iter()
+                .map(|s| (s.key(), s.suspiciousness))
+                .collect()
+        })
+        .collect();
+    let failing_hits = hits(&spectrum.failing);
+    let passing_hits = hits(&spectrum.passing);
+
+    let mut statements: Vec<&StatementInfo> = spectrum.statements.iter().collect();
+    statements.sort_by_key(|s| s.key());
+    statements
+        .into_iter()
+        .map(|s| {
+            let (ef, ep) = (s.failed_tests, s.passed_tests);
+            let (nf, np) = (
+                s.total_failed.saturating_sub(ef),
+                s.total_passed.saturating_sub(ep),
+            );
+            let counts = Smoothing::None.counts(ef, ep, s.total_failed, s.total_passed);
+            let file = spectrum.sources.get(&s.file).cloned();
+            let text = s.statement();
+            StatementFeatures {
+                churn: file.as_ref().and_then(|file| churn.get(file).copied()),
+                file,
+                line_number: s.line_number,
+                hash: s.hash(),
+                ef,
+                ep,
+                nf,
+                np,
+                failing_hits: failing_hits.get(&s.key()).copied().unwrap_or(0),
+                passing_hits: passing_hits.get(&s.key()).copied().unwrap_or(0),
+                dstar: smoothing
+                    .counts(ef, ep, s.total_failed, s.total_passed)
+                    .dstar(),
+                ochiai: Metric::Ochiai.suspiciousness(&counts),
+                tarantula: Metric::Tarantula.suspiciousness(&counts),
+                jaccard: Metric::Jaccard.suspiciousness(&counts),
+                op2: Metric::Op2.suspiciousness(&counts),
+                nearest_neighbor: scores[0][&s.key()],
+                union: scores[1][&s.key()],
+                intersection: scores[2][&s.key()],
+                barinel_bayes: scores[3][&s.key()],
+                indent: text.chars().take_while(|c| c.is_whitespace()).count() as u32,
+                branches: branches(text),
+                length: text.trim().chars().count() as u32,
+            }
+        })
+        .collect()
+}
+
+/// The number of commits that touched each file of the git repository at
+/// `root`, keyed by path relative to the repository's top level. Runs
+/// `git log` (or `$GIT`).
+pub fn file_churn(root: &Path) -> std::io::Result<HashMap<String, u32>> {
+    let program = std::env::var("GIT").unwrap_or_else(|_| "git".to_string());
+    let output = Command::new(program)
+        .arg("-C")
+        .arg(root)
+        .args(["log", "--format=", "--name-only", "--no-renames"])
+        .output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    let mut churn = HashMap::new();
+    for path in String::from_utf8_lossy(&output.stdout).lines() {
+        if !path.is_empty() {
+            *churn.entry(path.to_string()).or_insert(0) += 1;
+        }
+    }
+    Ok(churn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Coverage;
+
+    #[test]
+    fn test_extract() {
+        let test = |covered: &[u32]| -> Vec<LineInfo> {
+            [(1, "int a = 1;"), (2, "  if (a && b) {")]
+                .iter()
+                .map(|&(line_number, statement)| LineInfo {
+                    // the second line is in a.c, the first in a file the coverage didn't name
+                    file: line_number - 1,
+                    line_number,
+                    statement: statement.to_string(),
+                    coverage: match covered.contains(&line_number) {
+                        true => Coverage::Covered,
+                        false => Coverage::NotCovered,
+                    },
+                    execution_count: covered.contains(&line_number) as u64 * 2,
+                })
+                .collect()
+        };
+        let mut spectrum = Spectrum::new(vec![test(&[1]), test(&[1])], vec![test(&[1, 2])]);
+        spectrum.sources.insert(1, "a.c".to_string());
+        let churn = HashMap::from([("a.c".to_string(), 7)]);
+        let features = extract(&spectrum, Smoothing::None, &churn);
+        let (init, branch) = (&features[0], &features[1]);
+        assert_eq!((init.ef, init.ep, init.nf, init.np), (1, 2, 0, 0));
+        assert_eq!((init.failing_hits, init.passing_hits), (2, 4));
+        assert_eq!(init.dstar, 0.5);
+        assert!((init.ochiai - 1.0 / 3f32.sqrt()).abs() < 1e-6);
+        assert_eq!(init.tarantula, 0.5);
+        assert_eq!(init.churn, None);
+        assert!(branch.dstar.is_infinite());
+        assert_eq!(branch.ochiai, 1.0);
+        assert_eq!(branch.op2, 1.0);
+        assert_eq!(branch.union, 1.0);
+        assert_eq!(branch.churn, Some(7));
+        assert_eq!((branch.indent, branch.branches, branch.length), (2, 2, 13));
+    }
+}
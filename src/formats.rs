@@ -0,0 +1,740 @@
+use crate::{Coverage, FlError, LineInfo};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+/// The coverage formats the crate can read and write.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Gcov,
+    Lcov,
+    /// lcov exported by `llvm-cov export -format=lcov`, typically from Rust
+    LlvmCov,
+    /// `afl-showmap` edge maps, where each edge id stands in for a line
+    Showmap,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "gcov" => Ok(Format::Gcov),
+            "lcov" | "info" => Ok(Format::Lcov),
+            "llvm-cov" => Ok(Format::LlvmCov),
+            "showmap" | "afl-showmap" => Ok(Format::Showmap),
+            _ => Err(format!(
+                "unknown coverage format '{}' (expected gcov, lcov, llvm-cov or showmap)",
+                s
+            )),
+        }
+    }
+}
+
+impl Format {
+    /// Whether files only list what was executed, so tests need aligning
+    /// with [`crate::align_tests`] before they can be compared.
+    pub fn is_sparse(&self) -> bool {
+        matches!(self, Format::Showmap)
+    }
+}
+
+/// Coverage of a single source file, independent of the format it came from.
+#[derive(Debug)]
+pub struct SourceCoverage {
+    pub source: Option<String>,
+    pub lines: Vec<LineInfo>,
+    /// Input lines the parser left out of `lines`
+    pub skipped: Vec<SkippedLine>,
+}
+
+/// A line of a coverage file that was skipped rather than parsed, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedLine {
+    /// Line of the coverage file, from 1
+    pub number: usize,
+    pub text: String,
+    pub reason: String,
+}
+
+/// How forgiving the parsers are and what they keep. The default is lenient
+/// and keeps what ranking needs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParseOptions {
+    /// Fail on the first line that can't be parsed or isn't UTF-8, instead
+    /// of skipping or repairing it
+    pub strict: bool,
+    /// Keep gcov lines with no executable code (`-`), such as comments.
+    /// Spectra are built from executable lines only, so leave this off for
+    /// coverage that will be ranked.
+    pub keep_noncode: bool,
+    /// Keep execution counts; otherwise a covered line counts as run once
+    pub keep_counts: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            strict: false,
+            keep_noncode: false,
+            keep_counts: true,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Handle line `number` (from 0) that isn't valid `what`: an error when
+    /// strict, otherwise the line the caller skips.
+    pub(crate) fn malformed(
+        &self,
+        number: usize,
+        line: &str,
+        what: &'static str,
+    ) -> Result<SkippedLine, FlError> {
+        match self.strict {
+            true => Err(FlError::Malformed {
+                line: Some(number + 1),
+                expected: what,
+                text: line.to_string(),
+            }),
+            false => Ok(SkippedLine {
+                number: number + 1,
+                text: line.to_string(),
+                reason: format!("expected {}", what),
+            }),
+        }
+    }
+}
+
+/// Parse coverage in `format` with the default, lenient options. Parsing
+/// never panics, whatever the input: lines that can't be parsed are skipped,
+/// and bytes that aren't UTF-8 are replaced.
+pub fn read<R: BufRead>(format: Format, reader: R) -> Vec<SourceCoverage> {
+    // lenient parsing has no errors to report
+    read_with(format, reader, &ParseOptions::default()).unwrap_or_default()
+}
+
+/// Parse coverage in `format`. Only fails in strict mode.
+pub fn read_with<R: BufRead>(
+    format: Format,
+    reader: R,
+    options: &ParseOptions,
+) -> Result<Vec<SourceCoverage>, FlError> {
+    let mut coverage = match format {
+        Format::Gcov => crate::parse_gcov_reader(reader, options)?,
+        Format::Lcov => merge_duplicates(parse_lcov_reader(reader, options)?),
+        Format::LlvmCov => {
+            let mut coverage = merge_duplicates(parse_lcov_reader(reader, options)?);
+            let workspace = coverage
+                .iter()
+                .filter_map(|record| record.source.as_deref())
+                .find_map(|source| cargo_workspace_root(std::path::Path::new(source)));
+            if let Some(workspace) = workspace {
+                for record in &mut coverage {
+                    relativize(record, &workspace);
+                }
+            }
+            coverage
+        }
+        Format::Showmap => vec![parse_showmap_reader(reader, options)?],
+    };
+    if !options.keep_counts {
+        for line in coverage
+            .iter_mut()
+            .flat_map(|record| record.lines.iter_mut())
+        {
+            line.execution_count = (line.coverage == Coverage::Covered) as u64;
+        }
+    }
+    Ok(coverage)
+}
+
+/// Read a coverage file with [`read`], after decoding it with
+/// [`crate::encoding::read_to_string`].
+pub fn read_file(format: Format, path: &std::path::Path) -> Result<Vec<SourceCoverage>, FlError> {
+    let text = crate::encoding::read_to_string(path).map_err(|source| FlError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok(read(format, text.as_bytes()))
+}
+
+/// Present every record's source path the way reports show it; see
+/// [`crate::paths::display_path`].
+pub fn normalize_sources(
+    coverage: &mut [SourceCoverage],
+    maps: &[crate::paths::PathMap],
+    root: Option<&std::path::Path>,
+) {
+    for record in coverage {
+        if let Some(source) = &record.source {
+            record.source = Some(crate::paths::display_path(source, maps, root));
+        }
+    }
+}
+
+/// Drop records whose source path matches one of the `--exclude-path` globs.
+pub fn exclude_sources(coverage: &mut Vec<SourceCoverage>, patterns: &[String]) {
+    coverage.retain(|record| match &record.source {
+        Some(source) => !crate::paths::is_excluded(source, patterns),
+        None => true,
+    });
+}
+
+/// Treat lines executed fewer than `min_hits` times as not covered, so code
+/// that every test runs once in passing (initialization, say) doesn't count.
+/// A covered line without a count is taken to have run once.
+pub fn apply_min_hits(coverage: &mut [SourceCoverage], min_hits: u64) {
+    for line in coverage
+        .iter_mut()
+        .flat_map(|record| record.lines.iter_mut())
+    {
+        if line.coverage == Coverage::Covered && line.execution_count.max(1) < min_hits {
+            line.coverage = Coverage::NotCovered;
+        }
+    }
+}
+
+/// Whether a line is one compilers attribute coverage to without it holding
+/// a statement of its own: a closing brace that stands for a function's
+/// epilogue, say, or a template header.
+pub fn is_brace_line(statement: &str) -> bool {
+    let code = statement.split("//").next().unwrap_or("").trim();
+    let only_punctuation = !code.is_empty() && code.chars().all(|c| "{}();,".contains(c));
+    only_punctuation || code.starts_with("template<") || code.starts_with("template <")
+}
+
+/// Mark brace lines (see [`is_brace_line`]) as holding no executable code,
+/// so they aren't scored but still show in annotated source.
+pub fn drop_brace_lines(coverage: &mut [SourceCoverage]) {
+    for line in coverage
+        .iter_mut()
+        .flat_map(|record| record.lines.iter_mut())
+    {
+        if line.coverage != Coverage::NoExecutableCode && is_brace_line(&line.statement) {
+            line.coverage = Coverage::NoExecutableCode;
+            line.execution_count = 0;
+        }
+    }
+}
+
+/// Read a coverage file and flatten every record into a single list of lines.
+pub fn read_lines(format: Format, path: &std::path::Path) -> Result<Vec<LineInfo>, FlError> {
+    Ok(read_file(format, path)?
+        .into_iter()
+        .flat_map(|source| source.lines)
+        .collect())
+}
+
+pub fn write<W: Write>(
+    format: Format,
+    coverage: &[SourceCoverage],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    for source in coverage {
+        match format {
+            Format::Gcov => write_gcov(source, writer)?,
+            Format::Lcov | Format::LlvmCov => write_lcov(source, writer)?,
+            Format::Showmap => write_showmap(source, writer)?,
+        }
+    }
+    Ok(())
+}
+
+/// The lines of `reader` without line terminators. Invalid UTF-8 is
+/// replaced and a read error ends the input, unless `options` are strict,
+/// when both are errors.
+pub(crate) fn text_lines<R: BufRead>(
+    reader: R,
+    options: &ParseOptions,
+) -> impl Iterator<Item = Result<String, FlError>> {
+    let strict = options.strict;
+    reader
+        .split(b'\n')
+        .enumerate()
+        .map_while(move |(i, bytes)| match bytes {
+            Ok(bytes) => Some(match String::from_utf8(bytes) {
+                Ok(line) => Ok(line),
+                Err(_) if strict => Err(FlError::NotUtf8 { line: i + 1 }),
+                Err(e) => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+            }),
+            Err(e) if strict => Some(Err(FlError::Read(e))),
+            Err(_) => None,
+        })
+        .map(|line| line.map(|line| line.strip_suffix('\r').unwrap_or(&line).to_string()))
+}
+
+/// Parse an lcov tracefile. Each `SF:` ... `end_of_record` block becomes one
+/// `SourceCoverage`. Only line (`DA:`) records are used.
+fn parse_lcov_reader<R: BufRead>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<Vec<SourceCoverage>, FlError> {
+    let mut records = Vec::new();
+    let mut current = SourceCoverage {
+        source: None,
+        lines: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for (i, line) in text_lines(reader, options).enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("SF:") {
+            current.source = Some(name.to_string());
+        } else if let Some(data) = line.strip_prefix("DA:") {
+            // DA:<line number>,<execution count>[,<checksum>]
+            let mut fields = data.split(',').map(|field| field.trim().parse::<u64>());
+            let parsed = match (fields.next(), fields.next()) {
+                (Some(Ok(line_number)), Some(Ok(count))) => {
+                    u32::try_from(line_number).ok().map(|n| (n, count))
+                }
+                _ => None,
+            };
+            let Some((line_number, execution_count)) = parsed else {
+                let skipped = options.malformed(i, line, "DA:<line>,<count>")?;
+                current.skipped.push(skipped);
+                continue;
+            };
+            let coverage = match execution_count {
+                0 => Coverage::NotCovered,
+                _ => Coverage::Covered,
+            };
+            current.lines.push(LineInfo {
+                file: 0,
+                line_number,
+                statement: String::new(),
+                coverage,
+                execution_count,
+            });
+        } else if line == "end_of_record" {
+            let finished = std::mem::replace(
+                &mut current,
+                SourceCoverage {
+                    source: None,
+                    lines: Vec::new(),
+                    skipped: Vec::new(),
+                },
+            );
+            records.push(finished);
+        } else if !line.is_empty() && !line.contains(':') {
+            // other records (TN:, FN:, BRDA: ...) don't matter for line coverage
+            let skipped = options.malformed(i, line, "an lcov record")?;
+            current.skipped.push(skipped);
+        }
+    }
+    // tolerate a missing trailing end_of_record
+    if current.source.is_some() || !current.lines.is_empty() {
+        records.push(current);
+    } else if let Some(last) = records.last_mut() {
+        // junk after the last record still belongs to the file's audit trail
+        last.skipped.append(&mut current.skipped);
+    }
+    Ok(records)
+}
+
+/// Parse `afl-showmap` output: one `<edge id>:<hit count>` per line for every
+/// edge the input reached. Edge ids become line numbers; the bare `<edge id>`
+/// lines written with `-e` count as one hit.
+fn parse_showmap_reader<R: BufRead>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<SourceCoverage, FlError> {
+    let mut lines = Vec::new();
+    let mut skipped = Vec::new();
+    for (i, line) in text_lines(reader, options).enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (edge, count) = line.split_once(':').unwrap_or((line, "1"));
+        let (Ok(line_number), Ok(execution_count)) =
+            (edge.trim().parse::<u32>(), count.trim().parse::<u64>())
+        else {
+            skipped.push(options.malformed(i, line, "<edge>:<count>")?);
+            continue;
+        };
+        lines.push(LineInfo {
+            file: 0,
+            line_number,
+            statement: format!("edge {}", line_number),
+            coverage: match execution_count {
+                0 => Coverage::NotCovered,
+                _ => Coverage::Covered,
+            },
+            execution_count,
+        });
+    }
+    Ok(SourceCoverage {
+        source: None,
+        lines,
+        skipped,
+    })
+}
+
+fn write_showmap<W: Write>(source: &SourceCoverage, writer: &mut W) -> std::io::Result<()> {
+    for line in &source.lines {
+        if line.coverage == Coverage::Covered {
+            writeln!(
+                writer,
+                "{:06}:{}",
+                line.line_number,
+                line.execution_count.max(1)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// llvm-cov repeats records for code that was instantiated more than once,
+/// such as `#[inline]` and generic functions, and concatenated gcov and lcov
+/// files repeat the headers every unit includes. Merge records for the same
+/// source file and sum the counts of lines that appear more than once, so a
+/// test counts each line once.
+pub(crate) fn merge_duplicates(records: Vec<SourceCoverage>) -> Vec<SourceCoverage> {
+    let mut merged: Vec<SourceCoverage> = Vec::new();
+    // indexes into `merged` and its lines, so huge tracefiles merge in linear time
+    let mut by_source: HashMap<Option<String>, usize> = HashMap::new();
+    let mut by_line: HashMap<(usize, u32), usize> = HashMap::new();
+    for record in records {
+        let index = *by_source.entry(record.source.clone()).or_insert_with(|| {
+            merged.push(SourceCoverage {
+                source: record.source.clone(),
+                lines: Vec::new(),
+                skipped: Vec::new(),
+            });
+            merged.len() - 1
+        });
+        let target = &mut merged[index];
+        target.skipped.extend(record.skipped);
+        for line in record.lines {
+            match by_line.get(&(index, line.line_number)) {
+                Some(&position) => {
+                    let existing = &mut target.lines[position];
+                    existing.execution_count = existing
+                        .execution_count
+                        .saturating_add(line.execution_count);
+                    if line.coverage == Coverage::Covered {
+                        existing.coverage = Coverage::Covered;
+                    }
+                }
+                None => {
+                    by_line.insert((index, line.line_number), target.lines.len());
+                    target.lines.push(line);
+                }
+            }
+        }
+    }
+    for record in &mut merged {
+        record.lines.sort_by_key(|l| l.line_number);
+    }
+    merged
+}
+
+/// The outermost directory above `source` that contains a `Cargo.toml`.
+fn cargo_workspace_root(source: &std::path::Path) -> Option<std::path::PathBuf> {
+    if !source.is_absolute() {
+        return None;
+    }
+    source
+        .ancestors()
+        .skip(1)
+        .filter(|dir| dir.join("Cargo.toml").is_file())
+        .last()
+        .map(|dir| dir.to_path_buf())
+}
+
+/// Rewrite a record's source path relative to `root`. Paths outside of it,
+/// such as the standard library under `/rustc/<hash>/`, are left alone.
+fn relativize(record: &mut SourceCoverage, root: &std::path::Path) {
+    let relative = record
+        .source
+        .as_deref()
+        .and_then(|source| std::path::Path::new(source).strip_prefix(root).ok())
+        .map(|path| path.to_string_lossy().into_owned());
+    if let Some(relative) = relative {
+        record.source = Some(relative);
+    }
+}
+
+fn write_gcov<W: Write>(source: &SourceCoverage, writer: &mut W) -> std::io::Result<()> {
+    if let Some(name) = &source.source {
+        writeln!(writer, "{:>9}:{:>5}:Source:{}", "-", 0, name)?;
+    }
+    for line in &source.lines {
+        let count = match line.coverage {
+            Coverage::Covered => line.execution_count.max(1).to_string(),
+            Coverage::NotCovered => "#####".to_string(),
+            Coverage::NoExecutableCode => "-".to_string(),
+        };
+        writeln!(
+            writer,
+            "{:>9}:{:>5}:{}",
+            count, line.line_number, line.statement
+        )?;
+    }
+    Ok(())
+}
+
+fn write_lcov<W: Write>(source: &SourceCoverage, writer: &mut W) -> std::io::Result<()> {
+    writeln!(writer, "TN:")?;
+    writeln!(writer, "SF:{}", source.source.as_deref().unwrap_or(""))?;
+    let mut found = 0;
+    let mut hit = 0;
+    for line in &source.lines {
+        let count = match line.coverage {
+            Coverage::Covered => line.execution_count.max(1),
+            Coverage::NotCovered => 0,
+            Coverage::NoExecutableCode => continue,
+        };
+        found += 1;
+        if count > 0 {
+            hit += 1;
+        }
+        writeln!(writer, "DA:{},{}", line.line_number, count)?;
+    }
+    writeln!(writer, "LF:{}", found)?;
+    writeln!(writer, "LH:{}", hit)?;
+    writeln!(writer, "end_of_record")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GCOV: &str = "        -:    0:Source:tcas.c
+        -:    1:#include <stdio.h>
+        3:    2:int main() {
+    #####:    3:    return 1;
+        3:    4:}
+";
+
+    #[test]
+    fn test_gcov_to_lcov() {
+        let coverage = read(Format::Gcov, GCOV.as_bytes());
+        let mut out = Vec::new();
+        write(Format::Lcov, &coverage, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "TN:\nSF:tcas.c\nDA:2,3\nDA:3,0\nDA:4,3\nLF:3\nLH:2\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn test_llvm_cov_merges_inline_duplicates() {
+        let lcov = "SF:/rustc/abc/library/core/src/ptr.rs\nDA:1,1\nend_of_record\nSF:src/lib.rs\nDA:3,2\nDA:4,0\nend_of_record\nSF:src/lib.rs\nDA:3,5\nDA:4,1\nend_of_record\n";
+        let coverage = read(Format::LlvmCov, lcov.as_bytes());
+        assert_eq!(coverage.len(), 2);
+        assert_eq!(coverage[1].source.as_deref(), Some("src/lib.rs"));
+        assert_eq!(coverage[1].lines.len(), 2);
+        assert_eq!(coverage[1].lines[0].execution_count, 7);
+        assert_eq!(coverage[1].lines[1].coverage, Coverage::Covered);
+    }
+
+    #[test]
+    fn test_malformed_input_is_skipped() {
+        let inputs: [&[u8]; 7] = [
+            b"",
+            b"garbage",
+            b"    1:    x:int a;\n        1:    2:b();\n",
+            b"SF:a.c\nDA:\nDA:1\nDA:x,1\nDA:99999999999,1\nDA:2,1\n",
+            b"SF:a.c\nDA:1,18446744073709551615\nend_of_record\nSF:a.c\nDA:1,1\n",
+            b"12:\n:3\n\xff\xfe:1\n7:2\n",
+            b"\xff\xfe\x00\x01:::\r\n-:0:Source:\xc3(",
+        ];
+        for input in inputs {
+            for format in [Format::Gcov, Format::Lcov, Format::LlvmCov, Format::Showmap] {
+                read(format, input);
+            }
+        }
+        assert_eq!(read(Format::Gcov, inputs[2])[0].lines.len(), 1);
+        let lcov = read(Format::Lcov, inputs[3]);
+        assert_eq!(lcov[0].lines.len(), 1);
+        let skipped: Vec<usize> = lcov[0].skipped.iter().map(|s| s.number).collect();
+        assert_eq!(skipped, [2, 3, 4, 5]);
+        assert_eq!(lcov[0].skipped[0].reason, "expected DA:<line>,<count>");
+        let merged = read(Format::LlvmCov, inputs[4]);
+        assert_eq!(merged[0].lines[0].execution_count, u64::MAX);
+        assert_eq!(read(Format::Showmap, inputs[5])[0].lines.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let strict = ParseOptions {
+            strict: true,
+            ..ParseOptions::default()
+        };
+        let gcov = "        -:    0:Source:a.c\n        -:    1:// setup\n        3:    2:a();\n";
+        assert!(read_with(Format::Gcov, gcov.as_bytes(), &strict).is_ok());
+        let broken = format!("{}garbage\n", gcov);
+        assert_eq!(
+            read_with(Format::Gcov, broken.as_bytes(), &strict)
+                .unwrap_err()
+                .to_string(),
+            "line 4: expected a gcov line, found 'garbage'"
+        );
+        assert!(read_with(Format::Gcov, b"  1x:  2:a();\n".as_slice(), &strict).is_err());
+        assert!(read_with(Format::Lcov, b"SF:a.c\nDA:1\n".as_slice(), &strict).is_err());
+        assert!(read_with(
+            Format::Lcov,
+            b"TN:\nSF:a.c\nFNF:0\nDA:1,1\n".as_slice(),
+            &strict
+        )
+        .is_ok());
+        assert!(matches!(
+            read_with(Format::Showmap, b"1:1\n\xff\n".as_slice(), &strict),
+            Err(FlError::NotUtf8 { line: 2 })
+        ));
+
+        let everything = ParseOptions {
+            keep_noncode: true,
+            keep_counts: false,
+            ..ParseOptions::default()
+        };
+        let coverage = read_with(Format::Gcov, gcov.as_bytes(), &everything).unwrap();
+        assert_eq!(coverage[0].lines.len(), 2);
+        assert_eq!(coverage[0].lines[0].coverage, Coverage::NoExecutableCode);
+        assert_eq!(coverage[0].lines[1].execution_count, 1);
+    }
+
+    #[test]
+    fn test_concatenated_gcov() {
+        let gcov = "        -:    0:Source:a.c\n        1:    1:a();\n\
+                    \x20       -:    0:Source:b.c\n    #####:    1:b();\n";
+        let coverage = read(Format::Gcov, gcov.as_bytes());
+        let sources: Vec<_> = coverage.iter().map(|r| r.source.as_deref()).collect();
+        assert_eq!(sources, [Some("a.c"), Some("b.c")]);
+        assert_eq!(coverage[1].lines[0].coverage, Coverage::NotCovered);
+        let mut written = Vec::new();
+        write(Format::Gcov, &coverage, &mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), gcov);
+
+        // a header both units include is listed twice, and counted once
+        let gcov = "        -:    0:Source:h.h\n    #####:    1:h();\n        2:    2:g();\n\
+                    \x20       -:    0:Source:a.c\n        1:    1:a();\n\
+                    \x20       -:    0:Source:h.h\n        3:    1:h();\n    #####:    2:g();\n";
+        let everything = ParseOptions {
+            keep_counts: true,
+            ..ParseOptions::default()
+        };
+        let coverage = read_with(Format::Gcov, gcov.as_bytes(), &everything).unwrap();
+        let sources: Vec<_> = coverage.iter().map(|r| r.source.as_deref()).collect();
+        assert_eq!(sources, [Some("h.h"), Some("a.c")]);
+        let counts: Vec<_> = coverage[0]
+            .lines
+            .iter()
+            .map(|line| (line.line_number, line.coverage, line.execution_count))
+            .collect();
+        assert_eq!(
+            counts,
+            [(1, Coverage::Covered, 3), (2, Coverage::Covered, 2)]
+        );
+    }
+
+    #[test]
+    fn test_min_hits() {
+        let mut coverage = read(Format::Gcov, GCOV.as_bytes());
+        apply_min_hits(&mut coverage, 3);
+        let covered = |coverage: &[SourceCoverage]| -> Vec<Coverage> {
+            coverage[0].lines.iter().map(|line| line.coverage).collect()
+        };
+        assert_eq!(
+            covered(&coverage),
+            [Coverage::Covered, Coverage::NotCovered, Coverage::Covered]
+        );
+        apply_min_hits(&mut coverage, 4);
+        assert_eq!(covered(&coverage), [Coverage::NotCovered; 3]);
+    }
+
+    #[test]
+    fn test_brace_lines() {
+        for statement in ["}", "  };", "})); // end", "template <typename T>", "{"] {
+            assert!(is_brace_line(statement), "{}", statement);
+        }
+        for statement in ["} else {", "f();", "return {};", "", "// }"] {
+            assert!(!is_brace_line(statement), "{}", statement);
+        }
+        let gcov = "        1:    1:int f() {\n        1:    2:  return 1;\n        1:    3:}\n";
+        let mut coverage = read(Format::Gcov, gcov.as_bytes());
+        drop_brace_lines(&mut coverage);
+        let kinds: Vec<Coverage> = coverage[0].lines.iter().map(|l| l.coverage).collect();
+        assert_eq!(
+            kinds,
+            [
+                Coverage::Covered,
+                Coverage::Covered,
+                Coverage::NoExecutableCode
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relativize() {
+        let mut record = SourceCoverage {
+            source: Some("/home/me/project/src/main.rs".to_string()),
+            lines: Vec::new(),
+            skipped: Vec::new(),
+        };
+        relativize(&mut record, std::path::Path::new("/home/me/project"));
+        assert_eq!(record.source.as_deref(), Some("src/main.rs"));
+    }
+
+    #[test]
+    fn test_showmap() {
+        let coverage = read(Format::Showmap, "000017:1\n000042:3\n000099\n".as_bytes());
+        let lines = &coverage[0].lines;
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].line_number, 42);
+        assert_eq!(lines[1].execution_count, 3);
+        assert_eq!(lines[2].statement, "edge 99");
+        let mut out = Vec::new();
+        write(Format::Showmap, &coverage, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "000017:1\n000042:3\n000099:1\n"
+        );
+    }
+
+    #[test]
+    fn test_windows_artifacts() {
+        // gcov run on Windows: a drive letter in the header and CRLF line ends
+        let gcov = "        -:    0:Source:C:\\proj\\tcas.c\r\n        -:    1:#include <stdio.h>\r\n        3:    2:int a = 1;\r\n    #####:    3:b();\r\n";
+        let utf16: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain(gcov.encode_utf16().flat_map(|u| u.to_le_bytes()))
+            .collect();
+        let path = std::env::temp_dir().join(format!("fl_dstar-utf16-{}.gcov", std::process::id()));
+        std::fs::write(&path, utf16).unwrap();
+        let coverage = read_file(Format::Gcov, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(coverage[0].source.as_deref(), Some("C:\\proj\\tcas.c"));
+        assert_eq!(coverage[0].lines.len(), 2);
+        assert_eq!(coverage[0].lines[0].statement, "int a = 1;");
+        assert_eq!(coverage[0].lines[1].coverage, Coverage::NotCovered);
+
+        let mut coverage = read(
+            Format::Lcov,
+            "SF:C:\\proj\\src\\a.c\r\nDA:2,1\r\nend_of_record\r\n".as_bytes(),
+        );
+        assert_eq!(coverage[0].lines[0].line_number, 2);
+        normalize_sources(&mut coverage, &[], Some(std::path::Path::new("C:\\proj")));
+        assert_eq!(coverage[0].source.as_deref(), Some("src/a.c"));
+    }
+
+    #[test]
+    fn test_lcov_round_trip() {
+        let lcov = "TN:\nSF:a.c\nDA:2,3\nDA:3,0\nLF:2\nLH:1\nend_of_record\nSF:b.c\nDA:7,1\nend_of_record\n";
+        let coverage = read(Format::Lcov, lcov.as_bytes());
+        assert_eq!(coverage.len(), 2);
+        assert_eq!(coverage[0].source.as_deref(), Some("a.c"));
+        assert_eq!(coverage[0].lines[1].coverage, Coverage::NotCovered);
+        assert_eq!(coverage[1].lines[0].line_number, 7);
+
+        let mut gcov = Vec::new();
+        write(Format::Gcov, &coverage[..1], &mut gcov).unwrap();
+        let back = read(Format::Gcov, &gcov[..]);
+        assert_eq!(back[0].source.as_deref(), Some("a.c"));
+        assert_eq!(back[0].lines.len(), 2);
+        assert_eq!(back[0].lines[0].execution_count, 3);
+        assert_eq!(back[0].lines[1].coverage, Coverage::NotCovered);
+    }
+}
@@ -0,0 +1,373 @@
+use crate::demangle::{demangle, template_group};
+use crate::{round_serialize, StatementInfo};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Read};
+use std::str::FromStr;
+
+/// Function-level coverage of a single test, as printed by `gcov -f`.
+#[derive(Debug, PartialEq)]
+pub struct FunctionSummary {
+    pub name: String,
+    /// Percentage of the function's lines that were executed
+    pub lines_executed: f32,
+    pub line_count: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FunctionInfo {
+    pub function: String,
+    pub failed_tests: u32,
+    pub passed_tests: u32,
+    pub total_failed: u32,
+    #[serde(serialize_with = "round_serialize")]
+    pub suspiciousness: f32,
+}
+
+/// Parse the summary gcov writes to stdout when run with `-f`:
+///
+/// ```text
+/// Function 'main'
+/// Lines executed:85.71% of 7
+/// ```
+///
+/// The per-file summaries (`File '...'`) are skipped, and so are summaries
+/// that can't be parsed. Like the coverage readers, this never panics:
+/// bytes that aren't UTF-8 are replaced and a read error ends the input.
+pub fn parse_function_summaries<R: BufRead>(reader: R) -> Vec<FunctionSummary> {
+    let mut summaries = Vec::new();
+    let mut current: Option<String> = None;
+    // lenient lines are never errors
+    let lines = crate::formats::text_lines(reader, &crate::formats::ParseOptions::default());
+    for line in lines.map_while(Result::ok) {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("Function '") {
+            current = Some(name.trim_end_matches('\'').to_string());
+        } else if line.starts_with("File '") {
+            current = None;
+        } else if let Some(rest) = line.strip_prefix("Lines executed:") {
+            let name = match current.take() {
+                Some(name) => name,
+                None => continue,
+            };
+            // <percent>% of <count>
+            let Some((percent, count)) = rest.split_once("% of ") else {
+                continue;
+            };
+            let (Ok(lines_executed), Ok(line_count)) =
+                (percent.trim().parse::<f32>(), count.trim().parse::<u32>())
+            else {
+                continue;
+            };
+            summaries.push(FunctionSummary {
+                name,
+                lines_executed,
+                line_count,
+            });
+        } else if line.starts_with("No executable lines") {
+            current = None;
+        }
+    }
+    summaries
+}
+
+pub fn parse_function_summary_file(
+    path: &std::path::Path,
+) -> Result<Vec<FunctionSummary>, crate::FlError> {
+    let text = crate::encoding::read_to_string(path).map_err(|source| crate::FlError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok(parse_function_summaries(text.as_bytes()))
+}
+
+/// Score functions with D*, treating a function as covered by a test when any
+/// of its lines were executed. Mangled C++ names are demangled, and template
+/// instantiations of the same function are scored as one.
+pub fn score_functions(
+    passing: &[Vec<FunctionSummary>],
+    failing: &[Vec<FunctionSummary>],
+) -> Vec<FunctionInfo> {
+    let mut order: Vec<String> = Vec::new();
+    let mut infos: HashMap<String, StatementInfo> = HashMap::new();
+    let mut add = |test: &[FunctionSummary], is_passing: bool| {
+        // instantiations of one template share a name, so count each test once per name
+        let covered: HashSet<String> = test
+            .iter()
+            .filter(|f| f.lines_executed > 0.0)
+            .map(|f| template_group(&demangle(&f.name)))
+            .collect();
+        let mut names: Vec<String> = covered.into_iter().collect();
+        names.sort();
+        for name in names {
+            let info = infos.entry(name.clone()).or_insert_with(|| {
+                order.push(name.clone());
+                StatementInfo::new(0, name, failing.len() as u32)
+            });
+            match is_passing {
+                true => info.add_passing_coverage(),
+                false => info.add_failing_coverage(),
+            }
+        }
+    };
+    for test in passing {
+        add(test, true);
+    }
+    for test in failing {
+        add(test, false);
+    }
+    order
+        .into_iter()
+        .map(|name| {
+            let mut info = infos.remove(&name).unwrap();
+            info.calculate_suspiciousness();
+            FunctionInfo {
+                function: name,
+                failed_tests: info.failed_tests,
+                passed_tests: info.passed_tests,
+                total_failed: info.total_failed,
+                suspiciousness: info.suspiciousness,
+            }
+        })
+        .collect()
+}
+
+/// Static size and complexity of a function.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FunctionMetrics {
+    /// Lines of code
+    pub loc: Option<u32>,
+    /// Cyclomatic complexity
+    pub complexity: Option<u32>,
+}
+
+/// Read per-function metrics from CSV with a `function` column and either or
+/// both of `complexity` and `loc`. The column names lizard writes with
+/// `--csv` headers (`CCN`, `NLOC`) are accepted too. Names are demangled and
+/// template instantiations merged, as when scoring.
+pub fn parse_metrics<R: Read>(reader: R) -> Result<HashMap<String, FunctionMetrics>, String> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|h| names.iter().any(|name| h.trim().eq_ignore_ascii_case(name)))
+    };
+    let function_column = column(&["function"]).ok_or("missing function column")?;
+    let complexity_column = column(&["complexity", "ccn"]);
+    let loc_column = column(&["loc", "nloc"]);
+    if complexity_column.is_none() && loc_column.is_none() {
+        return Err("missing complexity and loc columns".to_string());
+    }
+    let mut metrics: HashMap<String, FunctionMetrics> = HashMap::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| e.to_string())?;
+        let number = |column: Option<usize>, what: &str| -> Result<Option<u32>, String> {
+            let Some(column) = column else {
+                return Ok(None);
+            };
+            let value = record.get(column).unwrap_or("").trim();
+            value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("row {}: invalid {} '{}'", i + 1, what, value))
+        };
+        let name = template_group(&demangle(record.get(function_column).unwrap_or("").trim()));
+        let found = FunctionMetrics {
+            loc: number(loc_column, "loc")?,
+            complexity: number(complexity_column, "complexity")?,
+        };
+        // instantiations share a name; keep the largest
+        let entry = metrics.entry(name).or_default();
+        entry.loc = entry.loc.max(found.loc);
+        entry.complexity = entry.complexity.max(found.complexity);
+    }
+    Ok(metrics)
+}
+
+/// The largest line count gcov reported for each function in any test.
+pub fn summary_loc(tests: &[&[FunctionSummary]]) -> HashMap<String, u32> {
+    let mut loc: HashMap<String, u32> = HashMap::new();
+    for summary in tests.iter().flat_map(|test| test.iter()) {
+        let entry = loc
+            .entry(template_group(&demangle(&summary.name)))
+            .or_insert(0);
+        *entry = (*entry).max(summary.line_count);
+    }
+    loc
+}
+
+/// Which static metric function scores are weighted by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Weighting {
+    None,
+    Loc,
+    Complexity,
+}
+
+impl FromStr for Weighting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Weighting::None),
+            "loc" => Ok(Weighting::Loc),
+            "complexity" | "ccn" => Ok(Weighting::Complexity),
+            _ => Err(format!(
+                "unknown weighting '{}' (expected none, loc or complexity)",
+                s
+            )),
+        }
+    }
+}
+
+/// A function score adjusted by the function's size or complexity.
+#[derive(Debug, Serialize)]
+pub struct WeightedFunction {
+    pub function: String,
+    pub failed_tests: u32,
+    pub passed_tests: u32,
+    pub total_failed: u32,
+    #[serde(serialize_with = "round_serialize")]
+    pub suspiciousness: f32,
+    pub loc: Option<u32>,
+    pub complexity: Option<u32>,
+    #[serde(serialize_with = "round_serialize")]
+    pub weighted_suspiciousness: f32,
+}
+
+/// Weight function scores by a static metric, as larger and more complex
+/// functions are more often faulty: each score is multiplied by
+/// `1 + ln(metric)`, which separates functions without letting one huge
+/// function swamp the ranking. Functions without the metric keep their
+/// score. Returned most suspicious first by weighted score.
+pub fn weight(
+    scores: Vec<FunctionInfo>,
+    metrics: &HashMap<String, FunctionMetrics>,
+    weighting: Weighting,
+) -> Vec<WeightedFunction> {
+    let mut weighted: Vec<WeightedFunction> = scores
+        .into_iter()
+        .map(|info| {
+            let found = metrics.get(&info.function).copied().unwrap_or_default();
+            let metric = match weighting {
+                Weighting::None => None,
+                Weighting::Loc => found.loc,
+                Weighting::Complexity => found.complexity,
+            };
+            let factor = metric.map_or(1.0, |m| 1.0 + (m.max(1) as f32).ln());
+            WeightedFunction {
+                function: info.function,
+                failed_tests: info.failed_tests,
+                passed_tests: info.passed_tests,
+                total_failed: info.total_failed,
+                suspiciousness: info.suspiciousness,
+                loc: found.loc,
+                complexity: found.complexity,
+                weighted_suspiciousness: info.suspiciousness * factor,
+            }
+        })
+        .collect();
+    weighted.sort_by(|a, b| {
+        b.weighted_suspiciousness
+            .total_cmp(&a.weighted_suspiciousness)
+            // an infinite score can't grow, so break those ties by the metric
+            .then(b.complexity.cmp(&a.complexity).then(b.loc.cmp(&a.loc)))
+            .then_with(|| a.function.cmp(&b.function))
+    });
+    weighted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUMMARY: &str = "Function 'Non_Crossing_Biased_Climb'
+Lines executed:100.00% of 7
+
+Function 'main'
+Lines executed:0.00% of 3
+
+File 'tcas.c'
+Lines executed:80.00% of 10
+Creating 'tcas.c.gcov'
+";
+
+    #[test]
+    fn test_parse_function_summaries() {
+        let summaries = parse_function_summaries(SUMMARY.as_bytes());
+        assert_eq!(
+            summaries,
+            vec![
+                FunctionSummary {
+                    name: "Non_Crossing_Biased_Climb".to_string(),
+                    lines_executed: 100.0,
+                    line_count: 7,
+                },
+                FunctionSummary {
+                    name: "main".to_string(),
+                    lines_executed: 0.0,
+                    line_count: 3,
+                },
+            ]
+        );
+
+        // malformed summaries are skipped rather than panicking
+        let broken = b"Function 'a'\nLines executed:12% of\nFunction 'b'\nLines executed:x% of 3\n\
+                      Function 'c'\nLines executed:50.00% of 2\n\xff\n";
+        let summaries = parse_function_summaries(&broken[..]);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].name, "c");
+        assert!(parse_function_summary_file(std::path::Path::new("/nonexistent/f.txt")).is_err());
+    }
+
+    #[test]
+    fn test_score_functions() {
+        let passing = vec![parse_function_summaries(SUMMARY.as_bytes())];
+        let failing = vec![parse_function_summaries(
+            "Function 'main'\nLines executed:50.00% of 2\n".as_bytes(),
+        )];
+        let scores = score_functions(&passing, &failing);
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores[0].function, "Non_Crossing_Biased_Climb");
+        assert_eq!(scores[0].suspiciousness, 0.0);
+        assert_eq!(scores[1].function, "main");
+        assert_eq!(scores[1].suspiciousness, f32::INFINITY);
+    }
+
+    #[test]
+    fn test_weight() {
+        let metrics = parse_metrics(
+            "NLOC,CCN,token,function\n7,1,40,Non_Crossing_Biased_Climb\n3,8,20,main\n".as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(metrics["main"].complexity, Some(8));
+        let main =
+            parse_function_summaries("Function 'main'\nLines executed:50.00% of 3\n".as_bytes());
+        let failing = vec![parse_function_summaries(SUMMARY.as_bytes()), main];
+        let passing = vec![parse_function_summaries(
+            "Function 'main'\nLines executed:50.00% of 3\n".as_bytes(),
+        )];
+        let scores = score_functions(&passing, &failing);
+        // main scores lower (0.5 against 1) but is far more complex
+        let weighted = weight(scores, &metrics, Weighting::Complexity);
+        assert_eq!(weighted[0].function, "main");
+        assert!((weighted[0].weighted_suspiciousness - 0.5 * (1.0 + 8f32.ln())).abs() < 1e-6);
+        assert_eq!(weighted[1].function, "Non_Crossing_Biased_Climb");
+        assert_eq!(weighted[1].weighted_suspiciousness, 1.0);
+        assert_eq!(summary_loc(&[&failing[0]])["main"], 3);
+        assert!(parse_metrics("function,tokens\nmain,4\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_score_functions_groups_templates() {
+        let failing = vec![parse_function_summaries(
+            "Function '_Z3maxIiET_S0_S0_'\nLines executed:100.00% of 1\n\nFunction '_Z3maxIdET_S0_S0_'\nLines executed:100.00% of 1\n"
+                .as_bytes(),
+        )];
+        let scores = score_functions(&[], &failing);
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores[0].function, "max");
+        assert_eq!(scores[0].failed_tests, 1);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::formats::SourceCoverage;
+use crate::{Coverage, LineInfo, Spectrum};
+
+/// What kind of spectrum to synthesize.
+#[derive(Debug, Clone, Copy)]
+pub struct GeneratorConfig {
+    /// Executable statements in the program
+    pub statements: u32,
+    pub passing: usize,
+    pub failing: usize,
+    /// Faulty statements; a test fails only if it runs one of them
+    pub faults: u32,
+    /// Chance that a test runs any given statement
+    pub density: f64,
+    /// Chance that a test runs a fault and passes anyway (coincidental
+    /// correctness)
+    pub noise: f64,
+    pub seed: u64,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            statements: 100,
+            passing: 20,
+            failing: 5,
+            faults: 1,
+            density: 0.5,
+            noise: 0.1,
+            seed: 0,
+        }
+    }
+}
+
+/// A synthesized spectrum and the faults planted in it, so rankings of it
+/// can be scored.
+#[derive(Debug, Clone)]
+pub struct GeneratedSpectrum {
+    pub passing: Vec<Vec<LineInfo>>,
+    pub failing: Vec<Vec<LineInfo>>,
+    /// Line numbers of the faulty statements, in order
+    pub faults: Vec<u32>,
+}
+
+impl GeneratedSpectrum {
+    pub fn into_spectrum(self) -> Spectrum {
+        Spectrum::new(self.passing, self.failing)
+    }
+
+    /// The tests as gcov-like coverage records of a single source file.
+    pub fn coverage(&self) -> (Vec<SourceCoverage>, Vec<SourceCoverage>) {
+        let records = |tests: &[Vec<LineInfo>]| {
+            tests
+                .iter()
+                .map(|lines| SourceCoverage {
+                    source: Some(SOURCE.to_string()),
+                    lines: lines.clone(),
+                    skipped: Vec::new(),
+                })
+                .collect()
+        };
+        (records(&self.passing), records(&self.failing))
+    }
+}
+
+/// Source file name given to generated coverage.
+pub const SOURCE: &str = "generated.c";
+
+/// SplitMix64, small and good enough for test data; std has no RNG.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+}
+
+/// Synthesize a spectrum. Each test runs each statement independently with
+/// probability `density`; tests that run a fault fail unless `noise` says
+/// otherwise, and the others pass. Tests are drawn until there are enough of
+/// both kinds, which fails if the configuration makes one kind too rare.
+/// The same configuration always gives the same spectrum.
+pub fn generate(config: &GeneratorConfig) -> Result<GeneratedSpectrum, String> {
+    if config.faults == 0 || config.faults > config.statements {
+        return Err(format!(
+            "need between 1 and {} faults, got {}",
+            config.statements, config.faults
+        ));
+    }
+    for (name, p) in [("density", config.density), ("noise", config.noise)] {
+        if !(0.0..=1.0).contains(&p) {
+            return Err(format!("{} must be between 0 and 1, got {}", name, p));
+        }
+    }
+    let mut rng = Rng(config.seed);
+    // pick distinct faulty lines with a partial Fisher-Yates shuffle
+    let mut lines: Vec<u32> = (1..=config.statements).collect();
+    for i in 0..config.faults as usize {
+        let j = i + (rng.next_u64() % (lines.len() - i) as u64) as usize;
+        lines.swap(i, j);
+    }
+    let mut faults = lines[..config.faults as usize].to_vec();
+    faults.sort_unstable();
+
+    let mut passing = Vec::with_capacity(config.passing);
+    let mut failing = Vec::with_capacity(config.failing);
+    let attempts = 1000 * (config.passing + config.failing).max(1);
+    for _ in 0..attempts {
+        if passing.len() == config.passing && failing.len() == config.failing {
+            break;
+        }
+        let covered: Vec<bool> = (0..config.statements)
+            .map(|_| rng.chance(config.density))
+            .collect();
+        let runs_fault = faults.iter().any(|&line| covered[line as usize - 1]);
+        let fails = runs_fault && !rng.chance(config.noise);
+        let bucket = match fails {
+            true if failing.len() < config.failing => &mut failing,
+            false if passing.len() < config.passing => &mut passing,
+            _ => continue,
+        };
+        bucket.push(
+            covered
+                .iter()
+                .enumerate()
+                .map(|(i, &covered)| LineInfo {
+                    file: 0,
+                    line_number: i as u32 + 1,
+                    statement: format!("stmt_{}();", i + 1),
+                    coverage: match covered {
+                        true => Coverage::Covered,
+                        false => Coverage::NotCovered,
+                    },
+                    execution_count: covered as u64,
+                })
+                .collect(),
+        );
+    }
+    if passing.len() < config.passing || failing.len() < config.failing {
+        return Err(format!(
+            "only drew {} passing and {} failing tests; raise the density or lower the noise",
+            passing.len(),
+            failing.len()
+        ));
+    }
+    Ok(GeneratedSpectrum {
+        passing,
+        failing,
+        faults,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let config = GeneratorConfig {
+            noise: 0.0,
+            ..GeneratorConfig::default()
+        };
+        let generated = generate(&config).unwrap();
+        assert_eq!(generated.passing.len(), 20);
+        assert_eq!(generated.failing.len(), 5);
+        assert_eq!(generated.faults.len(), 1);
+        let fault = generated.faults[0] as usize - 1;
+        // without noise every failing test runs the fault and no passing test does
+        assert!(generated
+            .failing
+            .iter()
+            .all(|test| test[fault].coverage == Coverage::Covered));
+        assert!(generated
+            .passing
+            .iter()
+            .all(|test| test[fault].coverage == Coverage::NotCovered));
+        let again = generate(&config).unwrap();
+        assert_eq!(again.faults, generated.faults);
+
+        let mut spectrum = generated.into_spectrum();
+        spectrum.score(
+            crate::techniques::Technique::Formula,
+            crate::smoothing::Smoothing::None,
+        );
+        crate::sort_by_suspiciousness(&mut spectrum.statements);
+        assert!(spectrum.statements[0].suspiciousness.is_infinite());
+    }
+
+    #[test]
+    fn test_generate_errors() {
+        let config = GeneratorConfig {
+            faults: 0,
+            ..GeneratorConfig::default()
+        };
+        assert!(generate(&config).is_err());
+        let config = GeneratorConfig {
+            density: 0.0,
+            ..GeneratorConfig::default()
+        };
+        assert!(generate(&config).unwrap_err().contains("only drew"));
+    }
+}
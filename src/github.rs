@@ -0,0 +1,233 @@
+use crate::ci::{issues, Severity};
+use crate::json::Json;
+use crate::StatementInfo;
+use std::collections::HashMap;
+
+/// Most annotations GitHub accepts in one Check Run request; more are added
+/// by updating the run.
+pub const ANNOTATIONS_PER_REQUEST: usize = 50;
+
+/// Where and as what a Check Run is published.
+#[derive(Debug, Clone)]
+pub struct CheckRun {
+    /// e.g. `https://api.github.com`
+    pub api_url: String,
+    /// `owner/name`
+    pub repo: String,
+    pub token: String,
+    pub head_sha: String,
+    pub name: String,
+}
+
+/// Line annotations for the first `limit` suspicious statements, which must
+/// be sorted.
+pub fn annotations(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    limit: usize,
+) -> Vec<Json> {
+    issues(statements, sources, limit)
+        .iter()
+        .map(|issue| {
+            let level = match issue.severity {
+                Severity::Critical | Severity::Major => "warning",
+                Severity::Minor | Severity::Info => "notice",
+            };
+            Json::Object(vec![
+                ("path".to_string(), Json::from(issue.path)),
+                (
+                    "start_line".to_string(),
+                    Json::from(issue.statement.line_number),
+                ),
+                (
+                    "end_line".to_string(),
+                    Json::from(issue.statement.line_number),
+                ),
+                ("annotation_level".to_string(), Json::from(level)),
+                (
+                    "title".to_string(),
+                    Json::from(format!("Suspicious statement #{}", issue.rank)),
+                ),
+                ("message".to_string(), Json::from(issue.message())),
+            ])
+        })
+        .collect()
+}
+
+/// The summary shown at the top of the check.
+pub fn summary(statements: &[StatementInfo], sources: &HashMap<u32, String>) -> String {
+    match statements.first() {
+        Some(top) => format!(
+            "Ranked {} statements. The most suspicious is {}:{} (score {:.2}): `{}`",
+            statements.len(),
+            sources
+                .get(&top.file)
+                .map(|s| s.as_str())
+                .unwrap_or("unknown"),
+            top.line_number,
+            top.suspiciousness,
+            top.statement().trim()
+        ),
+        None => "No statements were covered.".to_string(),
+    }
+}
+
+/// The requests that publish `annotations`: the method, the path under the
+/// API URL and the body. The first creates a completed Check Run with up to
+/// [`ANNOTATIONS_PER_REQUEST`] annotations; the rest append to it once its
+/// id is known, so their paths end in `{id}`.
+pub fn requests(
+    run: &CheckRun,
+    summary: &str,
+    annotations: &[Json],
+) -> Vec<(&'static str, String, Json)> {
+    let output = |batch: &[Json]| {
+        Json::Object(vec![
+            (
+                "title".to_string(),
+                Json::from(format!("{} suspicious statements", annotations.len())),
+            ),
+            ("summary".to_string(), Json::from(summary)),
+            ("annotations".to_string(), Json::Array(batch.to_vec())),
+        ])
+    };
+    let mut batches = annotations.chunks(ANNOTATIONS_PER_REQUEST);
+    let first = batches.next().unwrap_or(&[]);
+    let mut requests = vec![(
+        "POST",
+        format!("/repos/{}/check-runs", run.repo),
+        Json::Object(vec![
+            ("name".to_string(), Json::from(run.name.as_str())),
+            ("head_sha".to_string(), Json::from(run.head_sha.as_str())),
+            ("status".to_string(), Json::from("completed")),
+            // the ranking points at code to look at, it doesn't pass or fail
+            ("conclusion".to_string(), Json::from("neutral")),
+            ("output".to_string(), output(first)),
+        ]),
+    )];
+    for batch in batches {
+        requests.push((
+            "PATCH",
+            format!("/repos/{}/check-runs/{{id}}", run.repo),
+            Json::Object(vec![("output".to_string(), output(batch))]),
+        ));
+    }
+    requests
+}
+
+/// Create the Check Run through the GitHub REST API and return its URL.
+pub fn publish(run: &CheckRun, summary: &str, annotations: &[Json]) -> Result<String, String> {
+    let headers = [
+        format!("Authorization: Bearer {}", run.token),
+        "Accept: application/vnd.github+json".to_string(),
+        "X-GitHub-Api-Version: 2022-11-28".to_string(),
+        format!("User-Agent: fl_dstar/{}", env!("CARGO_PKG_VERSION")),
+    ];
+    let api_url = run.api_url.trim_end_matches('/');
+    let mut id: Option<String> = None;
+    let mut html_url = String::new();
+    for (method, path, body) in requests(run, summary, annotations) {
+        let path = match &id {
+            Some(id) => path.replace("{id}", id),
+            None => path,
+        };
+        let response = crate::http::send_json(
+            method,
+            &format!("{}{}", api_url, path),
+            &headers,
+            &body.to_string(),
+        )
+        .map_err(|e| format!("{} {} failed: {}", method, path, e))?;
+        if id.is_none() {
+            let created = crate::json::parse(&response)
+                .map_err(|e| format!("unexpected response from GitHub: {}", e))?;
+            // ids outgrew 32 bits long ago
+            let number = created
+                .get("id")
+                .and_then(|id| id.as_f64())
+                .ok_or("the created check run has no id")?;
+            id = Some(format!("{}", number as u64));
+            html_url = created
+                .get("html_url")
+                .and_then(|url| url.as_str())
+                .unwrap_or_default()
+                .to_string();
+        }
+    }
+    Ok(html_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked(count: u32) -> (Vec<StatementInfo>, HashMap<u32, String>) {
+        let statements = (1..=count)
+            .map(|line| {
+                let mut s = StatementInfo::new(line, format!("s{}();", line), 1);
+                s.file = (line == 1) as u32;
+                s.suspiciousness = 1.0 / line as f32;
+                s
+            })
+            .collect();
+        (statements, HashMap::from([(1, "src/t.c".to_string())]))
+    }
+
+    fn run() -> CheckRun {
+        CheckRun {
+            api_url: "https://api.github.com".to_string(),
+            repo: "org/repo".to_string(),
+            token: "secret".to_string(),
+            head_sha: "abc123".to_string(),
+            name: "fl_dstar".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_annotations() {
+        let (statements, sources) = ranked(3);
+        let annotations = annotations(&statements, &sources, 10);
+        assert_eq!(annotations.len(), 3);
+        assert_eq!(
+            annotations[0].get("path").unwrap().as_str(),
+            Some("src/t.c")
+        );
+        assert_eq!(
+            annotations[0].get("annotation_level").unwrap().as_str(),
+            Some("warning")
+        );
+        assert_eq!(
+            annotations[2].get("annotation_level").unwrap().as_str(),
+            Some("notice")
+        );
+        assert_eq!(annotations[1].get("start_line").unwrap().as_u32(), Some(2));
+        assert!(summary(&statements, &sources).contains("src/t.c:1 (score 1.00)"));
+    }
+
+    #[test]
+    fn test_requests() {
+        let (statements, sources) = ranked(120);
+        let annotations = annotations(&statements, &sources, 120);
+        let requests = requests(&run(), "summary", &annotations);
+        let methods: Vec<&str> = requests.iter().map(|(method, _, _)| *method).collect();
+        assert_eq!(methods, ["POST", "PATCH", "PATCH"]);
+        assert_eq!(requests[0].1, "/repos/org/repo/check-runs");
+        assert_eq!(requests[2].1, "/repos/org/repo/check-runs/{id}");
+        let body = &requests[0].2;
+        assert_eq!(body.get("head_sha").unwrap().as_str(), Some("abc123"));
+        let batch = |i: usize| {
+            requests[i]
+                .2
+                .get("output")
+                .unwrap()
+                .get("annotations")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len()
+        };
+        assert_eq!((batch(0), batch(1), batch(2)), (50, 50, 20));
+        let empty = super::requests(&run(), "summary", &[]);
+        assert_eq!(empty.len(), 1);
+    }
+}
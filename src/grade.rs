@@ -0,0 +1,246 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+/// One row of a ranking CSV, as written by the default command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankedRow {
+    pub file: Option<String>,
+    pub line_number: u32,
+    pub rank: usize,
+    pub suspiciousness: f32,
+}
+
+impl RankedRow {
+    fn key(&self) -> (Option<&str>, u32) {
+        (self.file.as_deref(), self.line_number)
+    }
+}
+
+/// Read a ranking CSV. It needs `line_number` and `suspiciousness` columns;
+/// `file` and `rank` are used when present, otherwise rows are ranked in the
+/// order they appear.
+pub fn read_ranking<R: Read>(reader: R) -> Result<Vec<RankedRow>, String> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let column = |name: &str| headers.iter().position(|h| h.trim() == name);
+    let line_column = column("line_number").ok_or("missing line_number column")?;
+    let score_column = column("suspiciousness").ok_or("missing suspiciousness column")?;
+    let (file_column, rank_column) = (column("file"), column("rank"));
+    let mut rows = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| e.to_string())?;
+        let field = |column: usize| record.get(column).unwrap_or("").trim();
+        let error =
+            |what: &str, value: &str| format!("row {}: invalid {} '{}'", i + 1, what, value);
+        let line = field(line_column);
+        let score = field(score_column);
+        let rank = match rank_column {
+            Some(column) => field(column)
+                .parse()
+                .map_err(|_| error("rank", field(column)))?,
+            None => i + 1,
+        };
+        rows.push(RankedRow {
+            file: file_column
+                .map(|column| field(column).to_string())
+                .filter(|file| !file.is_empty()),
+            line_number: line.parse().map_err(|_| error("line number", line))?,
+            rank,
+            suspiciousness: score.parse().map_err(|_| error("score", score))?,
+        });
+    }
+    Ok(rows)
+}
+
+/// How far a submission may stray from the reference and still get credit.
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    /// Largest accepted difference between scores
+    pub epsilon: f32,
+    /// Largest accepted difference between ranks
+    pub rank: usize,
+    /// Size of the head of the ranking compared by the top-k criterion
+    pub top: usize,
+}
+
+/// One part of a grade, with a score from 0 to 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Criterion {
+    pub name: &'static str,
+    pub score: f64,
+    pub details: String,
+}
+
+/// Up to five line numbers, for pointing a student at their mistakes.
+fn sample(lines: &[u32]) -> String {
+    let mut shown: Vec<String> = lines.iter().take(5).map(|l| l.to_string()).collect();
+    if lines.len() > 5 {
+        shown.push(format!("and {} more", lines.len() - 5));
+    }
+    shown.join(", ")
+}
+
+fn ratio(count: usize, total: usize) -> f64 {
+    match total {
+        0 => 1.0,
+        _ => count as f64 / total as f64,
+    }
+}
+
+/// Grade `submission` against `reference`. The criteria are, each out of 1:
+///
+/// - `statements`: the same statements were ranked (matched over all the
+///   statements in either ranking)
+/// - `scores`: reference statements whose submitted score is within
+///   `epsilon`
+/// - `ranks`: reference statements whose submitted rank is within the rank
+///   tolerance of the ranks the statement shares with its ties
+/// - `top_k`: overlap of the `top` most suspicious statements
+///
+/// followed by `total`, their mean.
+pub fn grade(
+    submission: &[RankedRow],
+    reference: &[RankedRow],
+    tolerance: Tolerance,
+) -> Vec<Criterion> {
+    let submitted: HashMap<_, &RankedRow> = submission.iter().map(|r| (r.key(), r)).collect();
+    let expected: HashSet<_> = reference.iter().map(|r| r.key()).collect();
+    // tied statements may come in any order, so any rank within the tie is right
+    let mut ties: HashMap<u32, (usize, usize)> = HashMap::new();
+    for row in reference {
+        let tie = ties
+            .entry(row.suspiciousness.to_bits())
+            .or_insert((row.rank, row.rank));
+        *tie = (tie.0.min(row.rank), tie.1.max(row.rank));
+    }
+    let mut missing = Vec::new();
+    let mut wrong_scores = Vec::new();
+    let mut wrong_ranks = Vec::new();
+    for row in reference {
+        let Some(answer) = submitted.get(&row.key()) else {
+            missing.push(row.line_number);
+            continue;
+        };
+        let close = answer.suspiciousness == row.suspiciousness
+            || (answer.suspiciousness - row.suspiciousness).abs() <= tolerance.epsilon;
+        if !close {
+            wrong_scores.push(row.line_number);
+        }
+        let (first, last) = ties[&row.suspiciousness.to_bits()];
+        if answer.rank + tolerance.rank < first || answer.rank > last + tolerance.rank {
+            wrong_ranks.push(row.line_number);
+        }
+    }
+    let extra: Vec<u32> = submission
+        .iter()
+        .filter(|r| !expected.contains(&r.key()))
+        .map(|r| r.line_number)
+        .collect();
+    let matched = reference.len() - missing.len();
+
+    let top = tolerance.top.min(reference.len());
+    let head = |rows: &[RankedRow]| -> HashSet<(Option<String>, u32)> {
+        let mut rows: Vec<&RankedRow> = rows.iter().collect();
+        rows.sort_by_key(|r| r.rank);
+        rows.iter()
+            .take(top)
+            .map(|r| (r.file.clone(), r.line_number))
+            .collect()
+    };
+    let shared_top = head(reference).intersection(&head(submission)).count();
+
+    let mut statement_details = Vec::new();
+    if !missing.is_empty() {
+        statement_details.push(format!("missing lines {}", sample(&missing)));
+    }
+    if !extra.is_empty() {
+        statement_details.push(format!("unexpected lines {}", sample(&extra)));
+    }
+    let with_problems = |kind: &str, lines: &[u32]| match lines.is_empty() {
+        true => format!("all {} within tolerance", kind),
+        false => format!("{} off at lines {}", kind, sample(lines)),
+    };
+    let mut criteria = vec![
+        Criterion {
+            name: "statements",
+            score: ratio(matched, reference.len() + extra.len()),
+            details: match statement_details.is_empty() {
+                true => "all statements ranked".to_string(),
+                false => statement_details.join("; "),
+            },
+        },
+        Criterion {
+            name: "scores",
+            score: ratio(matched - wrong_scores.len(), reference.len()),
+            details: with_problems("scores", &wrong_scores),
+        },
+        Criterion {
+            name: "ranks",
+            score: ratio(matched - wrong_ranks.len(), reference.len()),
+            details: with_problems("ranks", &wrong_ranks),
+        },
+        Criterion {
+            name: "top_k",
+            score: ratio(shared_top, top),
+            details: format!("{} of the top {} statements", shared_top, top),
+        },
+    ];
+    let total = criteria.iter().map(|c| c.score).sum::<f64>() / criteria.len() as f64;
+    criteria.push(Criterion {
+        name: "total",
+        score: total,
+        details: "mean of the criteria above".to_string(),
+    });
+    criteria
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFERENCE: &str = "\
+line_number,statement,failed_tests,passed_tests,total_failed,suspiciousness
+4,b();,1,0,1,inf
+2,int a = 1;,1,2,1,0.50
+3,if (a) {,1,2,1,0.50
+5,c();,0,1,1,0.00
+";
+
+    fn tolerance() -> Tolerance {
+        Tolerance {
+            epsilon: 0.01,
+            rank: 0,
+            top: 2,
+        }
+    }
+
+    #[test]
+    fn test_identical_rankings_get_full_marks() {
+        let reference = read_ranking(REFERENCE.as_bytes()).unwrap();
+        assert_eq!(reference[0].suspiciousness, f32::INFINITY);
+        assert_eq!(reference[3].rank, 4);
+        let criteria = grade(&reference, &reference, tolerance());
+        assert!(criteria.iter().all(|c| c.score == 1.0), "{:?}", criteria);
+    }
+
+    #[test]
+    fn test_grade_breakdown() {
+        let reference = read_ranking(REFERENCE.as_bytes()).unwrap();
+        let submission = read_ranking(
+            "rank,line_number,suspiciousness\n1,2,0.505\n2,4,inf\n3,3,0.4\n4,9,0\n".as_bytes(),
+        )
+        .unwrap();
+        let criteria = grade(&submission, &reference, tolerance());
+        let score = |name: &str| criteria.iter().find(|c| c.name == name).unwrap().score;
+        // 3 of the 5 statements in either ranking match
+        assert_eq!(score("statements"), 0.6);
+        assert_eq!(score("scores"), 0.5);
+        // line 3 may take rank 2 or 3, being tied with line 2
+        assert_eq!(score("ranks"), 0.25);
+        assert_eq!(score("top_k"), 1.0);
+        assert_eq!(criteria[0].details, "missing lines 5; unexpected lines 9");
+        assert_eq!(criteria[1].details, "scores off at lines 3");
+        assert!((score("total") - 0.5875).abs() < 1e-9);
+        assert!(read_ranking("rank,score\n1,0.5\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The name of a test stored at `path`: its file name up to the first dot,
+/// so `test_parser_empty.c.gcov` is `test_parser_empty`.
+pub fn test_name(path: &Path) -> String {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    file_name.split('.').next().unwrap_or("").to_string()
+}
+
+/// The group of a test: the first part of its name, split at `_`, `-` or
+/// `::`, after any leading `test` or `tests` part. `test_parser_empty` and
+/// `parser-utf8` are both in `parser`.
+pub fn group_of(name: &str) -> &str {
+    name.split("::")
+        .flat_map(|part| part.split(['_', '-']))
+        .find(|part| {
+            !part.is_empty()
+                && !part.eq_ignore_ascii_case("test")
+                && !part.eq_ignore_ascii_case("tests")
+        })
+        .unwrap_or(name)
+}
+
+/// Whether the test stored at `path` passed, judged by which of two globs
+/// its file name matches (see [`crate::paths::glob_match`]), for harnesses
+/// that write every test into one directory. `None` when it matches
+/// neither, and an error when it matches both.
+pub fn outcome(path: &Path, passing: &str, failing: &str) -> Result<Option<bool>, String> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let matches = |pattern| crate::paths::glob_match(pattern, &name);
+    match (matches(passing), matches(failing)) {
+        (true, true) => Err(format!(
+            "{} matches both '{}' and '{}'",
+            path.display(),
+            passing,
+            failing
+        )),
+        (true, false) => Ok(Some(true)),
+        (false, true) => Ok(Some(false)),
+        (false, false) => Ok(None),
+    }
+}
+
+/// How the tests of one group fared.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupStats {
+    pub group: String,
+    pub tests: usize,
+    pub failing: usize,
+    /// Share of the group's tests that fail, from 0 to 1
+    #[serde(serialize_with = "crate::round_serialize")]
+    pub failing_rate: f32,
+}
+
+/// Failing rates per group, given the group of each passing and each
+/// failing test, highest rate first, then most failing tests first.
+pub fn stats<'a>(
+    passing: impl IntoIterator<Item = &'a str>,
+    failing: impl IntoIterator<Item = &'a str>,
+) -> Vec<GroupStats> {
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for group in passing {
+        counts.entry(group).or_default().0 += 1;
+    }
+    for group in failing {
+        counts.entry(group).or_default().1 += 1;
+    }
+    let mut stats: Vec<GroupStats> = counts
+        .into_iter()
+        .map(|(group, (passing, failing))| GroupStats {
+            group: group.to_string(),
+            tests: passing + failing,
+            failing,
+            failing_rate: failing as f32 / (passing + failing) as f32,
+        })
+        .collect();
+    stats.sort_by(|a, b| {
+        b.failing_rate
+            .total_cmp(&a.failing_rate)
+            .then(b.failing.cmp(&a.failing))
+    });
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_of() {
+        assert_eq!(
+            test_name(Path::new("out/test_parser_empty.c.gcov")),
+            "test_parser_empty"
+        );
+        assert_eq!(group_of("test_parser_empty"), "parser");
+        assert_eq!(group_of("parser-utf8"), "parser");
+        assert_eq!(group_of("net::http::timeout"), "net");
+        assert_eq!(group_of("Tests_lexer"), "lexer");
+        assert_eq!(group_of("test"), "test");
+    }
+
+    #[test]
+    fn test_outcome() {
+        let outcome = |name| outcome(Path::new(name), "pass_*.gcov", "fail_*.gcov");
+        assert_eq!(outcome("out/pass_1.gcov"), Ok(Some(true)));
+        assert_eq!(outcome("fail_parser.gcov"), Ok(Some(false)));
+        assert_eq!(outcome("pass_1.gcda"), Ok(None));
+        assert!(super::outcome(Path::new("t.gcov"), "*.gcov", "t*").is_err());
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = stats(
+            ["test_parser_a", "test_parser_b", "test_lexer_a"].map(group_of),
+            ["test_parser_c", "test_net_a"].map(group_of),
+        );
+        let rows: Vec<(&str, usize, usize)> = stats
+            .iter()
+            .map(|s| (s.group.as_str(), s.tests, s.failing))
+            .collect();
+        assert_eq!(rows, [("net", 1, 1), ("parser", 3, 1), ("lexer", 1, 0)]);
+        assert!((stats[1].failing_rate - 1.0 / 3.0).abs() < 1e-6);
+    }
+}
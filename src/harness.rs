@@ -0,0 +1,96 @@
+use crate::formats::SourceCoverage;
+use crate::paths::glob_match;
+
+/// Statement prefixes that mark assertion macros and test registrations.
+const HARNESS_STATEMENTS: &[&str] = &[
+    "assert(",
+    "assert (",
+    "static_assert",
+    "ASSERT_",
+    "EXPECT_",
+    "CHECK(",
+    "REQUIRE(",
+    "CU_ASSERT",
+    "ck_assert",
+    "TEST(",
+    "TEST_F(",
+    "TEST_P(",
+];
+
+/// Files that hold test fixtures or the program's driver rather than the code under test.
+const HARNESS_FILES: &[&str] = &[
+    "main.c",
+    "main.cc",
+    "main.cpp",
+    "*_test.*",
+    "*_tests.*",
+    "test_*.*",
+    "*_driver.*",
+    "driver.*",
+    "test/**",
+    "tests/**",
+];
+
+/// Heuristic for recognizing test-harness code, which otherwise tends to sit
+/// at the top of a ranking because every failing test runs it.
+#[derive(Debug, Clone)]
+pub struct HarnessFilter {
+    pub statement_prefixes: Vec<String>,
+    pub file_patterns: Vec<String>,
+}
+
+impl Default for HarnessFilter {
+    fn default() -> Self {
+        HarnessFilter {
+            statement_prefixes: HARNESS_STATEMENTS.iter().map(|s| s.to_string()).collect(),
+            file_patterns: HARNESS_FILES.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+impl HarnessFilter {
+    pub fn is_harness_statement(&self, statement: &str) -> bool {
+        let statement = statement.trim_start();
+        self.statement_prefixes
+            .iter()
+            .any(|prefix| statement.starts_with(prefix.as_str()))
+    }
+
+    pub fn is_harness_file(&self, path: &str) -> bool {
+        self.file_patterns
+            .iter()
+            .any(|pattern| glob_match(pattern, path))
+    }
+
+    /// Drop harness files and harness statements from the coverage.
+    pub fn apply(&self, coverage: &mut Vec<SourceCoverage>) {
+        coverage.retain(|record| {
+            !record
+                .source
+                .as_deref()
+                .is_some_and(|source| self.is_harness_file(source))
+        });
+        for record in coverage {
+            record
+                .lines
+                .retain(|line| !self.is_harness_statement(&line.statement));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_harness_filter() {
+        let filter = HarnessFilter::default();
+        assert!(filter.is_harness_statement("    assert(x == 1);"));
+        assert!(filter.is_harness_statement("EXPECT_EQ(a, b);"));
+        assert!(!filter.is_harness_statement("my_assert_helper(x);"));
+        assert!(filter.is_harness_file("tests/fixtures.c"));
+        assert!(filter.is_harness_file("src/lexer_test.c"));
+        assert!(filter.is_harness_file("src/main.c"));
+        assert!(!filter.is_harness_file("src/tcas.c"));
+    }
+}
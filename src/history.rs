@@ -0,0 +1,193 @@
+use crate::json::{self, Json};
+use crate::StatementInfo;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Statements from the top of a ranking kept as its signature.
+pub const SIGNATURE_SIZE: usize = 20;
+
+/// Similarity from which a past bug is worth pointing out.
+pub const MATCH_THRESHOLD: f64 = 0.5;
+
+/// A bug localized in the past, kept so a similar ranking can point to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PastBug {
+    /// Commit that fixed the bug
+    pub commit: String,
+    /// Where the fault was, as path:line
+    pub faults: Vec<String>,
+    /// Day the bug was recorded, as YYYY-MM-DD
+    pub recorded: String,
+    /// Hashes of the most suspicious statements, most suspicious first
+    pub signature: Vec<String>,
+}
+
+impl PastBug {
+    fn to_json(&self) -> Json {
+        let strings =
+            |items: &[String]| Json::Array(items.iter().map(|s| Json::from(s.as_str())).collect());
+        Json::Object(vec![
+            ("commit".to_string(), Json::from(self.commit.as_str())),
+            ("faults".to_string(), strings(&self.faults)),
+            ("recorded".to_string(), Json::from(self.recorded.as_str())),
+            ("signature".to_string(), strings(&self.signature)),
+        ])
+    }
+
+    fn from_json(value: &Json) -> Result<PastBug, String> {
+        let string = |key: &str| {
+            value
+                .get(key)
+                .and_then(Json::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| format!("missing {}", key))
+        };
+        let strings = |key: &str| -> Result<Vec<String>, String> {
+            value
+                .get(key)
+                .and_then(Json::as_array)
+                .ok_or_else(|| format!("missing {}", key))?
+                .iter()
+                .map(|item| {
+                    item.as_str()
+                        .map(str::to_string)
+                        .ok_or_else(|| format!("invalid {}", key))
+                })
+                .collect()
+        };
+        Ok(PastBug {
+            commit: string("commit")?,
+            faults: strings("faults")?,
+            recorded: string("recorded")?,
+            signature: strings("signature")?,
+        })
+    }
+}
+
+/// The signature of a ranking: the hashes of its first statements, so it
+/// survives line numbers shifting between versions. `statements` must be
+/// in rank order.
+pub fn signature(statements: &[StatementInfo]) -> Vec<String> {
+    statements
+        .iter()
+        .take(SIGNATURE_SIZE)
+        .map(StatementInfo::hash)
+        .collect()
+}
+
+/// How alike two signatures are, from 0 to 1: the weighted Jaccard index of
+/// their statements, each weighing the reciprocal of its rank, so agreement
+/// at the top counts most.
+pub fn similarity(a: &[String], b: &[String]) -> f64 {
+    fn weights(signature: &[String]) -> HashMap<&str, f64> {
+        let mut weights = HashMap::new();
+        for (i, hash) in signature.iter().enumerate() {
+            // a repeated statement keeps the weight of its best rank
+            weights.entry(hash.as_str()).or_insert(1.0 / (i + 1) as f64);
+        }
+        weights
+    }
+    let (a, b) = (weights(a), weights(b));
+    let (mut shared, mut total) = (0.0, 0.0);
+    for (hash, &x) in &a {
+        let y = b.get(hash).copied().unwrap_or(0.0);
+        shared += x.min(y);
+        total += x.max(y);
+    }
+    total += b
+        .iter()
+        .filter(|(hash, _)| !a.contains_key(*hash))
+        .map(|(_, y)| y)
+        .sum::<f64>();
+    match total > 0.0 {
+        true => shared / total,
+        false => 0.0,
+    }
+}
+
+/// Past bugs whose signature is at least `threshold` similar to `signature`,
+/// most similar first.
+pub fn matches<'a>(
+    bugs: &'a [PastBug],
+    signature: &[String],
+    threshold: f64,
+) -> Vec<(&'a PastBug, f64)> {
+    let mut found: Vec<(&PastBug, f64)> = bugs
+        .iter()
+        .map(|bug| (bug, similarity(&bug.signature, signature)))
+        .filter(|(_, similarity)| *similarity >= threshold)
+        .collect();
+    found.sort_by(|a, b| b.1.total_cmp(&a.1));
+    found
+}
+
+pub fn parse(text: &str) -> Result<Vec<PastBug>, String> {
+    json::parse(text)
+        .map_err(|e| e.to_string())?
+        .get("bugs")
+        .and_then(Json::as_array)
+        .ok_or("missing bugs")?
+        .iter()
+        .enumerate()
+        .map(|(i, bug)| PastBug::from_json(bug).map_err(|e| format!("bug {}: {}", i + 1, e)))
+        .collect()
+}
+
+pub fn to_string(bugs: &[PastBug]) -> String {
+    let index = Json::Object(vec![
+        ("version".to_string(), Json::from(1)),
+        (
+            "bugs".to_string(),
+            Json::Array(bugs.iter().map(PastBug::to_json).collect()),
+        ),
+    ]);
+    format!("{}\n", index)
+}
+
+/// Read an index of past bugs. A missing file is an empty index.
+pub fn load(path: &Path) -> Result<Vec<PastBug>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let text = crate::encoding::read_to_string(path).map_err(|e| e.to_string())?;
+    parse(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashes(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_similarity() {
+        let a = hashes(&["x", "y", "z"]);
+        assert_eq!(similarity(&a, &a), 1.0);
+        assert_eq!(similarity(&a, &hashes(&["p", "q"])), 0.0);
+        // agreeing on the first statement beats agreeing on the last
+        let top = similarity(&a, &hashes(&["x", "p", "q"]));
+        let bottom = similarity(&a, &hashes(&["p", "q", "z"]));
+        assert!(top > bottom);
+        assert_eq!(similarity(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn test_round_trip_and_matches() {
+        let bug = PastBug {
+            commit: "abc123".to_string(),
+            faults: vec!["src/a.c:4".to_string()],
+            recorded: "2026-10-16".to_string(),
+            signature: hashes(&["x", "y"]),
+        };
+        let bugs = parse(&to_string(std::slice::from_ref(&bug))).unwrap();
+        assert_eq!(bugs, [bug]);
+        let found = matches(&bugs, &hashes(&["x", "y", "w"]), 0.5);
+        assert_eq!(found.len(), 1);
+        assert!(matches(&bugs, &hashes(&["w"]), 0.5).is_empty());
+        assert!(parse("{\"bugs\": [{\"commit\": \"a\"}]}")
+            .unwrap_err()
+            .starts_with("bug 1:"));
+    }
+}
@@ -0,0 +1,109 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Send an HTTP request with a JSON body through curl, which must be on the
+/// `PATH` (or named by the `CURL` environment variable), and return the
+/// response body. Responses with an error status are errors.
+///
+/// Headers are handed to curl on stdin rather than the command line, so
+/// tokens in them don't show up in the process list. The body goes through
+/// a temporary file only its owner can read (see [`body_file`]).
+pub fn send_json(
+    method: &str,
+    url: &str,
+    headers: &[String],
+    body: &str,
+) -> std::io::Result<String> {
+    let body_file = body_file(body)?;
+    let program = std::env::var("CURL").unwrap_or_else(|_| "curl".to_string());
+    let result = (|| {
+        let mut child = Command::new(program)
+            .args(["--silent", "--show-error", "--fail", "--request", method])
+            .args(["--header", "Content-Type: application/json"])
+            .args(["--header", "@-"])
+            .arg("--data-binary")
+            .arg(format!("@{}", body_file.display()))
+            .arg(url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        {
+            let mut stdin = child.stdin.take().unwrap();
+            for header in headers {
+                writeln!(stdin, "{}", header)?;
+            }
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(std::io::Error::other(format!(
+                "curl exited with {}",
+                output.status
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    })();
+    let _ = std::fs::remove_file(&body_file);
+    result
+}
+
+/// Write `body` to a new file in the temporary directory. The file is
+/// created afresh rather than opened, so a file or symlink planted under its
+/// name fails the attempt instead of being written through, and on Unix it
+/// is readable by its owner alone.
+fn body_file(body: &str) -> std::io::Result<std::path::PathBuf> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    // names taken by someone else are skipped
+    for _ in 0..100 {
+        let path = std::env::temp_dir().join(format!(
+            "fl_dstar-request-{}-{}.json",
+            std::process::id(),
+            REQUESTS.fetch_add(1, Ordering::Relaxed)
+        ));
+        match options.open(&path) {
+            Ok(mut file) => {
+                file.write_all(body.as_bytes())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        "could not create a file for the request body",
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_body_file() {
+        // plant a symlink under the next name the counter would give
+        let next = REQUESTS.load(Ordering::Relaxed);
+        let planted = std::env::temp_dir().join(format!(
+            "fl_dstar-request-{}-{}.json",
+            std::process::id(),
+            next
+        ));
+        let target = std::env::temp_dir().join(format!("fl_dstar-target-{}", std::process::id()));
+        std::os::unix::fs::symlink(&target, &planted).unwrap();
+
+        let path = body_file("{}").unwrap();
+        assert_ne!(path, planted);
+        assert!(!target.exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&planted).unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+use crate::StatementInfo;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// A run of suspicious lines close enough together to be read as one block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    pub file: Option<String>,
+    /// Id of the file, see [`crate::SourceKeys`]
+    pub file_id: u32,
+    pub first_line: u32,
+    pub last_line: u32,
+    /// Suspicious statements in the hunk
+    pub statements: usize,
+    /// Score of the most suspicious statement, which the hunk is ranked by
+    pub max_suspiciousness: f32,
+    pub mean_suspiciousness: f32,
+}
+
+/// Merge the suspicious statements (those scoring above zero) of each file
+/// into hunks, joining two statements when at most `gap` lines separate
+/// them. Hunks are returned most suspicious first, ties broken by their mean
+/// score and then by location.
+pub fn group(statements: &[StatementInfo], sources: &HashMap<u32, String>, gap: u32) -> Vec<Hunk> {
+    let mut suspicious: Vec<(Option<&String>, u32, u32, f32)> = statements
+        .iter()
+        .filter(|s| s.suspiciousness > 0.0)
+        .map(|s| {
+            let name = sources.get(&s.file);
+            (name, s.file, s.line_number, s.suspiciousness)
+        })
+        .collect();
+    suspicious.sort_by(|a, b| (a.0, a.1, a.2).cmp(&(b.0, b.1, b.2)));
+
+    let mut hunks: Vec<(Hunk, f64)> = Vec::new();
+    for (file, file_id, line, score) in suspicious {
+        let file = file.cloned();
+        match hunks.last_mut() {
+            Some((hunk, total))
+                if hunk.file_id == file_id && line - hunk.last_line <= gap.saturating_add(1) =>
+            {
+                hunk.last_line = line;
+                hunk.statements += 1;
+                hunk.max_suspiciousness = hunk.max_suspiciousness.max(score);
+                *total += score as f64;
+            }
+            _ => hunks.push((
+                Hunk {
+                    file,
+                    file_id,
+                    first_line: line,
+                    last_line: line,
+                    statements: 1,
+                    max_suspiciousness: score,
+                    mean_suspiciousness: score,
+                },
+                score as f64,
+            )),
+        }
+    }
+    let mut hunks: Vec<Hunk> = hunks
+        .into_iter()
+        .map(|(mut hunk, total)| {
+            // an infinite score makes the total infinite, which is the right mean
+            hunk.mean_suspiciousness = (total / hunk.statements as f64) as f32;
+            hunk
+        })
+        .collect();
+    hunks.sort_by(|a, b| {
+        b.max_suspiciousness
+            .total_cmp(&a.max_suspiciousness)
+            .then(b.mean_suspiciousness.total_cmp(&a.mean_suspiciousness))
+            .then_with(|| {
+                (&a.file, a.file_id, a.first_line).cmp(&(&b.file, b.file_id, b.first_line))
+            })
+    });
+    hunks
+}
+
+/// Write hunks as CSV, in the order given.
+pub fn write_hunks<W: Write>(writer: W, hunks: &[Hunk]) -> csv::Result<()> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    wtr.write_record([
+        "rank",
+        "file",
+        "first_line",
+        "last_line",
+        "statements",
+        "max_suspiciousness",
+        "mean_suspiciousness",
+    ])?;
+    for (i, hunk) in hunks.iter().enumerate() {
+        wtr.write_record([
+            (i + 1).to_string(),
+            hunk.file.clone().unwrap_or_default(),
+            hunk.first_line.to_string(),
+            hunk.last_line.to_string(),
+            hunk.statements.to_string(),
+            format!("{:.2}", hunk.max_suspiciousness),
+            format!("{:.2}", hunk.mean_suspiciousness),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group() {
+        let statements: Vec<StatementInfo> =
+            [(1, 0.5), (2, 0.0), (3, 2.0), (5, 1.0), (9, 3.0), (10, 0.5)]
+                .iter()
+                .map(|&(line, score)| {
+                    let mut statement = StatementInfo::new(line, String::new(), 1);
+                    statement.file = 1 + (line == 10) as u32;
+                    statement.suspiciousness = score;
+                    statement
+                })
+                .collect();
+        let sources = HashMap::from([(1, "a.c".to_string()), (2, "b.c".to_string())]);
+        let hunks = group(&statements, &sources, 1);
+        let ranges: Vec<(u32, u32)> = hunks.iter().map(|h| (h.first_line, h.last_line)).collect();
+        assert_eq!(ranges, [(9, 9), (1, 5), (10, 10)]);
+        assert_eq!(hunks[1].statements, 3);
+        assert_eq!(hunks[1].max_suspiciousness, 2.0);
+        assert!((hunks[1].mean_suspiciousness - 3.5 / 3.0).abs() < 1e-6);
+        assert_eq!(group(&statements, &sources, 0).len(), 5);
+
+        let mut out = vec![];
+        write_hunks(&mut out, &hunks[..1]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "rank,file,first_line,last_line,statements,max_suspiciousness,mean_suspiciousness\n\
+             1,a.c,9,9,1,3.00,3.00\n"
+        );
+    }
+}
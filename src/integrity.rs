@@ -0,0 +1,116 @@
+use crate::redact::hash_statement;
+use crate::LineInfo;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The hash a statement is identified by in reports. Redacted statements
+/// already are their hash.
+pub fn statement_hash(statement: &str) -> String {
+    match is_hash(statement) {
+        true => statement.to_string(),
+        false => hash_statement(statement),
+    }
+}
+
+fn is_hash(statement: &str) -> bool {
+    statement.len() == 17
+        && statement.starts_with('#')
+        && statement[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// A line whose source no longer matches the coverage it was ranked from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drift {
+    pub path: String,
+    pub line_number: u32,
+    /// The line as it is now, or `None` if the file has become shorter
+    pub found: Option<String>,
+}
+
+impl std::fmt::Display for Drift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.found {
+            Some(found) => write!(
+                f,
+                "{}:{} is now '{}'",
+                self.path,
+                self.line_number,
+                found.trim()
+            ),
+            None => write!(f, "{}:{} no longer exists", self.path, self.line_number),
+        }
+    }
+}
+
+/// Compare `lines` against the source files they came from, as named by
+/// `sources` and resolved against `root`. Files that can't be read are
+/// skipped, as the source needn't be on the machine doing the analysis.
+pub fn check(
+    lines: &[LineInfo],
+    sources: &HashMap<u32, String>,
+    root: Option<&Path>,
+) -> Vec<Drift> {
+    let mut files: HashMap<&str, Option<Vec<String>>> = HashMap::new();
+    let mut drifted = Vec::new();
+    for line in lines {
+        let Some(path) = sources.get(&line.file) else {
+            continue;
+        };
+        let text = files.entry(path).or_insert_with(|| {
+            let path = match root {
+                Some(root) => root.join(path),
+                None => Path::new(path).to_path_buf(),
+            };
+            crate::encoding::read_to_string(&path)
+                .ok()
+                .map(|text| text.lines().map(str::to_string).collect())
+        });
+        let Some(text) = text else {
+            continue;
+        };
+        let found = (line.line_number as usize)
+            .checked_sub(1)
+            .and_then(|i| text.get(i));
+        if found.map(|found| statement_hash(found)) != Some(statement_hash(&line.statement)) {
+            drifted.push(Drift {
+                path: path.clone(),
+                line_number: line.line_number,
+                found: found.cloned(),
+            });
+        }
+    }
+    drifted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Coverage;
+
+    #[test]
+    fn test_check() {
+        let dir = std::env::temp_dir().join(format!("fl_dstar_integrity_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("t.c"), "int a = 1;\n  b(2);\n").unwrap();
+        let line = |line_number: u32, statement: &str| LineInfo {
+            file: 1 + (line_number == 4) as u32,
+            line_number,
+            statement: statement.to_string(),
+            coverage: Coverage::Covered,
+            execution_count: 1,
+        };
+        let lines = [
+            line(1, "  int a = 1;"),
+            line(2, &hash_statement("b(1);")),
+            line(3, "c();"),
+            line(4, "gone();"),
+        ];
+        let sources = HashMap::from([(1, "t.c".to_string()), (2, "missing.c".to_string())]);
+        let drifted = check(&lines, &sources, Some(&dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(drifted.len(), 2);
+        assert_eq!(drifted[0].to_string(), "t.c:2 is now 'b(2);'");
+        assert_eq!(drifted[1].to_string(), "t.c:3 no longer exists");
+        assert_eq!(statement_hash(&lines[1].statement), lines[1].statement);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::StatementInfo;
+use std::collections::{HashMap, HashSet};
+
+/// Words too common in code or bug reports to say anything about a location.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "do", "else", "for", "from", "has",
+    "have", "if", "in", "int", "is", "it", "not", "of", "on", "or", "return", "that", "the",
+    "this", "to", "void", "was", "when", "while", "with",
+];
+
+/// Split text into lowercase terms, breaking identifiers at underscores and
+/// camelCase humps, so `parseHeader` and `parse_header` both give `parse` and
+/// `header`. Stop words and single letters are left out.
+pub fn terms(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let mut current = String::new();
+        let mut previous_lower = false;
+        for c in word.chars() {
+            if c.is_uppercase() && previous_lower {
+                terms.push(std::mem::take(&mut current));
+            }
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+            current.extend(c.to_lowercase());
+        }
+        terms.push(current);
+    }
+    terms.retain(|term| {
+        term.chars().count() > 1
+            && !term.chars().all(|c| c.is_ascii_digit())
+            && !STOP_WORDS.contains(&term.as_str())
+    });
+    terms
+}
+
+/// TF-IDF weighted term vector, with document frequencies from `idf`.
+fn vector(terms: &[String], idf: &HashMap<&str, f64>) -> HashMap<String, f64> {
+    let mut vector = HashMap::new();
+    for term in terms {
+        *vector.entry(term.clone()).or_insert(0.0) += 1.0;
+    }
+    for (term, weight) in vector.iter_mut() {
+        *weight *= idf.get(term.as_str()).copied().unwrap_or(0.0);
+    }
+    vector
+}
+
+fn cosine(a: &HashMap<String, f64>, b: &HashMap<String, f64>) -> f64 {
+    let dot: f64 = a
+        .iter()
+        .filter_map(|(term, x)| b.get(term).map(|y| x * y))
+        .sum();
+    let norm = |v: &HashMap<String, f64>| v.values().map(|x| x * x).sum::<f64>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    dot / denominator
+}
+
+/// How similar each statement's text is to a bug report, from 0 to 1, by
+/// the cosine of their TF-IDF vectors. Each statement is a document, so
+/// terms found all over the code weigh little.
+pub fn similarity(report: &str, statements: &[StatementInfo]) -> Vec<f64> {
+    let documents: Vec<Vec<String>> = statements.iter().map(|s| terms(s.statement())).collect();
+    let mut frequency: HashMap<&str, usize> = HashMap::new();
+    for document in &documents {
+        let unique: HashSet<&str> = document.iter().map(String::as_str).collect();
+        for term in unique {
+            *frequency.entry(term).or_insert(0) += 1;
+        }
+    }
+    let n = documents.len() as f64;
+    let idf: HashMap<&str, f64> = frequency
+        .into_iter()
+        .map(|(term, count)| (term, (1.0 + n / count as f64).ln()))
+        .collect();
+    let query = vector(&terms(report), &idf);
+    documents
+        .iter()
+        .map(|document| cosine(&query, &vector(document, &idf)))
+        .collect()
+}
+
+/// Blend each statement's spectrum score with its similarity to a bug
+/// report: `(1 - weight) * spectrum + weight * similarity`. The spectrum
+/// score is first turned into the fraction of statements it beats or ties
+/// with, which puts every technique on the same 0 to 1 scale and copes with
+/// infinite scores.
+pub fn blend(statements: &mut [StatementInfo], report: &str, weight: f32) {
+    let similarity = similarity(report, statements);
+    let mut scores: Vec<f32> = statements.iter().map(|s| s.suspiciousness).collect();
+    scores.sort_by(|a, b| b.total_cmp(a));
+    let n = statements.len() as f32;
+    for (statement, similarity) in statements.iter_mut().zip(similarity) {
+        let above = scores.partition_point(|&s| s > statement.suspiciousness) as f32;
+        let spectrum = 1.0 - above / n;
+        statement.suspiciousness = (1.0 - weight) * spectrum + weight * similarity as f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms() {
+        assert_eq!(
+            terms("if (parseHeader(buf) && HTTP_version2 == 0) x++;"),
+            ["parse", "header", "buf", "http", "version2"]
+        );
+    }
+
+    #[test]
+    fn test_blend() {
+        let mut statements: Vec<StatementInfo> = [
+            "init_buffer(buf);",
+            "len = header_length(buf);",
+            "log_debug(buf);",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, text)| StatementInfo::new(i as u32 + 1, text.to_string(), 1))
+        .collect();
+        statements[0].suspiciousness = f32::INFINITY;
+        statements[1].suspiciousness = 0.5;
+        statements[2].suspiciousness = 0.5;
+        let report = "Crash when the header length is negative";
+        let similarity = similarity(report, &statements);
+        assert_eq!(similarity[0], 0.0);
+        assert!(similarity[1] > 0.5);
+        blend(&mut statements, report, 0.5);
+        // the tie between lines 2 and 3 is broken by the report
+        assert_eq!(statements[0].suspiciousness, 0.5);
+        assert!(statements[1].suspiciousness > statements[0].suspiciousness);
+        assert!((statements[2].suspiciousness - 1.0 / 3.0).abs() < 1e-6);
+    }
+}
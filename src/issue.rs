@@ -0,0 +1,245 @@
+use crate::StatementInfo;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Lines shown above and below each suspicious line.
+pub const CONTEXT_LINES: u32 = 2;
+
+/// Markup of an issue tracker's descriptions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Markup {
+    /// GitHub-flavored Markdown, also accepted by GitLab and Jira's new editor
+    Github,
+    /// Jira wiki markup
+    Jira,
+}
+
+impl FromStr for Markup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "github" | "markdown" => Ok(Markup::Github),
+            "jira" => Ok(Markup::Jira),
+            _ => Err(format!("unknown markup '{}' (expected github or jira)", s)),
+        }
+    }
+}
+
+impl Markup {
+    fn heading(&self, text: &str) -> String {
+        match self {
+            Markup::Github => format!("## {}", text),
+            Markup::Jira => format!("h2. {}", text),
+        }
+    }
+
+    fn subheading(&self, text: &str) -> String {
+        match self {
+            Markup::Github => format!("### {}", text),
+            Markup::Jira => format!("h3. {}", text),
+        }
+    }
+
+    fn code(&self, text: &str) -> String {
+        match self {
+            Markup::Github => format!("`{}`", text),
+            Markup::Jira => format!("{{{{{}}}}}", text),
+        }
+    }
+
+    fn link(&self, url: &str, text: &str) -> String {
+        match self {
+            Markup::Github => format!("[{}]({})", text, url),
+            Markup::Jira => format!("[{}|{}]", text, url),
+        }
+    }
+
+    fn block(&self, text: &str) -> String {
+        match self {
+            Markup::Github => format!("```\n{}```", text),
+            Markup::Jira => format!("{{noformat}}\n{}{{noformat}}", text),
+        }
+    }
+}
+
+/// What the ranking was computed from, for the parts of an issue that don't
+/// come from the statements.
+pub struct Draft<'a> {
+    pub title: &'a str,
+    pub notes: Option<&'a str>,
+    /// Names of the failing tests, when known
+    pub failing_tests: &'a [String],
+    pub total_failed: usize,
+    pub total_passed: usize,
+    /// How the statements were scored, e.g. the formula and its smoothing
+    pub method: &'a str,
+    /// The command line that produced the ranking
+    pub command: &'a str,
+}
+
+/// A ready-to-file issue body: the failing tests, the `top` most suspicious
+/// statements (which must be sorted) with the lines around them, how they
+/// were scored and how to reproduce the ranking. `lines` gives the text of
+/// each line known from the coverage, for the context; with `repo_url`
+/// (e.g. `https://github.com/org/repo/blob/main`) each location links to
+/// the line.
+pub fn body(
+    draft: &Draft,
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    lines: &HashMap<u64, &str>,
+    top: usize,
+    repo_url: Option<&str>,
+    markup: Markup,
+) -> String {
+    let mut out = String::new();
+    writeln!(out, "{}\n", markup.heading(draft.title)).unwrap();
+    if let Some(notes) = draft.notes {
+        writeln!(out, "{}\n", notes).unwrap();
+    }
+    writeln!(
+        out,
+        "{}\n",
+        markup.subheading(&format!(
+            "Failing tests ({} failing, {} passing)",
+            draft.total_failed, draft.total_passed
+        ))
+    )
+    .unwrap();
+    for test in draft.failing_tests {
+        writeln!(out, "* {}", markup.code(test)).unwrap();
+    }
+    if draft.failing_tests.is_empty() {
+        writeln!(out, "The names of the tests aren't known.").unwrap();
+    }
+    writeln!(
+        out,
+        "\n{}\n",
+        markup.subheading(&format!(
+            "{} most suspicious of {} statements",
+            top.min(statements.len()),
+            statements.len()
+        ))
+    )
+    .unwrap();
+    writeln!(out, "Scored with {}.\n", draft.method).unwrap();
+    for (i, statement) in statements.iter().take(top).enumerate() {
+        let line = statement.line_number;
+        let file = sources.get(&statement.file);
+        let location = match file {
+            Some(file) => format!("{}:{}", file, line),
+            None => format!("line {}", line),
+        };
+        let location = match (repo_url, file) {
+            (Some(base), Some(file)) => markup.link(
+                &format!("{}/{}#L{}", base.trim_end_matches('/'), file, line),
+                &location,
+            ),
+            _ => location,
+        };
+        writeln!(
+            out,
+            "{}. {}: score {:.2}, executed by {} of {} failing and {} of {} passing tests",
+            i + 1,
+            location,
+            statement.suspiciousness,
+            statement.failed_tests,
+            statement.total_failed,
+            statement.passed_tests,
+            statement.total_passed
+        )
+        .unwrap();
+        writeln!(out, "\n{}\n", markup.block(&context(statement, lines))).unwrap();
+    }
+    writeln!(out, "{}\n", markup.subheading("Reproduction")).unwrap();
+    writeln!(
+        out,
+        "* Run the failing tests above with coverage enabled to collect their coverage again."
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "* Rank again with fl_dstar {}: {}",
+        env!("CARGO_PKG_VERSION"),
+        markup.code(draft.command)
+    )
+    .unwrap();
+    out
+}
+
+/// The lines around `statement` that are known, with `>` marking it.
+fn context(statement: &StatementInfo, lines: &HashMap<u64, &str>) -> String {
+    let line = statement.line_number;
+    let first = line.saturating_sub(CONTEXT_LINES).max(1);
+    let width = (line + CONTEXT_LINES).to_string().len();
+    let mut out = String::new();
+    for number in first..=line + CONTEXT_LINES {
+        let key = crate::statement_key(statement.file, number);
+        let text = match (number == line, lines.get(&key)) {
+            (true, _) => statement.statement(),
+            (false, Some(text)) => text,
+            (false, None) => continue,
+        };
+        let marker = if number == line { '>' } else { ' ' };
+        writeln!(out, "{} {:>width$} | {}", marker, number, text.trim_end()).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body() {
+        let mut statement = StatementInfo::new(4, "b();".to_string(), 1);
+        statement.failed_tests = 1;
+        statement.suspiciousness = 2.0;
+        statement.file = 1;
+        let sources = HashMap::from([(1, "src/t.c".to_string())]);
+        let lines: HashMap<u64, &str> = [(2, "int a = 1;"), (3, "if (a)"), (9, "}")]
+            .into_iter()
+            .map(|(line, text)| (crate::statement_key(1, line), text))
+            .collect();
+        let failing = vec!["fail_1".to_string()];
+        let draft = Draft {
+            title: "Crash in b",
+            notes: None,
+            failing_tests: &failing,
+            total_failed: 1,
+            total_passed: 2,
+            method: "D*",
+            command: "fl_dstar pass fail",
+        };
+        let github = body(
+            &draft,
+            &[statement.clone()],
+            &sources,
+            &lines,
+            5,
+            Some("https://example.com/repo/blob/main/"),
+            Markup::Github,
+        );
+        assert!(github.starts_with(
+            "## Crash in b\n\n### Failing tests (1 failing, 2 passing)\n\n* `fail_1`\n"
+        ));
+        assert!(github
+            .contains("1. [src/t.c:4](https://example.com/repo/blob/main/src/t.c#L4): score 2.00"));
+        assert!(github.contains("```\n  2 | int a = 1;\n  3 | if (a)\n> 4 | b();\n```"));
+        let jira = body(
+            &draft,
+            &[statement],
+            &sources,
+            &lines,
+            5,
+            None,
+            Markup::Jira,
+        );
+        assert!(jira.contains("h3. Failing tests"));
+        assert!(jira.contains("* {{fail_1}}"));
+        assert!(jira.contains("1. src/t.c:4: score 2.00"));
+        assert!(jira.contains("{noformat}\n  2 | int a = 1;"));
+    }
+}
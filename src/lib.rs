@@ -1,30 +1,199 @@
+extern crate alloc;
+
+#[cfg(feature = "reports")]
 use serde::{Serialize, Serializer};
+
+// The CSV writers return csv's errors and the report rows implement serde's
+// Serialize; these let callers name both without depending on the same
+// versions themselves.
+#[cfg(feature = "reports")]
+pub use csv;
+#[cfg(feature = "reports")]
+pub use serde;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::BufRead;
 
-#[derive(Debug, PartialEq, PartialOrd)]
+pub mod audit;
+#[cfg(feature = "reports")]
+pub mod bisect;
+#[cfg(feature = "reports")]
+pub mod blame;
+#[cfg(feature = "reports")]
+pub mod budget;
+pub mod ci;
+pub mod columnar;
+#[cfg(feature = "reports")]
+pub mod conditions;
+pub mod demangle;
+pub mod dependencies;
+pub mod diff;
+pub mod embedded;
+pub mod encoding;
+pub mod exclusive;
+pub mod explain;
+pub mod extsort;
+#[cfg(feature = "reports")]
+pub mod features;
+pub mod formats;
+#[cfg(feature = "reports")]
+pub mod functions;
+pub mod generate;
+#[cfg(feature = "integrations")]
+pub mod github;
+#[cfg(feature = "reports")]
+pub mod grade;
+#[cfg(feature = "reports")]
+pub mod groups;
+pub mod harness;
+pub mod history;
+#[cfg(feature = "integrations")]
+pub mod http;
+#[cfg(feature = "reports")]
+pub mod hunks;
+pub mod integrity;
+pub mod ir;
+pub mod issue;
+pub mod json;
+pub mod locale;
+#[cfg(feature = "reports")]
+pub mod manifest;
+pub mod memory;
+pub mod metrics;
+pub mod mhs;
+pub mod model;
+#[cfg(feature = "integrations")]
+pub mod notify;
+#[cfg(feature = "reports")]
+pub mod output;
+#[cfg(feature = "reports")]
+pub mod owners;
+pub mod paths;
+#[cfg(feature = "reports")]
+pub mod platforms;
+pub mod plot;
+pub mod prelude;
+pub mod provenance;
+#[cfg(feature = "reports")]
+pub mod query;
+pub mod redact;
+#[cfg(feature = "reports")]
+pub mod report;
+#[cfg(feature = "reports")]
+pub mod rpc;
+pub mod sanity;
+pub mod scaffold;
+pub mod scoring;
+#[cfg(feature = "reports")]
+pub mod show;
+pub mod smoothing;
+pub mod snapshot;
+pub mod sql;
+pub mod suggest;
+pub mod suppressions;
+#[cfg(feature = "integrations")]
+pub mod symbolize;
+pub mod techniques;
+pub mod template;
+#[cfg(feature = "integrations")]
+pub mod triage;
+
+/// A formula that scores a statement from how many failing and passing tests
+/// did and didn't cover it. [`metrics::Metric`] has the built-in ones;
+/// implement this to rank with another without forking the crate.
+pub trait SuspiciousnessMetric {
+    /// The formula's name, as shown in reports
+    fn name(&self) -> &str;
+    /// The score of a statement with these (possibly smoothed) counts
+    fn suspiciousness(&self, counts: &smoothing::Counts) -> f32;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub enum Coverage {
     Covered,
     NotCovered,
     NoExecutableCode,
 }
 
-#[derive(Debug)]
+/// A line number alone can't tell the same line of two source files apart,
+/// so statements are keyed by their file as well: the id [`SourceKeys`]
+/// gave it in the high 32 bits, and the line in the low 32. Lines of
+/// coverage that named no file have file 0, which no named file is given.
+pub fn statement_key(file: u32, line: u32) -> u64 {
+    (file as u64) << 32 | line as u64
+}
+
+/// The file id of a [`statement_key`].
+pub fn key_file(key: u64) -> u32 {
+    (key >> 32) as u32
+}
+
+/// The line of its source file that a [`statement_key`] stands for.
+pub fn key_line(key: u64) -> u32 {
+    key as u32
+}
+
+/// Numbers the source files of a project from 1, in the order they're first
+/// seen, to key their lines apart (see [`statement_key`]).
+#[derive(Debug, Default)]
+pub struct SourceKeys {
+    files: HashMap<String, u32>,
+}
+
+impl SourceKeys {
+    /// Give the lines of `coverage` the id of its source file. Coverage that
+    /// names no file keeps file 0.
+    pub fn rekey(&mut self, coverage: &mut formats::SourceCoverage) {
+        let Some(source) = &coverage.source else {
+            return;
+        };
+        let file = self.file(source);
+        for line in &mut coverage.lines {
+            line.file = file;
+        }
+    }
+
+    /// The id of `source`.
+    pub fn file(&mut self, source: &str) -> u32 {
+        let next = self.files.len() as u32 + 1;
+        *self.files.entry(source.to_string()).or_insert(next)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct LineInfo {
+    /// Id of the source file, see [`SourceKeys`]
+    pub file: u32,
     pub line_number: u32,
     pub statement: String,
     pub coverage: Coverage,
+    pub execution_count: u64,
+}
+
+impl LineInfo {
+    /// The key telling this line apart from those of other files, see
+    /// [`statement_key`].
+    pub fn key(&self) -> u64 {
+        statement_key(self.file, self.line_number)
+    }
 }
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "reports", derive(Serialize))]
 pub struct StatementInfo {
+    /// Id of the source file, see [`SourceKeys`]
+    #[cfg_attr(feature = "reports", serde(skip))]
+    pub file: u32,
     pub line_number: u32,
     statement: String,
-    failed_tests: u32,
-    passed_tests: u32,
-    total_failed: u32,
-    #[serde(serialize_with = "round_serialize")]
+    pub(crate) failed_tests: u32,
+    pub(crate) passed_tests: u32,
+    pub(crate) total_failed: u32,
+    #[cfg_attr(feature = "reports", serde(skip))]
+    pub(crate) total_passed: u32,
+    #[cfg_attr(feature = "reports", serde(serialize_with = "round_serialize"))]
     pub suspiciousness: f32,
 }
 
+#[cfg(feature = "reports")]
 fn round_serialize<S>(x: &f32, s: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -39,14 +208,36 @@ impl StatementInfo {
         let failed_tests = 0;
         let suspiciousness = 0.0;
         StatementInfo {
+            file: 0,
             line_number,
             statement,
             failed_tests,
             passed_tests,
             total_failed,
+            total_passed: 0,
             suspiciousness,
         }
     }
+    /// A statement for `line`, no test having covered it yet.
+    pub fn from_line(line: &LineInfo, total_failed: u32) -> StatementInfo {
+        let mut statement =
+            StatementInfo::new(line.line_number, line.statement.clone(), total_failed);
+        statement.file = line.file;
+        statement
+    }
+    pub fn statement(&self) -> &str {
+        &self.statement
+    }
+    /// The key telling this statement apart from those of other files, see
+    /// [`statement_key`].
+    pub fn key(&self) -> u64 {
+        statement_key(self.file, self.line_number)
+    }
+    /// Hash of the statement's text, for telling whether the source has
+    /// changed since (see [`integrity`]).
+    pub fn hash(&self) -> String {
+        integrity::statement_hash(&self.statement)
+    }
     pub fn add_passing_coverage(&mut self) {
         self.passed_tests += 1;
     }
@@ -54,77 +245,577 @@ impl StatementInfo {
         self.failed_tests += 1;
     }
     pub fn calculate_suspiciousness(&mut self) {
-        let failed_tests = self.failed_tests as f32;
-        let passed_tests = self.passed_tests as f32;
-        let total_failed = self.total_failed as f32;
-        let suspiciousness =
-            (failed_tests * failed_tests) / (passed_tests + total_failed - failed_tests);
-        self.suspiciousness = suspiciousness;
+        self.calculate_smoothed_suspiciousness(smoothing::Smoothing::None);
+    }
+    pub fn calculate_smoothed_suspiciousness(&mut self, smoothing: smoothing::Smoothing) {
+        self.calculate_metric_suspiciousness(&metrics::Metric::DSTAR, smoothing);
+    }
+    pub fn calculate_metric_suspiciousness(
+        &mut self,
+        metric: &dyn SuspiciousnessMetric,
+        smoothing: smoothing::Smoothing,
+    ) {
+        let counts = smoothing.counts(
+            self.failed_tests,
+            self.passed_tests,
+            self.total_failed,
+            self.total_passed,
+        );
+        self.suspiciousness = metric.suspiciousness(&counts);
+    }
+    /// How the formula arrived at this statement's score, with its counts
+    /// substituted.
+    pub fn explain(
+        &self,
+        smoothing: smoothing::Smoothing,
+        numbers: &locale::NumberFormat,
+    ) -> String {
+        smoothing
+            .counts(
+                self.failed_tests,
+                self.passed_tests,
+                self.total_failed,
+                self.total_passed,
+            )
+            .explain_dstar(numbers)
+    }
+}
+
+/// Why coverage or the files read with it couldn't be read. The coverage
+/// readers only fail on malformed input when their
+/// [`formats::ParseOptions`] are strict.
+#[derive(Debug)]
+pub enum FlError {
+    /// A coverage file couldn't be read
+    Io {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    /// Coverage couldn't be read from a stream
+    Read(std::io::Error),
+    /// A line isn't what the format expects
+    Malformed {
+        /// The line's number, from 1, when the line came from a file
+        line: Option<usize>,
+        expected: &'static str,
+        text: String,
+    },
+    /// A line isn't UTF-8
+    NotUtf8 { line: usize },
+    /// Text that isn't valid JSON
+    Json {
+        /// Where in the text, in bytes
+        offset: usize,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for FlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FlError::Io { path, source } => {
+                write!(f, "could not read {}: {}", path.display(), source)
+            }
+            FlError::Read(source) => write!(f, "{}", source),
+            FlError::Malformed {
+                line,
+                expected,
+                text,
+            } => {
+                if let Some(line) = line {
+                    write!(f, "line {}: ", line)?;
+                }
+                write!(f, "expected {}, found '{}'", expected, text)
+            }
+            FlError::NotUtf8 { line } => write!(f, "line {}: not UTF-8", line),
+            FlError::Json { offset, message } => {
+                write!(f, "invalid JSON at byte {}: {}", offset, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FlError::Io { source, .. } | FlError::Read(source) => Some(source),
+            FlError::Malformed { .. } | FlError::NotUtf8 { .. } | FlError::Json { .. } => None,
+        }
     }
 }
 
-fn parse_gcov_line(line: &str) -> LineInfo {
-    let line = line.split(':').collect::<Vec<&str>>();
+/// Parse one line of gcov output. A count that isn't a number is taken as
+/// one execution unless `strict`.
+fn parse_gcov_line(text: &str, strict: bool) -> Result<LineInfo, FlError> {
+    let malformed = || FlError::Malformed {
+        line: None,
+        expected: "a gcov line",
+        text: text.to_string(),
+    };
+    let line = text.split(':').collect::<Vec<&str>>();
+    if line.len() < 2 {
+        return Err(malformed());
+    }
     let coverage_str = line[0].trim();
     // The value can be one of three things:
     // It could be a number, indicating the number of times the line was executed
     // It could be #####, indicating the line was never executed
     // It could be a dash, indicating the line has no executable code
-    let coverage;
-    match coverage_str {
-        "-" => coverage = Coverage::NoExecutableCode,
-        "#####" => coverage = Coverage::NotCovered,
-        _ => coverage = Coverage::Covered,
-    }
-    let line_number = line[1].trim().parse::<u32>().unwrap();
+    let (coverage, execution_count) = match coverage_str {
+        "-" => (Coverage::NoExecutableCode, 0),
+        "#####" | "=====" => (Coverage::NotCovered, 0),
+        // gcov marks lines containing unexecuted blocks with a trailing '*'
+        _ => match coverage_str.trim_end_matches('*').parse::<u64>() {
+            Ok(count) => (Coverage::Covered, count),
+            Err(_) if strict => return Err(malformed()),
+            Err(_) => (Coverage::Covered, 1),
+        },
+    };
+    let line_number = line[1].trim().parse::<u32>().map_err(|_| malformed())?;
     // combine the rest of the line into a single string
     let mut statement = String::new();
-    for i in 2..line.len() {
+    for (i, part) in line.iter().enumerate().skip(2) {
         match i {
-            2 => statement.push_str(line[i].trim_start()),
-            _ => statement.push_str(&format!(":{}", line[i])),
+            2 => statement.push_str(part.trim_start()),
+            _ => statement.push_str(&format!(":{}", part)),
         }
     }
-    let line_info = LineInfo {
+    Ok(LineInfo {
+        file: 0,
         line_number,
         statement,
         coverage,
-    };
-    line_info
+        execution_count,
+    })
+}
+
+/// Whether a line is one of the notes `gcov -b` or `gcov --conditions` add
+/// under a source line, such as `branch  0 taken 2` or `condition outcomes
+/// covered 3/4`, rather than a line of the file.
+fn is_gcov_annotation(line: &str) -> bool {
+    let line = line.trim_start();
+    [
+        "branch ",
+        "call ",
+        "condition ",
+        "function ",
+        "unconditional ",
+    ]
+    .iter()
+    .any(|prefix| line.starts_with(prefix))
+}
+
+/// Inline markers in the source that leave statements out of the ranking.
+#[derive(Debug, PartialEq)]
+enum Suppression {
+    /// `fl-dstar: ignore` on the statement's own line
+    Line,
+    /// `fl-dstar: ignore-start` ... `fl-dstar: ignore-end` around a block
+    Start,
+    End,
+}
+
+fn suppression(statement: &str) -> Option<Suppression> {
+    let (_, marker) = statement.split_once("fl-dstar: ignore")?;
+    if marker.starts_with("-start") {
+        Some(Suppression::Start)
+    } else if marker.starts_with("-end") {
+        Some(Suppression::End)
+    } else {
+        Some(Suppression::Line)
+    }
+}
+
+/// The lines of a gcov file, of every source in it. Lines that aren't gcov
+/// output are skipped.
+pub fn parse_gcov_file(path: &std::path::Path) -> Result<Vec<LineInfo>, FlError> {
+    let text = encoding::read_to_string(path).map_err(|source| FlError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    // lenient parsing has no errors to report
+    let coverage =
+        parse_gcov_reader(text.as_bytes(), &formats::ParseOptions::default()).unwrap_or_default();
+    Ok(coverage
+        .into_iter()
+        .flat_map(|record| record.lines)
+        .collect())
 }
 
-pub fn parse_gcov_file(path: &std::path::PathBuf) -> Vec<LineInfo> {
+/// Parse gcov output from any reader, keeping the `Source:` header so the
+/// coverage can be written back out in another format. Each `Source:` header
+/// starts a record of its own, so the `.gcov` files of a program's sources
+/// can be concatenated into one file per test; a source listed more than
+/// once, such as a header several units include, is merged into one record
+/// (see [`formats::merge_duplicates`]). Lines that aren't gcov output
+/// are skipped, or are errors when `options` are strict.
+pub(crate) fn parse_gcov_reader<R: BufRead>(
+    reader: R,
+    options: &formats::ParseOptions,
+) -> Result<Vec<formats::SourceCoverage>, FlError> {
+    let mut records = Vec::new();
+    let mut source = None;
     let mut lines = Vec::new();
-    let file = std::fs::File::open(path).unwrap();
-    let reader = std::io::BufReader::new(file);
-    for line in reader.lines() {
-        let line = line.unwrap();
-        if line.is_empty() {
+    let mut skipped = Vec::new();
+    let mut ignoring = false;
+    for (i, line) in formats::text_lines(reader, options).enumerate() {
+        let line = line?;
+        if line.is_empty() || is_gcov_annotation(&line) {
             continue;
         }
-        let line_info = parse_gcov_line(&line);
-        // if this is a line with no executable code, skip it
-        if line_info.coverage == Coverage::NoExecutableCode {
+        let Ok(line_info) = parse_gcov_line(&line, options.strict) else {
+            skipped.push(options.malformed(i, &line, "a gcov line")?);
             continue;
-        }
-        // if this is a line with line number 0, skip it
+        };
+        // if this is a line with line number 0, it is a header; keep the source name
         if line_info.line_number == 0 {
+            if let Some(name) = line_info.statement.strip_prefix("Source:") {
+                if source.is_some() || !lines.is_empty() || !skipped.is_empty() {
+                    records.push(formats::SourceCoverage {
+                        source: source.take(),
+                        lines: std::mem::take(&mut lines),
+                        skipped: std::mem::take(&mut skipped),
+                    });
+                }
+                source = Some(name.to_string());
+                ignoring = false;
+            }
+            continue;
+        }
+        // markers usually sit on comment lines, so check them before dropping those
+        let reason = match suppression(&line_info.statement) {
+            Some(Suppression::Start) => {
+                ignoring = true;
+                Some("inside an fl-dstar: ignore-start block")
+            }
+            Some(Suppression::End) => {
+                ignoring = false;
+                None
+            }
+            Some(Suppression::Line) => Some("marked fl-dstar: ignore"),
+            None if ignoring => Some("inside an fl-dstar: ignore-start block"),
+            None => None,
+        };
+        if let Some(reason) = reason {
+            if line_info.coverage != Coverage::NoExecutableCode {
+                skipped.push(formats::SkippedLine {
+                    number: i + 1,
+                    text: line,
+                    reason: reason.to_string(),
+                });
+            }
+            continue;
+        }
+        if line_info.coverage == Coverage::NoExecutableCode && !options.keep_noncode {
             continue;
         }
         lines.push(line_info);
     }
+    records.push(formats::SourceCoverage {
+        source,
+        lines,
+        skipped,
+    });
+    Ok(formats::merge_duplicates(records))
+}
+
+/// The coverage of every passing and failing test, together with the
+/// statements they are scored over.
+#[derive(Debug, Clone)]
+pub struct Spectrum {
+    pub passing: Vec<Vec<LineInfo>>,
+    pub failing: Vec<Vec<LineInfo>>,
+    pub statements: Vec<StatementInfo>,
+    /// Name of each source file the coverage named, by its id (see
+    /// [`SourceKeys`])
+    pub sources: HashMap<u32, String>,
+    /// Whether the statements came from a baseline, so lines the tests list
+    /// beyond it are left out rather than added
+    pub baseline: bool,
+}
+
+impl Spectrum {
+    /// A spectrum of the given tests, scored over every executable line any
+    /// of them lists, in the order they are first listed. A statement a test
+    /// doesn't list counts as not covered by it. Either set may be empty.
+    pub fn new(passing: Vec<Vec<LineInfo>>, failing: Vec<Vec<LineInfo>>) -> Spectrum {
+        Spectrum::with_statements(Vec::new(), false, passing, failing)
+    }
+
+    /// A spectrum scored over the executable lines of `baseline` alone, such
+    /// as the coverage of a build with every line instrumented.
+    pub fn with_baseline(
+        baseline: &[LineInfo],
+        passing: Vec<Vec<LineInfo>>,
+        failing: Vec<Vec<LineInfo>>,
+    ) -> Spectrum {
+        let statements = executable_lines(baseline)
+            .into_iter()
+            .map(|line| StatementInfo::from_line(line, 0))
+            .collect();
+        Spectrum::with_statements(statements, true, passing, failing)
+    }
+
+    fn with_statements(
+        statements: Vec<StatementInfo>,
+        baseline: bool,
+        passing: Vec<Vec<LineInfo>>,
+        failing: Vec<Vec<LineInfo>>,
+    ) -> Spectrum {
+        let mut spectrum = Spectrum {
+            passing: Vec::with_capacity(passing.len()),
+            failing: Vec::with_capacity(failing.len()),
+            statements,
+            sources: HashMap::new(),
+            baseline,
+        };
+        let tests = passing
+            .into_iter()
+            .map(|test| (test, true))
+            .chain(failing.into_iter().map(|test| (test, false)));
+        for (test, is_passing) in tests {
+            spectrum.add_test(test, is_passing);
+        }
+        spectrum
+    }
+
+    /// List the executable lines of `test` the spectrum doesn't have yet,
+    /// as statements no test so far has covered. Does nothing for spectra
+    /// with a baseline.
+    fn extend_statements(&mut self, test: &[LineInfo]) {
+        let lines = executable_lines(test);
+        // usually the test lists the statements already known, in order
+        let known = lines.len() <= self.statements.len()
+            && lines
+                .iter()
+                .zip(&self.statements)
+                .all(|(line, statement)| line.key() == statement.key());
+        if self.baseline || known {
+            return;
+        }
+        let (total_failed, total_passed) = match self.statements.first() {
+            Some(statement) => (statement.total_failed, statement.total_passed),
+            None => (self.failing.len() as u32, self.passing.len() as u32),
+        };
+        let mut known: HashSet<u64> = self.statements.iter().map(|s| s.key()).collect();
+        for line in lines {
+            if known.insert(line.key()) {
+                let mut statement = StatementInfo::from_line(line, total_failed);
+                statement.total_passed = total_passed;
+                self.statements.push(statement);
+            }
+        }
+    }
+
+    /// Add the coverage of one more test, updating every statement's counts.
+    /// Suspiciousness needs to be recomputed with [`Spectrum::score`] afterwards.
+    pub fn add_test(&mut self, test: Vec<LineInfo>, is_passing: bool) {
+        self.extend_statements(&test);
+        add_test_to_statements(&mut self.statements, &test, is_passing);
+        match is_passing {
+            true => self.passing.push(test),
+            false => self.failing.push(test),
+        }
+        for statement in &mut self.statements {
+            statement.total_passed = self.passing.len() as u32;
+            statement.total_failed = self.failing.len() as u32;
+        }
+    }
+
+    /// Count one more test without keeping its coverage, for runs with too
+    /// many tests to hold at once. `passing` and `failing` then hold only
+    /// the tests that were added rather than folded, while the statements'
+    /// counts and scores cover them all.
+    pub fn fold_test(&mut self, test: &[LineInfo], is_passing: bool) {
+        self.extend_statements(test);
+        add_test_to_statements(&mut self.statements, test, is_passing);
+        for statement in &mut self.statements {
+            match is_passing {
+                true => statement.total_passed += 1,
+                false => statement.total_failed += 1,
+            }
+        }
+    }
+
+    /// How the ranking would change if `test` were added, for showing the
+    /// diagnostic value of a test that was just written or run. The changes
+    /// are ordered by the new ranking.
+    pub fn rank_after_adding(
+        &self,
+        test: &[LineInfo],
+        is_passing: bool,
+        technique: techniques::Technique,
+        smoothing: smoothing::Smoothing,
+    ) -> Vec<RankChange> {
+        let ranked = |spectrum: &mut Spectrum| -> HashMap<u64, (usize, f32)> {
+            spectrum.score(technique, smoothing);
+            sort_by_suspiciousness(&mut spectrum.statements);
+            spectrum
+                .statements
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (s.key(), (i + 1, s.suspiciousness)))
+                .collect()
+        };
+        // statements only the new test lists rank as uncovered before it
+        let mut before_spectrum = self.clone();
+        before_spectrum.extend_statements(test);
+        let before = ranked(&mut before_spectrum);
+        let mut after_spectrum = self.clone();
+        after_spectrum.add_test(test.to_vec(), is_passing);
+        let after = ranked(&mut after_spectrum);
+        after_spectrum
+            .statements
+            .iter()
+            .map(|statement| {
+                let (old_rank, old_suspiciousness) = before[&statement.key()];
+                let (new_rank, new_suspiciousness) = after[&statement.key()];
+                RankChange {
+                    file: statement.file,
+                    line_number: statement.line_number,
+                    old_rank,
+                    new_rank,
+                    old_suspiciousness,
+                    new_suspiciousness,
+                }
+            })
+            .collect()
+    }
+
+    /// Compute the suspiciousness of every statement with `metric` in place
+    /// of D*.
+    pub fn score_metric(
+        &mut self,
+        metric: &dyn SuspiciousnessMetric,
+        smoothing: smoothing::Smoothing,
+    ) {
+        for statement in &mut self.statements {
+            statement.calculate_metric_suspiciousness(metric, smoothing);
+        }
+    }
+
+    /// Compute the suspiciousness of every statement with the given technique.
+    /// Smoothing only affects formula-based scoring.
+    pub fn score(&mut self, technique: techniques::Technique, smoothing: smoothing::Smoothing) {
+        use techniques::Technique;
+        match technique {
+            Technique::Formula => self.statements.iter_mut().for_each(|statement| {
+                statement.calculate_smoothed_suspiciousness(smoothing);
+            }),
+            Technique::NearestNeighbor => {
+                techniques::nearest_neighbor(&mut self.statements, &self.passing, &self.failing)
+            }
+            Technique::Union => {
+                techniques::union(&mut self.statements, &self.passing, &self.failing)
+            }
+            Technique::Intersection => {
+                techniques::intersection(&mut self.statements, &self.passing, &self.failing)
+            }
+            Technique::BarinelBayes => {
+                techniques::barinel_bayes(&mut self.statements, &self.passing, &self.failing)
+            }
+        }
+    }
+}
+
+/// Where a statement ranks before and after a test is added; ranks start at 1
+/// for the most suspicious statement.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "reports", derive(Serialize))]
+pub struct RankChange {
+    /// Id of the source file, see [`SourceKeys`]
+    pub file: u32,
+    pub line_number: u32,
+    pub old_rank: usize,
+    pub new_rank: usize,
+    pub old_suspiciousness: f32,
+    pub new_suspiciousness: f32,
+}
+
+/// Order statements from most to least suspicious, breaking ties by file and
+/// line number.
+/// Scores are compared with `total_cmp`, so a NaN can't abort the sort.
+pub fn sort_by_suspiciousness(statements: &mut [StatementInfo]) {
+    statements.sort_by(|a, b| {
+        b.suspiciousness
+            .total_cmp(&a.suspiciousness)
+            .then(a.key().cmp(&b.key()))
+    });
+}
+
+/// The lines of a test that hold statements, leaving out comments and other
+/// lines kept for context (see [`formats::ParseOptions::keep_noncode`]).
+pub fn executable_lines(lines: &[LineInfo]) -> Vec<&LineInfo> {
     lines
+        .iter()
+        .filter(|line| line.coverage != Coverage::NoExecutableCode)
+        .collect()
 }
 
+/// The keys of the lines a single test executed, see [`statement_key`].
+pub(crate) fn covered_set(lines: &[LineInfo]) -> HashSet<u64> {
+    lines
+        .iter()
+        .filter(|line| line.coverage == Coverage::Covered)
+        .map(|line| line.key())
+        .collect()
+}
+
+/// Sparse inputs, such as address traces, only list what a test executed.
+/// Give every test the same statements, in line order, marking the ones it
+/// did not reach as not covered, so the tests can be scored side by side.
+pub fn align_tests(tests: &mut [Vec<LineInfo>]) {
+    let mut statements: BTreeMap<u64, String> = BTreeMap::new();
+    for line in tests.iter().flatten() {
+        statements
+            .entry(line.key())
+            .or_insert_with(|| line.statement.clone());
+    }
+    for test in tests.iter_mut() {
+        let mut by_line: HashMap<u64, LineInfo> =
+            test.drain(..).map(|line| (line.key(), line)).collect();
+        for (&key, statement) in &statements {
+            test.push(by_line.remove(&key).unwrap_or(LineInfo {
+                file: key_file(key),
+                line_number: key_line(key),
+                statement: statement.clone(),
+                coverage: Coverage::NotCovered,
+                execution_count: 0,
+            }));
+        }
+    }
+}
+
+/// Count the statements `tests` covered, matching its lines to the
+/// statements by file and line number. Lines with no statement are left out.
 pub fn add_test_to_statements(
-    statements: &mut Vec<StatementInfo>,
-    tests: &Vec<LineInfo>,
+    statements: &mut [StatementInfo],
+    tests: &[LineInfo],
     is_passing: bool,
 ) {
-    // the two vectors should be the same length
-    assert_eq!(statements.len(), tests.len());
-    for i in 0..statements.len() {
-        if tests[i].coverage == Coverage::Covered {
+    // lines with no executable code are only kept for context and aren't scored
+    let tests = executable_lines(tests);
+    // tests usually list the statements in the same order, so only index
+    // them by key once one doesn't
+    let mut index: Option<HashMap<u64, usize>> = None;
+    for (i, line) in tests.iter().enumerate() {
+        if line.coverage != Coverage::Covered {
+            continue;
+        }
+        let position = match statements.get(i) {
+            Some(statement) if statement.key() == line.key() => Some(i),
+            _ => index
+                .get_or_insert_with(|| {
+                    statements
+                        .iter()
+                        .enumerate()
+                        .map(|(i, s)| (s.key(), i))
+                        .collect()
+                })
+                .get(&line.key())
+                .copied(),
+        };
+        if let Some(i) = position {
             if is_passing {
                 statements[i].add_passing_coverage();
             } else {
@@ -136,13 +827,11 @@ pub fn add_test_to_statements(
 
 #[cfg(test)]
 mod tests {
-    use std::f32::INFINITY;
-
     use super::*;
     #[test]
     fn test_parse_gcov_line_no_executable() {
         let line = "        -:    0:Source:tcas4.c";
-        let line_info = parse_gcov_line(line);
+        let line_info = parse_gcov_line(line, false).unwrap();
         assert_eq!(line_info.line_number, 0);
         assert_eq!(line_info.statement, "Source:tcas4.c");
         assert_eq!(line_info.coverage, Coverage::NoExecutableCode);
@@ -150,22 +839,83 @@ mod tests {
     #[test]
     fn test_parse_gcov_line_not_covered() {
         let line = "    #####:   77:	result = Own_Above_Threat() && (Cur_Vertical_Sep >= MINSEP) && (Up_Separation >= ALIM());";
-        let line_info = parse_gcov_line(line);
+        let line_info = parse_gcov_line(line, false).unwrap();
         assert_eq!(line_info.line_number, 77);
         assert_eq!(line_info.statement, "result = Own_Above_Threat() && (Cur_Vertical_Sep >= MINSEP) && (Up_Separation >= ALIM());");
         assert_eq!(line_info.coverage, Coverage::NotCovered);
     }
 
+    #[test]
+    fn test_parse_gcov_line_malformed() {
+        let error = parse_gcov_line("  1x:  2:a();", true).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected a gcov line, found '  1x:  2:a();'"
+        );
+        assert!(parse_gcov_line("  1x:  2:a();", false).is_ok());
+        assert!(parse_gcov_line("garbage", false).is_err());
+        let missing = parse_gcov_file(std::path::Path::new("/nonexistent/t.gcov")).unwrap_err();
+        assert!(matches!(missing, FlError::Io { .. }));
+    }
+
     #[test]
     fn test_parse_gcov_line_covered() {
         let line = "        2:   61:    return (Climb_Inhibit ? Up_Separation + NOZCROSS : Up_Separation);";
-        let line_info = parse_gcov_line(line);
+        let line_info = parse_gcov_line(line, false).unwrap();
         assert_eq!(line_info.line_number, 61);
         assert_eq!(
             line_info.statement,
             "return (Climb_Inhibit ? Up_Separation + NOZCROSS : Up_Separation);"
         );
         assert_eq!(line_info.coverage, Coverage::Covered);
+        assert_eq!(line_info.execution_count, 2);
+    }
+
+    #[test]
+    fn test_parse_gcov_suppression_markers() {
+        let gcov = "        1:    1:a(); // fl-dstar: ignore
+        1:    2:b();
+        -:    3:// fl-dstar: ignore-start
+        1:    4:c();
+        -:    5:// fl-dstar: ignore-end
+        1:    6:d();
+";
+        let [coverage] =
+            &parse_gcov_reader(gcov.as_bytes(), &formats::ParseOptions::default()).unwrap()[..]
+        else {
+            panic!("expected one record");
+        };
+        let line_numbers: Vec<u32> = coverage.lines.iter().map(|l| l.line_number).collect();
+        assert_eq!(line_numbers, vec![2, 6]);
+        let skipped: Vec<(usize, &str)> = coverage
+            .skipped
+            .iter()
+            .map(|s| (s.number, s.reason.as_str()))
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                (1, "marked fl-dstar: ignore"),
+                (4, "inside an fl-dstar: ignore-start block")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_gcov_skips_annotations() {
+        let gcov = "        2:    3:if (a && b)
+condition outcomes covered 3/4
+condition  1 not covered (false)
+branch  0 taken 2 (fallthrough)
+call    0 returned 1
+        1:    4:x();
+";
+        let options = formats::ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let coverage = parse_gcov_reader(gcov.as_bytes(), &options).unwrap();
+        assert_eq!(coverage[0].lines.len(), 2);
     }
 
     // Tests for DStar calculation
@@ -183,7 +933,7 @@ mod tests {
     #[test]
     fn test_dstar_calculation_from_hw() {
         let mut statement_info = StatementInfo::new(1, "test".to_string(), 617);
-        for i in 0..616 {
+        for _ in 0..616 {
             statement_info.add_failing_coverage()
         }
         // Result should be (616) / (0 + 617 - 616) = 0.25
@@ -210,7 +960,170 @@ mod tests {
         statement_info.add_failing_coverage();
         // Result should be (3 * 3) / (3 + 0 - 3) = infinity
         statement_info.calculate_suspiciousness();
-        assert_eq!(statement_info.suspiciousness, INFINITY);
+        assert_eq!(statement_info.suspiciousness, f32::INFINITY);
+    }
+
+    #[test]
+    fn test_dstar_calculation_add_one() {
+        let mut statement_info = StatementInfo::new(1, "test".to_string(), 3);
+        statement_info.add_failing_coverage();
+        statement_info.add_failing_coverage();
+        statement_info.add_failing_coverage();
+        // Result should be (4 * 4) / (1 + 1) = 8 instead of infinity
+        statement_info.calculate_smoothed_suspiciousness(smoothing::Smoothing::AddOne);
+        assert_eq!(statement_info.suspiciousness, 8.0);
+    }
+
+    fn test_case(covered: &[u32]) -> Vec<LineInfo> {
+        (1..=3)
+            .map(|line_number| {
+                let is_covered = covered.contains(&line_number);
+                LineInfo {
+                    file: 0,
+                    line_number,
+                    statement: format!("s{}", line_number),
+                    coverage: match is_covered {
+                        true => Coverage::Covered,
+                        false => Coverage::NotCovered,
+                    },
+                    execution_count: is_covered as u64,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rank_after_adding() {
+        let spectrum = Spectrum::new(
+            vec![test_case(&[1, 2, 3]), test_case(&[1])],
+            vec![test_case(&[1, 2, 3])],
+        );
+        let changes = spectrum.rank_after_adding(
+            &test_case(&[3]),
+            true,
+            techniques::Technique::Formula,
+            smoothing::Smoothing::None,
+        );
+        // a passing test through line 3 leaves line 2 alone at the top and
+        // drops line 3 into a tie with line 1
+        assert_eq!(changes[0].line_number, 2);
+        assert_eq!(changes[0].old_rank, 1);
+        assert_eq!(changes[0].new_rank, 1);
+        let line_three = changes.iter().find(|c| c.line_number == 3).unwrap();
+        assert_eq!(line_three.old_rank, 2);
+        assert_eq!(line_three.new_rank, 3);
+        assert_eq!(line_three.old_suspiciousness, 1.0);
+        assert_eq!(line_three.new_suspiciousness, 0.5);
+        // the spectrum itself is untouched
+        assert_eq!(spectrum.passing.len(), 2);
+    }
+
+    #[test]
+    fn test_fold_test() {
+        let mut kept = Spectrum::new(
+            vec![test_case(&[1, 2, 3]), test_case(&[1])],
+            vec![test_case(&[1, 2, 3])],
+        );
+        let mut folded = Spectrum::new(vec![test_case(&[1, 2, 3])], Vec::new());
+        folded.fold_test(&test_case(&[1]), true);
+        folded.fold_test(&test_case(&[1, 2, 3]), false);
+        kept.score(techniques::Technique::Formula, smoothing::Smoothing::None);
+        folded.score(techniques::Technique::Formula, smoothing::Smoothing::None);
+        let counts = |spectrum: &Spectrum| -> Vec<(u32, u32, u32, u32, f32)> {
+            spectrum
+                .statements
+                .iter()
+                .map(|s| {
+                    let (failed, passed) = (s.failed_tests, s.passed_tests);
+                    (
+                        failed,
+                        passed,
+                        s.total_failed,
+                        s.total_passed,
+                        s.suspiciousness,
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(counts(&folded), counts(&kept));
+        assert_eq!(folded.passing.len(), 1);
+    }
+
+    #[test]
+    fn test_no_passing_tests() {
+        let mut spectrum = Spectrum::new(Vec::new(), vec![test_case(&[1, 2]), test_case(&[2])]);
+        spectrum.score(techniques::Technique::Formula, smoothing::Smoothing::None);
+        let scores: Vec<f32> = spectrum
+            .statements
+            .iter()
+            .map(|s| s.suspiciousness)
+            .collect();
+        assert_eq!(scores, [1.0, f32::INFINITY, 0.0]);
+        assert!(Spectrum::new(Vec::new(), Vec::new()).statements.is_empty());
+    }
+
+    #[test]
+    fn test_statement_universe() {
+        let only = |covered: &[u32], listed: &[u32]| -> Vec<LineInfo> {
+            test_case(covered)
+                .into_iter()
+                .filter(|line| listed.contains(&line.line_number))
+                .collect()
+        };
+        // line 3 is only listed by the failing test
+        let passing = vec![only(&[1], &[1, 2])];
+        let failing = vec![only(&[2, 3], &[2, 3])];
+        let spectrum = Spectrum::new(passing.clone(), failing.clone());
+        let counts: Vec<(u32, u32, u32, u32)> = spectrum
+            .statements
+            .iter()
+            .map(|s| {
+                (
+                    s.line_number,
+                    s.failed_tests,
+                    s.passed_tests,
+                    s.total_passed,
+                )
+            })
+            .collect();
+        assert_eq!(counts, [(1, 0, 1, 1), (2, 1, 0, 1), (3, 1, 0, 1)]);
+
+        let baseline = only(&[], &[2, 3]);
+        let spectrum = Spectrum::with_baseline(&baseline, passing.clone(), failing);
+        let lines: Vec<u32> = spectrum.statements.iter().map(|s| s.line_number).collect();
+        assert_eq!(lines, [2, 3]);
+        assert_eq!(spectrum.statements[0].total_failed, 1);
+
+        let mut folded = Spectrum::new(passing, Vec::new());
+        folded.fold_test(&only(&[3], &[3]), false);
+        let lines: Vec<(u32, u32)> = folded
+            .statements
+            .iter()
+            .map(|s| (s.line_number, s.total_failed))
+            .collect();
+        assert_eq!(lines, [(1, 1), (2, 1), (3, 1)]);
+    }
+
+    #[test]
+    fn test_add_test_with_other_lines() {
+        // another compiler's output: line 1 missing, an extra line 4 and the
+        // rest out of order, so positions don't match the statements
+        let mut statements: Vec<StatementInfo> = (1..=3)
+            .map(|line| StatementInfo::new(line, format!("s{}", line), 1))
+            .collect();
+        let mut test = test_case(&[2, 3]);
+        test.remove(0);
+        test.reverse();
+        test.push(LineInfo {
+            file: 0,
+            line_number: 4,
+            statement: "s4".to_string(),
+            coverage: Coverage::Covered,
+            execution_count: 1,
+        });
+        add_test_to_statements(&mut statements, &test, false);
+        let failed: Vec<u32> = statements.iter().map(|s| s.failed_tests).collect();
+        assert_eq!(failed, [0, 1, 1]);
     }
 
     #[test]
@@ -219,22 +1132,29 @@ mod tests {
         statements.push(StatementInfo::new(1, "test".to_string(), 2));
         statements.push(StatementInfo::new(2, "test".to_string(), 2));
         statements.push(StatementInfo::new(3, "test".to_string(), 2));
-        let mut tests = Vec::new();
-        tests.push(LineInfo {
-            line_number: 1,
-            statement: "test".to_string(),
-            coverage: Coverage::Covered,
-        });
-        tests.push(LineInfo {
-            line_number: 2,
-            statement: "test".to_string(),
-            coverage: Coverage::NotCovered,
-        });
-        tests.push(LineInfo {
-            line_number: 3,
-            statement: "test".to_string(),
-            coverage: Coverage::Covered,
-        });
+        let tests = vec![
+            LineInfo {
+                file: 0,
+                line_number: 1,
+                statement: "test".to_string(),
+                coverage: Coverage::Covered,
+                execution_count: 1,
+            },
+            LineInfo {
+                file: 0,
+                line_number: 2,
+                statement: "test".to_string(),
+                coverage: Coverage::NotCovered,
+                execution_count: 0,
+            },
+            LineInfo {
+                file: 0,
+                line_number: 3,
+                statement: "test".to_string(),
+                coverage: Coverage::Covered,
+                execution_count: 1,
+            },
+        ];
         add_test_to_statements(&mut statements, &tests, true);
         assert_eq!(statements[0].passed_tests, 1);
         assert_eq!(statements[0].failed_tests, 0);
@@ -243,4 +1163,44 @@ mod tests {
         assert_eq!(statements[2].passed_tests, 1);
         assert_eq!(statements[2].failed_tests, 0);
     }
+
+    #[test]
+    fn test_source_keys() {
+        let coverage = |source: Option<&str>, lines: &[u32]| formats::SourceCoverage {
+            source: source.map(str::to_string),
+            lines: test_case(lines),
+            skipped: Vec::new(),
+        };
+        let keys = |coverage: &formats::SourceCoverage| -> Vec<u64> {
+            coverage.lines.iter().map(|line| line.key()).collect()
+        };
+        let mut sources = SourceKeys::default();
+        let mut a = coverage(Some("a.c"), &[1]);
+        let mut b = coverage(Some("b.c"), &[1]);
+        let mut again = coverage(Some("a.c"), &[1]);
+        let mut unnamed = coverage(None, &[1]);
+        for coverage in [&mut a, &mut b, &mut again, &mut unnamed] {
+            sources.rekey(coverage);
+        }
+        assert_eq!(keys(&a), keys(&again));
+        assert_ne!(keys(&a), keys(&unnamed));
+        assert_ne!(keys(&a), keys(&b));
+        assert_eq!(
+            (a.lines[0].file, b.lines[0].file, unnamed.lines[0].file),
+            (1, 2, 0)
+        );
+        assert_eq!(b.lines[0].line_number, 1);
+
+        // lines past 2^20 keep their number and file
+        let mut long = coverage(Some("c.c"), &[]);
+        long.lines[0].line_number = 1 << 20;
+        long.lines[1].line_number = u32::MAX;
+        let mut next = coverage(Some("d.c"), &[]);
+        sources.rekey(&mut long);
+        sources.rekey(&mut next);
+        for (line, number) in long.lines.iter().zip([1 << 20, u32::MAX]) {
+            assert_eq!((key_file(line.key()), key_line(line.key())), (3, number));
+        }
+        assert!(keys(&next).iter().all(|key| !keys(&long).contains(key)));
+    }
 }
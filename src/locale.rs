@@ -0,0 +1,122 @@
+use std::str::FromStr;
+
+/// How numbers are written in human-oriented output (annotated slices,
+/// templated reports, plots). Machine formats such as CSV and JSON always
+/// use `.` with no grouping, whatever the locale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberFormat {
+    pub decimal: char,
+    pub grouping: Option<char>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            decimal: '.',
+            grouping: None,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// The conventions of the locale named by `LC_ALL`, `LC_NUMERIC` or
+    /// `LANG`, in that order, falling back to the default.
+    pub fn from_env() -> NumberFormat {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Format `value` with `decimals` places. Infinite scores stay `inf`.
+    pub fn format(&self, value: f64, decimals: usize) -> String {
+        if !value.is_finite() {
+            return format!("{}", value);
+        }
+        let text = format!("{:.*}", decimals, value.abs());
+        let (integer, fraction) = match text.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (text.as_str(), None),
+        };
+        let mut out = String::new();
+        // -0.00 reads as a bug in a report
+        if value < 0.0 && text.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            out.push('-');
+        }
+        out.push_str(&self.group(integer));
+        if let Some(fraction) = fraction {
+            out.push(self.decimal);
+            out.push_str(fraction);
+        }
+        out
+    }
+
+    /// Format a count, such as a rank, with thousands grouping.
+    pub fn format_count(&self, value: usize) -> String {
+        self.group(&value.to_string())
+    }
+
+    fn group(&self, digits: &str) -> String {
+        let separator = match self.grouping {
+            Some(separator) => separator,
+            None => return digits.to_string(),
+        };
+        let mut out = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push(separator);
+            }
+            out.push(digit);
+        }
+        out
+    }
+}
+
+/// Accepts a locale name such as `de_DE.UTF-8`, `fr` or `C`. Only the
+/// language (and region, for Swiss German) matters.
+impl FromStr for NumberFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.split(['.', '@']).next().unwrap_or("");
+        let language = name
+            .split(['_', '-'])
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        let (decimal, grouping) = match language.as_str() {
+            "c" | "posix" => ('.', None),
+            _ if name.eq_ignore_ascii_case("de_CH") => ('.', Some('\'')),
+            "en" | "ja" | "zh" | "ko" | "he" | "th" => ('.', Some(',')),
+            "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" | "el" | "ro" => (',', Some('.')),
+            "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "nb" | "nn" | "no" | "fi" | "uk" | "hu"
+            | "bg" => (',', Some(' ')),
+            _ => return Err(format!("unknown locale '{}'", s)),
+        };
+        Ok(NumberFormat { decimal, grouping })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let c = NumberFormat::default();
+        assert_eq!(c.format(1234.567, 2), "1234.57");
+        let german: NumberFormat = "de_DE.UTF-8".parse().unwrap();
+        assert_eq!(german.format(0.95, 2), "0,95");
+        assert_eq!(german.format(1234567.891, 2), "1.234.567,89");
+        assert_eq!(german.format(-0.001, 2), "0,00");
+        assert_eq!(german.format(f64::INFINITY, 2), "inf");
+        assert_eq!(german.format_count(12345), "12.345");
+        let french: NumberFormat = "fr_FR".parse().unwrap();
+        assert_eq!(french.format(-1234.5, 1), "-1 234,5");
+        let english: NumberFormat = "en_US".parse().unwrap();
+        assert_eq!(english.format(999.0, 0), "999");
+        assert!("xx_YY".parse::<NumberFormat>().is_err());
+    }
+}
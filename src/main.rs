@@ -1,85 +1,2927 @@
-use clap::Parser;
-use fl_dstar::{self, LineInfo};
+use clap::{Args, Parser, Subcommand};
+use fl_dstar::audit;
+use fl_dstar::formats::{self, Format};
+use fl_dstar::functions::Weighting;
+use fl_dstar::harness::HarnessFilter;
+use fl_dstar::locale::NumberFormat;
+use fl_dstar::memory::{ByteSize, Strategy};
+use fl_dstar::metrics::Metric;
+use fl_dstar::notify::Service;
+use fl_dstar::output::{self, Column, OutputFormat};
+use fl_dstar::paths::{self, PathMap, Symlinks};
+use fl_dstar::query::Report;
+use fl_dstar::smoothing::Smoothing;
+use fl_dstar::snapshot::Compression;
+use fl_dstar::suppressions::{Date, Suppression};
+use fl_dstar::techniques::Technique;
+use fl_dstar::{self, Coverage, LineInfo, Spectrum, SuspiciousnessMetric};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io;
+use std::io::Write;
 /// A simple CLI that will analyze coverage data from passing and failing tests
 /// and output lines most likely to contain bugs. This is determined using the dstar
 /// suspiciousness metric.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-    passing_dir: std::path::PathBuf,
-    failing_dir: std::path::PathBuf,
+    #[command(subcommand)]
+    command: Option<Commands>,
+    #[command(flatten)]
+    rank: RankArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+    /// Rewrite source paths starting with FROM to start with TO; may be repeated
+    #[arg(long = "path-map", value_name = "FROM=TO", global = true)]
+    path_map: Vec<PathMap>,
+    /// Project root that source paths are reported relative to; defaults to the git toplevel
+    #[arg(long, global = true)]
+    root: Option<std::path::PathBuf>,
+    /// Whether test directories follow or skip symbolic links; links to a file already
+    /// listed are skipped either way
+    #[arg(long, default_value = "follow", global = true)]
+    symlinks: Symlinks,
+    /// Drop sources matching this glob (e.g. 'third_party/**' or '*_test.c'); may be repeated
+    #[arg(long = "exclude-path", value_name = "GLOB", global = true)]
+    exclude_path: Vec<String>,
+    /// Leave assertion macros, test fixtures and driver files out of the ranking
+    #[arg(long, global = true)]
+    suppress_harness: bool,
+    /// Count a line as covered only when a test executed it at least K times
+    #[arg(long, value_name = "K", default_value_t = 1, global = true,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    min_hits: u64,
+    /// Leave lines that are only braces or a template header out of the ranking; compilers
+    /// attribute coverage to them, which clutters the top with `}` lines
+    #[arg(long, global = true)]
+    no_brace_lines: bool,
+    /// File of file:line entries to hide from reports; defaults to .fl-dstar-suppressions in
+    /// the project root
+    #[arg(long, value_name = "FILE", global = true)]
+    suppressions: Option<std::path::PathBuf>,
+    /// Fail on coverage lines that can't be parsed instead of skipping them
+    #[arg(long, global = true)]
+    strict: bool,
+    /// Keep lines with no executable code, such as comments, so annotated output can show the
+    /// whole source file without it at hand. They are never scored.
+    #[arg(long, global = true)]
+    keep_noncode: bool,
+    /// Replace statement text with hashes in every output, keeping line numbers and scores
+    #[arg(long, global = true)]
+    redact_statements: bool,
+    /// Extra statement prefix treated as harness code by --suppress-harness; may be repeated
+    #[arg(long, value_name = "PREFIX", global = true)]
+    harness_statement: Vec<String>,
+    /// Extra file glob treated as harness code by --suppress-harness; may be repeated
+    #[arg(long, value_name = "GLOB", global = true)]
+    harness_file: Vec<String>,
+    /// Treat inputs as address traces (text, .sancov or "@cov PROBE COUNT" counter dumps
+    /// captured from a target) and resolve them with addr2line against this binary
+    #[arg(long, value_name = "BINARY", global = true)]
+    binary: Option<std::path::PathBuf>,
+    /// GNU ld map file of the firmware, for counters named by symbol+offset
+    #[arg(long, value_name = "FILE", global = true, requires = "binary")]
+    linker_map: Option<std::path::PathBuf>,
+    /// Locale for numbers in human-readable output (e.g. de_DE or fr); `auto` reads LC_ALL,
+    /// LC_NUMERIC or LANG. CSV and JSON output is unaffected
+    #[arg(long, global = true)]
+    locale: Option<String>,
+    /// Decimal separator for human-readable output, overriding --locale
+    #[arg(long, value_name = "CHAR", global = true)]
+    decimal_separator: Option<char>,
+    /// Thousands separator for human-readable output, overriding --locale
+    #[arg(long, value_name = "CHAR", global = true)]
+    thousands_separator: Option<char>,
+    /// Write every discarded file, skipped line, dropped or uncovered statement, alignment
+    /// fix-up and suppression applied during the run to FILE, as JSON lines
+    #[arg(long, value_name = "FILE", global = true)]
+    audit_log: Option<std::path::PathBuf>,
+    /// Memory to stay within, e.g. 512M or 4G. Runs projected to need more stream the tests
+    /// into the statements' counts when they can, and stop before reading the tests when not
+    #[arg(long, value_name = "SIZE", global = true)]
+    max_memory: Option<ByteSize>,
+    /// Directory for the sorted runs of rankings too large to sort within --max-memory;
+    /// defaults to the system's temporary directory
+    #[arg(long, value_name = "DIR", global = true)]
+    temp_dir: Option<std::path::PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct RankArgs {
+    /// Directory of the passing tests' coverage, or of every test with --passing-pattern
+    #[arg(required_unless_present = "snapshot")]
+    passing_dir: Option<std::path::PathBuf>,
+    #[arg(required_unless_present_any = ["snapshot", "failing_pattern"])]
+    failing_dir: Option<std::path::PathBuf>,
+    /// Read every test from the one directory, taking the files whose names match this
+    /// glob as passing, e.g. 'pass_*.gcov'
+    #[arg(long, value_name = "GLOB", requires = "failing_pattern",
+          conflicts_with_all = ["failing_dir", "snapshot"])]
+    passing_pattern: Option<String>,
+    /// With --passing-pattern, the glob matching the failing tests' file names
+    #[arg(long, value_name = "GLOB", requires = "passing_pattern",
+          conflicts_with_all = ["failing_dir", "snapshot"])]
+    failing_pattern: Option<String>,
+    /// Read the tests from a snapshot written by `fl_dstar snapshot` instead of directories
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["passing_dir", "failing_dir"])]
+    snapshot: Option<std::path::PathBuf>,
+    /// Only rank with the tests of this group: the first part of a test's file name after
+    /// any leading test_, or its --group-by metadata (see --test-groups)
+    #[arg(long, conflicts_with = "snapshot")]
+    group: Option<String>,
+    /// CSV of per-test metadata, such as owner, shard or platform: a test column naming
+    /// each test by its file name up to the first dot, and one column per key
+    #[arg(long, value_name = "FILE", conflicts_with = "snapshot")]
+    manifest: Option<std::path::PathBuf>,
+    /// Only rank with the tests whose metadata has KEY=VALUE; may be repeated
+    #[arg(long, value_name = "KEY=VALUE", requires = "manifest")]
+    filter: Vec<fl_dstar::manifest::Filter>,
+    /// Group tests by this metadata key instead of their name, for --group and
+    /// --test-groups
+    #[arg(long, value_name = "KEY", requires = "manifest")]
+    group_by: Option<String>,
+    #[command(flatten)]
+    scoring: ScoreArgs,
+}
+
+/// Which columns the ranking CSV has.
+#[derive(Args, Debug)]
+struct OutputArgs {
+    /// Comma separated columns to write: file, rank, line_number, statement, hash, ef, ep, nf,
+    /// np, total_failed, total_passed, suspiciousness, percentile, exam, owner
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<Column>,
+    /// Write the default columns of this schema version. The latest, 3, adds the file,
+    /// percentile and exam columns
+    #[arg(long, default_value_t = output::DEFAULT_SCHEMA_VERSION)]
+    schema_version: u32,
+    /// Write runs of nearby suspicious lines as hunks, ranked by their most suspicious line,
+    /// instead of one row per line
+    #[arg(long, conflicts_with = "columns")]
+    hunks: bool,
+    /// Format of the ranking: csv, json (one array) or jsonl (one object per line). JSON
+    /// keeps counts as numbers and suspiciousness unrounded
+    #[arg(long, default_value = "csv", conflicts_with = "hunks")]
+    output_format: OutputFormat,
+    /// Lines allowed between two suspicious lines of the same hunk
+    #[arg(long, default_value_t = 3, requires = "hunks")]
+    hunk_gap: u32,
+    /// Write N statements worth inspecting, spread over files and blocks of code, instead of
+    /// the whole ranking
+    #[arg(long, value_name = "N", conflicts_with_all = ["columns", "hunks"])]
+    inspection_budget: Option<usize>,
+    /// How much each earlier pick in the same file (and again in the same block) lowers a
+    /// statement's worth under --inspection-budget
+    #[arg(long, default_value_t = 0.5, requires = "inspection_budget")]
+    budget_decay: f64,
+    /// Also write a histogram of the scores to this SVG file
+    #[arg(long, value_name = "FILE")]
+    plot: Option<std::path::PathBuf>,
+    /// Render the report with this Tera/Jinja-style template instead of writing CSV;
+    /// output is HTML-escaped when the file name ends in .html or .htm (before any .tera)
+    #[arg(long, value_name = "FILE")]
+    template: Option<std::path::PathBuf>,
+    /// Also write a self-contained HTML report to this file
+    #[arg(long, value_name = "FILE")]
+    html: Option<std::path::PathBuf>,
+    /// Let readers of the --html report pick failing tests to mark the lines each executed;
+    /// keeps every failing test's coverage in the report
+    #[arg(long, requires = "html")]
+    html_test_overlay: bool,
+    /// Largest per-test coverage --html-test-overlay may add to the report, e.g. 512K or 8M;
+    /// past it the overlay is left out
+    #[arg(
+        long,
+        value_name = "SIZE",
+        default_value = "8M",
+        requires = "html_test_overlay"
+    )]
+    html_overlay_limit: ByteSize,
+    /// Title shown in the header of HTML and templated reports
+    #[arg(long, default_value = "Fault localization report")]
+    title: String,
+    /// Free-form notes shown under the title, such as the bug or build being investigated
+    #[arg(long)]
+    notes: Option<String>,
+    /// Also write the report as JSON to this file, with the same fields templates see but
+    /// with scores as numbers rather than formatted text
+    #[arg(long, value_name = "FILE")]
+    json: Option<std::path::PathBuf>,
+    /// Add a provenance block to HTML, JSON and templated reports: the tool version and the
+    /// SHA-256 of every input file
+    #[arg(long)]
+    provenance: bool,
+    /// Sign the --html and --json reports with this ed25519 private key (PEM), writing a
+    /// detached REPORT.sig next to each; implies --provenance
+    #[arg(long, value_name = "KEY")]
+    sign: Option<std::path::PathBuf>,
+    /// Include each statement's per-test coverage as base64 bitvectors in JSON and templated
+    /// reports, for training learned models without the coverage files
+    #[arg(long)]
+    coverage_vectors: bool,
+    /// Index of past bugs (see `history record`); similar ones are pointed out on stderr
+    #[arg(long, value_name = "FILE")]
+    history: Option<std::path::PathBuf>,
+    /// Before the ranking, list on stderr the statements only failing tests execute and those
+    /// only passing tests execute
+    #[arg(long)]
+    exclusive: bool,
+    /// Also write the failing rate of each group of tests to this file as CSV, to see which
+    /// subsystem's tests drive the ranking
+    #[arg(long, value_name = "FILE")]
+    test_groups: Option<std::path::PathBuf>,
+    /// Also write a step-by-step trace of how every statement was scored to this file
+    #[arg(long, value_name = "FILE")]
+    explain_all: Option<std::path::PathBuf>,
+    /// Also write the suspicious lines as a GitLab Code Quality report to this file
+    #[arg(long, value_name = "FILE")]
+    code_quality: Option<std::path::PathBuf>,
+    /// Also write the suspicious lines as Jenkins Warnings NG JSON to this file
+    #[arg(long, value_name = "FILE")]
+    warnings_ng: Option<std::path::PathBuf>,
+    /// Also write the suspicious lines as SonarQube generic external issues to this file
+    #[arg(long, value_name = "FILE")]
+    sonar: Option<std::path::PathBuf>,
+    /// Most suspicious lines reported as CI issues
+    #[arg(long, default_value_t = 50)]
+    max_issues: usize,
+    /// Post the most suspicious lines to this Slack or Teams incoming webhook (uses curl)
+    #[arg(long, value_name = "WEBHOOK_URL")]
+    notify: Option<String>,
+    /// Webhook service: slack or teams; guessed from the URL by default
+    #[arg(long, requires = "notify")]
+    notify_service: Option<Service>,
+    /// Number of lines posted by --notify
+    #[arg(long, default_value_t = 5)]
+    notify_top: usize,
+    /// Also write a ready-to-file issue body to this file: the failing tests, the most
+    /// suspicious lines with the code around them, the formula used and how to reproduce
+    #[arg(long, value_name = "FILE")]
+    issue: Option<std::path::PathBuf>,
+    /// Markup of the --issue body: github (Markdown) or jira (wiki markup)
+    #[arg(long, default_value = "github", requires = "issue")]
+    issue_markup: fl_dstar::issue::Markup,
+    /// Number of lines listed in the --issue body
+    #[arg(long, default_value_t = 10, requires = "issue")]
+    issue_top: usize,
+    /// CODEOWNERS file naming who owns each source, relative to --root; adds an owner column
+    /// to the ranking
+    #[arg(long, value_name = "FILE")]
+    codeowners: Option<std::path::PathBuf>,
+    /// Also write, per owner in --codeowners, how many of their statements are suspicious and
+    /// their most suspicious one to this file as CSV, for routing results to teams
+    #[arg(long, value_name = "FILE", requires = "codeowners")]
+    owners_report: Option<std::path::PathBuf>,
+    /// Base URL that source paths are appended to for links, e.g.
+    /// https://github.com/org/repo/blob/main
+    #[arg(long, value_name = "URL")]
+    repo_url: Option<String>,
+    /// Instead of writing the ranking, answer JSON-RPC 2.0 requests on stdin, one per line,
+    /// until it closes: rank (limit), query (path, line), explain (path, line) and formulas.
+    /// For editor plugins that ask about many lines without starting fl_dstar for each
+    #[arg(long, conflicts_with = "columns")]
+    json_rpc: bool,
+}
+
+impl OutputArgs {
+    fn columns(&self) -> Vec<Column> {
+        if !self.columns.is_empty() {
+            if self.columns.contains(&Column::Owner) && self.codeowners.is_none() {
+                eprintln!("The owner column needs --codeowners");
+                std::process::exit(1);
+            }
+            return self.columns.clone();
+        }
+        let mut columns = output::default_columns(self.schema_version).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        if self.codeowners.is_some() {
+            columns.push(Column::Owner);
+        }
+        columns
+    }
+
+    /// The rules of --codeowners, empty when it wasn't given.
+    fn code_owners(&self) -> fl_dstar::owners::CodeOwners {
+        let Some(path) = &self.codeowners else {
+            return Default::default();
+        };
+        fl_dstar::encoding::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| fl_dstar::owners::CodeOwners::parse(&text))
+            .unwrap_or_else(|e| {
+                eprintln!("Could not read {}: {}", path.display(), e);
+                std::process::exit(1);
+            })
+    }
+
+    /// Whether the CSV ranking on stdout is the only output asked for.
+    fn csv_only(&self) -> bool {
+        let files = [
+            &self.plot,
+            &self.template,
+            &self.html,
+            &self.json,
+            &self.history,
+            &self.explain_all,
+            &self.code_quality,
+            &self.warnings_ng,
+            &self.sonar,
+            &self.owners_report,
+            &self.issue,
+        ];
+        files.iter().all(|file| file.is_none())
+            && self.notify.is_none()
+            && self.inspection_budget.is_none()
+            && !self.hunks
+            && !self.exclusive
+    }
+}
+
+/// How the coverage of each test is read and scored.
+#[derive(Args, Debug)]
+struct ScoreArgs {
+    /// Format of the per-test coverage files (gcov, lcov, llvm-cov or showmap)
+    #[arg(long, default_value = "gcov")]
+    format: Format,
+    /// Ranking technique: formula, nearest-neighbor, union, intersection or barinel-bayes
+    #[arg(long, default_value = "formula")]
+    technique: Technique,
+    /// Formula the formula technique scores with: dstar, tarantula, ochiai, jaccard, op2 or
+    /// barinel
+    #[arg(long, default_value = "dstar")]
+    metric: Metric,
+    /// Exponent of ef in the dstar metric; 3 and higher favor statements more failing tests
+    /// cover
+    #[arg(long, value_name = "K", default_value_t = fl_dstar::scoring::DEFAULT_STAR,
+          value_parser = clap::value_parser!(u32).range(1..))]
+    dstar_exponent: u32,
+    /// Correction for small failing sets: none, add-one, beta or beta:ALPHA,BETA
+    #[arg(long, default_value = "none")]
+    smoothing: Smoothing,
+    /// JSON edge list of data/control dependencies to spread scores along
+    #[arg(long, value_name = "FILE")]
+    dependencies: Option<std::path::PathBuf>,
+    /// Coverage file whose executable lines are the statements ranked, instead of every
+    /// executable line any test lists; not used with --snapshot
+    #[arg(long, value_name = "FILE")]
+    baseline: Option<std::path::PathBuf>,
+    /// Fraction of a statement's score passed to the statements it depends on, above 0 and
+    /// at most 1
+    #[arg(long, default_value_t = 0.5, requires = "dependencies",
+          value_parser = positive_fraction)]
+    propagation: f32,
+    /// Text of a bug report; statements whose identifiers resemble it are ranked higher
+    #[arg(long, value_name = "FILE")]
+    bug_report: Option<std::path::PathBuf>,
+    /// Share of the final score that comes from similarity to the bug report
+    #[arg(long, default_value_t = 0.3, requires = "bug_report",
+          value_parser = unit_interval)]
+    ir_weight: f32,
+}
+
+fn unit_interval(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(x) if (0.0..=1.0).contains(&x) => Ok(x),
+        _ => Err(format!("expected a number between 0 and 1, found '{}'", s)),
+    }
+}
+
+/// Like [`unit_interval`], without 0.
+fn positive_fraction(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(x) if x > 0.0 && x <= 1.0 => Ok(x),
+        _ => Err(format!(
+            "expected a number above 0 and at most 1, found '{}'",
+            s
+        )),
+    }
+}
+
+impl ScoreArgs {
+    /// The smoothing of the formula behind the scores, when the scores come
+    /// straight from the formula and can be explained with it.
+    fn formula(&self) -> Option<Smoothing> {
+        match (
+            self.technique,
+            self.metric(),
+            &self.dependencies,
+            &self.bug_report,
+        ) {
+            (Technique::Formula, Metric::DSTAR, None, None) => Some(self.smoothing),
+            _ => None,
+        }
+    }
+
+    /// The --metric, with the star of D* from --dstar-exponent.
+    fn metric(&self) -> Metric {
+        match self.metric {
+            Metric::DStar { .. } => Metric::DStar {
+                star: self.dstar_exponent,
+            },
+            metric => {
+                if self.dstar_exponent != fl_dstar::scoring::DEFAULT_STAR {
+                    eprintln!("--dstar-exponent only applies to the dstar metric");
+                    std::process::exit(1);
+                }
+                metric
+            }
+        }
+    }
+
+    /// How the scores were computed, as written in reports.
+    fn method(&self) -> String {
+        let mut method = match self.technique {
+            Technique::Formula => match self.metric() {
+                Metric::DStar { star: 2 } => format!(
+                    "the D* formula ef² / (ep + nf), smoothing {}",
+                    fl_dstar::explain::describe(self.smoothing)
+                ),
+                Metric::DStar { star } => format!(
+                    "the D* formula ef^{} / (ep + nf), smoothing {}",
+                    star,
+                    fl_dstar::explain::describe(self.smoothing)
+                ),
+                metric => format!(
+                    "the {} formula, smoothing {}",
+                    metric.name(),
+                    fl_dstar::explain::describe(self.smoothing)
+                ),
+            },
+            Technique::NearestNeighbor => "the nearest neighbor technique".to_string(),
+            Technique::Union => "the union technique".to_string(),
+            Technique::Intersection => "the intersection technique".to_string(),
+            Technique::BarinelBayes => "Barinel's Bayesian ranking".to_string(),
+        };
+        if self.dependencies.is_some() {
+            method.push_str(", spread along dependencies");
+        }
+        if self.bug_report.is_some() {
+            method.push_str(", weighted by similarity to a bug report");
+        }
+        method
+    }
+}
+
+/// How source paths are rewritten before they are reported, and which
+/// sources and statements are left out entirely.
+struct InputOptions {
+    maps: Vec<PathMap>,
+    symlinks: Symlinks,
+    root: Option<std::path::PathBuf>,
+    exclude: Vec<String>,
+    harness: Option<HarnessFilter>,
+    binary: Option<std::path::PathBuf>,
+    /// Symbol addresses from the --linker-map
+    symbols: Option<HashMap<String, u64>>,
+    parse: formats::ParseOptions,
+    /// Hits below which a line counts as not covered
+    min_hits: u64,
+    brace_lines: bool,
+    redact: bool,
+    /// Lines triaged as innocent, hidden from reports
+    suppressions: Vec<Suppression>,
+    /// How numbers are written in human-readable output
+    numbers: NumberFormat,
+    /// Where decisions are recorded for --audit-log
+    audit: Option<audit::Log>,
+    max_memory: Option<ByteSize>,
+    /// Whether the command looks at each test's coverage once the spectrum
+    /// is built, which rules out streaming
+    per_test: bool,
+    temp_dir: Option<std::path::PathBuf>,
+}
+
+// parsed once per run, so the size of the larger variants doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Convert a coverage file from one format to another
+    Convert {
+        /// Format of the input file (gcov, lcov, llvm-cov or showmap)
+        #[arg(long)]
+        from: Format,
+        /// Format to write (gcov, lcov or showmap)
+        #[arg(long)]
+        to: Format,
+        input: std::path::PathBuf,
+        /// Where to write the converted coverage; defaults to stdout
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Report the lines covered by one test and not the other
+    CovDiff {
+        first: std::path::PathBuf,
+        second: std::path::PathBuf,
+        /// Format of both coverage files
+        #[arg(long, default_value = "gcov")]
+        format: Format,
+    },
+    /// Rank functions using the summaries printed by `gcov -f`, one file per test
+    Functions {
+        passing_dir: std::path::PathBuf,
+        failing_dir: std::path::PathBuf,
+        /// Weight scores by a static metric: none, loc or complexity
+        #[arg(long, default_value = "none")]
+        weight: Weighting,
+        /// CSV of function metrics with function and complexity and/or loc columns (lizard's
+        /// --csv output with a header row works); loc defaults to gcov's line counts
+        #[arg(long, value_name = "FILE")]
+        metrics: Option<std::path::PathBuf>,
+    },
+    /// List sets of statements that together explain every failing test
+    Diagnose {
+        #[command(flatten)]
+        rank: RankArgs,
+        /// Largest number of faulty statements a candidate may contain
+        #[arg(long, default_value_t = 3)]
+        max_size: usize,
+        /// Stop after finding this many candidates
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Run fuzz inputs under coverage, classify crashes and rank the code most correlated with them
+    FuzzTriage {
+        /// Directory of inputs that are expected not to crash
+        #[arg(long)]
+        corpus: std::path::PathBuf,
+        /// Directory of crashing inputs
+        #[arg(long)]
+        crashes: std::path::PathBuf,
+        /// Shell command run for each input; `{input}` is replaced by the input
+        /// path and `{coverage}` by the file the command must write coverage to
+        #[arg(long)]
+        command: String,
+        /// Where per-input coverage is written; defaults to a temporary directory
+        #[arg(long)]
+        work_dir: Option<std::path::PathBuf>,
+        #[command(flatten)]
+        scoring: ScoreArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Walk through the statements a failing test executed, most suspicious first
+    Slice {
+        #[command(flatten)]
+        rank: RankArgs,
+        /// File name (or stem) of the failing test; may be omitted if there is only one
+        #[arg(long)]
+        test: Option<String>,
+        /// Print the test's lines in source order with rank and score annotations
+        #[arg(long)]
+        annotate: bool,
+    },
+    /// Print the most suspicious hunks as diff-style excerpts with score gutters, for pasting
+    /// into chat
+    Show {
+        #[command(flatten)]
+        rank: RankArgs,
+        /// Number of hunks to print
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+        /// Lines allowed between two suspicious lines of the same hunk
+        #[arg(long, default_value_t = 3)]
+        gap: u32,
+        /// Lines shown either side of each hunk
+        #[arg(long, default_value_t = 2)]
+        context: u32,
+        /// Color the output and highlight the code: auto, always or never
+        #[arg(long, default_value = "auto")]
+        color: fl_dstar::show::Color,
+    },
+    /// Score tests that have not been run by how much they would disambiguate the ranking
+    SuggestTests {
+        #[command(flatten)]
+        rank: RankArgs,
+        /// Directory with the expected coverage of each candidate test
+        #[arg(long)]
+        candidates: std::path::PathBuf,
+    },
+    /// Synthesize passing and failing gcov files with known faults, for benchmarks and exercises
+    Generate {
+        /// Directory to create passing/, failing/ and faults.csv in
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+        /// Executable statements in the program
+        #[arg(long, default_value_t = 100)]
+        statements: u32,
+        #[arg(long, default_value_t = 20)]
+        passing: usize,
+        #[arg(long, default_value_t = 5)]
+        failing: usize,
+        /// Number of faulty statements
+        #[arg(long, default_value_t = 1)]
+        faults: u32,
+        /// Chance that a test runs any given statement
+        #[arg(long, default_value_t = 0.5)]
+        density: f64,
+        /// Chance that a test runs a fault and passes anyway
+        #[arg(long, default_value_t = 0.1)]
+        noise: f64,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Grade a submitted ranking CSV against a reference ranking
+    Grade {
+        submission: std::path::PathBuf,
+        reference: std::path::PathBuf,
+        /// Largest difference between scores that still counts as correct
+        #[arg(long, default_value_t = 0.01)]
+        epsilon: f32,
+        /// Largest difference between ranks that still counts as correct
+        #[arg(long, default_value_t = 0)]
+        rank_tolerance: usize,
+        /// How many of the most suspicious statements the top-k criterion compares
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+    /// Write per-statement features (counts, scores of every formula and technique, churn and
+    /// static measures) as CSV, for learning-to-rank pipelines
+    Features {
+        #[command(flatten)]
+        rank: RankArgs,
+        /// Where to write the features; defaults to stdout
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Rank the commits that last touched the suspicious statements by their summed
+    /// suspiciousness, to find the commit most likely to have introduced the failure
+    BlameCommits {
+        #[command(flatten)]
+        rank: RankArgs,
+        /// How many commits to list; defaults to all of them
+        #[arg(long)]
+        top: Option<usize>,
+    },
+    /// Find the commit range where the top suspicious lines of the newest ranking first
+    /// became suspicious, to narrow a git bisect when tests are slow
+    BisectHint {
+        /// Snapshots taken at several commits, oldest first, as COMMIT=SNAPSHOT
+        #[arg(required = true, num_args = 2..)]
+        checkpoints: Vec<fl_dstar::bisect::Checkpoint>,
+        /// How many of the most suspicious statements to follow back
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        #[command(flatten)]
+        scoring: ScoreArgs,
+    },
+    /// List the statements only the failing platform's tests execute, for bugs that show on
+    /// one platform; needs a --manifest giving each test's platform
+    PlatformDiff {
+        #[command(flatten)]
+        rank: RankArgs,
+        /// Metadata key holding each test's platform
+        #[arg(long, default_value = "platform")]
+        platform_key: String,
+        /// Platform to compare against the others; defaults to the one with the highest
+        /// failing rate
+        #[arg(long)]
+        platform: Option<String>,
+    },
+    /// Rank condition outcomes instead of lines, from gcov's --conditions output (GCC 14 and
+    /// later), for projects that localize faults at the condition level
+    Conditions {
+        passing_dir: std::path::PathBuf,
+        failing_dir: std::path::PathBuf,
+        /// Correction for small failing sets: none, add-one, beta or beta:ALPHA,BETA
+        #[arg(long, default_value = "none")]
+        smoothing: Smoothing,
+    },
+    /// Look up the rank and score of a statement, for scripts and editors
+    Query {
+        /// Location as path:line, e.g. src/tcas.c:77
+        location: String,
+        #[command(flatten)]
+        rank: RankArgs,
+    },
+    /// Run a SQL query against the spectrum's statements, tests and coverage tables, e.g.
+    /// "SELECT line_number, statement FROM statements WHERE ep = 0 ORDER BY ef DESC"
+    Sql {
+        query: String,
+        #[command(flatten)]
+        rank: RankArgs,
+    },
+    /// Save the tests' coverage as a compact snapshot that can be ranked again later
+    Snapshot {
+        #[command(flatten)]
+        rank: RankArgs,
+        /// Where to write the snapshot
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+        /// none or zstd (runs the zstd command, or $ZSTD)
+        #[arg(long, default_value = "zstd")]
+        compression: Compression,
+    },
+    /// Keep an index of past bugs, so rankings that resemble one can point to its fix
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+    /// Publish the ranking to a code hosting service
+    Publish {
+        #[command(subcommand)]
+        target: PublishTarget,
+    },
+    /// Write a starter fl_dstar.env, test manifest and run_fl.sh, which collects each test's
+    /// coverage and ranks it, for the project in a directory
+    Init {
+        /// The project's top directory
+        #[arg(default_value = ".")]
+        dir: std::path::PathBuf,
+        /// Build system to write the run script for: make, cmake or cargo; by default, the one
+        /// whose files are in the directory
+        #[arg(long)]
+        build_system: Option<fl_dstar::scaffold::BuildSystem>,
+        /// Overwrite the files if they already exist
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum HistoryAction {
+    /// Add the bug the tests reveal, once it is fixed, to the index
+    Record {
+        #[command(flatten)]
+        rank: RankArgs,
+        /// Index file; created if it doesn't exist
+        #[arg(long, value_name = "FILE")]
+        index: std::path::PathBuf,
+        /// Commit that fixed the bug
+        #[arg(long)]
+        commit: String,
+        /// Location of the fault as path:line; may be repeated
+        #[arg(long, required = true)]
+        fault: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum PublishTarget {
+    /// Create a GitHub Check Run that annotates the most suspicious lines
+    GithubCheck {
+        #[command(flatten)]
+        rank: RankArgs,
+        /// Token allowed to write checks; defaults to $GITHUB_TOKEN
+        #[arg(long)]
+        token: Option<String>,
+        /// Repository as owner/name; defaults to $GITHUB_REPOSITORY
+        #[arg(long)]
+        repo: Option<String>,
+        /// Commit to attach the check to; defaults to $GITHUB_SHA
+        #[arg(long)]
+        sha: Option<String>,
+        /// API root, for GitHub Enterprise; defaults to $GITHUB_API_URL or https://api.github.com
+        #[arg(long)]
+        api_url: Option<String>,
+        /// Name of the check
+        #[arg(long, default_value = "fl_dstar")]
+        name: String,
+        /// Most suspicious lines annotated
+        #[arg(long, default_value_t = 50)]
+        max_annotations: usize,
+    },
 }
 
 fn main() {
     let args = Cli::parse();
+    let root = match &args.root {
+        Some(root) => Some(fs::canonicalize(root).unwrap_or_else(|_| root.clone())),
+        None => std::env::current_dir()
+            .ok()
+            .and_then(|dir| paths::find_project_root(&dir)),
+    };
+    let suppressions = fl_dstar::suppressions::load(args.suppressions.as_deref(), root.as_deref())
+        .unwrap_or_else(|e| {
+            eprintln!("Could not read suppressions {}", e);
+            std::process::exit(1);
+        });
+    let today = Date::today();
+    for suppression in &suppressions {
+        if !suppression.is_active(today) {
+            eprintln!(
+                "The suppression of {}:{} expired on {}",
+                suppression.path,
+                suppression.line_number,
+                suppression.expires.unwrap()
+            );
+        }
+    }
+    let options = InputOptions {
+        maps: args.path_map,
+        symlinks: args.symlinks,
+        root,
+        exclude: args.exclude_path,
+        harness: args.suppress_harness.then(|| {
+            let mut filter = HarnessFilter::default();
+            filter.statement_prefixes.extend(args.harness_statement);
+            filter.file_patterns.extend(args.harness_file);
+            filter
+        }),
+        binary: args.binary,
+        symbols: args.linker_map.map(|path| {
+            let text = fl_dstar::encoding::read_to_string(&path).unwrap_or_else(|e| {
+                eprintln!("Could not read {}: {}", path.display(), e);
+                std::process::exit(1);
+            });
+            fl_dstar::embedded::parse_linker_map(&text)
+        }),
+        parse: formats::ParseOptions {
+            strict: args.strict,
+            keep_noncode: args.keep_noncode,
+            ..formats::ParseOptions::default()
+        },
+        min_hits: args.min_hits,
+        brace_lines: !args.no_brace_lines,
+        redact: args.redact_statements,
+        suppressions,
+        numbers: number_format(
+            args.locale.as_deref(),
+            args.decimal_separator,
+            args.thousands_separator,
+        ),
+        audit: args.audit_log.is_some().then(audit::Log::default),
+        max_memory: args.max_memory,
+        per_test: match &args.command {
+            None => args.output.coverage_vectors || args.keep_noncode,
+            Some(Commands::Query { .. }) => false,
+            Some(_) => true,
+        },
+        temp_dir: args.temp_dir,
+    };
+    match args.command {
+        Some(Commands::Convert {
+            from,
+            to,
+            input,
+            output,
+        }) => convert(from, to, &input, output.as_deref(), &options),
+        Some(Commands::CovDiff {
+            first,
+            second,
+            format,
+        }) => cov_diff(&first, &second, format, &options),
+        Some(Commands::Functions {
+            passing_dir,
+            failing_dir,
+            weight,
+            metrics,
+        }) => functions(
+            &passing_dir,
+            &failing_dir,
+            weight,
+            metrics.as_deref(),
+            &options,
+        ),
+        Some(Commands::Diagnose {
+            rank,
+            max_size,
+            limit,
+        }) => diagnose(rank, max_size, limit, &options),
+        Some(Commands::FuzzTriage {
+            corpus,
+            crashes,
+            command,
+            work_dir,
+            scoring,
+            output,
+        }) => fuzz_triage(
+            &corpus, &crashes, &command, work_dir, &scoring, &output, &options,
+        ),
+        Some(Commands::Slice {
+            rank,
+            test,
+            annotate,
+        }) => slice(rank, test, annotate, &options),
+        Some(Commands::Show {
+            rank,
+            top,
+            gap,
+            context,
+            color,
+        }) => show(rank, top, gap, context, color, &options),
+        Some(Commands::SuggestTests { rank, candidates }) => {
+            suggest_tests(rank, &candidates, &options)
+        }
+        Some(Commands::Generate {
+            output,
+            statements,
+            passing,
+            failing,
+            faults,
+            density,
+            noise,
+            seed,
+        }) => generate(
+            &output,
+            &fl_dstar::generate::GeneratorConfig {
+                statements,
+                passing,
+                failing,
+                faults,
+                density,
+                noise,
+                seed,
+            },
+        ),
+        Some(Commands::Grade {
+            submission,
+            reference,
+            epsilon,
+            rank_tolerance,
+            top,
+        }) => grade(
+            &submission,
+            &reference,
+            fl_dstar::grade::Tolerance {
+                epsilon,
+                rank: rank_tolerance,
+                top,
+            },
+        ),
+        Some(Commands::Features { rank, output }) => features(rank, output.as_deref(), &options),
+        Some(Commands::BlameCommits { rank, top }) => blame_commits(rank, top, &options),
+        Some(Commands::BisectHint {
+            checkpoints,
+            top,
+            scoring,
+        }) => bisect_hint(&checkpoints, top, &scoring, &options),
+        Some(Commands::PlatformDiff {
+            rank,
+            platform_key,
+            platform,
+        }) => platform_diff(rank, &platform_key, platform, &options),
+        Some(Commands::Conditions {
+            passing_dir,
+            failing_dir,
+            smoothing,
+        }) => conditions(&passing_dir, &failing_dir, smoothing, &options),
+        Some(Commands::Query { location, rank }) => query(&location, rank, &options),
+        Some(Commands::Sql { query, rank }) => sql(&query, rank, &options),
+        Some(Commands::Snapshot {
+            rank,
+            output,
+            compression,
+        }) => {
+            let (_, spectrum) = load_spectrum(&rank, &options);
+            if let Err(e) = fl_dstar::snapshot::write(&spectrum, &output, compression) {
+                eprintln!("Could not write {}: {}", output.display(), e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::History { action }) => history(action, &options),
+        Some(Commands::Publish { target }) => publish(target, &options),
+        Some(Commands::Init {
+            dir,
+            build_system,
+            force,
+        }) => init(&dir, build_system, force),
+        None => rank(args.rank, &args.output, &options),
+    }
+    if let (Some(path), Some(log)) = (&args.audit_log, &options.audit) {
+        let written = fs::File::create(path).and_then(|file| log.write(io::BufWriter::new(file)));
+        if let Err(e) = written {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Stop on an error writing the output to stdout: quietly when whatever was
+/// reading it has gone, as `| head` does, and otherwise with the error.
+fn output_failed(e: impl std::error::Error + 'static) -> ! {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&e);
+    while let Some(error) = source {
+        // a csv error doesn't give its io error as its source
+        let io_error = match error.downcast_ref::<csv::Error>().map(csv::Error::kind) {
+            Some(csv::ErrorKind::Io(e)) => Some(e),
+            _ => error.downcast_ref::<io::Error>(),
+        };
+        if io_error.is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe) {
+            std::process::exit(0);
+        }
+        source = error.source();
+    }
+    eprintln!("Could not write the output: {}", e);
+    std::process::exit(1);
+}
+
+fn number_format(
+    locale: Option<&str>,
+    decimal: Option<char>,
+    grouping: Option<char>,
+) -> NumberFormat {
+    let mut numbers = match locale {
+        Some("auto") => NumberFormat::from_env(),
+        Some(locale) => locale.parse().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }),
+        None => NumberFormat::default(),
+    };
+    if let Some(decimal) = decimal {
+        numbers.decimal = decimal;
+    }
+    if grouping.is_some() {
+        numbers.grouping = grouping;
+    }
+    numbers
+}
+
+/// Read a coverage file with source paths normalized and excluded sources
+/// removed. With `--binary` the file is an address trace instead.
+fn read_coverage(
+    format: Format,
+    path: &std::path::Path,
+    options: &InputOptions,
+) -> Vec<formats::SourceCoverage> {
+    try_read_coverage(format, path, options).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+/// Like [`read_coverage`], but an unreadable file is reported to the caller
+/// rather than ending the program.
+fn try_read_coverage(
+    format: Format,
+    path: &std::path::Path,
+    options: &InputOptions,
+) -> Result<Vec<formats::SourceCoverage>, String> {
+    let mut coverage = match &options.binary {
+        Some(binary) => {
+            fl_dstar::symbolize::read_address_trace(path, binary, options.symbols.as_ref())
+                .map_err(|e| format!("Could not symbolize {}: {}", path.display(), e))?
+        }
+        None => {
+            let text = fl_dstar::encoding::read_to_string(path)
+                .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+            formats::read_with(format, text.as_bytes(), &options.parse)
+                .map_err(|e| format!("Could not parse {}: {}", path.display(), e))?
+        }
+    };
+    if let Some(log) = &options.audit {
+        for (source, skipped) in coverage
+            .iter()
+            .flat_map(|record| record.skipped.iter().map(move |s| (&record.source, s)))
+        {
+            log.record(audit::Event {
+                action: audit::Action::SkippedLine,
+                input: Some(path.display().to_string()),
+                source: source.clone(),
+                line: Some(skipped.number),
+                text: Some(skipped.text.clone()),
+                reason: skipped.reason.clone(),
+            });
+        }
+    }
+    formats::normalize_sources(&mut coverage, &options.maps, options.root.as_deref());
+    audited(
+        options,
+        path,
+        &mut coverage,
+        "excluded by --exclude-path",
+        |coverage| formats::exclude_sources(coverage, &options.exclude),
+    );
+    if let Some(harness) = &options.harness {
+        audited(options, path, &mut coverage, "harness code", |coverage| {
+            harness.apply(coverage)
+        });
+    }
+    let reason = format!("executed fewer than {} times", options.min_hits);
+    audited(options, path, &mut coverage, &reason, |coverage| {
+        formats::apply_min_hits(coverage, options.min_hits)
+    });
+    if !options.brace_lines {
+        audited(options, path, &mut coverage, "brace line", |coverage| {
+            formats::drop_brace_lines(coverage)
+        });
+    }
+    // after the harness filter, which matches statement text
+    if options.redact {
+        fl_dstar::redact::redact_coverage(&mut coverage);
+    }
+    Ok(coverage)
+}
+
+/// Apply `step` to the coverage read from `path`, recording the statements
+/// it drops or uncovers, for `reason`, when auditing.
+fn audited(
+    options: &InputOptions,
+    path: &std::path::Path,
+    coverage: &mut Vec<formats::SourceCoverage>,
+    reason: &str,
+    step: impl FnOnce(&mut Vec<formats::SourceCoverage>),
+) {
+    let Some(log) = &options.audit else {
+        return step(coverage);
+    };
+    let before = audit::Snapshot::of(coverage);
+    step(coverage);
+    for mut event in before.changes(coverage, reason) {
+        event.input = Some(path.display().to_string());
+        log.record(event);
+    }
+}
+
+/// Record that the coverage file `path` was left out, when auditing.
+fn audit_discarded(options: &InputOptions, path: &std::path::Path, reason: String) {
+    if let Some(log) = &options.audit {
+        let mut event = audit::Event::new(audit::Action::DiscardedFile, reason);
+        event.input = Some(path.display().to_string());
+        log.record(event);
+    }
+}
+
+fn read_lines(format: Format, path: &std::path::Path, options: &InputOptions) -> Vec<LineInfo> {
+    read_coverage(format, path, options)
+        .into_iter()
+        .flat_map(|record| record.lines)
+        .collect()
+}
+
+fn convert(
+    from: Format,
+    to: Format,
+    input: &std::path::Path,
+    output: Option<&std::path::Path>,
+    options: &InputOptions,
+) {
+    if !input.exists() {
+        eprintln!("The passed in input file does not exist");
+        std::process::exit(1);
+    }
+    let coverage = read_coverage(from, input, options);
+    let written = match output {
+        Some(path) => fs::File::create(path).and_then(|file| {
+            let mut writer = io::BufWriter::new(file);
+            formats::write(to, &coverage, &mut writer)?;
+            writer.flush()
+        }),
+        None => {
+            let mut writer = io::stdout().lock();
+            formats::write(to, &coverage, &mut writer).and_then(|_| writer.flush())
+        }
+    };
+    if let Err(e) = written {
+        let path = output.map_or("stdout".into(), |path| path.display().to_string());
+        eprintln!("Could not write {}: {}", path, e);
+        std::process::exit(1);
+    }
+}
+
+fn cov_diff(
+    first: &std::path::Path,
+    second: &std::path::Path,
+    format: Format,
+    options: &InputOptions,
+) {
+    for path in [first, second] {
+        if !path.exists() {
+            eprintln!("The passed in file {} does not exist", path.display());
+            std::process::exit(1);
+        }
+    }
+    let first_lines = read_lines(format, first, options);
+    let second_lines = read_lines(format, second, options);
+    let diff = fl_dstar::diff::coverage_diff(&first_lines, &second_lines);
+    // mirror diff(1): '<' lines belong to the first file, '>' to the second
+    let mut out = io::stdout().lock();
+    for line in diff.only_first {
+        writeln!(out, "< {}: {}", line.line_number, line.statement)
+            .unwrap_or_else(|e| output_failed(e));
+    }
+    for line in diff.only_second {
+        writeln!(out, "> {}: {}", line.line_number, line.statement)
+            .unwrap_or_else(|e| output_failed(e));
+    }
+}
+
+/// The files in `dir`, in name order. Only the entries are listed up front;
+/// each is checked as it's yielded, so artifact dumps with hundreds of
+/// thousands of entries aren't stat'ed before the first is read.
+/// Unreadable entries, subdirectories, dangling or looping links and, with
+/// `--symlinks skip`, every link are skipped with a warning. Hard links and
+/// symbolic links to a file already listed are skipped too, so a test
+/// reached twice is only counted once.
+fn list_dir(dir: &std::path::Path, symlinks: Symlinks) -> impl Iterator<Item = std::path::PathBuf> {
+    let entries = fs::read_dir(dir).unwrap_or_else(|e| {
+        eprintln!("Could not read {}: {}", dir.display(), e);
+        std::process::exit(1);
+    });
+    // in name order rather than the file system's, so the tests, and the
+    // keys of the sources they cover, are numbered the same on every machine
+    let mut entries: Vec<fs::DirEntry> = entries
+        .filter_map(|entry| {
+            entry
+                .map_err(|e| eprintln!("Skipping an unreadable directory entry: {}", e))
+                .ok()
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    let mut seen: HashMap<(u64, u64), std::path::PathBuf> = HashMap::new();
+    entries.into_iter().filter_map(move |entry| {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_symlink() && symlinks == Symlinks::Skip => {
+                eprintln!("Skipping symlink {}", path.display());
+                return None;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Skipping {}: {}", path.display(), e);
+                return None;
+            }
+        }
+        // follows links, so a dangling link or a loop of them fails here
+        let metadata = fs::metadata(&path)
+            .map_err(|e| eprintln!("Skipping {}: {}", path.display(), e))
+            .ok()?;
+        if metadata.is_dir() {
+            eprintln!("Skipping directory {}", path.display());
+            return None;
+        }
+        if let Some(id) = fl_dstar::paths::file_id(&metadata) {
+            if let Some(first) = seen.get(&id) {
+                eprintln!(
+                    "Skipping {}: the same file as {}",
+                    path.display(),
+                    first.display()
+                );
+                return None;
+            }
+            seen.insert(id, path.clone());
+        }
+        Some(path)
+    })
+}
+
+/// The coverage files a spectrum's tests were read from, in the order of
+/// its tests.
+#[derive(Default)]
+struct TestFiles {
+    passing: Vec<std::path::PathBuf>,
+    failing: Vec<std::path::PathBuf>,
+}
+
+/// Read every test in the passing and failing directories. Returns the
+/// test files alongside the spectrum so callers can refer back to them.
+fn load_spectrum(args: &RankArgs, options: &InputOptions) -> (TestFiles, Spectrum) {
+    if let Some(path) = &args.snapshot {
+        // without per-test output, count the snapshot's bits without expanding its tests;
+        // redaction rebuilds the spectrum from them
+        let per_test = options.per_test || options.redact;
+        let read = match per_test || args.scoring.technique != Technique::Formula {
+            true => fl_dstar::snapshot::read,
+            false => fl_dstar::snapshot::read_counts,
+        };
+        let mut spectrum = read(path).unwrap_or_else(|e| {
+            eprintln!("Could not read snapshot {}", e);
+            std::process::exit(1);
+        });
+        if options.redact {
+            fl_dstar::redact::redact_spectrum(&mut spectrum);
+        }
+        score_spectrum(&mut spectrum, &args.scoring);
+        // the snapshot doesn't know which files the tests came from
+        return (TestFiles::default(), spectrum);
+    }
+    let (passing_files, failing_files) = test_files(args, options);
+    let manifest = load_manifest(args);
+    let selected = |path: &std::path::PathBuf| {
+        let name = fl_dstar::groups::test_name(path);
+        if let Some(group) = &args.group {
+            if test_group(args, &manifest, &name) != *group {
+                audit_discarded(options, path, format!("not in group {}", group));
+                return false;
+            }
+        }
+        if !manifest.matches(&name, &args.filter) {
+            audit_discarded(options, path, "excluded by --filter".to_string());
+            return false;
+        }
+        true
+    };
+    spectrum_from_files(
+        passing_files.into_iter().filter(selected),
+        failing_files.into_iter().filter(selected),
+        &args.scoring,
+        options,
+    )
+}
+
+/// The coverage files of the passing and of the failing tests: those in the
+/// two test directories, or those in the one directory sorted by
+/// --passing-pattern and --failing-pattern.
+fn test_files(
+    args: &RankArgs,
+    options: &InputOptions,
+) -> (Vec<std::path::PathBuf>, Vec<std::path::PathBuf>) {
+    let passing_dir = args.passing_dir.as_ref().unwrap();
     // check that the passed in directories exist
-    if !args.passing_dir.exists() {
+    if !passing_dir.exists() {
         eprintln!("The passed in passing directory does not exist");
         std::process::exit(1);
     }
-    if !args.failing_dir.exists() {
-        eprintln!("The passed in failing directory does not exist");
+    let (Some(passing), Some(failing)) = (&args.passing_pattern, &args.failing_pattern) else {
+        let failing_dir = args.failing_dir.as_ref().unwrap();
+        if !failing_dir.exists() {
+            eprintln!("The passed in failing directory does not exist");
+            std::process::exit(1);
+        }
+        return (
+            list_dir(passing_dir, options.symlinks).collect(),
+            list_dir(failing_dir, options.symlinks).collect(),
+        );
+    };
+    let (mut passing_files, mut failing_files) = (Vec::new(), Vec::new());
+    for path in list_dir(passing_dir, options.symlinks) {
+        match fl_dstar::groups::outcome(&path, passing, failing) {
+            Ok(Some(true)) => passing_files.push(path),
+            Ok(Some(false)) => failing_files.push(path),
+            Ok(None) => {
+                eprintln!(
+                    "Skipping {}: matches neither --passing-pattern nor --failing-pattern",
+                    path.display()
+                );
+                audit_discarded(options, &path, "matches neither pattern".to_string());
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    (passing_files, failing_files)
+}
+
+/// Exit when no failing test could be read, as there is then no failure to
+/// localize, and warn when no passing test could, as statements can then
+/// only be told apart by how many failing tests ran them.
+fn check_test_sets(passing: usize, failing: usize) {
+    if failing == 0 {
+        eprintln!(
+            "No readable failing tests, so there is no failure to localize; check the failing \
+             directory"
+        );
         std::process::exit(1);
     }
-    // get a list of all the files in the passing and failing directories
-    let passing_files = fs::read_dir(&args.passing_dir)
-        .unwrap()
-        .map(|res| res.map(|e| e.path()))
-        .collect::<Result<Vec<_>, std::io::Error>>()
-        .unwrap();
+    if passing == 0 {
+        eprintln!(
+            "warning: no readable passing tests; statements are ranked only by how many of the \
+             {} failing tests ran them, and techniques that compare against passing tests score \
+             every statement the same",
+            failing
+        );
+    }
+}
 
-    let failing_files = fs::read_dir(&args.failing_dir)
-        .unwrap()
-        .map(|res| res.map(|e| e.path()))
-        .collect::<Result<Vec<_>, std::io::Error>>()
-        .unwrap();
+/// Read and score the coverage of the given passing and failing tests.
+/// Files that can't be read are skipped with a warning; the files that were
+/// read are returned alongside the spectrum.
+fn spectrum_from_files(
+    passing_files: impl IntoIterator<Item = std::path::PathBuf>,
+    failing_files: impl IntoIterator<Item = std::path::PathBuf>,
+    args: &ScoreArgs,
+    options: &InputOptions,
+) -> (TestFiles, Spectrum) {
+    // parse the coverage files, keying each source's lines apart and
+    // remembering which source each key came from
+    let mut keys = fl_dstar::SourceKeys::default();
+    let mut sources = HashMap::new();
+    let sparse = options.binary.is_some() || args.format.is_sparse();
+    let baseline = args.baseline.as_ref().map(|path| {
+        let coverage = try_read_coverage(args.format, path, options)
+            .map(|mut coverage| {
+                coverage.iter_mut().for_each(|record| keys.rekey(record));
+                coverage
+            })
+            .unwrap_or_else(|e| {
+                eprintln!("Could not read the baseline: {}", e);
+                std::process::exit(1);
+            });
+        let lines: Vec<LineInfo> = coverage
+            .into_iter()
+            .flat_map(|record| record.lines)
+            .collect();
+        lines
+    });
+    // the baseline's statements, to point out covered lines it leaves out
+    let statements: Option<HashSet<u64>> = baseline.as_ref().map(|lines| {
+        lines
+            .iter()
+            .filter(|line| line.coverage != Coverage::NoExecutableCode)
+            .map(|line| line.key())
+            .collect()
+    });
+    let mut unmatched = 0;
+    let mut read_test = |file: &std::path::Path| -> Option<Vec<LineInfo>> {
+        let coverage = try_read_coverage(args.format, file, options)
+            .map(|mut coverage| {
+                coverage.iter_mut().for_each(|record| keys.rekey(record));
+                coverage
+            })
+            .map_err(|e| {
+                eprintln!("Skipping a test: {}", e);
+                audit_discarded(options, file, e);
+            })
+            .ok()?;
+        let mut lines = Vec::new();
+        for record in coverage {
+            if let Some(source) = &record.source {
+                sources.insert(keys.file(source), source.clone());
+            }
+            lines.extend(record.lines);
+        }
+        // an empty dense file is a truncated artifact, not a test that ran nothing
+        if lines.is_empty() && !sparse {
+            eprintln!("Skipping a test: {} has no coverage", file.display());
+            audit_discarded(options, file, "no coverage".to_string());
+            return None;
+        }
+        if let Some(statements) = &statements {
+            let left_out = lines.iter().filter(|line| {
+                line.coverage == Coverage::Covered && !statements.contains(&line.key())
+            });
+            for line in left_out {
+                unmatched += 1;
+                if let Some(log) = &options.audit {
+                    log.record(audit::Event {
+                        action: audit::Action::DroppedStatement,
+                        input: Some(file.display().to_string()),
+                        source: sources.get(&line.file).cloned(),
+                        line: Some(line.line_number as usize),
+                        text: Some(line.statement.clone()),
+                        reason: "not a statement of the baseline".to_string(),
+                    });
+                }
+            }
+        }
+        Some(lines)
+    };
+    let new_spectrum = |passing, failing| match &baseline {
+        Some(baseline) => Spectrum::with_baseline(baseline, passing, failing),
+        None => Spectrum::new(passing, failing),
+    };
+    let passing_files: Vec<std::path::PathBuf> = passing_files.into_iter().collect();
+    let failing_files: Vec<std::path::PathBuf> = failing_files.into_iter().collect();
+    let tests = passing_files.len() + failing_files.len();
+    // folding tests in as they're read needs every test to list the same statements
+    let can_stream = !options.per_test && !sparse && args.technique == Technique::Formula;
+    let mut strategy = Strategy::InMemory;
+    let mut folded: Option<Spectrum> = None;
+    let mut read = TestFiles::default();
+    let mut passing_files_info: Vec<Vec<LineInfo>> = Vec::new();
+    let mut failing_files_info: Vec<Vec<LineInfo>> = Vec::new();
+    let all = passing_files
+        .into_iter()
+        .map(|file| (file, true))
+        .chain(failing_files.into_iter().map(|file| (file, false)));
+    for (file, is_passing) in all {
+        let Some(lines) = read_test(&file) else {
+            continue;
+        };
+        let first = read.passing.is_empty() && read.failing.is_empty();
+        if let (Some(budget), true, true) = (options.max_memory, first, is_passing) {
+            strategy =
+                fl_dstar::memory::plan(budget, tests, &lines, can_stream).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                });
+            if strategy == Strategy::Streaming {
+                eprintln!("Streaming {} tests to stay within {}", tests, budget);
+            }
+        }
+        match (strategy, &mut folded) {
+            (Strategy::Streaming, Some(spectrum)) => spectrum.fold_test(&lines, is_passing),
+            (Strategy::Streaming, None) => folded = Some(new_spectrum(vec![lines], Vec::new())),
+            (Strategy::InMemory, _) if is_passing => passing_files_info.push(lines),
+            (Strategy::InMemory, _) => failing_files_info.push(lines),
+        }
+        match is_passing {
+            true => read.passing.push(file),
+            false => read.failing.push(file),
+        }
+    }
+    if unmatched > 0 {
+        eprintln!(
+            "warning: the tests covered {} lines that aren't statements of the baseline, which \
+             were left out; check that the baseline comes from the same build",
+            unmatched
+        );
+    }
+    check_test_sets(read.passing.len(), read.failing.len());
+    if let Some(mut spectrum) = folded {
+        // the first test was kept to list the statements; the counts cover it
+        spectrum.passing.clear();
+        spectrum.sources = sources;
+        score_spectrum(&mut spectrum, args);
+        return (read, spectrum);
+    }
+    if sparse {
+        // address traces and edge maps only list what ran, so line the tests up first
+        let passing_count = passing_files_info.len();
+        let mut all = std::mem::take(&mut passing_files_info);
+        all.append(&mut failing_files_info);
+        let reported: Vec<HashSet<u64>> = all
+            .iter()
+            .map(|lines| lines.iter().map(|line| line.key()).collect())
+            .collect();
+        fl_dstar::align_tests(&mut all);
+        if let Some(log) = &options.audit {
+            let files = read.passing.iter().chain(&read.failing);
+            for ((file, lines), reported) in files.zip(&all).zip(&reported) {
+                for line in lines.iter().filter(|l| !reported.contains(&l.key())) {
+                    log.record(audit::Event {
+                        action: audit::Action::Aligned,
+                        input: Some(file.display().to_string()),
+                        source: sources.get(&line.file).cloned(),
+                        line: Some(line.line_number as usize),
+                        text: Some(line.statement.clone()),
+                        reason: "not reported by the test, so not covered".to_string(),
+                    });
+                }
+            }
+        }
+        failing_files_info = all.split_off(passing_count);
+        passing_files_info = all;
+    }
+    let mut spectrum = new_spectrum(passing_files_info, failing_files_info);
+    spectrum.sources = sources;
+    score_spectrum(&mut spectrum, args);
+    (read, spectrum)
+}
 
-    // parse the gcov files
-    let passing_files_info: Vec<Vec<LineInfo>> = passing_files
-        .iter()
-        .map(|file| fl_dstar::parse_gcov_file(file))
-        .collect();
-    let failing_files_info: Vec<Vec<LineInfo>> = failing_files
+/// Score the statements, spreading scores along dependencies when asked to.
+fn score_spectrum(spectrum: &mut Spectrum, args: &ScoreArgs) {
+    match args.technique {
+        Technique::Formula => spectrum.score_metric(&args.metric(), args.smoothing),
+        technique => spectrum.score(technique, args.smoothing),
+    }
+    if let Some(warning) = fl_dstar::sanity::check_labels(&spectrum.statements) {
+        eprintln!("warning: {}", warning);
+    }
+    if let Some(path) = &args.dependencies {
+        let dependencies = match fl_dstar::dependencies::parse_dependency_file(path) {
+            Ok(dependencies) => dependencies,
+            Err(e) => {
+                eprintln!("Could not read {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        };
+        fl_dstar::dependencies::propagate(
+            &mut spectrum.statements,
+            &dependencies,
+            args.propagation,
+        );
+    }
+    if let Some(path) = &args.bug_report {
+        let report = fl_dstar::encoding::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Could not read {}: {}", path.display(), e);
+            std::process::exit(1);
+        });
+        fl_dstar::ir::blend(&mut spectrum.statements, &report, args.ir_weight);
+    }
+}
+
+fn fuzz_triage(
+    corpus: &std::path::Path,
+    crashes: &std::path::Path,
+    command: &str,
+    work_dir: Option<std::path::PathBuf>,
+    scoring: &ScoreArgs,
+    output: &OutputArgs,
+    options: &InputOptions,
+) {
+    for dir in [corpus, crashes] {
+        if !dir.exists() {
+            eprintln!("The passed in directory {} does not exist", dir.display());
+            std::process::exit(1);
+        }
+    }
+    let work_dir = work_dir.unwrap_or_else(|| {
+        std::env::temp_dir().join(format!("fl_dstar-triage-{}", std::process::id()))
+    });
+    fs::create_dir_all(&work_dir).unwrap_or_else(|e| {
+        eprintln!("Could not create {}: {}", work_dir.display(), e);
+        std::process::exit(1);
+    });
+    let mut passing_files = Vec::new();
+    let mut failing_files = Vec::new();
+    // the outcome is decided by actually running each input, not by its directory
+    let inputs = list_dir(corpus, options.symlinks).chain(list_dir(crashes, options.symlinks));
+    for (i, input) in inputs.enumerate() {
+        let coverage = work_dir.join(format!("{}.cov", i));
+        let filled = fl_dstar::triage::fill_template(command, &input, &coverage);
+        let crashed = match fl_dstar::triage::run_crashes(&filled) {
+            Ok(crashed) => crashed,
+            Err(e) => {
+                eprintln!("Could not run '{}': {}", filled, e);
+                std::process::exit(1);
+            }
+        };
+        if !coverage.exists() {
+            eprintln!(
+                "No coverage was written for {}; skipping it",
+                input.display()
+            );
+            continue;
+        }
+        match crashed {
+            true => failing_files.push(coverage),
+            false => passing_files.push(coverage),
+        }
+    }
+    eprintln!(
+        "{} inputs crashed, {} did not",
+        failing_files.len(),
+        passing_files.len()
+    );
+    if passing_files.is_empty() || failing_files.is_empty() {
+        eprintln!("Triage needs both crashing and non-crashing inputs");
+        std::process::exit(1);
+    }
+    let (files, mut spectrum) = spectrum_from_files(passing_files, failing_files, scoring, options);
+    fl_dstar::sort_by_suspiciousness(&mut spectrum.statements);
+    let inputs: Vec<std::path::PathBuf> = files.passing.into_iter().chain(files.failing).collect();
+    write_ranking(&spectrum, &inputs, scoring, output, options);
+}
+
+/// Warn when the source files have changed since the coverage of `lines`
+/// was collected, as annotated output would then show the wrong code.
+fn warn_on_drift(lines: &[LineInfo], spectrum: &Spectrum, options: &InputOptions) {
+    let drifted = fl_dstar::integrity::check(lines, &spectrum.sources, options.root.as_deref());
+    if drifted.is_empty() {
+        return;
+    }
+    eprintln!(
+        "warning: {} lines have changed since coverage was collected:",
+        drifted.len()
+    );
+    for drift in drifted.iter().take(5) {
+        eprintln!("  {}", drift);
+    }
+    if drifted.len() > 5 {
+        eprintln!("  and {} more", drifted.len() - 5);
+    }
+}
+
+/// The ranked statements minus the suppressed ones.
+fn visible_statements(spectrum: &Spectrum, options: &InputOptions) -> Vec<fl_dstar::StatementInfo> {
+    let visible = fl_dstar::suppressions::filter(
+        &spectrum.statements,
+        &spectrum.sources,
+        &options.suppressions,
+        Date::today(),
+    );
+    if options.audit.is_some() {
+        let shown: HashSet<u64> = visible.iter().map(|s| s.key()).collect();
+        for statement in &spectrum.statements {
+            if !shown.contains(&statement.key()) {
+                audit_suppressed(options, &spectrum.sources, statement);
+            }
+        }
+    }
+    visible
+}
+
+/// Record that `statement` was hidden by a suppression, when auditing.
+fn audit_suppressed(
+    options: &InputOptions,
+    sources: &HashMap<u32, String>,
+    statement: &fl_dstar::StatementInfo,
+) {
+    let Some(log) = &options.audit else {
+        return;
+    };
+    let source = sources.get(&statement.file).cloned();
+    let expires = options
+        .suppressions
         .iter()
-        .map(|file| fl_dstar::parse_gcov_file(file))
+        .find(|s| s.line_number == statement.line_number && Some(&s.path) == source.as_ref())
+        .and_then(|s| s.expires);
+    log.record(audit::Event {
+        action: audit::Action::Suppressed,
+        input: None,
+        source,
+        line: Some(statement.line_number as usize),
+        text: Some(statement.statement().to_string()),
+        reason: match expires {
+            Some(expires) => format!("suppressed until {}", expires),
+            None => "suppressed".to_string(),
+        },
+    });
+}
+
+/// The --issue body for `statements`, ranked from `spectrum` read from the
+/// files `inputs`.
+fn issue_body(
+    spectrum: &Spectrum,
+    statements: &[fl_dstar::StatementInfo],
+    inputs: &[std::path::PathBuf],
+    scoring: &ScoreArgs,
+    output: &OutputArgs,
+) -> String {
+    let failing_tests = failing_test_names(spectrum, inputs);
+    let lines = line_texts(spectrum);
+    let command: Vec<String> = std::iter::once("fl_dstar".to_string())
+        .chain(std::env::args().skip(1))
+        .map(|arg| match arg.contains(char::is_whitespace) {
+            true => format!("'{}'", arg),
+            false => arg,
+        })
         .collect();
-    // make a list of all the statements in the file. This should be the same for all passing and failing test casees
-    let mut statement_info_list: Vec<fl_dstar::StatementInfo> = Vec::new();
-    for line in &passing_files_info[0] {
-        let statement_info = fl_dstar::StatementInfo::new(
-            line.line_number,
-            line.statement.clone(),
-            failing_files.len() as u32,
+    let method = scoring.method();
+    let command = command.join(" ");
+    let draft = fl_dstar::issue::Draft {
+        title: &output.title,
+        notes: output.notes.as_deref(),
+        failing_tests: &failing_tests,
+        total_failed: spectrum.failing.len(),
+        total_passed: spectrum.passing.len(),
+        method: &method,
+        command: &command,
+    };
+    fl_dstar::issue::body(
+        &draft,
+        statements,
+        &spectrum.sources,
+        &lines,
+        output.issue_top,
+        output.repo_url.as_deref(),
+        output.issue_markup,
+    )
+}
+
+/// The files of the failing tests of `spectrum`, read from the files
+/// `inputs`, or none when they aren't known.
+fn failing_test_names(spectrum: &Spectrum, inputs: &[std::path::PathBuf]) -> Vec<String> {
+    // the failing tests come last in the inputs, unless they were a snapshot
+    match inputs.len() == spectrum.passing.len() + spectrum.failing.len() {
+        true => inputs[spectrum.passing.len()..]
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+        false => Vec::new(),
+    }
+}
+
+/// The text of every line the tests list, by key (see
+/// [`fl_dstar::statement_key`]).
+fn line_texts(spectrum: &Spectrum) -> HashMap<u64, &str> {
+    let mut lines = HashMap::new();
+    for line in spectrum.passing.iter().chain(&spectrum.failing).flatten() {
+        lines.entry(line.key()).or_insert(line.statement.as_str());
+    }
+    lines
+}
+
+/// Sign a report written to `path` when `--sign` asks for it.
+fn sign_report(path: &std::path::Path, output: &OutputArgs) {
+    if let Some(key) = &output.sign {
+        if let Err(e) = fl_dstar::provenance::sign(path, key) {
+            eprintln!("Could not sign {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Write the ranking of `spectrum`, read from the files `inputs`, in every
+/// form `output` asks for.
+fn write_ranking(
+    spectrum: &Spectrum,
+    inputs: &[std::path::PathBuf],
+    scoring: &ScoreArgs,
+    output: &OutputArgs,
+    options: &InputOptions,
+) {
+    let statements = visible_statements(spectrum, options);
+    let provenance = (output.provenance || output.sign.is_some()).then(|| {
+        fl_dstar::provenance::Provenance::collect(inputs)
+            .unwrap_or_else(|e| {
+                eprintln!("Could not hash the inputs: {}", e);
+                std::process::exit(1);
+            })
+            .to_json()
+    });
+    let add_provenance = |model: &mut fl_dstar::json::Json| {
+        if let (Some(provenance), fl_dstar::json::Json::Object(members)) = (&provenance, model) {
+            members.push(("provenance".to_string(), provenance.clone()));
+        }
+    };
+    if output.exclusive {
+        fl_dstar::exclusive::write_section(io::stderr(), &statements, &spectrum.sources)
+            .unwrap_or_else(|e| output_failed(e));
+    }
+    // every test lists the same lines, so any one gives the whole file
+    let listing = match options.parse.keep_noncode {
+        true => spectrum
+            .passing
+            .first()
+            .map_or(&[][..], |test| test.as_slice()),
+        false => &[],
+    };
+    if !listing.is_empty() && (output.html.is_some() || output.template.is_some()) {
+        warn_on_drift(listing, spectrum, options);
+    }
+    if let Some(path) = &output.plot {
+        let scores: Vec<f32> = statements.iter().map(|s| s.suspiciousness).collect();
+        let svg = fl_dstar::plot::histogram_svg(&scores, 20, &options.numbers);
+        if let Err(e) = fs::write(path, svg) {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &output.html {
+        let mut model = fl_dstar::report::html_model(
+            &statements,
+            &spectrum.sources,
+            &options.numbers,
+            &output.title,
+            output.notes.as_deref(),
+            scoring.formula(),
+            listing,
         );
-        // Skip over lines that have no executable code
-        if line.coverage == fl_dstar::Coverage::NoExecutableCode {
+        if output.html_test_overlay {
+            let mut names = failing_test_names(spectrum, inputs);
+            if names.is_empty() {
+                names = (1..=spectrum.failing.len())
+                    .map(|i| format!("failing test {}", i))
+                    .collect();
+            }
+            let limit = output.html_overlay_limit;
+            let added = fl_dstar::report::add_test_overlay(
+                &mut model,
+                &names,
+                &spectrum.failing,
+                &spectrum.sources,
+                limit.0,
+            );
+            if let Err(size) = added {
+                eprintln!(
+                    "warning: leaving the test overlay out of {}: it would take {}, more than \
+                     --html-overlay-limit {}",
+                    path.display(),
+                    ByteSize(size),
+                    limit
+                );
+            }
+        }
+        add_provenance(&mut model);
+        let html = fl_dstar::report::render_html(model, &statements, &options.numbers);
+        if let Err(e) = fs::write(path, html) {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+        sign_report(path, output);
+    }
+    if let Some(path) = &output.history {
+        let bugs = fl_dstar::history::load(path).unwrap_or_else(|e| {
+            eprintln!("Could not read {}: {}", path.display(), e);
+            std::process::exit(1);
+        });
+        let signature = fl_dstar::history::signature(&statements);
+        let threshold = fl_dstar::history::MATCH_THRESHOLD;
+        for (bug, similarity) in fl_dstar::history::matches(&bugs, &signature, threshold) {
+            eprintln!(
+                "Resembles the bug fixed in {} ({:.0}% similar), at {}",
+                bug.commit,
+                similarity * 100.0,
+                bug.faults.join(", ")
+            );
+        }
+    }
+    if let Some(path) = &output.json {
+        let mut model = output::export_model(&statements, &spectrum.sources);
+        fl_dstar::report::add_metadata(&mut model, &output.title, output.notes.as_deref());
+        fl_dstar::report::add_exclusive(&mut model, &statements, &spectrum.sources);
+        if output.coverage_vectors {
+            fl_dstar::report::add_coverage_vectors(
+                &mut model,
+                &statements,
+                &spectrum.passing,
+                &spectrum.failing,
+            );
+        }
+        add_provenance(&mut model);
+        if let Err(e) = fs::write(path, format!("{}\n", model)) {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+        sign_report(path, output);
+    }
+    let owners = output.code_owners();
+    if let Some(path) = &output.owners_report {
+        let stats = fl_dstar::owners::by_owner(&statements, &spectrum.sources, &owners);
+        let written = fs::File::create(path)
+            .map_err(csv::Error::from)
+            .and_then(|file| fl_dstar::owners::write_owners(io::BufWriter::new(file), &stats));
+        if let Err(e) = written {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &output.issue {
+        let body = issue_body(spectrum, &statements, inputs, scoring, output);
+        if let Err(e) = fs::write(path, body) {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &output.explain_all {
+        let trace = fl_dstar::explain::trace(
+            &statements,
+            &spectrum.sources,
+            scoring.formula(),
+            &options.numbers,
+        );
+        if let Err(e) = fs::write(path, trace) {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(url) = &output.notify {
+        let service = output
+            .notify_service
+            .unwrap_or_else(|| Service::detect(url));
+        let message = fl_dstar::notify::message(
+            &statements,
+            &spectrum.sources,
+            output.notify_top,
+            output.repo_url.as_deref(),
+            service,
+        );
+        // the ranking is still written when the chat service is unreachable
+        if let Err(e) = fl_dstar::notify::post(url, &message) {
+            eprintln!("Could not post to the webhook: {}", e);
+        }
+    }
+    let ci_reports = [
+        (
+            &output.code_quality,
+            fl_dstar::ci::gitlab_code_quality as fn(_, _, _) -> _,
+        ),
+        (&output.warnings_ng, fl_dstar::ci::warnings_ng),
+        (&output.sonar, fl_dstar::ci::sonar_external_issues),
+    ];
+    for (path, report) in ci_reports {
+        if let Some(path) = path {
+            let json = report(&statements, &spectrum.sources, output.max_issues);
+            if let Err(e) = fs::write(path, format!("{}\n", json)) {
+                eprintln!("Could not write {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(path) = &output.template {
+        let template = fl_dstar::encoding::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Could not read {}: {}", path.display(), e);
+            std::process::exit(1);
+        });
+        let name = path.to_string_lossy().to_ascii_lowercase();
+        let name = name.strip_suffix(".tera").unwrap_or(&name);
+        let autoescape = name.ends_with(".html") || name.ends_with(".htm");
+        let mut model = output::report_model(&statements, &spectrum.sources, &options.numbers);
+        fl_dstar::report::add_metadata(&mut model, &output.title, output.notes.as_deref());
+        fl_dstar::report::add_exclusive(&mut model, &statements, &spectrum.sources);
+        if let Some(smoothing) = scoring.formula() {
+            fl_dstar::report::add_explanations(
+                &mut model,
+                &statements,
+                smoothing,
+                &options.numbers,
+            );
+        }
+        if !listing.is_empty() {
+            fl_dstar::report::add_listing(
+                &mut model,
+                listing,
+                &statements,
+                &spectrum.sources,
+                &options.numbers,
+            );
+        }
+        if output.coverage_vectors {
+            fl_dstar::report::add_coverage_vectors(
+                &mut model,
+                &statements,
+                &spectrum.passing,
+                &spectrum.failing,
+            );
+        }
+        add_provenance(&mut model);
+        match fl_dstar::template::render(&template, &model, autoescape) {
+            Ok(report) => io::stdout()
+                .write_all(report.as_bytes())
+                .unwrap_or_else(|e| output_failed(e)),
+            Err(e) => {
+                eprintln!("Could not render {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(budget) = output.inspection_budget {
+        let picked =
+            fl_dstar::budget::select(&statements, &spectrum.sources, budget, output.budget_decay);
+        fl_dstar::budget::write_selection(io::stdout(), &statements, &spectrum.sources, &picked)
+            .unwrap_or_else(|e| output_failed(e));
+        return;
+    }
+    if output.hunks {
+        let hunks = fl_dstar::hunks::group(&statements, &spectrum.sources, output.hunk_gap);
+        fl_dstar::hunks::write_hunks(io::stdout(), &hunks).unwrap_or_else(|e| output_failed(e));
+        return;
+    }
+    output::write_ranking(
+        io::stdout(),
+        output.output_format,
+        &output.columns(),
+        &statements,
+        &spectrum.sources,
+        &owners.line_owners(&spectrum.sources),
+    )
+    .unwrap_or_else(|e| output_failed(e));
+}
+
+fn rank(args: RankArgs, output: &OutputArgs, options: &InputOptions) {
+    if let Some(path) = &output.test_groups {
+        write_test_groups(&args, path, options);
+    }
+    if output.json_rpc && !output.csv_only() {
+        eprintln!("--json-rpc answers on stdout and can't be combined with other outputs");
+        std::process::exit(1);
+    }
+    let (files, mut spectrum) = load_spectrum(&args, options);
+    if output.json_rpc {
+        fl_dstar::sort_by_suspiciousness(&mut spectrum.statements);
+        return serve_json_rpc(&spectrum, &args.scoring, options);
+    }
+    if let Some(budget) = options.max_memory {
+        if fl_dstar::memory::ranking_bytes(&spectrum.statements) > budget.0 {
+            return write_ranking_external(spectrum, output, options, budget);
+        }
+    }
+    fl_dstar::sort_by_suspiciousness(&mut spectrum.statements);
+    let inputs: Vec<std::path::PathBuf> = match &args.snapshot {
+        Some(snapshot) => vec![snapshot.clone()],
+        None => files.passing.into_iter().chain(files.failing).collect(),
+    };
+    write_ranking(&spectrum, &inputs, &args.scoring, output, options);
+}
+
+/// Answer JSON-RPC requests about the ranking of `spectrum`, which must be
+/// sorted, until stdin closes.
+fn serve_json_rpc(spectrum: &Spectrum, scoring: &ScoreArgs, options: &InputOptions) {
+    let statements = visible_statements(spectrum, options);
+    let report = Report::new(statements, spectrum.sources.clone());
+    let server = fl_dstar::rpc::Server::new(report, scoring.formula());
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lines() {
+        let line = line.unwrap_or_else(|e| {
+            eprintln!("Could not read a request: {}", e);
+            std::process::exit(1);
+        });
+        if line.trim().is_empty() {
             continue;
         }
-        statement_info_list.push(statement_info);
+        if let Some(response) = server.respond(&line) {
+            // editors wait on each answer, so don't leave it in the buffer
+            if writeln!(stdout, "{}", response)
+                .and_then(|_| stdout.flush())
+                .is_err()
+            {
+                return;
+            }
+        }
     }
-    for i in 0..passing_files_info.len() {
-        fl_dstar::add_test_to_statements(&mut statement_info_list, &passing_files_info[i], true);
+}
+
+/// Write the CSV ranking of a spectrum whose ranking won't fit in `budget`,
+/// sorting it in runs on disk under --temp-dir.
+fn write_ranking_external(
+    spectrum: Spectrum,
+    output: &OutputArgs,
+    options: &InputOptions,
+    budget: ByteSize,
+) {
+    if !output.csv_only() {
+        eprintln!(
+            "The ranking is over the --max-memory budget of {}; only the CSV ranking can be \
+             sorted on disk",
+            budget
+        );
+        std::process::exit(1);
     }
-    for i in 0..failing_files_info.len() {
-        fl_dstar::add_test_to_statements(&mut statement_info_list, &failing_files_info[i], false);
+    let fail = |e: io::Error| -> ! {
+        eprintln!("Could not sort the ranking on disk: {}", e);
+        std::process::exit(1);
+    };
+    let parent = options.temp_dir.clone().unwrap_or_else(std::env::temp_dir);
+    let run_len = fl_dstar::memory::run_len(budget, &spectrum.statements);
+    let mut sort =
+        fl_dstar::extsort::ExternalSort::new(&parent, run_len).unwrap_or_else(|e| fail(e));
+    let today = Date::today();
+    let Spectrum {
+        statements,
+        sources,
+        ..
+    } = spectrum;
+    for statement in statements {
+        if fl_dstar::suppressions::is_suppressed(&statement, &sources, &options.suppressions, today)
+        {
+            audit_suppressed(options, &sources, &statement);
+            continue;
+        }
+        sort.push(statement).unwrap_or_else(|e| fail(e));
+    }
+    // a first pass for the scores that percentiles and EXAM are counted against
+    let scores: Vec<f32> = sort
+        .merge()
+        .and_then(|merged| merged.map(|s| s.map(|s| s.suspiciousness)).collect())
+        .unwrap_or_else(|e| fail(e));
+    let merged = sort.merge().unwrap_or_else(|e| fail(e));
+    let owners = output.code_owners().line_owners(&sources);
+    output::write_ranking_stream(
+        io::stdout(),
+        output.output_format,
+        &output.columns(),
+        merged,
+        &scores,
+        &sources,
+        &owners,
+    )
+    .unwrap_or_else(|e| output_failed(e));
+}
+
+fn history(action: HistoryAction, options: &InputOptions) {
+    match action {
+        HistoryAction::Record {
+            rank,
+            index,
+            commit,
+            fault,
+        } => {
+            for location in &fault {
+                if let Err(e) = fl_dstar::query::parse_location(location) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            let mut bugs = fl_dstar::history::load(&index).unwrap_or_else(|e| {
+                eprintln!("Could not read {}: {}", index.display(), e);
+                std::process::exit(1);
+            });
+            let (_, mut spectrum) = load_spectrum(&rank, options);
+            fl_dstar::sort_by_suspiciousness(&mut spectrum.statements);
+            bugs.push(fl_dstar::history::PastBug {
+                commit,
+                faults: fault,
+                recorded: Date::today().to_string(),
+                signature: fl_dstar::history::signature(&visible_statements(&spectrum, options)),
+            });
+            if let Err(e) = fs::write(&index, fl_dstar::history::to_string(&bugs)) {
+                eprintln!("Could not write {}: {}", index.display(), e);
+                std::process::exit(1);
+            }
+        }
     }
-    statement_info_list.iter_mut().for_each(|statement| {
-        statement.calculate_suspiciousness();
+}
+
+/// The test metadata named by `--manifest`; empty when there is none.
+fn load_manifest(args: &RankArgs) -> fl_dstar::manifest::Manifest {
+    let Some(path) = &args.manifest else {
+        return Default::default();
+    };
+    fl_dstar::manifest::Manifest::load(path).unwrap_or_else(|e| {
+        eprintln!("Could not read {}: {}", path.display(), e);
+        std::process::exit(1);
+    })
+}
+
+/// The group of the test named `name`: its `--group-by` metadata, or else the
+/// first part of its name.
+fn test_group(args: &RankArgs, manifest: &fl_dstar::manifest::Manifest, name: &str) -> String {
+    match &args.group_by {
+        Some(key) => manifest.get(name, key).unwrap_or("").to_string(),
+        None => fl_dstar::groups::group_of(name).to_string(),
+    }
+}
+
+/// Write the failing rate of each group of the tests in the test directories.
+fn write_test_groups(args: &RankArgs, path: &std::path::Path, options: &InputOptions) {
+    if args.snapshot.is_some() {
+        eprintln!("A snapshot has no test names to group");
+        return;
+    }
+    let (passing_files, failing_files) = test_files(args, options);
+    let manifest = load_manifest(args);
+    let names = |files: &[std::path::PathBuf]| -> Vec<String> {
+        files
+            .iter()
+            .map(|path| fl_dstar::groups::test_name(path))
+            .filter(|name| manifest.matches(name, &args.filter))
+            .map(|name| test_group(args, &manifest, &name))
+            .collect()
+    };
+    let (passing, failing) = (names(&passing_files), names(&failing_files));
+    let stats = fl_dstar::groups::stats(
+        passing.iter().map(String::as_str),
+        failing.iter().map(String::as_str),
+    );
+    let file = fs::File::create(path).unwrap_or_else(|e| {
+        eprintln!("Could not create {}: {}", path.display(), e);
+        std::process::exit(1);
     });
+    let fail = |e: &dyn std::fmt::Display| -> ! {
+        eprintln!("Could not write {}: {}", path.display(), e);
+        std::process::exit(1);
+    };
+    let mut wtr = csv::Writer::from_writer(file);
+    for row in stats {
+        wtr.serialize(row).unwrap_or_else(|e| fail(&e));
+    }
+    wtr.flush().unwrap_or_else(|e| fail(&e));
+}
 
-    statement_info_list.sort_by(|a, b| {
-        let sus_res = b.suspiciousness.partial_cmp(&a.suspiciousness).unwrap();
-        if sus_res == std::cmp::Ordering::Equal {
-            a.line_number.cmp(&b.line_number)
-        } else {
-            sus_res
+/// `value`, or the environment variable `name` when it wasn't given.
+fn arg_or_env(value: Option<String>, name: &str, flag: &str) -> String {
+    value
+        .or_else(|| std::env::var(name).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_else(|| {
+            eprintln!("Pass --{} or set {}", flag, name);
+            std::process::exit(1);
+        })
+}
+
+fn publish(target: PublishTarget, options: &InputOptions) {
+    match target {
+        PublishTarget::GithubCheck {
+            rank,
+            token,
+            repo,
+            sha,
+            api_url,
+            name,
+            max_annotations,
+        } => {
+            let run = fl_dstar::github::CheckRun {
+                api_url: api_url
+                    .or_else(|| std::env::var("GITHUB_API_URL").ok())
+                    .unwrap_or_else(|| "https://api.github.com".to_string()),
+                repo: arg_or_env(repo, "GITHUB_REPOSITORY", "repo"),
+                token: arg_or_env(token, "GITHUB_TOKEN", "token"),
+                head_sha: arg_or_env(sha, "GITHUB_SHA", "sha"),
+                name,
+            };
+            let (_, mut spectrum) = load_spectrum(&rank, options);
+            fl_dstar::sort_by_suspiciousness(&mut spectrum.statements);
+            let statements = visible_statements(&spectrum, options);
+            let annotations =
+                fl_dstar::github::annotations(&statements, &spectrum.sources, max_annotations);
+            let summary = fl_dstar::github::summary(&statements, &spectrum.sources);
+            match fl_dstar::github::publish(&run, &summary, &annotations) {
+                Ok(url) => println!("{}", url),
+                Err(e) => {
+                    eprintln!("Could not publish the check run: {}", e);
+                    std::process::exit(1);
+                }
+            }
         }
+    }
+}
+
+fn functions(
+    passing_dir: &std::path::Path,
+    failing_dir: &std::path::Path,
+    weighting: Weighting,
+    metrics: Option<&std::path::Path>,
+    options: &InputOptions,
+) {
+    for dir in [passing_dir, failing_dir] {
+        if !dir.exists() {
+            eprintln!("The passed in directory {} does not exist", dir.display());
+            std::process::exit(1);
+        }
+    }
+    let read = |dir| {
+        list_dir(dir, options.symlinks)
+            .map(|path| {
+                fl_dstar::functions::parse_function_summary_file(&path).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let (passing, failing) = (read(passing_dir), read(failing_dir));
+    let mut scores = fl_dstar::functions::score_functions(&passing, &failing);
+    if weighting != Weighting::None {
+        let mut metrics = match metrics {
+            Some(path) => fs::File::open(path)
+                .map_err(|e| e.to_string())
+                .and_then(fl_dstar::functions::parse_metrics)
+                .unwrap_or_else(|e| {
+                    eprintln!("Could not read {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }),
+            None if weighting == Weighting::Complexity => {
+                eprintln!("Weighting by complexity needs --metrics");
+                std::process::exit(1);
+            }
+            None => HashMap::new(),
+        };
+        let tests: Vec<&[_]> = passing
+            .iter()
+            .chain(&failing)
+            .map(|t| t.as_slice())
+            .collect();
+        for (function, loc) in fl_dstar::functions::summary_loc(&tests) {
+            let entry = metrics.entry(function).or_default();
+            entry.loc = entry.loc.or(Some(loc));
+        }
+        let mut wtr = csv::Writer::from_writer(io::stdout());
+        for score in fl_dstar::functions::weight(scores, &metrics, weighting) {
+            wtr.serialize(score).unwrap_or_else(|e| output_failed(e));
+        }
+        wtr.flush().unwrap_or_else(|e| output_failed(e));
+        return;
+    }
+    scores.sort_by(|a, b| {
+        b.suspiciousness
+            .total_cmp(&a.suspiciousness)
+            .then_with(|| a.function.cmp(&b.function))
     });
     let mut wtr = csv::Writer::from_writer(io::stdout());
-    for statement in statement_info_list {
-        wtr.serialize(statement).unwrap();
+    for score in scores {
+        wtr.serialize(score).unwrap_or_else(|e| output_failed(e));
+    }
+    wtr.flush().unwrap_or_else(|e| output_failed(e));
+}
+
+#[derive(serde::Serialize)]
+struct Diagnosis {
+    size: usize,
+    lines: String,
+    statements: String,
+    #[serde(rename = "mean_suspiciousness")]
+    score: String,
+}
+
+fn diagnose(args: RankArgs, max_size: usize, limit: usize, options: &InputOptions) {
+    let (_, mut spectrum) = load_spectrum(&args, options);
+    fl_dstar::sort_by_suspiciousness(&mut spectrum.statements);
+    // hitting sets are found over rank positions, most suspicious first
+    let positions: HashMap<u64, u32> = spectrum
+        .statements
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.key(), i as u32))
+        .collect();
+    let order: Vec<u32> = (0..spectrum.statements.len() as u32).collect();
+    let conflicts: Vec<_> = spectrum
+        .failing
+        .iter()
+        .map(|test| {
+            test.iter()
+                .filter(|line| line.coverage == Coverage::Covered)
+                .filter_map(|line| positions.get(&line.key()).copied())
+                .collect()
+        })
+        .collect();
+    let statements = |i: &u32| &spectrum.statements[*i as usize];
+    let mut candidates: Vec<(Vec<u32>, f32)> =
+        fl_dstar::mhs::minimal_hitting_sets(&conflicts, &order, max_size, limit)
+            .into_iter()
+            .map(|set| {
+                let total: f32 = set.iter().map(|i| statements(i).suspiciousness).sum();
+                let mean = total / set.len() as f32;
+                (set, mean)
+            })
+            .collect();
+    candidates.sort_by(|a, b| a.0.len().cmp(&b.0.len()).then_with(|| b.1.total_cmp(&a.1)));
+    let mut wtr = csv::Writer::from_writer(io::stdout());
+    for (set, mean) in candidates {
+        let lines: Vec<String> = set
+            .iter()
+            .map(|i| statements(i).line_number.to_string())
+            .collect();
+        let texts: Vec<&str> = set.iter().map(|i| statements(i).statement()).collect();
+        wtr.serialize(Diagnosis {
+            size: set.len(),
+            lines: lines.join(";"),
+            statements: texts.join(" ; "),
+            score: format!("{:.2}", mean),
+        })
+        .unwrap_or_else(|e| output_failed(e));
+    }
+    wtr.flush().unwrap_or_else(|e| output_failed(e));
+}
+
+fn generate(output: &std::path::Path, config: &fl_dstar::generate::GeneratorConfig) {
+    let generated = fl_dstar::generate::generate(config).unwrap_or_else(|e| {
+        eprintln!("Could not generate a spectrum: {}", e);
+        std::process::exit(1);
+    });
+    let fail = |path: &std::path::Path, e: io::Error| -> ! {
+        eprintln!("Could not write {}: {}", path.display(), e);
+        std::process::exit(1);
+    };
+    let (passing, failing) = generated.coverage();
+    for (name, tests) in [("passing", passing), ("failing", failing)] {
+        let dir = output.join(name);
+        fs::create_dir_all(&dir).unwrap_or_else(|e| fail(&dir, e));
+        for (i, test) in tests.iter().enumerate() {
+            let path = dir.join(format!("test_{:04}.gcov", i + 1));
+            let mut file = fs::File::create(&path).unwrap_or_else(|e| fail(&path, e));
+            formats::write(Format::Gcov, std::slice::from_ref(test), &mut file)
+                .unwrap_or_else(|e| fail(&path, e));
+        }
+    }
+    let path = output.join("faults.csv");
+    let mut faults = String::from("file,line_number\n");
+    for line in &generated.faults {
+        faults.push_str(&format!("{},{}\n", fl_dstar::generate::SOURCE, line));
+    }
+    fs::write(&path, faults).unwrap_or_else(|e| fail(&path, e));
+}
+
+fn init(dir: &std::path::Path, build: Option<fl_dstar::scaffold::BuildSystem>, force: bool) {
+    let Some(build) = build.or_else(|| fl_dstar::scaffold::BuildSystem::detect(dir)) else {
+        eprintln!(
+            "Could not tell how {} is built; pass --build-system make, cmake or cargo",
+            dir.display()
+        );
+        std::process::exit(1);
+    };
+    let files = fl_dstar::scaffold::starter_files(build);
+    // check them all first, so nothing is half written
+    if !force {
+        if let Some(file) = files.iter().find(|file| dir.join(file.name).exists()) {
+            eprintln!(
+                "{} already exists; pass --force to overwrite it",
+                dir.join(file.name).display()
+            );
+            std::process::exit(1);
+        }
+    }
+    for file in &files {
+        let path = dir.join(file.name);
+        fs::write(&path, &file.contents).unwrap_or_else(|e| {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            std::process::exit(1);
+        });
+        #[cfg(unix)]
+        if file.executable {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o755));
+        }
+        println!("wrote {}", path.display());
+    }
+    println!(
+        "Set up for {}: check the settings in {}, then run {}",
+        build.name(),
+        fl_dstar::scaffold::CONFIG,
+        fl_dstar::scaffold::SCRIPT
+    );
+}
+
+#[derive(serde::Serialize)]
+struct GradeRow {
+    criterion: &'static str,
+    percent: String,
+    details: String,
+}
+
+fn grade(
+    submission: &std::path::Path,
+    reference: &std::path::Path,
+    tolerance: fl_dstar::grade::Tolerance,
+) {
+    let read = |path: &std::path::Path| {
+        fs::File::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(fl_dstar::grade::read_ranking)
+            .unwrap_or_else(|e| {
+                eprintln!("Could not read {}: {}", path.display(), e);
+                std::process::exit(1);
+            })
+    };
+    let criteria = fl_dstar::grade::grade(&read(submission), &read(reference), tolerance);
+    let mut wtr = csv::Writer::from_writer(io::stdout());
+    for criterion in criteria {
+        wtr.serialize(GradeRow {
+            criterion: criterion.name,
+            percent: format!("{:.2}", criterion.score * 100.0),
+            details: criterion.details,
+        })
+        .unwrap_or_else(|e| output_failed(e));
+    }
+    wtr.flush().unwrap_or_else(|e| output_failed(e));
+}
+
+fn features(args: RankArgs, output: Option<&std::path::Path>, options: &InputOptions) {
+    let (_, spectrum) = load_spectrum(&args, options);
+    // churn is left out when the sources aren't in a git repository
+    let churn = options
+        .root
+        .as_deref()
+        .and_then(|root| fl_dstar::features::file_churn(root).ok())
+        .unwrap_or_default();
+    let features = fl_dstar::features::extract(&spectrum, args.scoring.smoothing, &churn);
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(io::BufWriter::new(fs::File::create(path).unwrap_or_else(
+            |e| {
+                eprintln!("Could not create {}: {}", path.display(), e);
+                std::process::exit(1);
+            },
+        ))),
+        None => Box::new(io::stdout()),
+    };
+    let mut wtr = csv::Writer::from_writer(writer);
+    for row in features {
+        wtr.serialize(row).unwrap_or_else(|e| output_failed(e));
+    }
+    wtr.flush().unwrap_or_else(|e| output_failed(e));
+}
+
+fn blame_commits(args: RankArgs, top: Option<usize>, options: &InputOptions) {
+    let Some(root) = options.root.as_deref() else {
+        eprintln!("blame-commits needs the sources in a git repository; pass --root");
+        std::process::exit(1);
+    };
+    let (_, spectrum) = load_spectrum(&args, options);
+    let mut statements = visible_statements(&spectrum, options);
+    fl_dstar::sort_by_suspiciousness(&mut statements);
+    let files: std::collections::BTreeSet<&String> = spectrum.sources.values().collect();
+    let mut blames = HashMap::new();
+    for file in files {
+        match fl_dstar::blame::blame(root, std::path::Path::new(file)) {
+            Ok(blame) => {
+                blames.insert(file.clone(), blame);
+            }
+            Err(e) => eprintln!("Could not blame {}: {}", file, e),
+        }
+    }
+    let commits = fl_dstar::blame::rank_commits(&statements, &spectrum.sources, &blames);
+    let mut wtr = csv::Writer::from_writer(io::stdout());
+    for commit in commits.into_iter().take(top.unwrap_or(usize::MAX)) {
+        wtr.serialize(commit).unwrap_or_else(|e| output_failed(e));
+    }
+    wtr.flush().unwrap_or_else(|e| output_failed(e));
+}
+
+fn bisect_hint(
+    checkpoints: &[fl_dstar::bisect::Checkpoint],
+    top: usize,
+    scoring: &ScoreArgs,
+    options: &InputOptions,
+) {
+    let rankings: Vec<fl_dstar::bisect::Ranking> = checkpoints
+        .iter()
+        .map(|checkpoint| {
+            let mut spectrum = fl_dstar::snapshot::read(&checkpoint.snapshot).unwrap_or_else(|e| {
+                eprintln!("Could not read snapshot {}", e);
+                std::process::exit(1);
+            });
+            if options.redact {
+                fl_dstar::redact::redact_spectrum(&mut spectrum);
+            }
+            score_spectrum(&mut spectrum, scoring);
+            let mut statements = visible_statements(&spectrum, options);
+            fl_dstar::sort_by_suspiciousness(&mut statements);
+            fl_dstar::bisect::Ranking {
+                commit: checkpoint.commit.clone(),
+                failing_tests: spectrum.failing.len(),
+                statements,
+            }
+        })
+        .collect();
+    let Some(hint) = fl_dstar::bisect::hint(&rankings, top) else {
+        eprintln!("The newest snapshot has no failing tests");
+        std::process::exit(1);
+    };
+    let mut wtr = csv::Writer::from_writer(io::stdout());
+    for step in &hint.steps {
+        wtr.serialize(step).unwrap_or_else(|e| output_failed(e));
+    }
+    wtr.flush().unwrap_or_else(|e| output_failed(e));
+    match &hint.good {
+        Some(good) => eprintln!(
+            "The top suspicious lines appeared between {} and {}; try: git bisect start {} {}",
+            good, hint.bad, hint.bad, good
+        ),
+        None => eprintln!(
+            "The top suspicious lines are already suspicious at the oldest commit, {}",
+            hint.bad
+        ),
+    }
+}
+
+fn platform_diff(
+    args: RankArgs,
+    platform_key: &str,
+    platform: Option<String>,
+    options: &InputOptions,
+) {
+    if args.manifest.is_none() {
+        eprintln!("platform-diff needs a --manifest giving each test's platform");
+        std::process::exit(1);
+    }
+    let manifest = load_manifest(&args);
+    let (files, spectrum) = load_spectrum(&args, options);
+    let platforms = |files: &[std::path::PathBuf]| -> Vec<Option<String>> {
+        files
+            .iter()
+            .map(|path| {
+                let name = fl_dstar::groups::test_name(path);
+                manifest.get(&name, platform_key).map(str::to_string)
+            })
+            .collect()
+    };
+    let (passing, failing) = (platforms(&files.passing), platforms(&files.failing));
+    let passing: Vec<Option<&str>> = passing.iter().map(Option::as_deref).collect();
+    let failing: Vec<Option<&str>> = failing.iter().map(Option::as_deref).collect();
+    let platform = platform
+        .or_else(|| {
+            fl_dstar::platforms::failing_platform(
+                passing.iter().flatten().copied(),
+                failing.iter().flatten().copied(),
+            )
+        })
+        .unwrap_or_else(|| {
+            eprintln!("No failing test has a {} in the manifest", platform_key);
+            std::process::exit(1);
+        });
+    let others: std::collections::BTreeSet<&str> = passing
+        .iter()
+        .chain(&failing)
+        .flatten()
+        .copied()
+        .filter(|p| *p != platform)
+        .collect();
+    if others.is_empty() {
+        eprintln!("Every test with a {} runs on {}", platform_key, platform);
+        std::process::exit(1);
+    }
+    eprintln!(
+        "Comparing {} against {}",
+        platform,
+        others.into_iter().collect::<Vec<_>>().join(", ")
+    );
+    let only = fl_dstar::platforms::only_on(&spectrum, &passing, &failing, &platform);
+    let mut wtr = csv::Writer::from_writer(io::stdout());
+    for row in only {
+        wtr.serialize(row).unwrap_or_else(|e| output_failed(e));
+    }
+    wtr.flush().unwrap_or_else(|e| output_failed(e));
+}
+
+fn conditions(
+    passing_dir: &std::path::Path,
+    failing_dir: &std::path::Path,
+    smoothing: Smoothing,
+    options: &InputOptions,
+) {
+    let read = |dir: &std::path::Path| -> Vec<Vec<fl_dstar::conditions::ConditionCoverage>> {
+        list_dir(dir, options.symlinks)
+            .filter_map(|path| {
+                let coverage = fl_dstar::encoding::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| fl_dstar::conditions::parse_gcov(&text));
+                match coverage {
+                    Ok(mut coverage) => {
+                        coverage.source = coverage.source.map(|source| {
+                            fl_dstar::paths::display_path(
+                                &source,
+                                &options.maps,
+                                options.root.as_deref(),
+                            )
+                        });
+                        Some(vec![coverage])
+                    }
+                    Err(e) => {
+                        eprintln!("Skipping a test: {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    };
+    let (passing, failing) = (read(passing_dir), read(failing_dir));
+    check_test_sets(passing.len(), failing.len());
+    let ranked = fl_dstar::conditions::rank(&passing, &failing, smoothing);
+    if ranked.is_empty() {
+        eprintln!("No condition coverage found; run gcov with --conditions");
+        std::process::exit(1);
+    }
+    let mut wtr = csv::Writer::from_writer(io::stdout());
+    for row in ranked {
+        wtr.serialize(row).unwrap_or_else(|e| output_failed(e));
+    }
+    wtr.flush().unwrap_or_else(|e| output_failed(e));
+}
+
+fn query(location: &str, args: RankArgs, options: &InputOptions) {
+    let (path, line) = fl_dstar::query::parse_location(location).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let (_, spectrum) = load_spectrum(&args, options);
+    let mut statements = visible_statements(&spectrum, options);
+    fl_dstar::sort_by_suspiciousness(&mut statements);
+    let report = Report::new(statements, spectrum.sources);
+    let Some(found) = report.score_at(&path, line) else {
+        eprintln!("No statement ranked at {}", location);
+        std::process::exit(1);
+    };
+    let mut wtr = csv::Writer::from_writer(io::stdout());
+    wtr.serialize(found).unwrap_or_else(|e| output_failed(e));
+    wtr.flush().unwrap_or_else(|e| output_failed(e));
+}
+
+fn sql(sql: &str, args: RankArgs, options: &InputOptions) {
+    let query = fl_dstar::sql::parse(sql).unwrap_or_else(|e| {
+        eprintln!("Invalid query: {}", e);
+        std::process::exit(1);
+    });
+    let (files, spectrum) = load_spectrum(&args, options);
+    let names: Vec<String> = files
+        .passing
+        .iter()
+        .chain(&files.failing)
+        .map(|path| path.display().to_string())
+        .collect();
+    let statements = fl_dstar::columnar::statements(&spectrum);
+    let tests = fl_dstar::columnar::tests(&spectrum, &names);
+    let coverage = fl_dstar::columnar::coverage(&spectrum);
+    let tables = [
+        ("statements", &statements),
+        ("tests", &tests),
+        ("coverage", &coverage),
+    ];
+    let rows = fl_dstar::sql::run(&query, &tables).unwrap_or_else(|e| {
+        eprintln!("Query failed: {}", e);
+        std::process::exit(1);
+    });
+    let mut wtr = csv::Writer::from_writer(io::stdout());
+    wtr.write_record(&rows.columns)
+        .unwrap_or_else(|e| output_failed(e));
+    for row in rows.rows {
+        wtr.write_record(row.iter().map(|value| value.to_string()))
+            .unwrap_or_else(|e| output_failed(e));
+    }
+    wtr.flush().unwrap_or_else(|e| output_failed(e));
+}
+
+#[derive(serde::Serialize)]
+struct Suggestion {
+    test: String,
+    failure_probability: String,
+    expected_entropy: String,
+    entropy_reduction: String,
+}
+
+fn suggest_tests(args: RankArgs, candidates_dir: &std::path::Path, options: &InputOptions) {
+    if !candidates_dir.exists() {
+        eprintln!("The passed in candidates directory does not exist");
+        std::process::exit(1);
+    }
+    let (_, spectrum) = load_spectrum(&args, options);
+    let candidates: Vec<(String, Vec<LineInfo>)> = list_dir(candidates_dir, options.symlinks)
+        .map(|file| {
+            let name = file.file_name().unwrap().to_string_lossy().into_owned();
+            (name, read_lines(args.scoring.format, &file, options))
+        })
+        .collect();
+    let suggestions = fl_dstar::suggest::suggest_tests(
+        &spectrum,
+        &candidates,
+        args.scoring.technique,
+        args.scoring.smoothing,
+    );
+    let mut wtr = csv::Writer::from_writer(io::stdout());
+    for suggestion in suggestions {
+        wtr.serialize(Suggestion {
+            test: suggestion.name,
+            failure_probability: format!("{:.2}", suggestion.failure_probability),
+            expected_entropy: format!("{:.2}", suggestion.expected_entropy),
+            entropy_reduction: format!("{:.2}", suggestion.entropy_reduction),
+        })
+        .unwrap_or_else(|e| output_failed(e));
+    }
+    wtr.flush().unwrap_or_else(|e| output_failed(e));
+}
+
+fn show(
+    args: RankArgs,
+    top: usize,
+    gap: u32,
+    context: u32,
+    color: fl_dstar::show::Color,
+    options: &InputOptions,
+) {
+    use fl_dstar::show::Color;
+    use std::io::IsTerminal;
+    let (_, mut spectrum) = load_spectrum(&args, options);
+    fl_dstar::sort_by_suspiciousness(&mut spectrum.statements);
+    let statements = visible_statements(&spectrum, options);
+    let color = match color {
+        Color::Always => true,
+        Color::Never => false,
+        Color::Auto => io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+    };
+    let scores: HashMap<u64, f32> = statements
+        .iter()
+        .map(|s| (s.key(), s.suspiciousness))
+        .collect();
+    let lines = line_texts(&spectrum);
+    let hunks = fl_dstar::hunks::group(&statements, &spectrum.sources, gap);
+    if hunks.is_empty() {
+        eprintln!("No statement is suspicious");
+    }
+    let mut out = io::stdout().lock();
+    for (i, hunk) in hunks.iter().take(top).enumerate() {
+        let excerpt = fl_dstar::show::excerpt(i + 1, hunk, &scores, &lines, context, color);
+        let separator = if i > 0 { "\n" } else { "" };
+        write!(out, "{}{}", separator, excerpt).unwrap_or_else(|e| output_failed(e));
+    }
+}
+
+fn slice(args: RankArgs, test: Option<String>, annotate: bool, options: &InputOptions) {
+    let (files, mut spectrum) = load_spectrum(&args, options);
+    let failing_files = files.failing;
+    let index = match test {
+        Some(name) => failing_files.iter().position(|path| {
+            path.as_os_str() == name.as_str()
+                || path.file_name().is_some_and(|n| n == name.as_str())
+                || path.file_stem().is_some_and(|n| n == name.as_str())
+        }),
+        None if failing_files.len() == 1 => Some(0),
+        None => None,
+    };
+    let index = match index {
+        Some(index) => index,
+        None => {
+            eprintln!("Choose a failing test with --test; the failing tests are:");
+            for path in &failing_files {
+                eprintln!("  {}", path.display());
+            }
+            std::process::exit(1);
+        }
+    };
+    fl_dstar::sort_by_suspiciousness(&mut spectrum.statements);
+    // rank of each line in the overall ordering, 1 being most suspicious
+    let ranks: HashMap<u64, (usize, f32)> = spectrum
+        .statements
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.key(), (i + 1, s.suspiciousness)))
+        .collect();
+    let trace = &spectrum.failing[index];
+    let mut out = io::stdout().lock();
+    if annotate {
+        warn_on_drift(trace, &spectrum, options);
+        // walk the test's lines in source order, marking the ones it executed
+        for line in trace {
+            match (line.coverage == Coverage::Covered, ranks.get(&line.key())) {
+                (true, Some((rank, score))) => writeln!(
+                    out,
+                    "{:>5} {:>8} #{:<4}| {}",
+                    line.line_number,
+                    options.numbers.format(*score as f64, 2),
+                    options.numbers.format_count(*rank),
+                    line.statement
+                ),
+                _ => writeln!(
+                    out,
+                    "{:>5} {:>8} {:<5}| {}",
+                    line.line_number, "", "", line.statement
+                ),
+            }
+            .unwrap_or_else(|e| output_failed(e));
+        }
+    } else {
+        writeln!(out, "{:>4} {:>5} {:>8}  statement", "rank", "line", "score")
+            .unwrap_or_else(|e| output_failed(e));
+        for statement in &spectrum.statements {
+            let executed = trace
+                .iter()
+                .any(|l| l.key() == statement.key() && l.coverage == Coverage::Covered);
+            if executed {
+                let (rank, score) = ranks[&statement.key()];
+                writeln!(
+                    out,
+                    "{:>4} {:>5} {:>8}  {}",
+                    options.numbers.format_count(rank),
+                    statement.line_number,
+                    options.numbers.format(score as f64, 2),
+                    statement.statement()
+                )
+                .unwrap_or_else(|e| output_failed(e));
+            }
+        }
     }
-    wtr.flush().unwrap();
 }
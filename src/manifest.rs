@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+/// A `KEY=VALUE` condition on a test's metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub key: String,
+    pub value: String,
+}
+
+impl std::str::FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok(Filter {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(format!("invalid filter '{}' (expected KEY=VALUE)", s)),
+        }
+    }
+}
+
+/// Metadata about each test, such as its owner, shard or platform, read from
+/// a CSV file with a `test` column naming the test (see
+/// [`crate::groups::test_name`]) and one column per key.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Manifest {
+    tests: HashMap<String, HashMap<String, String>>,
+}
+
+impl Manifest {
+    pub fn parse<R: Read>(reader: R) -> Result<Manifest, String> {
+        let mut rdr = csv::Reader::from_reader(reader);
+        let headers = rdr.headers().map_err(|e| e.to_string())?.clone();
+        let test_column = headers
+            .iter()
+            .position(|header| header == "test")
+            .ok_or("missing test column")?;
+        let mut tests = HashMap::new();
+        for (i, record) in rdr.records().enumerate() {
+            // the header is line 1
+            let record = record.map_err(|e| format!("line {}: {}", i + 2, e))?;
+            let metadata = headers
+                .iter()
+                .zip(record.iter())
+                .enumerate()
+                .filter(|&(column, _)| column != test_column)
+                .map(|(_, (key, value))| (key.to_string(), value.to_string()))
+                .collect();
+            tests.insert(record[test_column].to_string(), metadata);
+        }
+        Ok(Manifest { tests })
+    }
+
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        Manifest::parse(file)
+    }
+
+    /// The value of `key` for `test`, when the manifest has one.
+    pub fn get(&self, test: &str, key: &str) -> Option<&str> {
+        self.tests.get(test)?.get(key).map(String::as_str)
+    }
+
+    /// Whether `test` meets every filter. Tests missing from the manifest
+    /// meet none.
+    pub fn matches(&self, test: &str, filters: &[Filter]) -> bool {
+        filters
+            .iter()
+            .all(|filter| self.get(test, &filter.key) == Some(filter.value.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest() {
+        let manifest = Manifest::parse(
+            "owner,test,platform\nana,test_parser_a,linux\nbo,test_net_a,windows\n".as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(manifest.get("test_net_a", "owner"), Some("bo"));
+        assert_eq!(manifest.get("test_net_a", "shard"), None);
+        let linux: Filter = "platform=linux".parse().unwrap();
+        assert!(manifest.matches("test_parser_a", std::slice::from_ref(&linux)));
+        assert!(!manifest.matches("test_net_a", std::slice::from_ref(&linux)));
+        assert!(!manifest.matches("test_unknown", &[linux]));
+        assert!(manifest.matches("test_unknown", &[]));
+        assert!("=linux".parse::<Filter>().is_err());
+        assert_eq!(
+            Manifest::parse("owner\nana\n".as_bytes()).unwrap_err(),
+            "missing test column"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{LineInfo, StatementInfo};
+use std::fmt;
+use std::mem::size_of;
+use std::str::FromStr;
+
+/// An amount of memory, such as the `--max-memory` budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    /// A number of bytes with an optional binary suffix: `4096`, `512M`,
+    /// `2GiB`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid size '{}' (expected e.g. 512M or 2G)", s);
+        let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let shift = match s[digits.len()..].to_ascii_uppercase().as_str() {
+            "" | "B" => 0,
+            "K" | "KB" | "KIB" => 10,
+            "M" | "MB" | "MIB" => 20,
+            "G" | "GB" | "GIB" => 30,
+            "T" | "TB" | "TIB" => 40,
+            _ => return Err(invalid()),
+        };
+        let number = digits.trim().parse::<u64>().map_err(|_| invalid())?;
+        number
+            .checked_mul(1 << shift)
+            .map(ByteSize)
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+        let mut size = self.0 as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < units.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        match unit {
+            0 => write!(f, "{} B", self.0),
+            _ => write!(f, "{:.1} {}", size, units[unit]),
+        }
+    }
+}
+
+/// How the tests are held while a spectrum is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Keep every test's coverage, as commands that revisit tests need
+    InMemory,
+    /// Fold each test into the statements' counts as it is read and drop
+    /// it, so memory stays flat however many tests there are
+    Streaming,
+}
+
+/// Bytes one test's coverage takes once parsed.
+pub fn test_bytes(lines: &[LineInfo]) -> u64 {
+    lines
+        .iter()
+        .map(|line| (size_of::<LineInfo>() + line.statement.capacity()) as u64)
+        .sum()
+}
+
+/// Bytes the scored statements take, given the coverage of one test.
+pub fn statement_bytes(lines: &[LineInfo]) -> u64 {
+    lines
+        .iter()
+        .map(|line| (size_of::<StatementInfo>() + line.statement.len()) as u64)
+        .sum()
+}
+
+/// Bytes ranking `statements` in memory takes: the ranked copy alongside
+/// the statements themselves.
+pub fn ranking_bytes(statements: &[StatementInfo]) -> u64 {
+    let one = |s: &StatementInfo| (size_of::<StatementInfo>() + s.statement().len()) as u64;
+    2 * statements.iter().map(one).sum::<u64>()
+}
+
+/// Statements per sorted run when a ranking is sorted on disk, so that a
+/// run takes about a quarter of `budget`.
+pub fn run_len(budget: ByteSize, statements: &[StatementInfo]) -> usize {
+    let average = ranking_bytes(statements) / 2 / statements.len().max(1) as u64;
+    (budget.0 / 4 / average.max(1)).max(1) as usize
+}
+
+/// Choose how to hold `tests` tests within `budget`, projecting from the
+/// coverage of the first. Streaming is only chosen when the caller
+/// `can_stream`; a run that fits neither way is an error, raised before
+/// the tests are read rather than by the operating system part way through.
+pub fn plan(
+    budget: ByteSize,
+    tests: usize,
+    first: &[LineInfo],
+    can_stream: bool,
+) -> Result<Strategy, String> {
+    let in_memory = ByteSize(test_bytes(first) * tests as u64 + statement_bytes(first));
+    if in_memory <= budget {
+        return Ok(Strategy::InMemory);
+    }
+    // the test being read is held alongside the counts
+    let streaming = ByteSize(test_bytes(first) + statement_bytes(first));
+    match can_stream {
+        true if streaming <= budget => Ok(Strategy::Streaming),
+        true => Err(format!(
+            "Scoring needs about {} even when streaming, over the --max-memory budget of {}",
+            streaming, budget
+        )),
+        false => Err(format!(
+            "Keeping the {} tests needs about {}, over the --max-memory budget of {}; only \
+             ranking with a formula and without per-test output can stream",
+            tests, in_memory, budget
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Coverage;
+
+    #[test]
+    fn test_byte_size() {
+        assert_eq!("4096".parse(), Ok(ByteSize(4096)));
+        assert_eq!("512M".parse(), Ok(ByteSize(512 << 20)));
+        assert_eq!("2gib".parse(), Ok(ByteSize(2 << 30)));
+        assert!("2X".parse::<ByteSize>().is_err());
+        assert!("G".parse::<ByteSize>().is_err());
+        assert!("99999999999T".parse::<ByteSize>().is_err());
+        assert_eq!(ByteSize(1536 << 20).to_string(), "1.5 GiB");
+        assert_eq!(ByteSize(12).to_string(), "12 B");
+    }
+
+    #[test]
+    fn test_plan() {
+        let first: Vec<LineInfo> = (1..=100)
+            .map(|line_number| LineInfo {
+                file: 0,
+                line_number,
+                statement: "x = x + 1;".to_string(),
+                coverage: Coverage::Covered,
+                execution_count: 1,
+            })
+            .collect();
+        let one = test_bytes(&first);
+        let counts = statement_bytes(&first);
+        let budget = ByteSize(one * 10 + counts);
+        assert_eq!(plan(budget, 10, &first, false), Ok(Strategy::InMemory));
+        assert_eq!(plan(budget, 1000, &first, true), Ok(Strategy::Streaming));
+        assert!(plan(budget, 1000, &first, false).is_err());
+        assert!(plan(ByteSize(one), 1000, &first, true).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use crate::scoring::DEFAULT_STAR;
+use crate::smoothing::Counts;
+use crate::SuspiciousnessMetric;
+use std::str::FromStr;
+
+/// The suspiciousness formulas that come with the crate. Each is written in
+/// terms of ef, ep, nf and np, the failing and passing tests that did and
+/// didn't cover a statement; a formula that divides by zero scores 0, except
+/// D*, whose infinite scores mark statements only failing tests cover.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    /// ef^star / (ep + nf)
+    DStar { star: u32 },
+    /// (ef / F) / (ef / F + ep / P)
+    Tarantula,
+    /// ef / √(F · (ef + ep))
+    Ochiai,
+    /// ef / (ef + nf + ep)
+    Jaccard,
+    /// ef − ep / (P + 1)
+    Op2,
+    /// 1 − ep / (ep + ef)
+    Barinel,
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dstar" | "d*" => Ok(Metric::DSTAR),
+            "tarantula" => Ok(Metric::Tarantula),
+            "ochiai" => Ok(Metric::Ochiai),
+            "jaccard" => Ok(Metric::Jaccard),
+            "op2" => Ok(Metric::Op2),
+            "barinel" => Ok(Metric::Barinel),
+            _ => Err(format!(
+                "unknown metric '{}' (expected dstar, tarantula, ochiai, jaccard, op2 or barinel)",
+                s
+            )),
+        }
+    }
+}
+
+/// A setting of a formula, such as the star of D*. Every parameter so far
+/// is a whole number.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: &'static str,
+    /// The command line option that sets it
+    pub flag: &'static str,
+    pub description: &'static str,
+    pub default: u32,
+    pub minimum: u32,
+}
+
+/// What a UI needs to list a formula and let it be configured.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Description {
+    /// The name `--metric` takes
+    pub id: &'static str,
+    /// The name shown in reports, as [`SuspiciousnessMetric::name`]
+    pub name: &'static str,
+    /// The formula in terms of ef, ep, nf and np
+    pub formula: &'static str,
+    pub summary: &'static str,
+    /// The paper that introduced it
+    pub reference: &'static str,
+    pub parameters: Vec<Parameter>,
+}
+
+impl Metric {
+    /// D* with the star at [`DEFAULT_STAR`], the crate's default metric.
+    pub const DSTAR: Metric = Metric::DStar { star: DEFAULT_STAR };
+
+    /// Every built-in formula, with its parameters at their defaults.
+    pub const ALL: [Metric; 6] = [
+        Metric::DSTAR,
+        Metric::Tarantula,
+        Metric::Ochiai,
+        Metric::Jaccard,
+        Metric::Op2,
+        Metric::Barinel,
+    ];
+
+    pub fn describe(&self) -> Description {
+        let (id, name, formula, summary, reference) = match self {
+            Metric::DStar { .. } => (
+                "dstar",
+                "D*",
+                "ef^star / (ep + nf)",
+                "Ranks statements many failing and few passing tests run; infinite when only \
+                 failing tests run one.",
+                "Wong, Debroy, Gao and Li, \"The DStar Method for Effective Software Fault \
+                 Localization\", IEEE Transactions on Reliability 63(1), 2014",
+            ),
+            Metric::Tarantula => (
+                "tarantula",
+                "Tarantula",
+                "(ef / F) / (ef / F + ep / P)",
+                "Compares the share of failing tests that run a statement with the share of \
+                 passing tests that do.",
+                "Jones, Harrold and Stasko, \"Visualization of Test Information to Assist Fault \
+                 Localization\", ICSE 2002",
+            ),
+            Metric::Ochiai => (
+                "ochiai",
+                "Ochiai",
+                "ef / √(F · (ef + ep))",
+                "The cosine similarity of a statement's coverage and the failures.",
+                "Abreu, Zoeteweij and van Gemund, \"On the Accuracy of Spectrum-based Fault \
+                 Localization\", TAICPART-MUTATION 2007",
+            ),
+            Metric::Jaccard => (
+                "jaccard",
+                "Jaccard",
+                "ef / (ef + nf + ep)",
+                "The overlap of the tests that run a statement and the tests that fail.",
+                "Chen, Kiciman, Fratkin, Fox and Brewer, \"Pinpoint: Problem Determination in \
+                 Large, Dynamic Internet Services\", DSN 2002",
+            ),
+            Metric::Op2 => (
+                "op2",
+                "Op2",
+                "ef − ep / (P + 1)",
+                "Optimal for programs with a single fault: failing tests count, passing tests \
+                 only break ties.",
+                "Naish, Lee and Ramamohanarao, \"A Model for Spectra-based Software Diagnosis\", \
+                 ACM TOSEM 20(3), 2011",
+            ),
+            Metric::Barinel => (
+                "barinel",
+                "Barinel",
+                "1 − ep / (ep + ef)",
+                "The share of the tests running a statement that fail, as Barinel ranks single \
+                 statements.",
+                "Abreu, Zoeteweij and van Gemund, \"Spectrum-based Multiple Fault \
+                 Localization\", ASE 2009",
+            ),
+        };
+        let parameters = match self {
+            Metric::DStar { .. } => vec![Parameter {
+                name: "star",
+                flag: "--dstar-exponent",
+                description: "Exponent of ef; 3 and higher favor statements more failing tests \
+                              run",
+                default: DEFAULT_STAR,
+                minimum: 1,
+            }],
+            _ => Vec::new(),
+        };
+        Description {
+            id,
+            name,
+            formula,
+            summary,
+            reference,
+            parameters,
+        }
+    }
+}
+
+/// `numerator / denominator`, or 0 when the formula is undefined.
+fn ratio(numerator: f32, denominator: f32) -> f32 {
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    numerator / denominator
+}
+
+impl SuspiciousnessMetric for Metric {
+    fn name(&self) -> &str {
+        self.describe().name
+    }
+
+    fn suspiciousness(&self, counts: &Counts) -> f32 {
+        let Counts {
+            failed: ef,
+            passed: ep,
+            not_failed: nf,
+            not_passed: np,
+        } = *counts;
+        match self {
+            Metric::DStar { star } => counts.dstar_with(*star),
+            Metric::Tarantula => {
+                let fail_rate = ratio(ef, ef + nf);
+                let pass_rate = ratio(ep, ep + np);
+                ratio(fail_rate, fail_rate + pass_rate)
+            }
+            Metric::Ochiai => ratio(ef, ((ef + nf) * (ef + ep)).sqrt()),
+            Metric::Jaccard => ratio(ef, ef + nf + ep),
+            Metric::Op2 => ef - ep / (ep + np + 1.0),
+            Metric::Barinel => match ep + ef {
+                0.0 => 0.0,
+                covered => 1.0 - ep / covered,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smoothing::Smoothing;
+
+    #[test]
+    fn test_metrics() {
+        // covered by one of two failing tests and one of four passing tests
+        let counts = Smoothing::None.counts(1, 1, 2, 4);
+        let score = |metric: &str| metric.parse::<Metric>().unwrap().suspiciousness(&counts);
+        assert_eq!(score("dstar"), 1.0 / 2.0);
+        assert_eq!(score("tarantula"), 0.5 / (0.5 + 0.25));
+        assert_eq!(score("ochiai"), 1.0 / 4f32.sqrt());
+        assert_eq!(score("jaccard"), 1.0 / 3.0);
+        assert_eq!(score("op2"), 1.0 - 1.0 / 5.0);
+        assert_eq!(score("barinel"), 0.5);
+        assert!("ample".parse::<Metric>().is_err());
+        assert_eq!(Metric::DStar { star: 3 }.suspiciousness(&counts), 1.0 / 2.0);
+        let twice = Smoothing::None.counts(2, 1, 2, 4);
+        assert_eq!(Metric::DStar { star: 3 }.suspiciousness(&twice), 8.0);
+        assert_eq!(Metric::DSTAR.suspiciousness(&twice), twice.dstar());
+
+        for metric in Metric::ALL {
+            let description = metric.describe();
+            assert_eq!(description.id.parse::<Metric>(), Ok(metric));
+        }
+        assert_eq!(Metric::DSTAR.describe().parameters[0].default, DEFAULT_STAR);
+
+        let never = Smoothing::None.counts(0, 0, 2, 4);
+        for metric in ["tarantula", "ochiai", "jaccard", "barinel"] {
+            let metric = metric.parse::<Metric>().unwrap();
+            assert_eq!(metric.suspiciousness(&never), 0.0, "{}", metric.name());
+        }
+    }
+}
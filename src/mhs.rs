@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+/// Minimal hitting sets of `conflicts`: sets of statements that intersect
+/// every failing test's coverage, where no proper subset does. Each set is a
+/// candidate explanation of all failures by possibly several faults.
+///
+/// Statements are tried in the given `order` (most suspicious first), as
+/// Staccato does, so good candidates are found before the search is cut off
+/// by `max_size` or `limit`.
+pub fn minimal_hitting_sets(
+    conflicts: &[HashSet<u32>],
+    order: &[u32],
+    max_size: usize,
+    limit: usize,
+) -> Vec<Vec<u32>> {
+    let remaining: Vec<&HashSet<u32>> = conflicts.iter().collect();
+    let mut found = Vec::new();
+    // iterative deepening returns smaller candidates first
+    for size in 1..=max_size {
+        let mut candidate = Vec::new();
+        search(
+            &remaining,
+            order,
+            size,
+            limit,
+            &mut candidate,
+            &mut HashSet::new(),
+            &mut found,
+        );
+        if found.len() >= limit {
+            break;
+        }
+    }
+    found
+}
+
+fn search(
+    remaining: &[&HashSet<u32>],
+    order: &[u32],
+    max_size: usize,
+    limit: usize,
+    candidate: &mut Vec<u32>,
+    excluded: &mut HashSet<u32>,
+    found: &mut Vec<Vec<u32>>,
+) {
+    if found.len() >= limit {
+        return;
+    }
+    if remaining.is_empty() {
+        let is_minimal = !found
+            .iter()
+            .any(|set| set.iter().all(|line| candidate.contains(line)));
+        if is_minimal {
+            let mut set = candidate.clone();
+            set.sort();
+            found.push(set);
+        }
+        return;
+    }
+    if candidate.len() == max_size {
+        return;
+    }
+    // every hitting set contains an element of the smallest conflict, so branch on it
+    let conflict = remaining.iter().min_by_key(|c| c.len()).unwrap();
+    let mut tried = Vec::new();
+    for &line in order {
+        if !conflict.contains(&line) || excluded.contains(&line) {
+            continue;
+        }
+        let next: Vec<&HashSet<u32>> = remaining
+            .iter()
+            .filter(|c| !c.contains(&line))
+            .copied()
+            .collect();
+        candidate.push(line);
+        search(&next, order, max_size, limit, candidate, excluded, found);
+        candidate.pop();
+        // later branches need not consider this statement again
+        excluded.insert(line);
+        tried.push(line);
+    }
+    for line in tried {
+        excluded.remove(&line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(lines: &[u32]) -> HashSet<u32> {
+        lines.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_minimal_hitting_sets() {
+        let conflicts = vec![set(&[1, 2]), set(&[2, 3]), set(&[1, 3])];
+        let sets = minimal_hitting_sets(&conflicts, &[1, 2, 3], 3, 100);
+        assert_eq!(sets, vec![vec![1, 2], vec![1, 3], vec![2, 3]]);
+    }
+
+    #[test]
+    fn test_single_fault_explains_everything() {
+        let conflicts = vec![set(&[1, 2, 4]), set(&[2, 3])];
+        let sets = minimal_hitting_sets(&conflicts, &[2, 1, 3, 4], 2, 100);
+        assert_eq!(sets[0], vec![2]);
+        // {2} hits everything, so no set containing 2 is minimal
+        assert!(sets[1..].iter().all(|s| !s.contains(&2)));
+        assert!(sets.contains(&vec![1, 3]));
+    }
+}
@@ -0,0 +1,434 @@
+//! The data model the crate exchanges with other programs, kept apart from
+//! the internal types so those can change freely.
+//!
+//! Each version is a module (`v1`, later `v2`) whose types mirror the
+//! messages of the service contract in `proto/fl_dstar/<version>`. Within a
+//! version, changes are additive only: new optional fields and new enum
+//! variants, which the `#[non_exhaustive]` attributes leave room for, so
+//! consumers built against an earlier release keep compiling and keep
+//! reading what later releases write. Anything else is a new version, and
+//! the old one stays, converted to and from the internal types with `From`,
+//! or with constructors taking the source names where the internal types
+//! key lines by file (see [`crate::SourceKeys`]).
+//!
+//! Snapshots version their own binary format; see [`crate::snapshot`].
+
+pub mod v1 {
+    use crate::json::Json;
+    use std::collections::HashMap;
+
+    /// The version these types serialize as.
+    pub const VERSION: u32 = 1;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[non_exhaustive]
+    pub enum Coverage {
+        Covered,
+        NotCovered,
+        NoExecutableCode,
+    }
+
+    impl Coverage {
+        fn name(&self) -> &'static str {
+            match self {
+                Coverage::Covered => "covered",
+                Coverage::NotCovered => "not_covered",
+                Coverage::NoExecutableCode => "no_executable_code",
+            }
+        }
+
+        fn from_name(name: &str) -> Result<Coverage, String> {
+            match name {
+                "covered" => Ok(Coverage::Covered),
+                "not_covered" => Ok(Coverage::NotCovered),
+                "no_executable_code" => Ok(Coverage::NoExecutableCode),
+                _ => Err(format!("unknown coverage '{}'", name)),
+            }
+        }
+    }
+
+    impl From<crate::Coverage> for Coverage {
+        fn from(coverage: crate::Coverage) -> Self {
+            match coverage {
+                crate::Coverage::Covered => Coverage::Covered,
+                crate::Coverage::NotCovered => Coverage::NotCovered,
+                crate::Coverage::NoExecutableCode => Coverage::NoExecutableCode,
+            }
+        }
+    }
+
+    impl From<Coverage> for crate::Coverage {
+        fn from(coverage: Coverage) -> Self {
+            match coverage {
+                Coverage::Covered => crate::Coverage::Covered,
+                Coverage::NotCovered => crate::Coverage::NotCovered,
+                Coverage::NoExecutableCode => crate::Coverage::NoExecutableCode,
+            }
+        }
+    }
+
+    /// One line of a test's coverage.
+    #[derive(Debug, Clone, PartialEq)]
+    #[non_exhaustive]
+    pub struct LineInfo {
+        /// Source file, when the coverage named one
+        pub file: Option<String>,
+        pub line_number: u32,
+        pub statement: String,
+        pub coverage: Coverage,
+        pub execution_count: u64,
+    }
+
+    impl LineInfo {
+        pub fn new(line_number: u32, statement: String, coverage: Coverage) -> LineInfo {
+            LineInfo {
+                file: None,
+                line_number,
+                statement,
+                coverage,
+                execution_count: 0,
+            }
+        }
+    }
+
+    impl LineInfo {
+        /// A line of a spectrum's test, named by its file in `sources` (see
+        /// [`crate::Spectrum::sources`]) and its line in that file.
+        pub fn from_spectrum(line: &crate::LineInfo, sources: &HashMap<u32, String>) -> Self {
+            LineInfo {
+                file: sources.get(&line.file).cloned(),
+                line_number: line.line_number,
+                statement: line.statement.clone(),
+                coverage: line.coverage.into(),
+                execution_count: line.execution_count,
+            }
+        }
+
+        /// The line as a spectrum holds it, numbering its file with `keys` so
+        /// the same line of two files stays apart.
+        pub fn into_spectrum(self, keys: &mut crate::SourceKeys) -> crate::LineInfo {
+            crate::LineInfo {
+                file: self.file.as_deref().map_or(0, |file| keys.file(file)),
+                line_number: self.line_number,
+                statement: self.statement,
+                coverage: self.coverage.into(),
+                execution_count: self.execution_count,
+            }
+        }
+    }
+
+    /// The coverage of one test.
+    #[derive(Debug, Clone, PartialEq)]
+    #[non_exhaustive]
+    pub struct TestCoverage {
+        pub name: String,
+        pub passing: bool,
+        pub lines: Vec<LineInfo>,
+    }
+
+    impl TestCoverage {
+        pub fn new(name: String, passing: bool, lines: Vec<LineInfo>) -> TestCoverage {
+            TestCoverage {
+                name,
+                passing,
+                lines,
+            }
+        }
+
+        pub fn to_json(&self) -> Json {
+            Json::Object(vec![
+                ("version".to_string(), Json::from(VERSION)),
+                ("name".to_string(), Json::from(self.name.as_str())),
+                ("passing".to_string(), Json::from(self.passing)),
+                (
+                    "lines".to_string(),
+                    Json::Array(self.lines.iter().map(line_to_json).collect()),
+                ),
+            ])
+        }
+
+        /// Read a test written by [`TestCoverage::to_json`] of this version.
+        /// Members a later release added are ignored.
+        pub fn from_json(json: &Json) -> Result<TestCoverage, String> {
+            check_version(json)?;
+            let lines = json
+                .get("lines")
+                .and_then(Json::as_array)
+                .ok_or("expected lines")?
+                .iter()
+                .map(line_from_json)
+                .collect::<Result<_, String>>()?;
+            Ok(TestCoverage {
+                name: string(json, "name")?,
+                passing: matches!(json.get("passing"), Some(Json::Bool(true))),
+                lines,
+            })
+        }
+    }
+
+    /// A statement's place in a ranking.
+    #[derive(Debug, Clone, PartialEq)]
+    #[non_exhaustive]
+    pub struct RankedStatement {
+        /// 1 for the most suspicious statement
+        pub rank: u32,
+        pub file: Option<String>,
+        pub line_number: u32,
+        pub statement: String,
+        pub failed_tests: u32,
+        pub passed_tests: u32,
+        /// Infinite when only failing tests cover the statement
+        pub suspiciousness: f64,
+    }
+
+    /// A finished ranking, most suspicious statement first.
+    #[derive(Debug, Clone, PartialEq)]
+    #[non_exhaustive]
+    pub struct Ranking {
+        pub total_passing: u32,
+        pub total_failing: u32,
+        pub statements: Vec<RankedStatement>,
+    }
+
+    impl From<&crate::Spectrum> for Ranking {
+        /// The ranking of a scored spectrum.
+        fn from(spectrum: &crate::Spectrum) -> Self {
+            let mut statements = spectrum.statements.clone();
+            crate::sort_by_suspiciousness(&mut statements);
+            // the statements count every test, including those folded or
+            // streamed in and those of a snapshot, which `passing` and
+            // `failing` don't hold
+            let (total_passing, total_failing) = match spectrum.statements.first() {
+                Some(s) => (s.total_passed, s.total_failed),
+                None => (spectrum.passing.len() as u32, spectrum.failing.len() as u32),
+            };
+            Ranking {
+                total_passing,
+                total_failing,
+                statements: statements
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| RankedStatement {
+                        rank: i as u32 + 1,
+                        file: spectrum.sources.get(&s.file).cloned(),
+                        line_number: s.line_number,
+                        statement: s.statement().to_string(),
+                        failed_tests: s.failed_tests,
+                        passed_tests: s.passed_tests,
+                        suspiciousness: s.suspiciousness as f64,
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    impl RankedStatement {
+        pub fn to_json(&self) -> Json {
+            let mut members = vec![("rank".to_string(), Json::from(self.rank))];
+            if let Some(file) = &self.file {
+                members.push(("file".to_string(), Json::from(file.as_str())));
+            }
+            members.extend([
+                ("line_number".to_string(), Json::from(self.line_number)),
+                ("statement".to_string(), Json::from(self.statement.as_str())),
+                ("failed_tests".to_string(), Json::from(self.failed_tests)),
+                ("passed_tests".to_string(), Json::from(self.passed_tests)),
+                (
+                    "suspiciousness".to_string(),
+                    score_to_json(self.suspiciousness),
+                ),
+            ]);
+            Json::Object(members)
+        }
+    }
+
+    impl Ranking {
+        pub fn to_json(&self) -> Json {
+            let statements = self
+                .statements
+                .iter()
+                .map(RankedStatement::to_json)
+                .collect();
+            Json::Object(vec![
+                ("version".to_string(), Json::from(VERSION)),
+                ("total_passing".to_string(), Json::from(self.total_passing)),
+                ("total_failing".to_string(), Json::from(self.total_failing)),
+                ("statements".to_string(), Json::Array(statements)),
+            ])
+        }
+
+        /// Read a ranking written by [`Ranking::to_json`] of this version.
+        /// Members a later release added are ignored.
+        pub fn from_json(json: &Json) -> Result<Ranking, String> {
+            check_version(json)?;
+            let statements = json
+                .get("statements")
+                .and_then(Json::as_array)
+                .ok_or("expected statements")?
+                .iter()
+                .map(|s| {
+                    Ok(RankedStatement {
+                        rank: number(s, "rank")?,
+                        file: s.get("file").and_then(Json::as_str).map(str::to_string),
+                        line_number: number(s, "line_number")?,
+                        statement: string(s, "statement")?,
+                        failed_tests: number(s, "failed_tests")?,
+                        passed_tests: number(s, "passed_tests")?,
+                        suspiciousness: score_from_json(s.get("suspiciousness"))?,
+                    })
+                })
+                .collect::<Result<_, String>>()?;
+            Ok(Ranking {
+                total_passing: number(json, "total_passing")?,
+                total_failing: number(json, "total_failing")?,
+                statements,
+            })
+        }
+    }
+
+    fn check_version(json: &Json) -> Result<(), String> {
+        match json.get("version").and_then(Json::as_u32) {
+            Some(VERSION) => Ok(()),
+            Some(version) => Err(format!(
+                "version {} can't be read as version {}",
+                version, VERSION
+            )),
+            None => Err("expected a version".to_string()),
+        }
+    }
+
+    fn number(json: &Json, key: &str) -> Result<u32, String> {
+        json.get(key)
+            .and_then(Json::as_u32)
+            .ok_or_else(|| format!("expected {}", key))
+    }
+
+    fn string(json: &Json, key: &str) -> Result<String, String> {
+        json.get(key)
+            .and_then(Json::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| format!("expected {}", key))
+    }
+
+    /// JSON has no infinity, so infinite scores are written as strings.
+    fn score_to_json(score: f64) -> Json {
+        match score {
+            f64::INFINITY => Json::from("Infinity"),
+            score => Json::Number(score),
+        }
+    }
+
+    fn score_from_json(json: Option<&Json>) -> Result<f64, String> {
+        match json {
+            Some(Json::String(s)) if s == "Infinity" => Ok(f64::INFINITY),
+            Some(Json::Number(n)) => Ok(*n),
+            _ => Err("expected suspiciousness".to_string()),
+        }
+    }
+
+    fn line_to_json(line: &LineInfo) -> Json {
+        let mut members = Vec::new();
+        if let Some(file) = &line.file {
+            members.push(("file".to_string(), Json::from(file.as_str())));
+        }
+        members.extend([
+            ("line_number".to_string(), Json::from(line.line_number)),
+            ("statement".to_string(), Json::from(line.statement.as_str())),
+            ("coverage".to_string(), Json::from(line.coverage.name())),
+            (
+                "execution_count".to_string(),
+                Json::Number(line.execution_count as f64),
+            ),
+        ]);
+        Json::Object(members)
+    }
+
+    fn line_from_json(json: &Json) -> Result<LineInfo, String> {
+        let coverage = json
+            .get("coverage")
+            .and_then(Json::as_str)
+            .ok_or("expected coverage")?;
+        Ok(LineInfo {
+            file: json.get("file").and_then(Json::as_str).map(str::to_string),
+            line_number: number(json, "line_number")?,
+            statement: string(json, "statement")?,
+            coverage: Coverage::from_name(coverage)?,
+            execution_count: json
+                .get("execution_count")
+                .and_then(Json::as_f64)
+                .map_or(0, |n| n as u64),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::v1;
+    use crate::json::{self, Json};
+    use crate::{Coverage, LineInfo, Spectrum};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_v1_round_trip() {
+        let line = |line_number, coverage| LineInfo {
+            file: 1,
+            line_number,
+            statement: format!("s{}();", line_number),
+            coverage,
+            execution_count: 2,
+        };
+        let passing = vec![vec![
+            line(1, Coverage::Covered),
+            line(2, Coverage::NotCovered),
+        ]];
+        let failing = vec![vec![line(1, Coverage::Covered), line(2, Coverage::Covered)]];
+        let sources = HashMap::from([(1, "t.c".to_string())]);
+        let lines: Vec<v1::LineInfo> = failing[0]
+            .iter()
+            .map(|line| v1::LineInfo::from_spectrum(line, &sources))
+            .collect();
+        assert_eq!(lines[1].file.as_deref(), Some("t.c"));
+        assert_eq!(lines[1].line_number, 2);
+        let test = v1::TestCoverage::new("f1".to_string(), false, lines);
+        let read = v1::TestCoverage::from_json(&json::parse(&test.to_json().to_string()).unwrap());
+        assert_eq!(read.as_ref(), Ok(&test));
+        // t.c is the first file these keys see, so it gets id 1 again
+        let mut keys = crate::SourceKeys::default();
+        let back = read.unwrap().lines[1].clone().into_spectrum(&mut keys);
+        assert_eq!(
+            (
+                back.file,
+                back.line_number,
+                back.coverage,
+                back.execution_count
+            ),
+            (1, 2, Coverage::Covered, 2)
+        );
+
+        let mut spectrum = Spectrum::new(passing, failing);
+        spectrum.sources.insert(1, "t.c".to_string());
+        for statement in &mut spectrum.statements {
+            statement.calculate_suspiciousness();
+        }
+        let ranking = v1::Ranking::from(&spectrum);
+        assert_eq!(ranking.statements[0].line_number, 2);
+        assert!(ranking.statements[0].suspiciousness.is_infinite());
+        let text = ranking.to_json().to_string();
+        assert!(text.contains("\"Infinity\""));
+        let read = v1::Ranking::from_json(&json::parse(&text).unwrap());
+        assert_eq!(read, Ok(ranking));
+
+        // a snapshot's spectrum keeps no tests, only their counts
+        spectrum.passing.clear();
+        spectrum.failing.clear();
+        let ranking = v1::Ranking::from(&spectrum);
+        assert_eq!((ranking.total_passing, ranking.total_failing), (1, 1));
+
+        // later versions are refused rather than misread
+        let mut later = test.to_json();
+        if let Json::Object(members) = &mut later {
+            members[0].1 = Json::from(2u32);
+        }
+        assert!(v1::TestCoverage::from_json(&later).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::json::Json;
+use crate::StatementInfo;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Chat services that accept incoming webhooks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Service {
+    Slack,
+    Teams,
+}
+
+impl FromStr for Service {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "slack" => Ok(Service::Slack),
+            "teams" => Ok(Service::Teams),
+            _ => Err(format!("unknown service '{}' (expected slack or teams)", s)),
+        }
+    }
+}
+
+impl Service {
+    /// Guess the service from a webhook URL; anything that isn't Slack is
+    /// assumed to be a Teams (Office 365 or Power Automate) webhook.
+    pub fn detect(url: &str) -> Service {
+        match url.contains("slack.com") {
+            true => Service::Slack,
+            false => Service::Teams,
+        }
+    }
+
+    fn link(&self, url: &str, text: &str) -> String {
+        match self {
+            Service::Slack => format!("<{}|{}>", url, text),
+            Service::Teams => format!("[{}]({})", text, url),
+        }
+    }
+}
+
+/// The chat message for the `top` most suspicious statements, which must be
+/// sorted. With `repo_url` (e.g. `https://github.com/org/repo/blob/main`)
+/// each location links to the line.
+pub fn message(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    top: usize,
+    repo_url: Option<&str>,
+    service: Service,
+) -> Json {
+    let mut lines = vec![format!(
+        "fl_dstar: {} most suspicious of {} statements",
+        top.min(statements.len()),
+        statements.len()
+    )];
+    for (i, statement) in statements.iter().take(top).enumerate() {
+        let file = sources.get(&statement.file);
+        let location = match file {
+            Some(file) => format!("{}:{}", file, statement.line_number),
+            None => format!("line {}", statement.line_number),
+        };
+        let location = match (repo_url, file) {
+            (Some(base), Some(file)) => service.link(
+                &format!(
+                    "{}/{}#L{}",
+                    base.trim_end_matches('/'),
+                    file,
+                    statement.line_number
+                ),
+                &location,
+            ),
+            _ => location,
+        };
+        lines.push(format!(
+            "{}. {} ({:.2}) `{}`",
+            i + 1,
+            location,
+            statement.suspiciousness,
+            statement.statement().trim()
+        ));
+    }
+    // Teams renders single newlines as spaces
+    let separator = match service {
+        Service::Slack => "\n",
+        Service::Teams => "\n\n",
+    };
+    Json::Object(vec![(
+        "text".to_string(),
+        Json::from(lines.join(separator)),
+    )])
+}
+
+/// POST `payload` to a webhook; see [`crate::http::send_json`].
+pub fn post(url: &str, payload: &Json) -> std::io::Result<()> {
+    crate::http::send_json("POST", url, &[], &payload.to_string()).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        let statements: Vec<StatementInfo> = [(4, "b();"), (2, "int a = 1;")]
+            .iter()
+            .map(|&(line, text)| {
+                let mut s = StatementInfo::new(line, text.to_string(), 1);
+                s.file = (line == 4) as u32;
+                s.suspiciousness = 1.0 / line as f32;
+                s
+            })
+            .collect();
+        let sources = HashMap::from([(1, "src/t.c".to_string())]);
+        let slack = message(
+            &statements,
+            &sources,
+            1,
+            Some("https://example.com/repo/blob/main/"),
+            Service::detect("https://hooks.slack.com/services/T0/B0/x"),
+        );
+        assert_eq!(
+            slack.get("text").unwrap().as_str(),
+            Some(
+                "fl_dstar: 1 most suspicious of 2 statements\n\
+                 1. <https://example.com/repo/blob/main/src/t.c#L4|src/t.c:4> (0.25) `b();`"
+            )
+        );
+        let teams = message(&statements, &sources, 5, None, Service::Teams);
+        let text = teams.get("text").unwrap().as_str().unwrap();
+        assert!(text.contains("\n\n1. src/t.c:4 (0.25)"));
+        assert!(text.ends_with("2. line 2 (0.50) `int a = 1;`"));
+    }
+}
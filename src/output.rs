@@ -0,0 +1,561 @@
+use crate::json::Json;
+use crate::locale::NumberFormat;
+use crate::StatementInfo;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Version of the ranking CSV schema. The default columns of a released
+/// version never change; new fields are only written when asked for with
+/// `--columns` or under a later version.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// The schema version written when none is asked for: the first, so scripts
+/// reading the default ranking keep working as versions are added. This is
+/// kept apart from [`SCHEMA_VERSION`] so that the default only changes on
+/// purpose; `tests/golden/mini/header.csv` holds the header it gives.
+pub const DEFAULT_SCHEMA_VERSION: u32 = 1;
+
+/// A column of the ranking CSV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    File,
+    Rank,
+    LineNumber,
+    Statement,
+    /// Hash of the statement's text, see [`crate::integrity`]
+    Hash,
+    /// Failing tests that cover the statement (ef)
+    FailedTests,
+    /// Passing tests that cover the statement (ep)
+    PassedTests,
+    /// Failing tests that don't cover the statement (nf)
+    NotFailed,
+    /// Passing tests that don't cover the statement (np)
+    NotPassed,
+    TotalFailed,
+    TotalPassed,
+    Suspiciousness,
+    /// Percentage of statements scored strictly lower
+    Percentile,
+    /// Percentage of statements inspected to reach this one, see [`exam`]
+    Exam,
+    /// Who owns the statement's file, see [`crate::owners`]
+    Owner,
+}
+
+/// How the ranking is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Csv,
+    /// One array of objects, one per row
+    Json,
+    /// One object per line, for piping into jq and other line-based tools
+    Jsonl,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" | "json-lines" | "ndjson" => Ok(OutputFormat::Jsonl),
+            _ => Err(format!(
+                "unknown output format '{}' (expected csv, json or jsonl)",
+                s
+            )),
+        }
+    }
+}
+
+/// One statement's row, with everything its columns are computed from.
+struct Row<'a> {
+    rank: usize,
+    file: Option<&'a str>,
+    owner: Option<&'a str>,
+    statement: &'a StatementInfo,
+    percentile: f64,
+    exam: f64,
+}
+
+impl<'a> Row<'a> {
+    fn new(
+        index: usize,
+        statement: &'a StatementInfo,
+        scores: &[f32],
+        sources: &'a HashMap<u32, String>,
+        owners: &'a HashMap<u32, String>,
+    ) -> Row<'a> {
+        Row {
+            rank: index + 1,
+            file: sources.get(&statement.file).map(|s| s.as_str()),
+            owner: owners.get(&statement.file).map(|s| s.as_str()),
+            statement,
+            percentile: percentile_of(scores, statement.suspiciousness),
+            exam: exam_of(scores, statement.suspiciousness),
+        }
+    }
+}
+
+impl Column {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::File => "file",
+            Column::Rank => "rank",
+            Column::LineNumber => "line_number",
+            Column::Statement => "statement",
+            Column::Hash => "hash",
+            Column::FailedTests => "failed_tests",
+            Column::PassedTests => "passed_tests",
+            Column::NotFailed => "not_failed",
+            Column::NotPassed => "not_passed",
+            Column::TotalFailed => "total_failed",
+            Column::TotalPassed => "total_passed",
+            Column::Suspiciousness => "suspiciousness",
+            Column::Percentile => "percentile",
+            Column::Exam => "exam",
+            Column::Owner => "owner",
+        }
+    }
+
+    fn value(&self, row: &Row) -> String {
+        let statement = row.statement;
+        match self {
+            Column::File => row.file.unwrap_or("").to_string(),
+            Column::Rank => row.rank.to_string(),
+            Column::LineNumber => statement.line_number.to_string(),
+            Column::Statement => statement.statement().to_string(),
+            Column::Hash => statement.hash(),
+            Column::FailedTests => statement.failed_tests.to_string(),
+            Column::PassedTests => statement.passed_tests.to_string(),
+            Column::NotFailed => statement
+                .total_failed
+                .saturating_sub(statement.failed_tests)
+                .to_string(),
+            Column::NotPassed => statement
+                .total_passed
+                .saturating_sub(statement.passed_tests)
+                .to_string(),
+            Column::TotalFailed => statement.total_failed.to_string(),
+            Column::TotalPassed => statement.total_passed.to_string(),
+            Column::Suspiciousness => format!("{:.2}", statement.suspiciousness),
+            Column::Percentile => format!("{:.2}", row.percentile),
+            Column::Exam => format!("{:.2}", row.exam),
+            Column::Owner => row.owner.unwrap_or("").to_string(),
+        }
+    }
+
+    /// The value as JSON: counts as numbers, and scores as [`json_score`]
+    /// writes them.
+    fn json(&self, row: &Row) -> Json {
+        let statement = row.statement;
+        match self {
+            Column::Rank => Json::from(row.rank as u32),
+            Column::LineNumber => Json::from(statement.line_number),
+            Column::FailedTests => Json::from(statement.failed_tests),
+            Column::PassedTests => Json::from(statement.passed_tests),
+            Column::NotFailed => Json::from(
+                statement
+                    .total_failed
+                    .saturating_sub(statement.failed_tests),
+            ),
+            Column::NotPassed => Json::from(
+                statement
+                    .total_passed
+                    .saturating_sub(statement.passed_tests),
+            ),
+            Column::TotalFailed => Json::from(statement.total_failed),
+            Column::TotalPassed => Json::from(statement.total_passed),
+            Column::Suspiciousness => json_score(f64_of(statement.suspiciousness)),
+            Column::Percentile => json_score(row.percentile),
+            Column::Exam => json_score(row.exam),
+            Column::File | Column::Owner => match self.value(row) {
+                value if value.is_empty() => Json::Null,
+                value => Json::from(value),
+            },
+            Column::Statement | Column::Hash => Json::from(self.value(row)),
+        }
+    }
+}
+
+/// A score as JSON, unrounded. An infinite score is written as the string
+/// `Infinity`, which JSON has no number for.
+fn json_score(x: f64) -> Json {
+    match x {
+        f64::INFINITY => Json::from("Infinity"),
+        x => Json::Number(x),
+    }
+}
+
+/// `x` through its own shortest form, so 0.1 isn't written as
+/// 0.10000000149011612.
+fn f64_of(x: f32) -> f64 {
+    x.to_string().parse().unwrap_or(f64::NAN)
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(Column::File),
+            "rank" => Ok(Column::Rank),
+            "line" | "line_number" => Ok(Column::LineNumber),
+            "statement" => Ok(Column::Statement),
+            "hash" => Ok(Column::Hash),
+            "ef" | "failed_tests" => Ok(Column::FailedTests),
+            "ep" | "passed_tests" => Ok(Column::PassedTests),
+            "nf" | "not_failed" => Ok(Column::NotFailed),
+            "np" | "not_passed" => Ok(Column::NotPassed),
+            "total_failed" => Ok(Column::TotalFailed),
+            "total_passed" => Ok(Column::TotalPassed),
+            "suspiciousness" => Ok(Column::Suspiciousness),
+            "percentile" => Ok(Column::Percentile),
+            "exam" => Ok(Column::Exam),
+            "owner" => Ok(Column::Owner),
+            _ => Err(format!("unknown column '{}'", s)),
+        }
+    }
+}
+
+/// Parse a comma separated column list such as `rank,line,ef,ep,suspiciousness`.
+pub fn parse_columns(spec: &str) -> Result<Vec<Column>, String> {
+    spec.split(',').map(|name| name.trim().parse()).collect()
+}
+
+/// The columns written by default under a schema version.
+pub fn default_columns(version: u32) -> Result<Vec<Column>, String> {
+    let v1 = vec![
+        Column::LineNumber,
+        Column::Statement,
+        Column::FailedTests,
+        Column::PassedTests,
+        Column::TotalFailed,
+        Column::Suspiciousness,
+    ];
+    match version {
+        1 => Ok(v1),
+        2 => Ok([v1, vec![Column::Percentile, Column::Exam]].concat()),
+        // line numbers alone collide once the coverage spans several files
+        3 => Ok([
+            vec![Column::File],
+            v1,
+            vec![Column::Percentile, Column::Exam],
+        ]
+        .concat()),
+        _ => Err(format!(
+            "unknown schema version {}; the latest is {}",
+            version, SCHEMA_VERSION
+        )),
+    }
+}
+
+/// Scores sorted from most to least suspicious, for counting how many
+/// statements rank above or alongside a score.
+fn descending_scores(statements: &[StatementInfo]) -> Vec<f32> {
+    let mut scores: Vec<f32> = statements.iter().map(|s| s.suspiciousness).collect();
+    scores.sort_by(|a, b| b.total_cmp(a));
+    scores
+}
+
+fn percentile_of(scores: &[f32], score: f32) -> f64 {
+    let higher_or_tied = scores.partition_point(|&x| x >= score);
+    100.0 * (scores.len() - higher_or_tied) as f64 / scores.len() as f64
+}
+
+fn exam_of(scores: &[f32], score: f32) -> f64 {
+    let higher = scores.partition_point(|&x| x > score);
+    let tied = scores.partition_point(|&x| x >= score) - higher;
+    100.0 * (higher as f64 + (tied as f64 + 1.0) / 2.0) / scores.len() as f64
+}
+
+/// For each statement, the percentage of statements scored strictly lower.
+pub fn percentile(statements: &[StatementInfo]) -> Vec<f64> {
+    let scores = descending_scores(statements);
+    statements
+        .iter()
+        .map(|s| percentile_of(&scores, s.suspiciousness))
+        .collect()
+}
+
+/// The EXAM score of each statement: the percentage of statements a
+/// developer inspects, going down the ranking, until they reach it. Tied
+/// statements are inspected in no particular order, so a statement is
+/// charged everything ranked above it and, on average, half of its ties.
+pub fn exam(statements: &[StatementInfo]) -> Vec<f64> {
+    let scores = descending_scores(statements);
+    statements
+        .iter()
+        .map(|s| exam_of(&scores, s.suspiciousness))
+        .collect()
+}
+
+/// The ranking as a JSON model, for rendering with user templates (see
+/// [`crate::template`]). Scores are preformatted with `numbers`, since
+/// templated reports are read by people.
+pub fn report_model(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    numbers: &NumberFormat,
+) -> Json {
+    ranking_model(statements, sources, Some(numbers))
+}
+
+/// The fields of [`report_model`] for other programs to read: scores are
+/// plain numbers, whatever the locale, and hashes have no leading `#`.
+pub fn export_model(statements: &[StatementInfo], sources: &HashMap<u32, String>) -> Json {
+    ranking_model(statements, sources, None)
+}
+
+fn ranking_model(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    numbers: Option<&NumberFormat>,
+) -> Json {
+    let score = |value: f64| match numbers {
+        Some(numbers) => Json::from(numbers.format(value, 2)),
+        None => json_score(value),
+    };
+    let percentiles = percentile(statements);
+    let exams = exam(statements);
+    let top = statements.first().map(|s| s.suspiciousness);
+    let rows = statements
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let file = sources.get(&s.file).cloned().unwrap_or_default();
+            Json::Object(vec![
+                ("rank".to_string(), Json::from(i as u32 + 1)),
+                ("file".to_string(), Json::from(file)),
+                ("line_number".to_string(), Json::from(s.line_number)),
+                ("statement".to_string(), Json::from(s.statement())),
+                (
+                    "hash".to_string(),
+                    match numbers {
+                        Some(_) => Json::from(s.hash()),
+                        None => Json::from(s.hash().trim_start_matches('#')),
+                    },
+                ),
+                ("failed_tests".to_string(), Json::from(s.failed_tests)),
+                ("passed_tests".to_string(), Json::from(s.passed_tests)),
+                (
+                    "suspiciousness".to_string(),
+                    match numbers {
+                        Some(_) => score(s.suspiciousness as f64),
+                        None => score(f64_of(s.suspiciousness)),
+                    },
+                ),
+                ("percentile".to_string(), score(percentiles[i])),
+                ("exam".to_string(), score(exams[i])),
+                ("top".to_string(), Json::from(Some(s.suspiciousness) == top)),
+            ])
+        })
+        .collect();
+    let totals = |f: fn(&StatementInfo) -> u32| statements.first().map(f).unwrap_or(0);
+    Json::Object(vec![
+        ("version".to_string(), Json::from(env!("CARGO_PKG_VERSION"))),
+        ("schema_version".to_string(), Json::from(SCHEMA_VERSION)),
+        (
+            "failing_tests".to_string(),
+            Json::from(totals(|s| s.total_failed)),
+        ),
+        (
+            "passing_tests".to_string(),
+            Json::from(totals(|s| s.total_passed)),
+        ),
+        (
+            "statement_count".to_string(),
+            Json::from(statements.len() as u32),
+        ),
+        ("statements".to_string(), Json::Array(rows)),
+    ])
+}
+
+/// Write ranked statements in `format` with the given columns. Statements
+/// must already be in rank order; `sources` gives the name of each file id
+/// when known, and `owners` its owners (see
+/// [`crate::owners::CodeOwners::line_owners`]).
+pub fn write_ranking<W: Write>(
+    writer: W,
+    format: OutputFormat,
+    columns: &[Column],
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    owners: &HashMap<u32, String>,
+) -> csv::Result<()> {
+    let scores = descending_scores(statements);
+    write_ranking_stream(
+        writer,
+        format,
+        columns,
+        statements.iter().map(Ok),
+        &scores,
+        sources,
+        owners,
+    )
+}
+
+/// Like [`write_ranking`], for statements read one at a time in rank order,
+/// such as from an [`crate::extsort::ExternalSort`]. `scores` holds every
+/// statement's score from most to least suspicious, for the percentile and
+/// EXAM columns.
+pub fn write_ranking_stream<W, I, S>(
+    mut writer: W,
+    format: OutputFormat,
+    columns: &[Column],
+    statements: I,
+    scores: &[f32],
+    sources: &HashMap<u32, String>,
+    owners: &HashMap<u32, String>,
+) -> csv::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = std::io::Result<S>>,
+    S: Borrow<StatementInfo>,
+{
+    if format == OutputFormat::Csv {
+        let mut wtr = csv::Writer::from_writer(writer);
+        wtr.write_record(columns.iter().map(|c| c.name()))?;
+        for (i, statement) in statements.into_iter().enumerate() {
+            let statement = statement?;
+            let row = Row::new(i, statement.borrow(), scores, sources, owners);
+            wtr.write_record(columns.iter().map(|c| c.value(&row)))?;
+        }
+        wtr.flush()?;
+        return Ok(());
+    }
+    for (i, statement) in statements.into_iter().enumerate() {
+        let statement = statement?;
+        let row = Row::new(i, statement.borrow(), scores, sources, owners);
+        let object = Json::Object(
+            columns
+                .iter()
+                .map(|c| (c.name().to_string(), c.json(&row)))
+                .collect(),
+        );
+        match (format, i) {
+            (OutputFormat::Json, 0) => write!(writer, "[\n{}", object)?,
+            (OutputFormat::Json, _) => write!(writer, ",\n{}", object)?,
+            _ => writeln!(writer, "{}", object)?,
+        }
+    }
+    if format == OutputFormat::Json {
+        // an empty ranking is an empty array
+        match scores.is_empty() {
+            true => writeln!(writer, "[]")?,
+            false => writeln!(writer, "\n]")?,
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement() -> StatementInfo {
+        let mut statement = StatementInfo::new(4, "b();".to_string(), 2);
+        statement.total_passed = 3;
+        statement.add_failing_coverage();
+        statement.add_passing_coverage();
+        statement.calculate_suspiciousness();
+        statement
+    }
+
+    #[test]
+    fn test_default_columns_match_serialized_struct() {
+        let mut old = csv::Writer::from_writer(vec![]);
+        old.serialize(statement()).unwrap();
+        let old = String::from_utf8(old.into_inner().unwrap()).unwrap();
+        let mut new = vec![];
+        let columns = default_columns(1).unwrap();
+        write_ranking(
+            &mut new,
+            OutputFormat::Csv,
+            &columns,
+            &[statement()],
+            &HashMap::new(),
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(new).unwrap(), old);
+    }
+
+    #[test]
+    fn test_default_columns_name_the_file() {
+        let mut first = statement();
+        first.file = 1;
+        let mut other = statement();
+        other.file = 2;
+        let sources = HashMap::from([(1, "a.c".to_string()), (2, "b.c".to_string())]);
+        let mut out = vec![];
+        write_ranking(
+            &mut out,
+            OutputFormat::Csv,
+            &default_columns(SCHEMA_VERSION).unwrap(),
+            &[first, other],
+            &sources,
+            &HashMap::new(),
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = out.lines().collect();
+        assert!(rows[0].starts_with("file,line_number,"));
+        assert!(rows[1].starts_with("a.c,4,"));
+        assert!(rows[2].starts_with("b.c,4,"));
+    }
+
+    #[test]
+    fn test_exam_and_percentile() {
+        let statements: Vec<StatementInfo> = [3.0, 1.0, 1.0, 0.0]
+            .iter()
+            .enumerate()
+            .map(|(i, &score)| {
+                let mut s = StatementInfo::new(i as u32 + 1, String::new(), 1);
+                s.suspiciousness = score;
+                s
+            })
+            .collect();
+        assert_eq!(exam(&statements), vec![25.0, 62.5, 62.5, 100.0]);
+        assert_eq!(percentile(&statements), vec![75.0, 25.0, 25.0, 0.0]);
+    }
+
+    #[test]
+    fn test_selected_columns() {
+        let columns = parse_columns("rank,file,line,ef,ep,nf,np,owner").unwrap();
+        let mut statement = statement();
+        statement.file = 1;
+        let sources = HashMap::from([(1, "src/t.c".to_string())]);
+        let owners = HashMap::from([(1, "@org/core".to_string())]);
+        let write = |format| {
+            let mut out = vec![];
+            write_ranking(
+                &mut out,
+                format,
+                &columns,
+                std::slice::from_ref(&statement),
+                &sources,
+                &owners,
+            )
+            .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(
+            write(OutputFormat::Csv),
+            "rank,file,line_number,failed_tests,passed_tests,not_failed,not_passed,owner\n\
+             1,src/t.c,4,1,1,1,2,@org/core\n"
+        );
+        assert_eq!(
+            write(OutputFormat::Jsonl),
+            "{\"rank\":1,\"file\":\"src/t.c\",\"line_number\":4,\"failed_tests\":1,\
+             \"passed_tests\":1,\"not_failed\":1,\"not_passed\":2,\"owner\":\"@org/core\"}\n"
+        );
+        assert!(parse_columns("rank,bogus").is_err());
+        assert!("xml".parse::<OutputFormat>().is_err());
+        assert!(default_columns(SCHEMA_VERSION + 1).is_err());
+    }
+}
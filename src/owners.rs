@@ -0,0 +1,234 @@
+use crate::paths::glob_match;
+use crate::StatementInfo;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// One CODEOWNERS rule: a path pattern and who owns what it matches.
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    /// The pattern as a glob for [`glob_match`], relative to the root
+    glob: String,
+    owners: Vec<String>,
+}
+
+/// The rules of a CODEOWNERS file, as GitHub and GitLab read them: a
+/// pattern and its owners per line, with the last matching rule winning.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CodeOwners {
+    rules: Vec<Rule>,
+}
+
+/// A gitignore-style CODEOWNERS pattern as a glob: a leading `/` anchors it
+/// at the root, a pattern with no other `/` matches at any depth, and a
+/// directory matches everything under it.
+fn to_glob(pattern: &str) -> String {
+    let directory = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    let glob = match trimmed.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if !trimmed.contains('/') => format!("**/{}", trimmed),
+        None => trimmed.to_string(),
+    };
+    match directory {
+        true => format!("{}/**", glob),
+        false => glob,
+    }
+}
+
+impl CodeOwners {
+    pub fn parse(text: &str) -> Result<CodeOwners, String> {
+        let mut rules = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+                // GitLab section headers are in brackets; their rules follow as usual
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let pattern = fields.next().unwrap_or_default();
+            if pattern.starts_with('!') {
+                return Err(format!(
+                    "line {}: negated patterns aren't supported in CODEOWNERS",
+                    i + 1
+                ));
+            }
+            let owners = fields
+                .take_while(|field| !field.starts_with('#'))
+                .map(str::to_string)
+                .collect();
+            rules.push(Rule {
+                glob: to_glob(pattern),
+                owners,
+            });
+        }
+        Ok(CodeOwners { rules })
+    }
+
+    /// The owners of `path`, relative to the repository root. Empty when no
+    /// rule matches or the last matching rule names no one.
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        let path = path.trim_start_matches("./");
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                glob_match(&rule.glob, path) || glob_match(&format!("{}/**", rule.glob), path)
+            })
+            .map_or(&[], |rule| &rule.owners)
+    }
+
+    /// The owners of each source file, by file id, space separated, as
+    /// reports show them.
+    pub fn line_owners(&self, sources: &HashMap<u32, String>) -> HashMap<u32, String> {
+        sources
+            .iter()
+            .map(|(file, source)| (*file, self.owners_of(source).join(" ")))
+            .filter(|(_, owners)| !owners.is_empty())
+            .collect()
+    }
+}
+
+/// How one owner's code fares in a ranking.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnerStats {
+    /// An owner as CODEOWNERS names them; empty for code no one owns
+    pub owner: String,
+    pub statements: usize,
+    /// Statements scoring above zero
+    pub suspicious: usize,
+    /// Rank of the owner's most suspicious statement
+    pub best_rank: usize,
+    pub top_file: Option<String>,
+    pub top_line: u32,
+    pub max_suspiciousness: f32,
+}
+
+/// Group ranked statements by owner, so results can be routed to the teams
+/// that own the code. A statement with several owners counts for each.
+/// Owners are returned by their best rank.
+pub fn by_owner(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    owners: &CodeOwners,
+) -> Vec<OwnerStats> {
+    let mut stats: Vec<OwnerStats> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let unowned = [String::new()];
+    for (i, statement) in statements.iter().enumerate() {
+        let file = sources.get(&statement.file);
+        let names = match file.map(|file| owners.owners_of(file)) {
+            Some(names) if !names.is_empty() => names,
+            _ => &unowned,
+        };
+        for name in names {
+            let entry = *index.entry(name.clone()).or_insert_with(|| {
+                stats.push(OwnerStats {
+                    owner: name.clone(),
+                    statements: 0,
+                    suspicious: 0,
+                    best_rank: i + 1,
+                    top_file: file.cloned(),
+                    top_line: statement.line_number,
+                    max_suspiciousness: statement.suspiciousness,
+                });
+                stats.len() - 1
+            });
+            stats[entry].statements += 1;
+            if statement.suspiciousness > 0.0 {
+                stats[entry].suspicious += 1;
+            }
+        }
+    }
+    stats
+}
+
+pub fn write_owners<W: Write>(writer: W, stats: &[OwnerStats]) -> csv::Result<()> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    wtr.write_record([
+        "owner",
+        "statements",
+        "suspicious",
+        "best_rank",
+        "top_file",
+        "top_line",
+        "max_suspiciousness",
+    ])?;
+    for owner in stats {
+        wtr.write_record([
+            owner.owner.clone(),
+            owner.statements.to_string(),
+            owner.suspicious.to_string(),
+            owner.best_rank.to_string(),
+            owner.top_file.clone().unwrap_or_default(),
+            owner.top_line.to_string(),
+            format!("{:.2}", owner.max_suspiciousness),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODEOWNERS: &str = "\
+# default owners
+*       @org/core
+*.js    @org/web   # frontend
+/docs/  @org/docs
+src/net @alice @org/net
+/src/net/legacy.c
+";
+
+    #[test]
+    fn test_owners_of() {
+        let owners = CodeOwners::parse(CODEOWNERS).unwrap();
+        assert_eq!(owners.owners_of("src/parse.c"), ["@org/core"]);
+        assert_eq!(owners.owners_of("ui/app/main.js"), ["@org/web"]);
+        assert_eq!(owners.owners_of("docs/guide/intro.md"), ["@org/docs"]);
+        assert_eq!(owners.owners_of("src/docs/x.c"), ["@org/core"]);
+        assert_eq!(owners.owners_of("./src/net/http.c"), ["@alice", "@org/net"]);
+        // a rule with no owners leaves the file unowned
+        assert!(owners.owners_of("src/net/legacy.c").is_empty());
+        assert!(CodeOwners::parse("!src/x.c @a").is_err());
+    }
+
+    #[test]
+    fn test_by_owner() {
+        let owners = CodeOwners::parse(CODEOWNERS).unwrap();
+        let statements: Vec<StatementInfo> = [(1, 2.0), (2, 1.0), (3, 0.0), (4, 0.5)]
+            .iter()
+            .map(|&(line, score)| {
+                let mut s = StatementInfo::new(line, String::new(), 1);
+                // each of the first three lines is in a file of its own
+                s.file = if line <= 3 { line } else { 0 };
+                s.suspiciousness = score;
+                s
+            })
+            .collect();
+        let sources: HashMap<u32, String> = [
+            (1, "src/net/http.c"),
+            (2, "src/parse.c"),
+            (3, "src/net/tcp.c"),
+        ]
+        .iter()
+        .map(|&(file, source)| (file, source.to_string()))
+        .collect();
+        let stats = by_owner(&statements, &sources, &owners);
+        let rows: Vec<(&str, usize, usize, usize)> = stats
+            .iter()
+            .map(|s| (s.owner.as_str(), s.statements, s.suspicious, s.best_rank))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("@alice", 2, 1, 1),
+                ("@org/net", 2, 1, 1),
+                ("@org/core", 1, 1, 2),
+                ("", 1, 1, 4)
+            ]
+        );
+        assert_eq!(owners.line_owners(&sources)[&3], "@alice @org/net");
+    }
+}
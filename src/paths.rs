@@ -0,0 +1,309 @@
+use std::path::{Path, PathBuf};
+
+/// A `FROM=TO` prefix rewrite applied to source paths, so coverage recorded on
+/// a CI machine can point at a local checkout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathMap {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl std::str::FromStr for PathMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((from, to)) if !from.is_empty() => Ok(PathMap {
+                from: PathBuf::from(from),
+                to: PathBuf::from(to),
+            }),
+            _ => Err(format!("invalid path map '{}' (expected FROM=TO)", s)),
+        }
+    }
+}
+
+/// Rewrite `path` with the first map whose `from` is a prefix of it. Prefixes
+/// match whole path components, so `/build/src` does not match `/build/srcs`.
+/// Windows paths from any host are understood: `\` separates components and
+/// `C:\Build` matches `c:/build`.
+pub fn remap(path: &str, maps: &[PathMap]) -> String {
+    for map in maps {
+        if let Some(rest) = strip_path_prefix(path, &map.from.to_string_lossy()) {
+            let to = map.to.to_string_lossy();
+            if rest.is_empty() {
+                return to.into_owned();
+            }
+            let separator = match is_windows_path(&to) && !to.contains('/') {
+                true => "\\",
+                false => "/",
+            };
+            return format!(
+                "{}{}{}",
+                to.trim_end_matches(['/', '\\']),
+                separator,
+                rest.join(separator)
+            );
+        }
+    }
+    path.to_string()
+}
+
+/// Whether `path` was written on Windows: it has a drive letter or uses
+/// backslashes.
+pub fn is_windows_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.contains('\\') || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+/// The components of `path` after `prefix`, if `prefix` names a directory
+/// containing it (or the path itself). Either may use `/` or `\`; Windows
+/// paths compare case-insensitively, as NTFS does.
+fn strip_path_prefix<'a>(path: &'a str, prefix: &str) -> Option<Vec<&'a str>> {
+    let ignore_case = is_windows_path(path) || is_windows_path(prefix);
+    let path_parts = components(path);
+    let prefix_parts = components(prefix);
+    if prefix_parts.len() > path_parts.len() {
+        return None;
+    }
+    let matches = path_parts
+        .iter()
+        .zip(&prefix_parts)
+        .all(|(a, b)| match ignore_case {
+            true => a.eq_ignore_ascii_case(b),
+            false => a == b,
+        });
+    match matches {
+        true => Some(path_parts[prefix_parts.len()..].to_vec()),
+        false => None,
+    }
+}
+
+/// Split a path on either separator, keeping a leading empty component for
+/// absolute Unix paths and dropping `.` and repeated separators.
+fn components(path: &str) -> Vec<&str> {
+    path.split(['/', '\\'])
+        .enumerate()
+        .filter(|(i, part)| (*i == 0 || !part.is_empty()) && *part != ".")
+        .map(|(_, part)| part)
+        .collect()
+}
+
+/// The git toplevel containing `start`, found by walking up to the nearest
+/// directory with a `.git` entry (a file in the case of worktrees).
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(|dir| dir.to_path_buf())
+}
+
+/// Express an absolute `path` relative to `root`, with `/` separators so
+/// reports read the same on every platform. Relative paths and paths outside
+/// the root are returned unchanged.
+pub fn relative_to_root(path: &str, root: &Path) -> String {
+    match strip_path_prefix(path, &root.to_string_lossy()) {
+        Some(relative) if !relative.is_empty() => relative.join("/"),
+        _ => path.to_string(),
+    }
+}
+
+/// How source paths are presented in every report: first rewritten with the
+/// `--path-map` rules, then made relative to the project root.
+pub fn display_path(path: &str, maps: &[PathMap], root: Option<&Path>) -> String {
+    let mapped = remap(path, maps);
+    match root {
+        Some(root) => relative_to_root(&mapped, root),
+        None => mapped,
+    }
+}
+
+/// Match `path` against a glob where `*` and `?` stay within one path
+/// component and `**` crosses directories. A pattern without a `/` is matched
+/// against the file name alone, so `*_test.c` excludes test files anywhere.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    if !pattern.contains('/') {
+        let name = path.rsplit('/').next().unwrap_or(&path);
+        return glob_match_bytes(pattern.as_bytes(), name.as_bytes());
+    }
+    glob_match_bytes(pattern.as_bytes(), path.as_bytes())
+}
+
+fn glob_match_bytes(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // `**/` may also match no directories at all
+            if let Some(after) = rest.strip_prefix(b"/") {
+                if glob_match_bytes(after, path) {
+                    return true;
+                }
+            }
+            (0..=path.len()).any(|i| glob_match_bytes(rest, &path[i..]))
+        }
+        [b'*', rest @ ..] => {
+            let component = path.iter().position(|&c| c == b'/').unwrap_or(path.len());
+            (0..=component).any(|i| glob_match_bytes(rest, &path[i..]))
+        }
+        [b'?', rest @ ..] => match path {
+            [c, path_rest @ ..] if *c != b'/' => glob_match_bytes(rest, path_rest),
+            _ => false,
+        },
+        [c, rest @ ..] => match path {
+            [p, path_rest @ ..] if p == c => glob_match_bytes(rest, path_rest),
+            _ => false,
+        },
+    }
+}
+
+/// What directory scans do with symbolic links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symlinks {
+    /// Read the file a link points at
+    Follow,
+    /// Leave links out, for artifact layouts that link to tests kept elsewhere
+    Skip,
+}
+
+impl std::str::FromStr for Symlinks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "follow" => Ok(Symlinks::Follow),
+            "skip" => Ok(Symlinks::Skip),
+            _ => Err(format!(
+                "unknown symlink handling '{}' (expected follow or skip)",
+                s
+            )),
+        }
+    }
+}
+
+/// What identifies the file behind `metadata` however it was reached: its
+/// device and inode, the same for every hard link and every symbolic link
+/// to it. `None` on platforms without inodes.
+#[cfg(unix)]
+pub fn file_id(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn file_id(_metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Whether `path` matches any of the `--exclude-path` globs.
+pub fn is_excluded(path: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| glob_match(pattern, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_id() {
+        let dir = std::env::temp_dir().join(format!("fl_dstar_file_id_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (file, hard, soft, other) = (
+            dir.join("t.gcov"),
+            dir.join("hard.gcov"),
+            dir.join("soft.gcov"),
+            dir.join("other.gcov"),
+        );
+        std::fs::write(&file, "").unwrap();
+        std::fs::write(&other, "").unwrap();
+        std::fs::hard_link(&file, &hard).unwrap();
+        std::os::unix::fs::symlink(&file, &soft).unwrap();
+        let id = |path: &Path| file_id(&std::fs::metadata(path).unwrap());
+        assert_eq!(id(&hard), id(&file));
+        assert_eq!(id(&soft), id(&file));
+        assert_ne!(id(&other), id(&file));
+        assert_eq!("skip".parse(), Ok(Symlinks::Skip));
+        assert!("ignore".parse::<Symlinks>().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remap() {
+        let maps: Vec<PathMap> = vec![
+            "/build/src=/home/me/project/src".parse().unwrap(),
+            "/build=/opt/build".parse().unwrap(),
+        ];
+        assert_eq!(
+            remap("/build/src/tcas.c", &maps),
+            "/home/me/project/src/tcas.c"
+        );
+        assert_eq!(remap("/build/srcs/a.c", &maps), "/opt/build/srcs/a.c");
+        assert_eq!(remap("/elsewhere/a.c", &maps), "/elsewhere/a.c");
+        assert!("no-equals".parse::<PathMap>().is_err());
+    }
+
+    #[test]
+    fn test_display_path() {
+        let maps: Vec<PathMap> = vec!["/build=/home/me/project".parse().unwrap()];
+        let root = Path::new("/home/me/project");
+        assert_eq!(
+            display_path("/build/src/tcas.c", &maps, Some(root)),
+            "src/tcas.c"
+        );
+        assert_eq!(display_path("tcas.c", &maps, Some(root)), "tcas.c");
+        assert_eq!(
+            display_path("/usr/include/stdio.h", &maps, Some(root)),
+            "/usr/include/stdio.h"
+        );
+    }
+
+    #[test]
+    fn test_windows_paths() {
+        let maps: Vec<PathMap> = vec![
+            "C:\\agent\\_work\\1\\s=/home/me/project".parse().unwrap(),
+            "D:/build=E:\\src".parse().unwrap(),
+        ];
+        assert_eq!(
+            remap("c:\\Agent\\_work\\1\\s\\src\\tcas.c", &maps),
+            "/home/me/project/src/tcas.c"
+        );
+        assert_eq!(remap("D:\\build\\lib\\a.c", &maps), "E:\\src\\lib\\a.c");
+        assert_eq!(remap("D:\\builds\\a.c", &maps), "D:\\builds\\a.c");
+        assert_eq!(
+            relative_to_root("C:\\proj\\src\\a.c", Path::new("C:\\proj")),
+            "src/a.c"
+        );
+        assert_eq!(
+            relative_to_root("C:/proj/src/a.c", Path::new("c:\\Proj\\")),
+            "src/a.c"
+        );
+        assert!(is_excluded(
+            "C:\\proj\\third_party\\z.c",
+            &["**/third_party/**".to_string()]
+        ));
+        assert!(is_windows_path("C:/proj"));
+        assert!(!is_windows_path("/home/me:1"));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("third_party/**", "third_party/zlib/inflate.c"));
+        assert!(!glob_match("third_party/**", "src/third_party.c"));
+        assert!(glob_match("*_test.c", "src/parser/lexer_test.c"));
+        assert!(!glob_match("*_test.c", "src/lexer.c"));
+        assert!(glob_match("src/*.c", "src/a.c"));
+        assert!(!glob_match("src/*.c", "src/nested/a.c"));
+        assert!(glob_match("**/vendor/*.h", "vendor/x.h"));
+        assert!(glob_match("src/?.c", "src/a.c"));
+    }
+
+    #[test]
+    fn test_find_project_root() {
+        let root = env!("CARGO_MANIFEST_DIR");
+        let found = find_project_root(&Path::new(root).join("src"));
+        // the crate may be built from a tarball without a .git directory
+        if let Some(found) = found {
+            assert!(Path::new(root).starts_with(found));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{Coverage, LineInfo, Spectrum};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// A statement only the tests of one platform execute.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlatformOnly {
+    pub file: Option<String>,
+    pub line_number: u32,
+    pub statement: String,
+    /// Failing tests of the platform that execute the statement
+    pub failing_tests: usize,
+    #[serde(serialize_with = "crate::round_serialize")]
+    pub suspiciousness: f32,
+}
+
+/// The platform with the highest failing rate, given the platform of each
+/// passing and each failing test.
+pub fn failing_platform<'a>(
+    passing: impl IntoIterator<Item = &'a str>,
+    failing: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    crate::groups::stats(passing, failing)
+        .into_iter()
+        .find(|stats| stats.failing > 0)
+        .map(|stats| stats.group)
+}
+
+fn covered(test: &[LineInfo]) -> impl Iterator<Item = u64> + '_ {
+    test.iter()
+        .filter(|line| line.coverage == Coverage::Covered)
+        .map(|line| line.key())
+}
+
+/// Statements executed by a failing test of `platform` but by no test, passing
+/// or failing, of any other platform: code behind a platform check is the
+/// usual suspect when a bug only shows on one platform. `passing` and
+/// `failing` give the platform of each test of `spectrum`, in order; tests of
+/// unknown platform are left out. Most failing tests first, then most
+/// suspicious.
+pub fn only_on(
+    spectrum: &Spectrum,
+    passing: &[Option<&str>],
+    failing: &[Option<&str>],
+    platform: &str,
+) -> Vec<PlatformOnly> {
+    let tests = spectrum
+        .passing
+        .iter()
+        .zip(passing)
+        .chain(spectrum.failing.iter().zip(failing));
+    let elsewhere: HashSet<u64> = tests
+        .filter(|(_, test_platform)| test_platform.is_some_and(|p| p != platform))
+        .flat_map(|(test, _)| covered(test))
+        .collect();
+    let on_platform: Vec<HashSet<u64>> = spectrum
+        .failing
+        .iter()
+        .zip(failing)
+        .filter(|(_, test_platform)| **test_platform == Some(platform))
+        .map(|(test, _)| covered(test).collect())
+        .collect();
+    let mut only: Vec<PlatformOnly> = spectrum
+        .statements
+        .iter()
+        .filter(|s| !elsewhere.contains(&s.key()))
+        .map(|s| PlatformOnly {
+            file: spectrum.sources.get(&s.file).cloned(),
+            line_number: s.line_number,
+            statement: s.statement().to_string(),
+            failing_tests: on_platform
+                .iter()
+                .filter(|test| test.contains(&s.key()))
+                .count(),
+            suspiciousness: s.suspiciousness,
+        })
+        .filter(|row| row.failing_tests > 0)
+        .collect();
+    only.sort_by(|a, b| {
+        b.failing_tests
+            .cmp(&a.failing_tests)
+            .then(b.suspiciousness.total_cmp(&a.suspiciousness))
+            .then(a.line_number.cmp(&b.line_number))
+    });
+    only
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smoothing::Smoothing;
+    use crate::techniques::Technique;
+
+    fn test(covered: &[u32]) -> Vec<LineInfo> {
+        (1..=4)
+            .map(|line_number| LineInfo {
+                file: 0,
+                line_number,
+                statement: format!("s{};", line_number),
+                coverage: match covered.contains(&line_number) {
+                    true => Coverage::Covered,
+                    false => Coverage::NotCovered,
+                },
+                execution_count: covered.contains(&line_number) as u64,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_only_on() {
+        // line 3 is the windows-only path, line 4 runs on windows only when passing
+        let mut spectrum = Spectrum::new(
+            vec![test(&[1, 2]), test(&[1, 4])],
+            vec![test(&[1, 3]), test(&[1, 2, 3])],
+        );
+        spectrum.score(Technique::Formula, Smoothing::None);
+        let passing = [Some("linux"), Some("windows")];
+        let failing = [Some("windows"), Some("windows")];
+        assert_eq!(
+            failing_platform(passing.map(Option::unwrap), failing.map(Option::unwrap)).as_deref(),
+            Some("windows")
+        );
+        let only = only_on(&spectrum, &passing, &failing, "windows");
+        let rows: Vec<(u32, usize)> = only
+            .iter()
+            .map(|r| (r.line_number, r.failing_tests))
+            .collect();
+        assert_eq!(rows, [(3, 2)]);
+        // without the linux test everything windows runs is windows-only
+        let only = only_on(&spectrum, &[None, Some("windows")], &failing, "windows");
+        let lines: Vec<u32> = only.iter().map(|r| r.line_number).collect();
+        assert_eq!(lines, [3, 1, 2]);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::locale::NumberFormat;
+use std::fmt::Write;
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 360.0;
+const MARGIN: f64 = 48.0;
+
+/// A histogram of suspiciousness scores as a standalone SVG document. A
+/// sharp head shows as a few statements far to the right of the rest; a flat
+/// ranking as one tall bar. Infinite scores get their own bar on the right.
+pub fn histogram_svg(scores: &[f32], bins: usize, numbers: &NumberFormat) -> String {
+    let finite: Vec<f32> = scores.iter().copied().filter(|s| s.is_finite()).collect();
+    let infinite = scores.len() - finite.len();
+    let low = finite.iter().copied().fold(f32::INFINITY, f32::min);
+    let high = finite.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let bins = bins.max(1);
+    let mut counts = vec![0usize; bins];
+    for score in &finite {
+        let position = match high > low {
+            true => ((score - low) / (high - low) * bins as f32) as usize,
+            false => 0,
+        };
+        counts[position.min(bins - 1)] += 1;
+    }
+    let labels: Vec<String> = (0..bins)
+        .map(|i| match high > low {
+            true => numbers.format((low + (high - low) * i as f32 / bins as f32) as f64, 2),
+            false if finite.is_empty() => String::new(),
+            false => numbers.format(low as f64, 2),
+        })
+        .collect();
+    let mut bars: Vec<(String, usize)> = labels.into_iter().zip(counts).collect();
+    if infinite > 0 {
+        bars.push(("inf".to_string(), infinite));
+    }
+
+    let tallest = bars
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let plot_width = WIDTH - 2.0 * MARGIN;
+    let plot_height = HEIGHT - 2.0 * MARGIN;
+    let bar_width = plot_width / bars.len() as f64;
+    // leave room between axis labels when there are many narrow bars
+    let label_every = (40.0 / bar_width).ceil().max(1.0) as usize;
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="11">"#,
+        w = WIDTH,
+        h = HEIGHT
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{}" y="20" text-anchor="middle" font-size="14">Suspiciousness of {} statements</text>"#,
+        WIDTH / 2.0,
+        scores.len()
+    )
+    .unwrap();
+    for (i, (label, count)) in bars.iter().enumerate() {
+        let height = plot_height * *count as f64 / tallest as f64;
+        let x = MARGIN + i as f64 * bar_width;
+        let fill = match label.as_str() {
+            "inf" => "#c0392b",
+            _ => "#4a78b0",
+        };
+        writeln!(
+            svg,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"><title>{}: {}</title></rect>"#,
+            x + 1.0,
+            HEIGHT - MARGIN - height,
+            (bar_width - 2.0).max(1.0),
+            height,
+            fill,
+            label,
+            count
+        )
+        .unwrap();
+        if i % label_every != 0 && label != "inf" {
+            continue;
+        }
+        writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+            x + bar_width / 2.0,
+            HEIGHT - MARGIN + 14.0,
+            label
+        )
+        .unwrap();
+    }
+    writeln!(
+        svg,
+        r#"<line x1="{m}" y1="{y}" x2="{x2}" y2="{y}" stroke="black"/>"#,
+        m = MARGIN,
+        y = HEIGHT - MARGIN,
+        x2 = WIDTH - MARGIN
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{}" y="{:.1}" text-anchor="end">{}</text>"#,
+        MARGIN - 4.0,
+        MARGIN + 4.0,
+        tallest
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="middle">score</text>"#,
+        WIDTH / 2.0,
+        HEIGHT - 12.0
+    )
+    .unwrap();
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_svg() {
+        let svg = histogram_svg(
+            &[0.0, 0.5, 0.5, 1.0, f32::INFINITY],
+            2,
+            &NumberFormat::default(),
+        );
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert!(svg.contains("<title>0.00: 1</title>"));
+        assert!(svg.contains("<title>0.50: 3</title>"));
+        assert!(svg.contains("<title>inf: 1</title>"));
+        assert!(histogram_svg(&[], 10, &NumberFormat::default()).contains("of 0 statements"));
+    }
+}
@@ -0,0 +1,15 @@
+//! The types most programs built on the crate use, for a single
+//! `use fl_dstar::prelude::*`: coverage and how it's read, the spectrum and
+//! its scores, the built-in formulas, finished rankings, and the trait to
+//! plug in formulas of one's own.
+
+pub use crate::formats::{Format, ParseOptions, SourceCoverage};
+pub use crate::metrics::{Description, Metric, Parameter};
+pub use crate::model::v1::{RankedStatement, Ranking};
+#[cfg(feature = "reports")]
+pub use crate::query::Report;
+pub use crate::smoothing::{Counts, Smoothing};
+pub use crate::techniques::Technique;
+pub use crate::{
+    Coverage, FlError, LineInfo, SourceKeys, Spectrum, StatementInfo, SuspiciousnessMetric,
+};
@@ -0,0 +1,162 @@
+use crate::json::Json;
+use crate::suppressions::Date;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The SHA-256 digest of `data`, as lowercase hex.
+pub fn sha256(data: &[u8]) -> String {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+    h.iter().map(|word| format!("{:08x}", word)).collect()
+}
+
+/// Where a report came from: the tool that wrote it and the inputs it read,
+/// so a report can be traced back to the coverage data behind it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    pub tool: String,
+    /// Day the report was written, as YYYY-MM-DD
+    pub generated: String,
+    /// Each input file with the SHA-256 of its contents
+    pub inputs: Vec<(String, String)>,
+}
+
+impl Provenance {
+    /// Hash the files a report is about to be written from.
+    pub fn collect(inputs: &[PathBuf]) -> std::io::Result<Provenance> {
+        let inputs = inputs
+            .iter()
+            .map(|path| {
+                let data = std::fs::read(path)?;
+                Ok((path.display().to_string(), sha256(&data)))
+            })
+            .collect::<std::io::Result<_>>()?;
+        Ok(Provenance {
+            tool: format!("fl_dstar {}", env!("CARGO_PKG_VERSION")),
+            generated: Date::today().to_string(),
+            inputs,
+        })
+    }
+
+    pub fn to_json(&self) -> Json {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|(path, hash)| {
+                Json::Object(vec![
+                    ("path".to_string(), Json::from(path.as_str())),
+                    ("sha256".to_string(), Json::from(hash.as_str())),
+                ])
+            })
+            .collect();
+        Json::Object(vec![
+            ("tool".to_string(), Json::from(self.tool.as_str())),
+            ("generated".to_string(), Json::from(self.generated.as_str())),
+            ("inputs".to_string(), Json::Array(inputs)),
+        ])
+    }
+}
+
+/// Sign `path` with the ed25519 private key `key` (PEM), writing the raw
+/// signature next to it as `path.sig`. Runs `openssl pkeyutl` (or
+/// `$OPENSSL`); check a report with `openssl pkeyutl -verify -pubin -inkey
+/// public.pem -rawin -in report.html -sigfile report.html.sig`.
+pub fn sign(path: &Path, key: &Path) -> std::io::Result<PathBuf> {
+    let mut signature = path.as_os_str().to_owned();
+    signature.push(".sig");
+    let signature = PathBuf::from(signature);
+    let program = std::env::var("OPENSSL").unwrap_or_else(|_| "openssl".to_string());
+    let output = Command::new(program)
+        .args(["pkeyutl", "-sign", "-rawin", "-inkey"])
+        .arg(key)
+        .arg("-in")
+        .arg(path)
+        .arg("-out")
+        .arg(&signature)
+        .output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // two blocks of padding
+        assert_eq!(
+            sha256(&[b'a'; 56]),
+            "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{Spectrum, StatementInfo};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Where one statement stands in a ranking.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RankedStatement {
+    /// 1 for the most suspicious statement
+    pub rank: usize,
+    pub file: Option<String>,
+    pub line_number: u32,
+    pub statement: String,
+    pub failed_tests: u32,
+    pub passed_tests: u32,
+    #[serde(serialize_with = "crate::round_serialize")]
+    pub suspiciousness: f32,
+}
+
+/// A finished ranking that can be looked up by location.
+#[derive(Debug, Clone)]
+pub struct Report {
+    statements: Vec<StatementInfo>,
+    sources: HashMap<u32, String>,
+}
+
+impl Report {
+    /// A report of `statements`, which must already be in rank order.
+    pub fn new(statements: Vec<StatementInfo>, sources: HashMap<u32, String>) -> Report {
+        Report {
+            statements,
+            sources,
+        }
+    }
+
+    /// A report of a scored spectrum.
+    pub fn from_spectrum(spectrum: &Spectrum) -> Report {
+        let mut statements = spectrum.statements.clone();
+        crate::sort_by_suspiciousness(&mut statements);
+        Report::new(statements, spectrum.sources.clone())
+    }
+
+    /// The statement at `line` of `path`. The path may be given as the
+    /// coverage names it or with leading directories added or removed, as an
+    /// editor would pass an absolute path, so long as whole components match.
+    /// When the coverage named no file, any path matches.
+    pub fn score_at(&self, path: &str, line: u32) -> Option<RankedStatement> {
+        self.position(path, line).map(|i| self.ranked(i))
+    }
+
+    /// The statement at `line` of `path`, matched as [`Report::score_at`]
+    /// does, with its counts.
+    pub fn statement_at(&self, path: &str, line: u32) -> Option<&StatementInfo> {
+        self.position(path, line).map(|i| &self.statements[i])
+    }
+
+    /// The `limit` most suspicious statements, or all of them when `limit`
+    /// is 0.
+    pub fn top(&self, limit: usize) -> Vec<RankedStatement> {
+        let len = match limit {
+            0 => self.statements.len(),
+            limit => limit.min(self.statements.len()),
+        };
+        (0..len).map(|i| self.ranked(i)).collect()
+    }
+
+    /// The failing and passing tests the statements were scored with.
+    pub fn totals(&self) -> (u32, u32) {
+        self.statements
+            .first()
+            .map_or((0, 0), |s| (s.total_failed, s.total_passed))
+    }
+
+    fn position(&self, path: &str, line: u32) -> Option<usize> {
+        self.statements.iter().position(|s| {
+            s.line_number == line
+                && self
+                    .sources
+                    .get(&s.file)
+                    .is_none_or(|source| same_file(source, path))
+        })
+    }
+
+    fn ranked(&self, i: usize) -> RankedStatement {
+        let statement = &self.statements[i];
+        RankedStatement {
+            rank: i + 1,
+            file: self.sources.get(&statement.file).cloned(),
+            line_number: statement.line_number,
+            statement: statement.statement().to_string(),
+            failed_tests: statement.failed_tests,
+            passed_tests: statement.passed_tests,
+            suspiciousness: statement.suspiciousness,
+        }
+    }
+}
+
+impl From<RankedStatement> for crate::model::v1::RankedStatement {
+    fn from(s: RankedStatement) -> Self {
+        crate::model::v1::RankedStatement {
+            rank: s.rank as u32,
+            file: s.file,
+            line_number: s.line_number,
+            statement: s.statement,
+            failed_tests: s.failed_tests,
+            passed_tests: s.passed_tests,
+            suspiciousness: s.suspiciousness as f64,
+        }
+    }
+}
+
+/// Whether one path is the other with leading directories removed.
+fn same_file(a: &str, b: &str) -> bool {
+    let components = |path: &str| -> Vec<String> {
+        path.replace('\\', "/")
+            .split('/')
+            .filter(|part| !part.is_empty() && *part != ".")
+            .map(str::to_string)
+            .collect()
+    };
+    let (a, b) = (components(a), components(b));
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    !short.is_empty() && long.ends_with(&short)
+}
+
+/// Split a `path:line` location.
+pub fn parse_location(location: &str) -> Result<(String, u32), String> {
+    // rsplit, as Windows paths have a colon of their own
+    location
+        .rsplit_once(':')
+        .and_then(|(path, line)| Some((path.to_string(), line.parse().ok()?)))
+        .filter(|(path, _)| !path.is_empty())
+        .ok_or_else(|| format!("expected path:line, found '{}'", location))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> Report {
+        let mut statements: Vec<StatementInfo> = [(4, 0.5), (77, 2.0)]
+            .iter()
+            .map(|&(line, score)| {
+                let mut statement = StatementInfo::new(line, format!("line{}();", line), 1);
+                statement.file = 1 + (line == 77) as u32;
+                statement.suspiciousness = score;
+                statement
+            })
+            .collect();
+        crate::sort_by_suspiciousness(&mut statements);
+        let sources = HashMap::from([(1, "src/a.c".to_string()), (2, "src/tcas.c".to_string())]);
+        Report::new(statements, sources)
+    }
+
+    #[test]
+    fn test_score_at() {
+        let report = report();
+        let found = report.score_at("src/tcas.c", 77).unwrap();
+        assert_eq!(found.rank, 1);
+        assert_eq!(found.statement, "line77();");
+        assert_eq!(
+            report.score_at("/home/me/project/src/a.c", 4).unwrap().rank,
+            2
+        );
+        assert_eq!(report.score_at("tcas.c", 77).unwrap().rank, 1);
+        assert!(report.score_at("as.c", 77).is_none());
+        assert!(report.score_at("src/a.c", 77).is_none());
+        assert!(report.score_at("src/a.c", 5).is_none());
+    }
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(
+            parse_location("src/tcas.c:77").unwrap(),
+            ("src/tcas.c".to_string(), 77)
+        );
+        assert_eq!(parse_location("C:\\src\\a.c:4").unwrap().0, "C:\\src\\a.c");
+        assert!(parse_location("src/tcas.c").is_err());
+        assert!(parse_location(":4").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use crate::formats::SourceCoverage;
+use crate::{LineInfo, Spectrum};
+
+/// FNV-1a, which unlike std's hasher is guaranteed not to change between
+/// Rust releases, so hashes written today still match next year.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// What a statement is replaced with when redacting: a hash of its text,
+/// ignoring indentation, so identical statements can still be matched up.
+/// Blank statements stay blank.
+///
+/// The hash is not keyed, so very short or common statements (`return 0;`)
+/// can be recovered by hashing guesses.
+pub fn hash_statement(statement: &str) -> String {
+    let statement = statement.trim();
+    match statement.is_empty() {
+        true => String::new(),
+        false => format!("#{:016x}", fnv1a(statement.as_bytes())),
+    }
+}
+
+fn redact_lines(lines: &mut [LineInfo]) {
+    for line in lines {
+        line.statement = hash_statement(&line.statement);
+    }
+}
+
+/// Replace the text of every statement in `coverage` with its hash.
+pub fn redact_coverage(coverage: &mut [SourceCoverage]) {
+    for record in coverage {
+        redact_lines(&mut record.lines);
+    }
+}
+
+/// Replace the text of every statement in `spectrum` with its hash. Scores
+/// are reset, so the spectrum needs scoring again.
+pub fn redact_spectrum(spectrum: &mut Spectrum) {
+    let mut passing = std::mem::take(&mut spectrum.passing);
+    let mut failing = std::mem::take(&mut spectrum.failing);
+    for test in passing.iter_mut().chain(failing.iter_mut()) {
+        redact_lines(test);
+    }
+    let sources = std::mem::take(&mut spectrum.sources);
+    *spectrum = Spectrum::new(passing, failing);
+    spectrum.sources = sources;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Coverage;
+
+    #[test]
+    fn test_redact_spectrum() {
+        let line = |statement: &str, coverage| LineInfo {
+            file: 0,
+            line_number: 7,
+            statement: statement.to_string(),
+            coverage,
+            execution_count: 1,
+        };
+        let mut spectrum = Spectrum::new(
+            vec![vec![line("  secret();", Coverage::NotCovered)]],
+            vec![vec![line("secret();", Coverage::Covered)]],
+        );
+        redact_spectrum(&mut spectrum);
+        let statement = &spectrum.statements[0];
+        assert_eq!(statement.statement(), hash_statement("secret();"));
+        assert!(statement.statement().starts_with('#'));
+        assert_eq!(statement.statement().len(), 17);
+        assert_eq!((statement.line_number, statement.failed_tests), (7, 1));
+        assert_eq!(spectrum.failing[0][0].statement, statement.statement());
+        assert_eq!(hash_statement("   "), "");
+    }
+}
@@ -0,0 +1,471 @@
+use crate::json::Json;
+use crate::locale::NumberFormat;
+use crate::smoothing::Smoothing;
+use crate::{Coverage, LineInfo, StatementInfo};
+use std::collections::HashMap;
+
+const HTML_TEMPLATE: &str = include_str!("templates/report.html.tera");
+
+/// Add the `--title` and `--notes` header fields to a report model.
+pub fn add_metadata(model: &mut Json, title: &str, notes: Option<&str>) {
+    if let Json::Object(members) = model {
+        members.push(("title".to_string(), Json::from(title)));
+        members.push(("notes".to_string(), Json::from(notes.unwrap_or(""))));
+    }
+}
+
+/// Add to each statement of a report model the formula that gave its score,
+/// with the statement's counts substituted. `statements` must be the ones
+/// the model was built from.
+pub fn add_explanations(
+    model: &mut Json,
+    statements: &[StatementInfo],
+    smoothing: Smoothing,
+    numbers: &NumberFormat,
+) {
+    if let Some(Json::Array(rows)) = model_statements(model) {
+        for (row, statement) in rows.iter_mut().zip(statements) {
+            if let Json::Object(members) = row {
+                members.push((
+                    "explanation".to_string(),
+                    Json::from(statement.explain(smoothing, numbers)),
+                ));
+            }
+        }
+    }
+}
+
+/// Add the whole source file to a report model as `listing`, one row per
+/// line with the rank and score of the lines that hold statements, so the
+/// report can show them in context without the source at hand. Each row
+/// also has its `file`, its `heat`, from 1 for the most suspicious statement
+/// down to 0, and `source_html`, the line syntax-highlighted. `statements`
+/// must be in rank order.
+pub fn add_listing(
+    model: &mut Json,
+    listing: &[LineInfo],
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    numbers: &NumberFormat,
+) {
+    let ranks: HashMap<u64, (usize, f32)> = statements
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.key(), (i + 1, s.suspiciousness)))
+        .collect();
+    let rows = listing
+        .iter()
+        .map(|line| {
+            // null for lines without a statement, and for suppressed statements
+            let (rank, score, heat) = match ranks.get(&line.key()) {
+                Some((rank, score)) => (
+                    Json::from(*rank as u32),
+                    Json::from(numbers.format(*score as f64, 2)),
+                    match *score > 0.0 {
+                        true => 1.0 - (*rank - 1) as f64 / statements.len() as f64,
+                        false => 0.0,
+                    },
+                ),
+                None => (Json::Null, Json::Null, 0.0),
+            };
+            let file = sources.get(&line.file).map_or("", String::as_str);
+            Json::Object(vec![
+                ("file".to_string(), Json::from(file)),
+                ("line_number".to_string(), Json::from(line.line_number)),
+                ("statement".to_string(), Json::from(line.statement.as_str())),
+                (
+                    "executable".to_string(),
+                    Json::from(line.coverage != Coverage::NoExecutableCode),
+                ),
+                ("rank".to_string(), rank),
+                ("suspiciousness".to_string(), score),
+                ("heat".to_string(), Json::from(format!("{:.2}", heat))),
+                (
+                    "source_html".to_string(),
+                    Json::from(highlight_html(&line.statement)),
+                ),
+            ])
+        })
+        .collect();
+    if let Json::Object(members) = model {
+        members.push(("listing".to_string(), Json::Array(rows)));
+    }
+}
+
+/// Add the statements only failing tests execute and those only passing
+/// tests execute to a report model, as `failing_only` and `passing_only`,
+/// each row with the number of tests that execute the statement.
+pub fn add_exclusive(
+    model: &mut Json,
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+) {
+    let rows = |list: Vec<&StatementInfo>, failing: bool| {
+        let rows = list
+            .into_iter()
+            .map(|s| {
+                let file = sources
+                    .get(&s.file)
+                    .map_or(Json::Null, |f| Json::from(f.as_str()));
+                let tests = match failing {
+                    true => s.failed_tests,
+                    false => s.passed_tests,
+                };
+                Json::Object(vec![
+                    ("file".to_string(), file),
+                    ("line_number".to_string(), Json::from(s.line_number)),
+                    ("statement".to_string(), Json::from(s.statement())),
+                    ("tests".to_string(), Json::from(tests)),
+                ])
+            })
+            .collect();
+        Json::Array(rows)
+    };
+    let failing_only = rows(crate::exclusive::failing_only(statements), true);
+    let passing_only = rows(crate::exclusive::passing_only(statements), false);
+    if let Json::Object(members) = model {
+        members.push(("failing_only".to_string(), failing_only));
+        members.push(("passing_only".to_string(), passing_only));
+    }
+}
+
+/// Add to each statement of a report model which tests covered it, as
+/// `failing_coverage` and `passing_coverage`: base64 bitvectors with bit `i`
+/// (least significant first within each byte) set when test `i` covered
+/// the statement, in the order of `failing` and `passing`. With these a
+/// spectrum can be rebuilt without the original coverage files.
+pub fn add_coverage_vectors(
+    model: &mut Json,
+    statements: &[StatementInfo],
+    passing: &[Vec<LineInfo>],
+    failing: &[Vec<LineInfo>],
+) {
+    let vector = |tests: &[std::collections::HashSet<u64>], key: u64| {
+        let mut bits = vec![0u8; tests.len().div_ceil(8)];
+        for (i, test) in tests.iter().enumerate() {
+            if test.contains(&key) {
+                bits[i / 8] |= 1 << (i % 8);
+            }
+        }
+        crate::encoding::base64(&bits)
+    };
+    let passing: Vec<_> = passing.iter().map(|t| crate::covered_set(t)).collect();
+    let failing: Vec<_> = failing.iter().map(|t| crate::covered_set(t)).collect();
+    if let Some(Json::Array(rows)) = model_statements(model) {
+        for (row, statement) in rows.iter_mut().zip(statements) {
+            if let Json::Object(members) = row {
+                members.push((
+                    "failing_coverage".to_string(),
+                    Json::from(vector(&failing, statement.key())),
+                ));
+                members.push((
+                    "passing_coverage".to_string(),
+                    Json::from(vector(&passing, statement.key())),
+                ));
+            }
+        }
+    }
+}
+
+/// Add the lines each failing test executed to a report model as
+/// `test_overlay`, one row per test with its `name` and its `lines`, each
+/// `file:line` on a line of its own, for the HTML report to mark them when
+/// the test is picked. Nothing is added when the rows would take more than
+/// `limit` bytes; the error is the size they would have taken.
+pub fn add_test_overlay(
+    model: &mut Json,
+    names: &[String],
+    failing: &[Vec<LineInfo>],
+    sources: &HashMap<u32, String>,
+    limit: u64,
+) -> Result<(), u64> {
+    let rows: Vec<(String, String)> = names
+        .iter()
+        .zip(failing)
+        .map(|(name, test)| {
+            let mut keys: Vec<u64> = crate::covered_set(test).into_iter().collect();
+            keys.sort_unstable();
+            let lines: Vec<String> = keys
+                .into_iter()
+                .map(|key| {
+                    let file = sources
+                        .get(&crate::key_file(key))
+                        .map_or("", String::as_str);
+                    format!("{}:{}", file, crate::key_line(key))
+                })
+                .collect();
+            (name.clone(), lines.join("\n"))
+        })
+        .collect();
+    let size: u64 = rows
+        .iter()
+        .map(|(name, lines)| (name.len() + lines.len()) as u64)
+        .sum();
+    if size > limit {
+        return Err(size);
+    }
+    let rows = rows
+        .into_iter()
+        .map(|(name, lines)| {
+            Json::Object(vec![
+                ("name".to_string(), Json::from(name)),
+                ("lines".to_string(), Json::from(lines)),
+            ])
+        })
+        .collect();
+    if let Json::Object(members) = model {
+        members.push(("test_overlay".to_string(), Json::Array(rows)));
+    }
+    Ok(())
+}
+
+/// A line of code as HTML, its keywords, literals and comments in spans
+/// the built-in report styles.
+fn highlight_html(code: &str) -> String {
+    use crate::show::Kind;
+    let mut html = String::new();
+    for (kind, token) in crate::show::tokens(code) {
+        let class = match kind {
+            Some(Kind::Keyword) => "hl-kw",
+            Some(Kind::Text) => "hl-str",
+            Some(Kind::Number) => "hl-num",
+            Some(Kind::Comment) => "hl-com",
+            None => {
+                html.push_str(&crate::template::escape_html(token));
+                continue;
+            }
+        };
+        html.push_str(&format!(
+            "<span class=\"{}\">{}</span>",
+            class,
+            crate::template::escape_html(token)
+        ));
+    }
+    html
+}
+
+fn model_statements(model: &mut Json) -> Option<&mut Json> {
+    match model {
+        Json::Object(members) => members
+            .iter_mut()
+            .find(|(key, _)| key == "statements")
+            .map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+/// The built-in HTML report. It is a single file with the styles, script
+/// and score histogram inlined, so it can be attached to a bug report and
+/// opened offline. With `formula`, the smoothing the scores were computed
+/// with, hovering a score shows how the formula arrived at it. A non-empty
+/// `listing`, the lines of one test including those without code, adds the
+/// annotated source file.
+pub fn html_report(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    numbers: &NumberFormat,
+    title: &str,
+    notes: Option<&str>,
+    formula: Option<Smoothing>,
+    listing: &[LineInfo],
+) -> String {
+    let model = html_model(statements, sources, numbers, title, notes, formula, listing);
+    render_html(model, statements, numbers)
+}
+
+/// The model [`html_report`] renders, for callers that add to it first.
+pub fn html_model(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    numbers: &NumberFormat,
+    title: &str,
+    notes: Option<&str>,
+    formula: Option<Smoothing>,
+    listing: &[LineInfo],
+) -> Json {
+    let mut model = crate::output::report_model(statements, sources, numbers);
+    add_metadata(&mut model, title, notes);
+    add_exclusive(&mut model, statements, sources);
+    if let Some(smoothing) = formula {
+        add_explanations(&mut model, statements, smoothing, numbers);
+    }
+    if !listing.is_empty() {
+        add_listing(&mut model, listing, statements, sources, numbers);
+    }
+    model
+}
+
+/// Render a model from [`html_model`] as the built-in HTML report.
+pub fn render_html(
+    mut model: Json,
+    statements: &[StatementInfo],
+    numbers: &NumberFormat,
+) -> String {
+    let scores: Vec<f32> = statements.iter().map(|s| s.suspiciousness).collect();
+    let svg = crate::plot::histogram_svg(&scores, 20, numbers);
+    let plot = format!(
+        "data:image/svg+xml;base64,{}",
+        crate::encoding::base64(svg.as_bytes())
+    );
+    if let Json::Object(members) = &mut model {
+        members.push(("plot".to_string(), Json::from(plot)));
+    }
+    crate::template::render(HTML_TEMPLATE, &model, true).expect("built-in report template is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_report_is_self_contained() {
+        let mut statement = StatementInfo::new(4, "if (a < b)".to_string(), 1);
+        statement.add_failing_coverage();
+        statement.calculate_suspiciousness();
+        let html = html_report(
+            &[statement],
+            &HashMap::new(),
+            &NumberFormat::default(),
+            "Crash in <parser>",
+            Some("Seen on CI run 12"),
+            Some(Smoothing::None),
+            &[],
+        );
+        assert!(html.contains("<h1>Crash in &lt;parser&gt;</h1>"));
+        assert!(html.contains("Seen on CI run 12"));
+        assert!(html.contains("<code>if (a &lt; b)</code>"));
+        assert!(html.contains("title=\"ef² / (ep + nf) = 1² / (0 + 0) = inf\""));
+        assert!(html.contains("src=\"data:image/svg+xml;base64,"));
+        assert!(!html.contains("http://") && !html.contains("https://"));
+        assert!(!html.contains("id=\"listing\""));
+        assert!(html.contains("<h2>Executed only by failing tests</h2>"));
+        assert!(!html.contains("<h2>Executed only by passing tests</h2>"));
+    }
+
+    #[test]
+    fn test_coverage_vectors() {
+        let statements = [
+            StatementInfo::new(1, "a();".to_string(), 1),
+            StatementInfo::new(2, "b();".to_string(), 1),
+        ];
+        let test = |covered: &[u32]| -> Vec<LineInfo> {
+            [1, 2]
+                .iter()
+                .map(|&line_number| LineInfo {
+                    file: 0,
+                    line_number,
+                    statement: String::new(),
+                    coverage: match covered.contains(&line_number) {
+                        true => Coverage::Covered,
+                        false => Coverage::NotCovered,
+                    },
+                    execution_count: 0,
+                })
+                .collect()
+        };
+        let passing: Vec<Vec<LineInfo>> = (0..9)
+            .map(|i| test(if i == 8 { &[1] } else { &[] }))
+            .collect();
+        let failing = vec![test(&[1, 2])];
+        let mut model =
+            crate::output::report_model(&statements, &HashMap::new(), &NumberFormat::default());
+        add_coverage_vectors(&mut model, &statements, &passing, &failing);
+        let rows = model.get("statements").and_then(Json::as_array).unwrap();
+        // only the ninth passing test covers line 1: bit 0 of the second byte
+        assert_eq!(
+            rows[0].get("passing_coverage").and_then(Json::as_str),
+            Some("AAE=")
+        );
+        assert_eq!(
+            rows[1].get("passing_coverage").and_then(Json::as_str),
+            Some("AAA=")
+        );
+        assert_eq!(
+            rows[1].get("failing_coverage").and_then(Json::as_str),
+            Some("AQ==")
+        );
+    }
+
+    #[test]
+    fn test_listing() {
+        let mut statement = StatementInfo::new(2, "b();".to_string(), 1);
+        statement.add_failing_coverage();
+        statement.calculate_suspiciousness();
+        let line = |line_number: u32, statement: &str, coverage| LineInfo {
+            file: 0,
+            line_number,
+            statement: statement.to_string(),
+            coverage,
+            execution_count: 0,
+        };
+        let listing = [
+            line(1, "// the bug is below", Coverage::NoExecutableCode),
+            line(2, "b();", Coverage::Covered),
+        ];
+        let html = html_report(
+            &[statement],
+            &HashMap::new(),
+            &NumberFormat::default(),
+            "t",
+            None,
+            None,
+            &listing,
+        );
+        assert!(html.contains("id=\"listing\""));
+        assert!(html.contains(
+            "<td class=\"num\"></td><td class=\"num\"></td>\
+             <td><code><span class=\"hl-com\">// the bug is below</span></code>"
+        ));
+        assert!(html.contains("<tr data-line=\":2\" style=\"--heat: 1.00\">"));
+        assert!(html
+            .contains("<td class=\"num\">1</td><td class=\"num\">inf</td><td><code>b();</code>"));
+        assert_eq!(
+            highlight_html("if (s < \"x\") n = 10;"),
+            "<span class=\"hl-kw\">if</span> (s &lt; <span class=\"hl-str\">&quot;x&quot;</span>) \
+             n = <span class=\"hl-num\">10</span>;"
+        );
+    }
+
+    #[test]
+    fn test_test_overlay() {
+        let mut statement = StatementInfo::new(2, "b();".to_string(), 1);
+        statement.add_failing_coverage();
+        let test = |covered: &[u32]| -> Vec<LineInfo> {
+            [3, 1, 2]
+                .iter()
+                .map(|&line_number| LineInfo {
+                    // line 3 is in t.c, the others in a file the coverage didn't name
+                    file: (line_number == 3) as u32,
+                    line_number,
+                    statement: String::new(),
+                    coverage: match covered.contains(&line_number) {
+                        true => Coverage::Covered,
+                        false => Coverage::NotCovered,
+                    },
+                    execution_count: 0,
+                })
+                .collect()
+        };
+        let failing = [test(&[3, 2]), test(&[])];
+        let names = ["crash".to_string(), "<hang>".to_string()];
+        let mut model = html_model(
+            &[statement.clone()],
+            &HashMap::new(),
+            &NumberFormat::default(),
+            "t",
+            None,
+            None,
+            &[],
+        );
+        let sources = HashMap::from([(1, "t.c".to_string())]);
+        let overlay =
+            |model: &mut Json, limit| add_test_overlay(model, &names, &failing, &sources, limit);
+        assert_eq!(overlay(&mut model, 8), Err(19));
+        assert!(model.get("test_overlay").is_none());
+        overlay(&mut model, 19).unwrap();
+        let html = render_html(model, &[statement], &NumberFormat::default());
+        assert!(html.contains("<input type=\"checkbox\" data-lines=\":2\nt.c:3\"> crash</label>"));
+        assert!(html.contains("<input type=\"checkbox\" data-lines=\"\"> &lt;hang&gt;</label>"));
+        assert!(html.contains("<tr data-line=\":2\" class=\"top\">"));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::json::{self, Json};
+use crate::locale::NumberFormat;
+use crate::metrics::{Description, Metric};
+use crate::model::v1;
+use crate::query::Report;
+use crate::smoothing::Smoothing;
+
+/// Error codes JSON-RPC 2.0 reserves for these failures.
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+/// Answers JSON-RPC 2.0 requests about a finished ranking, one request per
+/// line, so an editor plugin can keep one process open and ask about lines
+/// as the user moves through the code. The methods are:
+///
+/// - `rank`, with an optional `limit`: the ranking, as in the JSON report
+/// - `query`, with `path` and `line`: that line's place in the ranking
+/// - `explain`, with `path` and `line`: its counters and the steps of its
+///   score, as `--explain-all` writes them
+/// - `formulas`: the formulas `--metric` can rank with, their references
+///   and parameters, for listing them in a settings page
+///
+/// `query` and `explain` answer `null` for lines that weren't ranked.
+pub struct Server {
+    report: Report,
+    /// The smoothing of the formula behind the scores, when they came
+    /// straight from D* and `explain` can list the formula's terms
+    formula: Option<Smoothing>,
+}
+
+impl Server {
+    pub fn new(report: Report, formula: Option<Smoothing>) -> Server {
+        Server { report, formula }
+    }
+
+    /// The response to one line of input, or `None` for a notification,
+    /// which JSON-RPC answers with nothing.
+    pub fn respond(&self, line: &str) -> Option<String> {
+        let request = match json::parse(line) {
+            Ok(request) => request,
+            Err(e) => return Some(error(&Json::Null, PARSE_ERROR, &e.to_string()).to_string()),
+        };
+        let id = request.get("id").cloned();
+        let method = request.get("method").and_then(Json::as_str);
+        let result = match (request.get("jsonrpc").and_then(Json::as_str), method) {
+            (Some("2.0"), Some(method)) => self.call(method, request.get("params")),
+            _ => Err((
+                INVALID_REQUEST,
+                "expected a JSON-RPC 2.0 request with a method".to_string(),
+            )),
+        };
+        let id = id?;
+        let response = match result {
+            Ok(result) => Json::Object(vec![
+                ("jsonrpc".to_string(), Json::from("2.0")),
+                ("id".to_string(), id),
+                ("result".to_string(), result),
+            ]),
+            Err((code, message)) => error(&id, code, &message),
+        };
+        Some(response.to_string())
+    }
+
+    fn call(&self, method: &str, params: Option<&Json>) -> Result<Json, (i32, String)> {
+        match method {
+            "rank" => {
+                let limit = match params.and_then(|params| params.get("limit")) {
+                    None => 0,
+                    Some(limit) => limit
+                        .as_u32()
+                        .ok_or((INVALID_PARAMS, "limit must be a count".to_string()))?,
+                };
+                let (total_failing, total_passing) = self.report.totals();
+                let ranking = v1::Ranking {
+                    total_passing,
+                    total_failing,
+                    statements: self
+                        .report
+                        .top(limit as usize)
+                        .into_iter()
+                        .map(v1::RankedStatement::from)
+                        .collect(),
+                };
+                Ok(ranking.to_json())
+            }
+            "query" => {
+                let (path, line) = location(params)?;
+                Ok(self
+                    .report
+                    .score_at(path, line)
+                    .map_or(Json::Null, |found| {
+                        v1::RankedStatement::from(found).to_json()
+                    }))
+            }
+            "explain" => {
+                let (path, line) = location(params)?;
+                let (Some(found), Some(statement)) = (
+                    self.report.score_at(path, line),
+                    self.report.statement_at(path, line),
+                ) else {
+                    return Ok(Json::Null);
+                };
+                let steps =
+                    crate::explain::steps(statement, self.formula, &NumberFormat::default())
+                        .lines()
+                        .map(|step| Json::from(step.trim()))
+                        .collect();
+                Ok(Json::Object(vec![
+                    (
+                        "statement".to_string(),
+                        v1::RankedStatement::from(found).to_json(),
+                    ),
+                    (
+                        "total_failing".to_string(),
+                        Json::from(statement.total_failed),
+                    ),
+                    (
+                        "total_passing".to_string(),
+                        Json::from(statement.total_passed),
+                    ),
+                    ("steps".to_string(), Json::Array(steps)),
+                ]))
+            }
+            "formulas" => Ok(Json::Array(
+                Metric::ALL
+                    .iter()
+                    .map(|metric| describe(&metric.describe()))
+                    .collect(),
+            )),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+        }
+    }
+}
+
+/// The `path` and `line` parameters of `query` and `explain`.
+fn location(params: Option<&Json>) -> Result<(&str, u32), (i32, String)> {
+    let path = params.and_then(|params| params.get("path")?.as_str());
+    let line = params.and_then(|params| params.get("line")?.as_u32());
+    match (path, line) {
+        (Some(path), Some(line)) => Ok((path, line)),
+        _ => Err((INVALID_PARAMS, "expected a path and a line".to_string())),
+    }
+}
+
+fn describe(description: &Description) -> Json {
+    let parameters = description
+        .parameters
+        .iter()
+        .map(|parameter| {
+            Json::Object(vec![
+                ("name".to_string(), Json::from(parameter.name)),
+                ("flag".to_string(), Json::from(parameter.flag)),
+                ("description".to_string(), Json::from(parameter.description)),
+                ("type".to_string(), Json::from("integer")),
+                ("default".to_string(), Json::from(parameter.default)),
+                ("minimum".to_string(), Json::from(parameter.minimum)),
+            ])
+        })
+        .collect();
+    Json::Object(vec![
+        ("id".to_string(), Json::from(description.id)),
+        ("name".to_string(), Json::from(description.name)),
+        ("formula".to_string(), Json::from(description.formula)),
+        ("summary".to_string(), Json::from(description.summary)),
+        ("reference".to_string(), Json::from(description.reference)),
+        ("parameters".to_string(), Json::Array(parameters)),
+    ])
+}
+
+fn error(id: &Json, code: i32, message: &str) -> Json {
+    Json::Object(vec![
+        ("jsonrpc".to_string(), Json::from("2.0")),
+        ("id".to_string(), id.clone()),
+        (
+            "error".to_string(),
+            Json::Object(vec![
+                ("code".to_string(), Json::Number(code as f64)),
+                ("message".to_string(), Json::from(message)),
+            ]),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StatementInfo;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_respond() {
+        let mut statements: Vec<StatementInfo> = [4, 77]
+            .iter()
+            .map(|&line| {
+                let mut statement = StatementInfo::new(line, format!("line{}();", line), 1);
+                statement.file = 1 + (line == 77) as u32;
+                statement.total_passed = 1;
+                statement.add_failing_coverage();
+                statement
+            })
+            .collect();
+        statements[1].add_passing_coverage();
+        for statement in &mut statements {
+            statement.calculate_smoothed_suspiciousness(Smoothing::None);
+        }
+        crate::sort_by_suspiciousness(&mut statements);
+        let sources = HashMap::from([(1, "src/a.c".to_string()), (2, "src/b.c".to_string())]);
+        let server = Server::new(Report::new(statements, sources), Some(Smoothing::None));
+        let respond = |request: &str| server.respond(request).unwrap();
+
+        assert_eq!(
+            respond(
+                r#"{"jsonrpc":"2.0","id":1,"method":"query","params":{"path":"b.c","line":77}}"#
+            ),
+            r#"{"jsonrpc":"2.0","id":1,"result":{"rank":2,"file":"src/b.c","line_number":77,"statement":"line77();","failed_tests":1,"passed_tests":1,"suspiciousness":1}}"#
+        );
+        assert!(respond(r#"{"jsonrpc":"2.0","id":"r","method":"rank","params":{"limit":1}}"#)
+            .starts_with(r#"{"jsonrpc":"2.0","id":"r","result":{"version":1,"total_passing":1,"total_failing":1,"statements":[{"rank":1,"file":"src/a.c","line_number":4,"#));
+        let explained = respond(
+            r#"{"jsonrpc":"2.0","id":2,"method":"explain","params":{"path":"src/a.c","line":4}}"#,
+        );
+        assert!(explained.contains(r#""suspiciousness":"Infinity""#));
+        assert!(explained.contains(r#""denominator: ep + nf = 0 + 0 = 0""#));
+        assert_eq!(
+            respond(
+                r#"{"jsonrpc":"2.0","id":3,"method":"query","params":{"path":"a.c","line":5}}"#
+            ),
+            r#"{"jsonrpc":"2.0","id":3,"result":null}"#
+        );
+
+        let formulas = respond(r#"{"jsonrpc":"2.0","id":7,"method":"formulas"}"#);
+        assert!(formulas.contains(r#"{"id":"dstar","name":"D*","formula":"ef^star / (ep + nf)","#));
+        assert!(formulas.contains(r#""parameters":[{"name":"star","flag":"--dstar-exponent","#));
+
+        assert!(respond("{").contains(r#""id":null,"error":{"code":-32700"#));
+        assert!(respond(r#"{"id":4,"method":"rank"}"#).contains(r#""code":-32600"#));
+        assert!(respond(r#"{"jsonrpc":"2.0","id":5,"method":"blame"}"#)
+            .contains(r#""code":-32601,"message":"unknown method 'blame'""#));
+        assert!(
+            respond(r#"{"jsonrpc":"2.0","id":6,"method":"query","params":{"line":4}}"#)
+                .contains(r#""code":-32602"#)
+        );
+        assert!(server
+            .respond(r#"{"jsonrpc":"2.0","method":"rank"}"#)
+            .is_none());
+    }
+}
@@ -0,0 +1,70 @@
+use crate::StatementInfo;
+
+/// How far apart the failing and passing coverage rates of every statement
+/// can be for the two sets to count as indistinguishable.
+pub const INDISTINGUISHABLE: f64 = 0.01;
+
+/// A warning when the scores of `statements` suggest the passing and
+/// failing tests were swapped or mislabeled: the failing tests cover every
+/// statement at about the rate the passing ones do, or every statement
+/// scored the same. Either way the ranking carries no information, and
+/// without a warning it would look like a ranking that does.
+pub fn check_labels(statements: &[StatementInfo]) -> Option<String> {
+    let first = statements.first()?;
+    let (failed, passed) = (first.total_failed, first.total_passed);
+    if failed == 0 || passed == 0 {
+        return None;
+    }
+    let rates_match = statements.iter().all(|s| {
+        let failing = s.failed_tests as f64 / failed as f64;
+        let passing = s.passed_tests as f64 / passed as f64;
+        (failing - passing).abs() <= INDISTINGUISHABLE
+    });
+    if rates_match {
+        return Some(format!(
+            "the {} failing tests cover the statements at the same rates as the {} passing \
+             ones, so the ranking can't tell them apart; check that the passing and failing \
+             directories aren't swapped or mislabeled",
+            failed, passed
+        ));
+    }
+    let score = first.suspiciousness;
+    let uniform = statements
+        .iter()
+        .all(|s| s.suspiciousness.total_cmp(&score).is_eq() || s.suspiciousness.is_nan());
+    if statements.len() > 1 && uniform {
+        return Some(format!(
+            "every statement scored {}, so the ranking is no better than chance; check that the \
+             failing tests' coverage was collected and that the passing and failing directories \
+             aren't swapped or mislabeled",
+            score
+        ));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement(line_number: u32, failed: u32, passed: u32, score: f32) -> StatementInfo {
+        let mut statement = StatementInfo::new(line_number, String::new(), 2);
+        statement.total_passed = 4;
+        statement.failed_tests = failed;
+        statement.passed_tests = passed;
+        statement.suspiciousness = score;
+        statement
+    }
+
+    #[test]
+    fn test_check_labels() {
+        // half the failing and half the passing tests run each statement
+        let same = [statement(1, 1, 2, 0.5), statement(2, 2, 4, 1.0)];
+        assert!(check_labels(&same).unwrap().contains("same rates"));
+        let zero = [statement(1, 1, 4, 0.0), statement(2, 0, 1, 0.0)];
+        assert!(check_labels(&zero).unwrap().contains("scored 0"));
+        let useful = [statement(1, 2, 0, f32::INFINITY), statement(2, 1, 4, 0.25)];
+        assert_eq!(check_labels(&useful), None);
+        assert_eq!(check_labels(&[]), None);
+    }
+}
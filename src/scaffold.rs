@@ -0,0 +1,203 @@
+use crate::formats::Format;
+use std::path::Path;
+use std::str::FromStr;
+
+const MAKE_SCRIPT: &str = include_str!("templates/init/make.sh");
+const CMAKE_SCRIPT: &str = include_str!("templates/init/cmake.sh");
+const CARGO_SCRIPT: &str = include_str!("templates/init/cargo.sh");
+
+/// The settings file the run script sources.
+pub const CONFIG: &str = "fl_dstar.env";
+/// The test metadata template, for `--manifest`.
+pub const MANIFEST: &str = "fl_tests.csv";
+/// The script that collects each test's coverage and ranks it.
+pub const SCRIPT: &str = "run_fl.sh";
+
+/// The build systems `fl_dstar init` can write a run script for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildSystem {
+    Make,
+    CMake,
+    Cargo,
+}
+
+impl FromStr for BuildSystem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "make" => Ok(BuildSystem::Make),
+            "cmake" => Ok(BuildSystem::CMake),
+            "cargo" => Ok(BuildSystem::Cargo),
+            _ => Err(format!(
+                "unknown build system '{}' (expected make, cmake or cargo)",
+                s
+            )),
+        }
+    }
+}
+
+impl BuildSystem {
+    /// The build system of the project in `dir`, from the files at its top.
+    /// CMake wins over Make, since in-source CMake builds leave a Makefile
+    /// next to CMakeLists.txt.
+    pub fn detect(dir: &Path) -> Option<BuildSystem> {
+        let has = |name: &str| dir.join(name).is_file();
+        if has("Cargo.toml") {
+            Some(BuildSystem::Cargo)
+        } else if has("CMakeLists.txt") {
+            Some(BuildSystem::CMake)
+        } else if ["GNUmakefile", "makefile", "Makefile"]
+            .iter()
+            .any(|&name| has(name))
+        {
+            Some(BuildSystem::Make)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuildSystem::Make => "Make",
+            BuildSystem::CMake => "CMake",
+            BuildSystem::Cargo => "Cargo",
+        }
+    }
+
+    /// The format the run script collects coverage in.
+    pub fn format(&self) -> Format {
+        match self {
+            BuildSystem::Make | BuildSystem::CMake => Format::Gcov,
+            BuildSystem::Cargo => Format::LlvmCov,
+        }
+    }
+}
+
+/// A file written by `fl_dstar init`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StarterFile {
+    pub name: &'static str,
+    pub contents: String,
+    /// Whether the file should be executable
+    pub executable: bool,
+}
+
+/// The starter files for a project built with `build`: the settings in
+/// [`CONFIG`], a test metadata template in [`MANIFEST`] and the run script
+/// in [`SCRIPT`], which reads the settings, files each test's coverage
+/// under a passing and a failing directory and ranks them.
+pub fn starter_files(build: BuildSystem) -> Vec<StarterFile> {
+    let script = match build {
+        BuildSystem::Make => MAKE_SCRIPT,
+        BuildSystem::CMake => CMAKE_SCRIPT,
+        BuildSystem::Cargo => CARGO_SCRIPT,
+    };
+    vec![
+        StarterFile {
+            name: CONFIG,
+            contents: config(build),
+            executable: false,
+        },
+        StarterFile {
+            name: MANIFEST,
+            contents: "test,owner,shard\nexample_test,@org/team,1\n".to_string(),
+            executable: false,
+        },
+        StarterFile {
+            name: SCRIPT,
+            contents: script.to_string(),
+            executable: true,
+        },
+    ]
+}
+
+fn config(build: BuildSystem) -> String {
+    let format = match build.format() {
+        Format::Gcov => "gcov",
+        _ => "llvm-cov",
+    };
+    let build_specific = match build {
+        BuildSystem::Make => {
+            "\
+# The make targets that each run one test; the coverage of each is named
+# after its target
+FL_TESTS=\"check-example\"
+"
+        }
+        BuildSystem::CMake => {
+            "\
+# Where the instrumented build goes, apart from the usual one; every test
+# ctest lists is run
+FL_BUILD_DIR=build-fl
+"
+        }
+        BuildSystem::Cargo => {
+            "\
+# Where the instrumented build goes, apart from the usual one; every test
+# `cargo test --tests` lists is run
+FL_BUILD_DIR=target-fl
+"
+        }
+    };
+    format!(
+        "\
+# Settings for {script}, written by `fl_dstar init` for a {name} project.
+# {script} sources this file, so it's shell syntax.
+
+# The fl_dstar to run
+FL_DSTAR=fl_dstar
+# Where each test's coverage is filed: a test that fails goes in FL_FAIL_DIR.
+# A test's file is named after it, which is how {manifest} refers to it
+FL_PASS_DIR=fl_coverage/pass
+FL_FAIL_DIR=fl_coverage/fail
+# The format {script} collects coverage in
+FL_FORMAT={format}
+# Formula to rank with: dstar, tarantula, ochiai, jaccard, op2 or barinel
+FL_METRIC=dstar
+# Per-test metadata such as owner, shard or platform, for --filter and
+# --group-by; set it to {manifest} once that lists your tests
+FL_MANIFEST=
+# Any other options, e.g. --suppress-harness --columns file,line,suspiciousness
+FL_OPTIONS=
+{build_specific}",
+        script = SCRIPT,
+        name = build.name(),
+        manifest = MANIFEST,
+        format = format,
+        build_specific = build_specific,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let dir = std::env::temp_dir().join(format!("fl_dstar_scaffold_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(BuildSystem::detect(&dir), None);
+        std::fs::write(dir.join("Makefile"), "all:\n").unwrap();
+        assert_eq!(BuildSystem::detect(&dir), Some(BuildSystem::Make));
+        std::fs::write(dir.join("CMakeLists.txt"), "project(x)\n").unwrap();
+        assert_eq!(BuildSystem::detect(&dir), Some(BuildSystem::CMake));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!("CMake".parse(), Ok(BuildSystem::CMake));
+        assert!("bazel".parse::<BuildSystem>().is_err());
+    }
+
+    #[test]
+    fn test_starter_files() {
+        let files = starter_files(BuildSystem::Cargo);
+        let names: Vec<&str> = files.iter().map(|file| file.name).collect();
+        assert_eq!(names, [CONFIG, MANIFEST, SCRIPT]);
+        assert!(files[0].contents.contains("\nFL_FORMAT=llvm-cov\n"));
+        assert!(files[0].contents.contains("\nFL_BUILD_DIR=target-fl\n"));
+        #[cfg(feature = "reports")]
+        assert!(crate::manifest::Manifest::parse(files[1].contents.as_bytes()).is_ok());
+        assert!(files[2].executable);
+        assert!(files[2].contents.contains("llvm-cov"));
+    }
+}
@@ -0,0 +1,352 @@
+//! The formula math and the counters it works on, written against `core` and
+//! `alloc` alone so the module can be lifted into a `#![no_std]` build, such
+//! as a test harness running on embedded firmware, unchanged. Parsing and
+//! explaining scores need `std` and live in [`crate::smoothing`].
+#![deny(clippy::std_instead_of_core, clippy::std_instead_of_alloc)]
+
+use alloc::vec::Vec;
+
+/// Corrections applied to a statement's counts before scoring. With only one
+/// or two failing tests the raw counts put large tied blocks of infinite
+/// scores at the top of the ranking; smoothing keeps those finite and lets
+/// the passing counts separate them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Smoothing {
+    None,
+    /// Add one to each of the failed, passed and not-failed counts
+    AddOne,
+    /// Replace the counts with their expectation under a Beta(alpha, beta)
+    /// prior on the probability that a failing (or passing) test covers the
+    /// statement
+    Beta {
+        alpha: f32,
+        beta: f32,
+    },
+}
+
+/// The star of D* unless another is asked for, the exponent Wong et al.
+/// found to work best.
+pub const DEFAULT_STAR: u32 = 2;
+
+/// Counts that feed a suspiciousness formula, possibly fractional once smoothed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Counts {
+    /// failing tests that covered the statement
+    pub failed: f32,
+    /// passing tests that covered the statement
+    pub passed: f32,
+    /// failing tests that did not cover the statement
+    pub not_failed: f32,
+    /// passing tests that did not cover the statement
+    pub not_passed: f32,
+}
+
+impl Counts {
+    /// The D* suspiciousness of these counts, with the star at [`DEFAULT_STAR`].
+    pub fn dstar(&self) -> f32 {
+        let (numerator, denominator) = self.dstar_terms();
+        numerator / denominator
+    }
+
+    /// The D* suspiciousness of these counts with `star` as the exponent of
+    /// `ef`; a larger star favors statements more failing tests cover.
+    pub fn dstar_with(&self, star: u32) -> f32 {
+        let numerator: f32 = (0..star).map(|_| self.failed).product();
+        numerator / (self.passed + self.not_failed)
+    }
+
+    /// The numerator (`ef²`) and denominator (`ep + nf`) of D*.
+    pub fn dstar_terms(&self) -> (f32, f32) {
+        (self.failed * self.failed, self.passed + self.not_failed)
+    }
+}
+
+impl Smoothing {
+    pub fn counts(
+        &self,
+        failed_tests: u32,
+        passed_tests: u32,
+        total_failed: u32,
+        total_passed: u32,
+    ) -> Counts {
+        let failed = failed_tests as f32;
+        let passed = passed_tests as f32;
+        let total_failed = total_failed as f32;
+        let total_passed = total_passed as f32;
+        match *self {
+            Smoothing::None => Counts {
+                failed,
+                passed,
+                not_failed: total_failed - failed,
+                not_passed: total_passed - passed,
+            },
+            Smoothing::AddOne => Counts {
+                failed: failed + 1.0,
+                passed: passed + 1.0,
+                not_failed: total_failed - failed + 1.0,
+                not_passed: total_passed - passed,
+            },
+            Smoothing::Beta { alpha, beta } => {
+                let failed = total_failed * (failed + alpha) / (total_failed + alpha + beta);
+                let passed = total_passed * (passed + alpha) / (total_passed + alpha + beta);
+                Counts {
+                    failed,
+                    passed,
+                    not_failed: total_failed - failed,
+                    not_passed: total_passed - passed,
+                }
+            }
+        }
+    }
+}
+
+/// How often one statement was covered, kept up to date test by test, for
+/// harnesses that score as results come in rather than from coverage files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// failing tests that covered the statement
+    pub failed: u32,
+    /// passing tests that covered the statement
+    pub passed: u32,
+    pub total_failed: u32,
+    pub total_passed: u32,
+}
+
+impl Counters {
+    /// Count one more test, which did or didn't cover the statement.
+    pub fn record(&mut self, covered: bool, failed: bool) {
+        match failed {
+            true => {
+                self.total_failed += 1;
+                self.failed += covered as u32;
+            }
+            false => {
+                self.total_passed += 1;
+                self.passed += covered as u32;
+            }
+        }
+    }
+
+    /// The D* suspiciousness of the statement.
+    pub fn score(&self, smoothing: Smoothing) -> f32 {
+        smoothing
+            .counts(
+                self.failed,
+                self.passed,
+                self.total_failed,
+                self.total_passed,
+            )
+            .dstar()
+    }
+}
+
+/// The D* suspiciousness of every statement.
+pub fn score_all(counters: &[Counters], smoothing: Smoothing) -> Vec<f32> {
+    counters.iter().map(|c| c.score(smoothing)).collect()
+}
+
+/// Indices of `scores` from most to least suspicious, ties keeping their
+/// order, so statements given in line order break ties by line number.
+pub fn rank(scores: &[f32]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+    order
+}
+
+/// Which statements each test covered, one bit per statement packed into
+/// `u64` words, a row per test. Counting the rows into [`Counters`] works a
+/// word at a time, 64 statements per operation, which is what makes
+/// spectra of a hundred thousand tests quick to score.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageMatrix {
+    statements: usize,
+    /// Words in a row
+    width: usize,
+    rows: Vec<u64>,
+    failed: Vec<bool>,
+}
+
+/// Matrices with fewer tests than this are counted bit by bit; below it,
+/// reading the counts back out of the bit planes costs more than it saves.
+const WORDWISE_MIN_TESTS: usize = 64;
+
+impl CoverageMatrix {
+    pub fn new(statements: usize) -> CoverageMatrix {
+        CoverageMatrix {
+            statements,
+            width: statements.div_ceil(64),
+            rows: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+
+    pub fn tests(&self) -> usize {
+        self.failed.len()
+    }
+
+    /// Add a test whose coverage of statement `i` is bit `i % 64` of word
+    /// `i / 64`. Bits past the last statement are ignored.
+    pub fn push_words(&mut self, words: &[u64], failed: bool) {
+        assert_eq!(words.len(), self.width, "a row has one bit per statement");
+        self.rows.extend_from_slice(words);
+        if let (Some(last), 1..=63) = (self.rows.last_mut(), self.statements % 64) {
+            *last &= (1 << (self.statements % 64)) - 1;
+        }
+        self.failed.push(failed);
+    }
+
+    /// Add a test from whether it covered each statement, in order.
+    pub fn push(&mut self, covered: impl IntoIterator<Item = bool>, failed: bool) {
+        let mut words = alloc::vec![0u64; self.width];
+        for (i, covered) in covered.into_iter().take(self.statements).enumerate() {
+            words[i / 64] |= (covered as u64) << (i % 64);
+        }
+        self.push_words(&words, failed);
+    }
+
+    /// The counters of every statement.
+    pub fn counters(&self) -> Vec<Counters> {
+        match self.tests() < WORDWISE_MIN_TESTS {
+            true => self.counters_scalar(),
+            false => self.counters_wordwise(),
+        }
+    }
+
+    /// [`CoverageMatrix::counters`] a bit at a time, the reference the
+    /// word-wise count is checked against.
+    pub fn counters_scalar(&self) -> Vec<Counters> {
+        let mut counters = alloc::vec![Counters::default(); self.statements];
+        for (row, &failed) in self.rows.chunks_exact(self.width.max(1)).zip(&self.failed) {
+            for (i, counter) in counters.iter_mut().enumerate() {
+                counter.record(row[i / 64] >> (i % 64) & 1 == 1, failed);
+            }
+        }
+        counters
+    }
+
+    /// [`CoverageMatrix::counters`] a word at a time. Each outcome keeps
+    /// bit-sliced counters: plane `p` holds bit `p` of the count of every
+    /// statement, and adding a row is a ripple-carry add of the row into the
+    /// planes. Whole rows are added plane by plane, a loop without branches
+    /// that the compiler turns into vector instructions, and the ripple stops
+    /// at the first plane no carry reaches.
+    pub fn counters_wordwise(&self) -> Vec<Counters> {
+        if self.statements == 0 {
+            return Vec::new();
+        }
+        let width = self.width;
+        let planes = (usize::BITS - self.tests().leading_zeros()).max(1) as usize;
+        let mut passed = alloc::vec![0u64; width * planes];
+        let mut failed = alloc::vec![0u64; width * planes];
+        let mut carry = alloc::vec![0u64; width];
+        for (row, &is_failed) in self.rows.chunks_exact(width.max(1)).zip(&self.failed) {
+            let sums = match is_failed {
+                true => &mut failed,
+                false => &mut passed,
+            };
+            carry.copy_from_slice(row);
+            for plane in sums.chunks_exact_mut(width) {
+                let mut any = 0;
+                for (sum, carry) in plane.iter_mut().zip(carry.iter_mut()) {
+                    let next = *sum & *carry;
+                    *sum ^= *carry;
+                    *carry = next;
+                    any |= next;
+                }
+                if any == 0 {
+                    break;
+                }
+            }
+        }
+        let total_failed = self.failed.iter().filter(|&&f| f).count() as u32;
+        let total_passed = self.tests() as u32 - total_failed;
+        let count = |sums: &[u64], i: usize| -> u32 {
+            sums.chunks_exact(width)
+                .enumerate()
+                .map(|(p, plane)| ((plane[i / 64] >> (i % 64) & 1) as u32) << p)
+                .sum()
+        };
+        (0..self.statements)
+            .map(|i| Counters {
+                failed: count(&failed, i),
+                passed: count(&passed, i),
+                total_failed,
+                total_passed,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters() {
+        // two statements over three tests; the first is only covered when failing
+        let mut counters = [Counters::default(); 2];
+        for (covered, failed) in [
+            ([true, true], true),
+            ([false, true], false),
+            ([false, true], false),
+        ] {
+            for (counter, covered) in counters.iter_mut().zip(covered) {
+                counter.record(covered, failed);
+            }
+        }
+        assert_eq!(
+            counters[1],
+            Counters {
+                failed: 1,
+                passed: 2,
+                total_failed: 1,
+                total_passed: 2
+            }
+        );
+        let scores = score_all(&counters, Smoothing::None);
+        assert!(scores[0].is_infinite());
+        assert_eq!(scores[1], 0.5);
+        assert_eq!(rank(&scores), [0, 1]);
+        assert_eq!(rank(&[0.5, 1.0, 0.5]), [1, 0, 2]);
+    }
+
+    #[test]
+    fn test_coverage_matrix() {
+        // 130 statements spill into a third word; 200 tests need 8 planes
+        let mut matrix = CoverageMatrix::new(130);
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        for test in 0..200 {
+            let words: Vec<u64> = (0..3)
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    seed
+                })
+                .collect();
+            matrix.push_words(&words, test % 7 == 0);
+        }
+        let counters = matrix.counters();
+        assert_eq!(counters, matrix.counters_scalar());
+        assert_eq!(counters.len(), 130);
+        assert_eq!(
+            (counters[0].total_failed, counters[0].total_passed),
+            (29, 171)
+        );
+
+        assert!(CoverageMatrix::new(0).counters_wordwise().is_empty());
+        let mut small = CoverageMatrix::new(3);
+        small.push([true, false, true], true);
+        small.push([true, true, false], false);
+        assert_eq!(small.counters_wordwise(), small.counters_scalar());
+        assert_eq!(
+            small.counters()[0],
+            Counters {
+                failed: 1,
+                passed: 1,
+                total_failed: 1,
+                total_passed: 1
+            }
+        );
+    }
+}
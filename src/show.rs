@@ -0,0 +1,288 @@
+use crate::hunks::Hunk;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// When `show` colors its output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    /// When writing to a terminal and `NO_COLOR` isn't set
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Color::Auto),
+            "always" => Ok(Color::Always),
+            "never" => Ok(Color::Never),
+            _ => Err(format!(
+                "unknown color '{}' (expected auto, always or never)",
+                s
+            )),
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const MAGENTA: &str = "\x1b[35m";
+const CYAN: &str = "\x1b[36m";
+const GREY: &str = "\x1b[90m";
+
+/// Keywords of C, C++ and Rust, the languages gcov and llvm-cov report on.
+const KEYWORDS: &[&str] = &[
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "float",
+    "fn",
+    "for",
+    "goto",
+    "if",
+    "impl",
+    "inline",
+    "int",
+    "let",
+    "long",
+    "match",
+    "mod",
+    "mut",
+    "namespace",
+    "new",
+    "nullptr",
+    "NULL",
+    "private",
+    "protected",
+    "pub",
+    "public",
+    "return",
+    "self",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "true",
+    "typedef",
+    "union",
+    "unsigned",
+    "use",
+    "void",
+    "while",
+];
+
+fn paint(out: &mut String, color: &str, text: &str) {
+    write!(out, "{}{}{}", color, text, RESET).unwrap();
+}
+
+/// What a highlighted token of code is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Keyword,
+    /// A string or character literal
+    Text,
+    Number,
+    Comment,
+}
+
+/// `code` split into tokens, each with its kind when it is highlighted.
+/// Tokens are recognized line by line, so a block comment spanning lines is
+/// only recognized on the line that opens it.
+pub fn tokens(code: &str) -> Vec<(Option<Kind>, &str)> {
+    let mut tokens: Vec<(Option<Kind>, &str)> = Vec::new();
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let (kind, len) = if rest.starts_with("//") {
+            (Some(Kind::Comment), rest.len())
+        } else if rest.starts_with("/*") {
+            let len = rest.find("*/").map_or(rest.len(), |end| end + 2);
+            (Some(Kind::Comment), len)
+        } else if c == '"' || c == '\'' {
+            let mut escaped = false;
+            let end = rest[1..].char_indices().find(|&(_, ch)| {
+                let closes = ch == c && !escaped;
+                escaped = ch == '\\' && !escaped;
+                closes
+            });
+            (Some(Kind::Text), end.map_or(rest.len(), |(i, _)| i + 2))
+        } else if c.is_alphanumeric() || c == '_' {
+            let len = rest
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .unwrap_or(rest.len());
+            let kind = match (c.is_ascii_digit(), KEYWORDS.contains(&&rest[..len])) {
+                (true, _) => Some(Kind::Number),
+                (false, true) => Some(Kind::Keyword),
+                (false, false) => None,
+            };
+            (kind, len)
+        } else {
+            (None, c.len_utf8())
+        };
+        // plain characters join the plain token before them
+        match (kind, tokens.last_mut()) {
+            (None, Some((None, last))) => {
+                let start = code.len() - rest.len() - last.len();
+                *last = &code[start..start + last.len() + len];
+            }
+            _ => tokens.push((kind, &rest[..len])),
+        }
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+/// `code` with ANSI colors for keywords, strings, numbers and comments.
+pub fn highlight(code: &str) -> String {
+    let mut out = String::new();
+    for (kind, token) in tokens(code) {
+        match kind {
+            Some(Kind::Keyword) => paint(&mut out, MAGENTA, token),
+            Some(Kind::Text) => paint(&mut out, GREEN, token),
+            Some(Kind::Number) => paint(&mut out, CYAN, token),
+            Some(Kind::Comment) => paint(&mut out, GREY, token),
+            None => out.push_str(token),
+        }
+    }
+    out
+}
+
+/// A hunk as a unified-diff-style excerpt: an `@@` header with its rank and
+/// scores, then its lines and `context` lines either side, each with a
+/// gutter of its score. Suspicious lines are marked `-`, so chat apps that
+/// highlight diffs show them in red. `scores` gives the score of every
+/// statement and `lines` the text of each line known from the coverage, by
+/// their [`crate::statement_key`].
+pub fn excerpt(
+    rank: usize,
+    hunk: &Hunk,
+    scores: &HashMap<u64, f32>,
+    lines: &HashMap<u64, &str>,
+    context: u32,
+    color: bool,
+) -> String {
+    let mut out = String::new();
+    let header = format!(
+        "@@ {}:{}-{} @@ #{} max {:.2}, mean {:.2}, {} suspicious",
+        hunk.file.as_deref().unwrap_or("unknown"),
+        hunk.first_line,
+        hunk.last_line,
+        rank,
+        hunk.max_suspiciousness,
+        hunk.mean_suspiciousness,
+        hunk.statements
+    );
+    match color {
+        true => paint(&mut out, CYAN, &header),
+        false => out.push_str(&header),
+    }
+    out.push('\n');
+    let first = hunk.first_line.saturating_sub(context).max(1);
+    let last = hunk.last_line.saturating_add(context);
+    let width = last.to_string().len();
+    for number in first..=last {
+        let key = crate::statement_key(hunk.file_id, number);
+        let Some(text) = lines.get(&key) else {
+            continue;
+        };
+        let text = text.trim_end();
+        let score = scores.get(&key).copied().filter(|&score| score > 0.0);
+        let gutter = match score {
+            Some(score) => format!("-{:>6.2} {:>width$} |", score, number),
+            None => format!(" {:>6} {:>width$} |", "", number),
+        };
+        match (color, score) {
+            (true, Some(score)) if score == hunk.max_suspiciousness => {
+                paint(&mut out, &format!("{}{}", BOLD, RED), &gutter)
+            }
+            (true, Some(_)) => paint(&mut out, RED, &gutter),
+            (true, None) => paint(&mut out, GREY, &gutter),
+            (false, _) => out.push_str(&gutter),
+        }
+        match (color, text.is_empty()) {
+            (_, true) => {}
+            (true, false) => write!(out, " {}", highlight(text)).unwrap(),
+            (false, false) => write!(out, " {}", text).unwrap(),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excerpt() {
+        let hunk = Hunk {
+            file: Some("t.c".to_string()),
+            file_id: 0,
+            first_line: 2,
+            last_line: 4,
+            statements: 2,
+            max_suspiciousness: f32::INFINITY,
+            mean_suspiciousness: f32::INFINITY,
+        };
+        let scores = HashMap::from([(2, 0.5), (4, f32::INFINITY), (5, 0.0)]);
+        let lines = HashMap::from([
+            (1, "#include <x>"),
+            (2, "int a = 1;"),
+            (3, "if (a) {"),
+            (4, "b();"),
+            (5, "c();"),
+            (7, "}"),
+        ]);
+        let expected = [
+            "@@ t.c:2-4 @@ #1 max inf, mean inf, 2 suspicious",
+            "        1 | #include <x>",
+            "-  0.50 2 | int a = 1;",
+            "        3 | if (a) {",
+            "-   inf 4 | b();",
+            "        5 | c();",
+            "",
+        ];
+        assert_eq!(
+            excerpt(1, &hunk, &scores, &lines, 1, false),
+            expected.join("\n")
+        );
+        let colored = excerpt(1, &hunk, &scores, &lines, 0, true);
+        assert!(colored.contains("\x1b[1m\x1b[31m-   inf 4 |\x1b[0m b();"));
+    }
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight(r#"if (n > 10) puts("a \" b"); // done"#),
+            "\x1b[35mif\x1b[0m (n > \x1b[36m10\x1b[0m) puts(\x1b[32m\"a \\\" b\"\x1b[0m); \
+             \x1b[90m// done\x1b[0m"
+        );
+        assert_eq!(highlight("x /* y */ z"), "x \x1b[90m/* y */\x1b[0m z");
+        assert_eq!(
+            tokens("a = 'b';"),
+            [(None, "a = "), (Some(Kind::Text), "'b'"), (None, ";")]
+        );
+    }
+}
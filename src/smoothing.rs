@@ -0,0 +1,101 @@
+use crate::locale::NumberFormat;
+
+pub use crate::scoring::{Counts, Smoothing};
+
+impl Counts {
+    /// D* with these counts substituted, e.g. `ef² / (ep + nf) = 6² / (1 + 0) = 36.00`.
+    pub fn explain_dstar(&self, numbers: &NumberFormat) -> String {
+        let count = |x: f32| match x.fract() == 0.0 {
+            true => numbers.format(x as f64, 0),
+            false => numbers.format(x as f64, 2),
+        };
+        format!(
+            "ef² / (ep + nf) = {}² / ({} + {}) = {}",
+            count(self.failed),
+            count(self.passed),
+            count(self.not_failed),
+            numbers.format(self.dstar() as f64, 2)
+        )
+    }
+}
+
+impl std::str::FromStr for Smoothing {
+    type Err = String;
+
+    /// `none`, `add-one`, `beta` (a uniform Beta(1, 1) prior) or `beta:A,B`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_ascii_lowercase();
+        match lower.as_str() {
+            "none" => return Ok(Smoothing::None),
+            "add-one" | "laplace" => return Ok(Smoothing::AddOne),
+            "beta" => {
+                return Ok(Smoothing::Beta {
+                    alpha: 1.0,
+                    beta: 1.0,
+                })
+            }
+            _ => {}
+        }
+        let params = lower.strip_prefix("beta:").and_then(|p| p.split_once(','));
+        let parsed =
+            params.and_then(|(a, b)| Some((a.trim().parse().ok()?, b.trim().parse().ok()?)));
+        match parsed {
+            Some((alpha, beta)) if alpha > 0.0 && beta > 0.0 => Ok(Smoothing::Beta { alpha, beta }),
+            _ => Err(format!(
+                "unknown smoothing '{}' (expected none, add-one, beta or beta:ALPHA,BETA)",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_smoothing() {
+        assert_eq!("none".parse::<Smoothing>(), Ok(Smoothing::None));
+        assert_eq!("add-one".parse::<Smoothing>(), Ok(Smoothing::AddOne));
+        assert_eq!(
+            "beta:2,0.5".parse::<Smoothing>(),
+            Ok(Smoothing::Beta {
+                alpha: 2.0,
+                beta: 0.5
+            })
+        );
+        assert!("beta:0,1".parse::<Smoothing>().is_err());
+    }
+
+    #[test]
+    fn test_beta_counts() {
+        // one failing test that covers the statement, no passing coverage
+        let counts = Smoothing::Beta {
+            alpha: 1.0,
+            beta: 1.0,
+        }
+        .counts(1, 0, 1, 4);
+        assert!((counts.failed - 2.0 / 3.0).abs() < 1e-6);
+        assert!((counts.passed - 4.0 / 6.0).abs() < 1e-6);
+        assert!((counts.not_failed - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_explain_dstar() {
+        let numbers = NumberFormat::default();
+        let counts = Smoothing::None.counts(6, 1, 6, 10);
+        assert_eq!(
+            counts.explain_dstar(&numbers),
+            "ef² / (ep + nf) = 6² / (1 + 0) = 36.00"
+        );
+        let smoothed = Smoothing::AddOne.counts(1, 0, 2, 3);
+        assert_eq!(
+            smoothed.explain_dstar(&"de".parse().unwrap()),
+            "ef² / (ep + nf) = 2² / (1 + 2) = 1,33"
+        );
+        assert_eq!(
+            Smoothing::None.counts(1, 0, 1, 1).explain_dstar(&numbers),
+            "ef² / (ep + nf) = 1² / (0 + 0) = inf"
+        );
+    }
+}
@@ -0,0 +1,474 @@
+//! A compact on-disk form of a [`Spectrum`], for archiving alongside CI
+//! artifacts and ranking again later without the original coverage files.
+//!
+//! A snapshot is an 8 byte header followed by the body, which is compressed
+//! with zstd unless the header says otherwise:
+//!
+//! | bytes | header field                                 |
+//! |-------|----------------------------------------------|
+//! | 4     | magic `FLDS`                                 |
+//! | 2     | format version, little endian                |
+//! | 1     | compression: 0 for none, 1 for zstd          |
+//! | 1     | reserved, 0                                  |
+//!
+//! The body is a list of sections, each a 4 byte tag, a little endian `u32`
+//! length and that many bytes:
+//!
+//! - `STRS`: the string table, a `u32` count then each string as a `u32`
+//!   length and UTF-8 bytes
+//! - `LINE`: a `u32` count then, per statement, its line number in its
+//!   source file, the string index of its text and the string index of its
+//!   source file plus one (0 when the source is unknown), all `u32`
+//! - `TEST`: a `u32` count then, per test, a flags byte (bit 0 set for a
+//!   passing test) and one bit per statement, set when the test covered it
+//!
+//! Execution counts are not kept, only whether each statement was covered.
+//!
+//! Compatibility: readers skip sections they don't know, so new information
+//! is added as new sections without changing the version. The version only
+//! changes when an existing section does, and every version ever written
+//! stays readable by later releases. Version 1 kept the position of a
+//! statement's source file in the bits of its line number above the lowest
+//! 20, so its lines that name a source are read modulo 2^20.
+
+use crate::scoring::CoverageMatrix;
+use crate::{Coverage, LineInfo, Spectrum, StatementInfo};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+const MAGIC: &[u8; 4] = b"FLDS";
+
+/// The version written by this release.
+pub const FORMAT_VERSION: u16 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    /// Through the `zstd` command (or the one named by `ZSTD`)
+    Zstd,
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Compression::None),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!(
+                "unknown compression '{}' (expected none or zstd)",
+                s
+            )),
+        }
+    }
+}
+
+/// Encode the tests and statements of `spectrum`. Scores are not stored;
+/// they are recomputed when the snapshot is read.
+pub fn encode(spectrum: &Spectrum, compression: Compression) -> std::io::Result<Vec<u8>> {
+    let mut strings = StringTable::new();
+    let mut lines = Vec::new();
+    put_u32(&mut lines, spectrum.statements.len() as u32);
+    for statement in &spectrum.statements {
+        put_u32(&mut lines, statement.line_number);
+        put_u32(&mut lines, strings.index(statement.statement()));
+        let source = match spectrum.sources.get(&statement.file) {
+            Some(source) => strings.index(source) + 1,
+            None => 0,
+        };
+        put_u32(&mut lines, source);
+    }
+    let mut tests = Vec::new();
+    put_u32(
+        &mut tests,
+        (spectrum.passing.len() + spectrum.failing.len()) as u32,
+    );
+    let all = spectrum
+        .passing
+        .iter()
+        .map(|test| (test, true))
+        .chain(spectrum.failing.iter().map(|test| (test, false)));
+    let index: HashMap<u64, usize> = spectrum
+        .statements
+        .iter()
+        .enumerate()
+        .map(|(i, statement)| (statement.key(), i))
+        .collect();
+    for (test, passing) in all {
+        tests.push(passing as u8);
+        let mut bits = vec![0u8; spectrum.statements.len().div_ceil(8)];
+        for line in crate::executable_lines(test) {
+            if let (Coverage::Covered, Some(&i)) = (line.coverage, index.get(&line.key())) {
+                bits[i / 8] |= 1 << (i % 8);
+            }
+        }
+        tests.extend(bits);
+    }
+
+    let mut body = Vec::new();
+    put_section(&mut body, b"STRS", &strings.bytes);
+    put_section(&mut body, b"LINE", &lines);
+    put_section(&mut body, b"TEST", &tests);
+    let mut out = MAGIC.to_vec();
+    out.extend(FORMAT_VERSION.to_le_bytes());
+    match compression {
+        Compression::None => {
+            out.extend([0, 0]);
+            out.extend(body);
+        }
+        Compression::Zstd => {
+            out.extend([1, 0]);
+            out.extend(zstd(&["-q", "-c"], body)?);
+        }
+    }
+    Ok(out)
+}
+
+/// Decode a snapshot into an unscored spectrum.
+pub fn decode(bytes: &[u8]) -> Result<Spectrum, String> {
+    decode_with(bytes, true)
+}
+
+/// Decode a snapshot's statements and their counts alone, counting the
+/// tests' bits a word at a time without expanding each test into lines.
+/// The spectrum's `passing` and `failing` are left empty, so it suits
+/// formula scoring but nothing that looks at single tests.
+pub fn decode_counts(bytes: &[u8]) -> Result<Spectrum, String> {
+    decode_with(bytes, false)
+}
+
+fn decode_with(bytes: &[u8], keep_tests: bool) -> Result<Spectrum, String> {
+    if bytes.len() < 8 || &bytes[..4] != MAGIC {
+        return Err("not an fl_dstar snapshot".to_string());
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version > FORMAT_VERSION {
+        return Err(format!(
+            "snapshot format {} is newer than this release understands ({})",
+            version, FORMAT_VERSION
+        ));
+    }
+    let body = match bytes[6] {
+        0 => bytes[8..].to_vec(),
+        1 => zstd(&["-q", "-d", "-c"], bytes[8..].to_vec())
+            .map_err(|e| format!("could not decompress: {}", e))?,
+        other => return Err(format!("unknown compression {}", other)),
+    };
+
+    let mut sections = HashMap::new();
+    let mut reader = Reader { bytes: &body };
+    while !reader.bytes.is_empty() {
+        let tag = reader.take(4)?;
+        let length = reader.u32()? as usize;
+        sections.insert(tag, reader.take(length)?);
+    }
+    let section = |tag: &[u8]| {
+        sections
+            .get(tag)
+            .copied()
+            .map(|bytes| Reader { bytes })
+            .ok_or_else(|| format!("missing {} section", String::from_utf8_lossy(tag)))
+    };
+
+    let mut reader = section(b"STRS")?;
+    let strings = (0..reader.u32()?)
+        .map(|_| {
+            let length = reader.u32()? as usize;
+            String::from_utf8(reader.take(length)?.to_vec()).map_err(|e| e.to_string())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let string = |index: u32| {
+        strings
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| format!("string {} is out of range", index))
+    };
+
+    let mut reader = section(b"LINE")?;
+    let mut statements = Vec::new();
+    let mut keys = crate::SourceKeys::default();
+    let mut sources = HashMap::new();
+    for _ in 0..reader.u32()? {
+        let line_number = reader.u32()?;
+        let text = string(reader.u32()?)?;
+        let file = match reader.u32()? {
+            0 => 0,
+            source => {
+                let source = string(source - 1)?;
+                let file = keys.file(&source);
+                sources.insert(file, source);
+                file
+            }
+        };
+        let line_number = match (version, file) {
+            (1, 1..) => line_number % (1 << 20),
+            _ => line_number,
+        };
+        statements.push((file, line_number, text));
+    }
+
+    let mut reader = section(b"TEST")?;
+    if !keep_tests {
+        let mut matrix = CoverageMatrix::new(statements.len());
+        let mut words = vec![0u64; statements.len().div_ceil(64)];
+        for _ in 0..reader.u32()? {
+            let flags = reader.take(1)?[0];
+            let bits = reader.take(statements.len().div_ceil(8))?;
+            words.fill(0);
+            for (i, byte) in bits.iter().enumerate() {
+                words[i / 8] |= (*byte as u64) << (i % 8 * 8);
+            }
+            matrix.push_words(&words, flags & 1 == 0);
+        }
+        let counters = matrix.counters();
+        let has_both = counters
+            .first()
+            .is_none_or(|c| c.total_failed > 0 && c.total_passed > 0);
+        if matrix.tests() == 0 || !has_both {
+            return Err("a snapshot needs at least one passing and one failing test".to_string());
+        }
+        let statements = statements
+            .into_iter()
+            .zip(counters)
+            .map(|((file, line_number, text), counter)| {
+                let mut statement = StatementInfo::new(line_number, text, counter.total_failed);
+                statement.file = file;
+                statement.failed_tests = counter.failed;
+                statement.passed_tests = counter.passed;
+                statement.total_passed = counter.total_passed;
+                statement
+            })
+            .collect();
+        return Ok(Spectrum {
+            passing: Vec::new(),
+            failing: Vec::new(),
+            statements,
+            sources,
+            baseline: false,
+        });
+    }
+    let (mut passing, mut failing) = (Vec::new(), Vec::new());
+    for _ in 0..reader.u32()? {
+        let flags = reader.take(1)?[0];
+        let bits = reader.take(statements.len().div_ceil(8))?;
+        let test = statements
+            .iter()
+            .enumerate()
+            .map(|(i, (file, line_number, statement))| {
+                let covered = bits[i / 8] & (1 << (i % 8)) != 0;
+                LineInfo {
+                    file: *file,
+                    line_number: *line_number,
+                    statement: statement.clone(),
+                    coverage: match covered {
+                        true => Coverage::Covered,
+                        false => Coverage::NotCovered,
+                    },
+                    execution_count: covered as u64,
+                }
+            })
+            .collect();
+        match flags & 1 {
+            1 => passing.push(test),
+            _ => failing.push(test),
+        }
+    }
+    if passing.is_empty() || failing.is_empty() {
+        return Err("a snapshot needs at least one passing and one failing test".to_string());
+    }
+    let mut spectrum = Spectrum::new(passing, failing);
+    spectrum.sources = sources;
+    Ok(spectrum)
+}
+
+pub fn write(spectrum: &Spectrum, path: &Path, compression: Compression) -> std::io::Result<()> {
+    std::fs::write(path, encode(spectrum, compression)?)
+}
+
+pub fn read(path: &Path) -> Result<Spectrum, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    decode(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Read a snapshot with [`decode_counts`].
+pub fn read_counts(path: &Path) -> Result<Spectrum, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    decode_counts(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+struct StringTable {
+    indexes: HashMap<String, u32>,
+    count: u32,
+    /// The count, then the encoded strings
+    bytes: Vec<u8>,
+}
+
+impl StringTable {
+    fn new() -> StringTable {
+        StringTable {
+            indexes: HashMap::new(),
+            count: 0,
+            bytes: vec![0; 4],
+        }
+    }
+
+    fn index(&mut self, s: &str) -> u32 {
+        if let Some(&index) = self.indexes.get(s) {
+            return index;
+        }
+        put_u32(&mut self.bytes, s.len() as u32);
+        self.bytes.extend(s.as_bytes());
+        let index = self.count;
+        self.count += 1;
+        self.bytes[..4].copy_from_slice(&self.count.to_le_bytes());
+        self.indexes.insert(s.to_string(), index);
+        index
+    }
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_section(out: &mut Vec<u8>, tag: &[u8; 4], content: &[u8]) {
+    out.extend(tag);
+    put_u32(out, content.len() as u32);
+    out.extend(content);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if count > self.bytes.len() {
+            return Err("snapshot is truncated".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Pipe `input` through the zstd command with `args`.
+fn zstd(args: &[&str], input: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    let program = std::env::var("ZSTD").unwrap_or_else(|_| "zstd".to_string());
+    let mut child = Command::new(&program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| std::io::Error::new(e.kind(), format!("could not run {}: {}", program, e)))?;
+    let mut stdin = child.stdin.take().unwrap();
+    // write from another thread so a full stdout pipe can't deadlock us
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let mut output = Vec::new();
+    child.stdout.take().unwrap().read_to_end(&mut output)?;
+    writer.join().unwrap()?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        )));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test(covered: &[bool]) -> Vec<LineInfo> {
+        covered
+            .iter()
+            .enumerate()
+            .map(|(i, &covered)| LineInfo {
+                file: (i == 0) as u32,
+                line_number: i as u32 + 2,
+                statement: format!("s{}();", i % 2),
+                coverage: match covered {
+                    true => Coverage::Covered,
+                    false => Coverage::NotCovered,
+                },
+                execution_count: covered as u64,
+            })
+            .collect()
+    }
+
+    fn spectrum() -> Spectrum {
+        let mut spectrum = Spectrum::new(
+            vec![test(&[true; 10]), test(&[false; 10])],
+            vec![test(&[
+                true, false, true, false, true, false, true, false, true, true,
+            ])],
+        );
+        spectrum.sources.insert(1, "src/t.c".to_string());
+        spectrum
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = spectrum();
+        let bytes = encode(&original, Compression::None).unwrap();
+        assert_eq!(&bytes[..8], b"FLDS\x02\x00\x00\x00");
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.passing.len(), 2);
+        assert_eq!(decoded.failing.len(), 1);
+        assert_eq!(decoded.sources, original.sources);
+        for (a, b) in decoded.statements.iter().zip(&original.statements) {
+            assert_eq!(
+                (a.key(), a.statement(), a.failed_tests, a.passed_tests),
+                (b.key(), b.statement(), b.failed_tests, b.passed_tests)
+            );
+        }
+        let counts = decode_counts(&bytes).unwrap();
+        assert!(counts.passing.is_empty());
+        assert_eq!(counts.sources, original.sources);
+        for (a, b) in counts.statements.iter().zip(&original.statements) {
+            assert_eq!(
+                (a.key(), a.failed_tests, a.passed_tests),
+                (b.key(), b.failed_tests, b.passed_tests)
+            );
+            assert_eq!((a.total_failed, a.total_passed), (1, 2));
+        }
+    }
+
+    #[test]
+    fn test_version_1_lines() {
+        // version 1 kept the position of the file above the lowest 20 bits
+        let mut old = spectrum();
+        old.statements[0].line_number += 1 << 20;
+        let mut bytes = encode(&old, Compression::None).unwrap();
+        bytes[4] = 1;
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.statements[0].line_number, 2);
+        assert_eq!(decoded.sources[&decoded.statements[0].file], "src/t.c");
+    }
+
+    #[test]
+    fn test_unknown_sections_are_skipped() {
+        let mut bytes = encode(&spectrum(), Compression::None).unwrap();
+        put_section(&mut bytes, b"XTRA", b"from a later release");
+        assert!(decode(&bytes).is_ok());
+    }
+
+    #[test]
+    fn test_rejected_snapshots() {
+        let bytes = encode(&spectrum(), Compression::None).unwrap();
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode(b"PK\x03\x04....").is_err());
+        let mut newer = bytes.clone();
+        newer[4] = 3;
+        assert!(decode(&newer).unwrap_err().contains("newer"));
+    }
+}
@@ -0,0 +1,700 @@
+use crate::columnar::{Column, DataType, Table};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// One cell of a table or a literal in a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Int(i64),
+    Float(f32),
+    Bool(bool),
+    Str(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Str(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl Value {
+    fn of(column: &Column, row: usize) -> Value {
+        let value = match column.data_type {
+            DataType::UInt32 => column.u32(row).map(|n| Value::Int(n as i64)),
+            DataType::UInt64 => column.u64(row).map(|n| Value::Int(n as i64)),
+            DataType::Float32 => column.f32(row).map(Value::Float),
+            DataType::Boolean => column.bool(row).map(Value::Bool),
+            DataType::Utf8 => column.str(row).map(|s| Value::Str(s.to_string())),
+        };
+        value.unwrap_or(Value::Null)
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::Float(x) => Some(*x as f64),
+            _ => None,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Int(_) | Value::Float(_) => "a number",
+            Value::Bool(_) => "a boolean",
+            Value::Str(_) => "text",
+        }
+    }
+
+    /// How two values compare, or an error for values of different types.
+    /// `None` when either is null, which SQL treats as unknown.
+    fn compare(&self, other: &Value) -> Result<Option<Ordering>, String> {
+        match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => Ok(None),
+            (Value::Str(a), Value::Str(b)) => Ok(Some(a.cmp(b))),
+            (Value::Bool(a), Value::Bool(b)) => Ok(Some(a.cmp(b))),
+            (a, b) => match (a.number(), b.number()) {
+                (Some(a), Some(b)) => Ok(a.partial_cmp(&b)),
+                _ => Err(format!(
+                    "cannot compare {} with {}",
+                    a.type_name(),
+                    b.type_name()
+                )),
+            },
+        }
+    }
+
+    /// The order rows are sorted in: nulls first, then booleans, numbers
+    /// and text.
+    fn sort_cmp(&self, other: &Value) -> Ordering {
+        let class = |value: &Value| match value {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Int(_) | Value::Float(_) => 2,
+            Value::Str(_) => 3,
+        };
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Str(a), Value::Str(b)) => a.cmp(b),
+            (a, b) => match (a.number(), b.number()) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                _ => class(a).cmp(&class(b)),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Column(String),
+    Literal(Value),
+    Compare(Box<Expr>, Op, Box<Expr>),
+    Like(Box<Expr>, String, bool),
+    IsNull(Box<Expr>, bool),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Select {
+    All,
+    Count,
+    Columns(Vec<String>),
+}
+
+/// A parsed `SELECT` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    select: Select,
+    table: String,
+    filter: Option<Expr>,
+    /// Columns to sort by, each with whether it is descending
+    order: Vec<(String, bool)>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A name or keyword, lowercased
+    Word(String),
+    Number(String),
+    Str(String),
+    Symbol(&'static str),
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars
+                .peek()
+                .filter(|(_, c)| c.is_alphanumeric() || *c == '_')
+            {
+                word.push(c.to_ascii_lowercase());
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&(_, c)) = chars
+                .peek()
+                .filter(|(_, c)| c.is_ascii_digit() || *c == '.')
+            {
+                number.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Number(number));
+        } else if c == '\'' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    // '' inside a string is a quote
+                    Some((_, '\'')) if chars.peek().is_some_and(|&(_, c)| c == '\'') => {
+                        text.push('\'');
+                        chars.next();
+                    }
+                    Some((_, '\'')) => break,
+                    Some((_, c)) => text.push(c),
+                    None => return Err(format!("unterminated string at {}", start + 1)),
+                }
+            }
+            tokens.push(Token::Str(text));
+        } else {
+            let rest = &sql[start..];
+            let symbol = [
+                "<=", ">=", "<>", "!=", "=", "<", ">", "(", ")", ",", "*", "-", ";",
+            ]
+            .into_iter()
+            .find(|symbol| rest.starts_with(symbol))
+            .ok_or_else(|| format!("unexpected '{}' at {}", c, start + 1))?;
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    /// Consume the keyword `word` if it comes next.
+    fn keyword(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(w)) if w == word);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn found(&self) -> String {
+        match self.peek() {
+            None => "the end of the query".to_string(),
+            Some(Token::Word(w)) => format!("'{}'", w),
+            Some(Token::Number(n)) => format!("'{}'", n),
+            Some(Token::Str(s)) => format!("'{}'", s),
+            Some(Token::Symbol(s)) => format!("'{}'", s),
+        }
+    }
+
+    fn expect_keyword(&mut self, word: &str) -> Result<(), String> {
+        match self.keyword(word) {
+            true => Ok(()),
+            false => Err(format!(
+                "expected {}, found {}",
+                word.to_uppercase(),
+                self.found()
+            )),
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        match self.symbol(symbol) {
+            true => Ok(()),
+            false => Err(format!("expected '{}', found {}", symbol, self.found())),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Word(w)) if !KEYWORDS.contains(&w.as_str()) => {
+                let name = w.clone();
+                self.next += 1;
+                Ok(name)
+            }
+            _ => Err(format!("expected a name, found {}", self.found())),
+        }
+    }
+
+    fn query(&mut self) -> Result<Query, String> {
+        self.expect_keyword("select")?;
+        let select = if self.symbol("*") {
+            Select::All
+        } else if self.keyword("count") {
+            self.expect_symbol("(")?;
+            self.expect_symbol("*")?;
+            self.expect_symbol(")")?;
+            Select::Count
+        } else {
+            let mut columns = vec![self.name()?];
+            while self.symbol(",") {
+                columns.push(self.name()?);
+            }
+            Select::Columns(columns)
+        };
+        self.expect_keyword("from")?;
+        let table = self.name()?;
+        let filter = match self.keyword("where") {
+            true => Some(self.or()?),
+            false => None,
+        };
+        let mut order = Vec::new();
+        if self.keyword("order") {
+            self.expect_keyword("by")?;
+            loop {
+                let column = self.name()?;
+                let descending = self.keyword("desc");
+                if !descending {
+                    self.keyword("asc");
+                }
+                order.push((column, descending));
+                if !self.symbol(",") {
+                    break;
+                }
+            }
+        }
+        let limit = match self.keyword("limit") {
+            true => match self.advance() {
+                Some(Token::Number(n)) => Some(
+                    n.parse::<usize>()
+                        .map_err(|_| format!("invalid LIMIT '{}'", n))?,
+                ),
+                _ => return Err("expected a number after LIMIT".to_string()),
+            },
+            false => None,
+        };
+        self.symbol(";");
+        if self.peek().is_some() {
+            return Err(format!("unexpected {}", self.found()));
+        }
+        Ok(Query {
+            select,
+            table,
+            filter,
+            order,
+            limit,
+        })
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        match self.keyword("not") {
+            true => Ok(Expr::Not(Box::new(self.not()?))),
+            false => self.predicate(),
+        }
+    }
+
+    fn predicate(&mut self) -> Result<Expr, String> {
+        let left = self.primary()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("=")) => Some(Op::Eq),
+            Some(Token::Symbol("!=")) | Some(Token::Symbol("<>")) => Some(Op::Ne),
+            Some(Token::Symbol("<")) => Some(Op::Lt),
+            Some(Token::Symbol("<=")) => Some(Op::Le),
+            Some(Token::Symbol(">")) => Some(Op::Gt),
+            Some(Token::Symbol(">=")) => Some(Op::Ge),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.next += 1;
+            return Ok(Expr::Compare(Box::new(left), op, Box::new(self.primary()?)));
+        }
+        if self.keyword("is") {
+            let negated = self.keyword("not");
+            self.expect_keyword("null")?;
+            return Ok(Expr::IsNull(Box::new(left), negated));
+        }
+        let negated = self.keyword("not");
+        if self.keyword("like") {
+            return match self.advance() {
+                Some(Token::Str(pattern)) => Ok(Expr::Like(Box::new(left), pattern, negated)),
+                _ => Err("expected a string after LIKE".to_string()),
+            };
+        }
+        if negated {
+            return Err(format!("expected LIKE after NOT, found {}", self.found()));
+        }
+        Ok(left)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        if self.symbol("(") {
+            let expr = self.or()?;
+            self.expect_symbol(")")?;
+            return Ok(expr);
+        }
+        let negative = self.symbol("-");
+        match self.peek().cloned() {
+            Some(Token::Number(n)) => {
+                self.next += 1;
+                let n = match negative {
+                    true => format!("-{}", n),
+                    false => n,
+                };
+                let value = match n.parse::<i64>() {
+                    Ok(n) => Value::Int(n),
+                    Err(_) => Value::Float(
+                        n.parse::<f32>()
+                            .map_err(|_| format!("invalid number '{}'", n))?,
+                    ),
+                };
+                Ok(Expr::Literal(value))
+            }
+            _ if negative => Err(format!(
+                "expected a number after '-', found {}",
+                self.found()
+            )),
+            Some(Token::Str(s)) => {
+                self.next += 1;
+                Ok(Expr::Literal(Value::Str(s)))
+            }
+            Some(Token::Word(w)) if w == "true" || w == "false" => {
+                self.next += 1;
+                Ok(Expr::Literal(Value::Bool(w == "true")))
+            }
+            Some(Token::Word(w)) if w == "null" => {
+                self.next += 1;
+                Ok(Expr::Literal(Value::Null))
+            }
+            _ => Ok(Expr::Column(self.name()?)),
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "select", "from", "where", "order", "by", "asc", "desc", "limit", "and", "or", "not", "is",
+    "null", "like", "true", "false", "count",
+];
+
+/// Parse a query of the form `SELECT columns FROM table [WHERE condition]
+/// [ORDER BY column [ASC|DESC], ...] [LIMIT n]`, where the columns are `*`,
+/// `COUNT(*)` or a list of names, and the condition compares columns and
+/// literals with `= != <> < <= > >=`, `LIKE`, `IS [NOT] NULL`, `AND`, `OR`
+/// and `NOT`. Keywords and names are case-insensitive.
+pub fn parse(sql: &str) -> Result<Query, String> {
+    let mut parser = Parser {
+        tokens: tokenize(sql)?,
+        next: 0,
+    };
+    parser.query()
+}
+
+/// Whether `text` matches a LIKE pattern, where `%` is any run of
+/// characters and `_` is any one.
+fn like(text: &[char], pattern: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('%', rest)) => (0..=text.len()).any(|skip| like(&text[skip..], rest)),
+        Some((&p, rest)) => text
+            .split_first()
+            .is_some_and(|(&c, text)| (p == '_' || p == c) && like(text, rest)),
+    }
+}
+
+fn truth(value: &Value) -> Result<Option<bool>, String> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Bool(b) => Ok(Some(*b)),
+        other => Err(format!("expected a condition, found {}", other.type_name())),
+    }
+}
+
+fn eval(expr: &Expr, table: &Table, row: usize) -> Result<Value, String> {
+    let condition = |truth: Option<bool>| truth.map_or(Value::Null, Value::Bool);
+    Ok(match expr {
+        Expr::Column(name) => Value::of(column(table, name)?, row),
+        Expr::Literal(value) => value.clone(),
+        Expr::Compare(left, op, right) => {
+            let ordering = eval(left, table, row)?.compare(&eval(right, table, row)?)?;
+            condition(ordering.map(|ordering| match op {
+                Op::Eq => ordering == Ordering::Equal,
+                Op::Ne => ordering != Ordering::Equal,
+                Op::Lt => ordering == Ordering::Less,
+                Op::Le => ordering != Ordering::Greater,
+                Op::Gt => ordering == Ordering::Greater,
+                Op::Ge => ordering != Ordering::Less,
+            }))
+        }
+        Expr::Like(operand, pattern, negated) => match eval(operand, table, row)? {
+            Value::Null => Value::Null,
+            Value::Str(text) => {
+                let text: Vec<char> = text.chars().collect();
+                let pattern: Vec<char> = pattern.chars().collect();
+                Value::Bool(like(&text, &pattern) != *negated)
+            }
+            other => return Err(format!("LIKE needs text, found {}", other.type_name())),
+        },
+        Expr::IsNull(operand, negated) => {
+            Value::Bool((eval(operand, table, row)? == Value::Null) != *negated)
+        }
+        // unknown and false is false, unknown or true is true
+        Expr::And(left, right) => {
+            match (
+                truth(&eval(left, table, row)?)?,
+                truth(&eval(right, table, row)?)?,
+            ) {
+                (Some(false), _) | (_, Some(false)) => Value::Bool(false),
+                (Some(true), Some(true)) => Value::Bool(true),
+                _ => Value::Null,
+            }
+        }
+        Expr::Or(left, right) => {
+            match (
+                truth(&eval(left, table, row)?)?,
+                truth(&eval(right, table, row)?)?,
+            ) {
+                (Some(true), _) | (_, Some(true)) => Value::Bool(true),
+                (Some(false), Some(false)) => Value::Bool(false),
+                _ => Value::Null,
+            }
+        }
+        Expr::Not(operand) => condition(truth(&eval(operand, table, row)?)?.map(|b| !b)),
+    })
+}
+
+fn column<'a>(table: &'a Table, name: &str) -> Result<&'a Column, String> {
+    table.column(name).ok_or_else(|| {
+        let names: Vec<&str> = table.fields.iter().map(|(name, _)| name.as_str()).collect();
+        format!(
+            "no column '{}' (expected one of {})",
+            name,
+            names.join(", ")
+        )
+    })
+}
+
+/// The result of a query: a header and rows of values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Run `query` against the named `tables`.
+pub fn run(query: &Query, tables: &[(&str, &Table)]) -> Result<Rows, String> {
+    let table = tables
+        .iter()
+        .find(|(name, _)| *name == query.table)
+        .map(|(_, table)| *table)
+        .ok_or_else(|| {
+            let names: Vec<&str> = tables.iter().map(|(name, _)| *name).collect();
+            format!(
+                "no table '{}' (expected one of {})",
+                query.table,
+                names.join(", ")
+            )
+        })?;
+    let mut matching = Vec::new();
+    for row in 0..table.rows() {
+        let keep = match &query.filter {
+            Some(filter) => truth(&eval(filter, table, row)?)? == Some(true),
+            None => true,
+        };
+        if keep {
+            matching.push(row);
+        }
+    }
+    if query.select == Select::Count {
+        return Ok(Rows {
+            columns: vec!["count".to_string()],
+            rows: vec![vec![Value::Int(matching.len() as i64)]],
+        });
+    }
+    let mut keys = Vec::new();
+    for (name, descending) in &query.order {
+        keys.push((column(table, name)?, *descending));
+    }
+    // stable, so ties keep the table's order
+    matching.sort_by(|&a, &b| {
+        keys.iter()
+            .map(|(column, descending)| {
+                let ordering = Value::of(column, a).sort_cmp(&Value::of(column, b));
+                match descending {
+                    true => ordering.reverse(),
+                    false => ordering,
+                }
+            })
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+    matching.truncate(query.limit.unwrap_or(usize::MAX));
+    let columns: Vec<(String, &Column)> = match &query.select {
+        Select::Columns(names) => names
+            .iter()
+            .map(|name| Ok((name.clone(), column(table, name)?)))
+            .collect::<Result<_, String>>()?,
+        _ => table
+            .fields
+            .iter()
+            .map(|(name, column)| (name.clone(), column))
+            .collect(),
+    };
+    Ok(Rows {
+        rows: matching
+            .iter()
+            .map(|&row| {
+                columns
+                    .iter()
+                    .map(|(_, column)| Value::of(column, row))
+                    .collect()
+            })
+            .collect(),
+        columns: columns.into_iter().map(|(name, _)| name).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        Table {
+            fields: vec![
+                (
+                    "file".to_string(),
+                    Column::from_strs([Some("a.c"), None, Some("b.c"), Some("a.c")]),
+                ),
+                ("line".to_string(), Column::from_u32([1, 2, 3, 4])),
+                (
+                    "score".to_string(),
+                    Column::from_f32([0.5, f32::INFINITY, 2.0, 0.5]),
+                ),
+                (
+                    "text".to_string(),
+                    Column::from_strs([Some("a();"), Some("it's"), Some("free(p);"), Some("}")]),
+                ),
+            ],
+        }
+    }
+
+    fn query(sql: &str) -> Result<Vec<Vec<String>>, String> {
+        let table = table();
+        let rows = run(&parse(sql)?, &[("t", &table)])?;
+        Ok(rows
+            .rows
+            .iter()
+            .map(|row| row.iter().map(Value::to_string).collect())
+            .collect())
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(
+            query("select line, score from t order by score desc, line limit 3").unwrap(),
+            [["2", "inf"], ["3", "2"], ["1", "0.5"]]
+        );
+        assert_eq!(
+            query("SELECT line FROM t WHERE file = 'a.c' AND NOT line > 3;").unwrap(),
+            [["1"]]
+        );
+        // null files are neither a.c nor not a.c
+        assert_eq!(
+            query("select line from t where file != 'a.c'").unwrap(),
+            [["3"]]
+        );
+        assert_eq!(
+            query("select line from t where file is null").unwrap(),
+            [["2"]]
+        );
+        assert_eq!(
+            query("select line from t where text like '%(%' or text = 'it''s'").unwrap(),
+            [["1"], ["2"], ["3"]]
+        );
+        assert_eq!(
+            query("select count(*) from t where score >= 0.5").unwrap(),
+            [["4"]]
+        );
+        assert_eq!(query("select * from t where line = -1").unwrap().len(), 0);
+        let table = table();
+        let rows = run(&parse("select * from t").unwrap(), &[("t", &table)]).unwrap();
+        assert_eq!(rows.columns, ["file", "line", "score", "text"]);
+        assert_eq!(rows.rows[1][0], Value::Null);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(query("select line from u")
+            .unwrap_err()
+            .contains("no table 'u'"));
+        assert!(query("select lines from t")
+            .unwrap_err()
+            .contains("no column 'lines'"));
+        assert!(query("select line from t where text > 1").is_err());
+        assert!(query("select line from t where line").is_err());
+        assert!(query("select line from t where").is_err());
+        assert!(query("select line from t limit x").is_err());
+        assert!(query("select line from t where text = 'a").is_err());
+        assert!(query("select line from t order line").is_err());
+        assert!(query("select line from t junk").is_err());
+    }
+}
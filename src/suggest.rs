@@ -0,0 +1,167 @@
+use crate::smoothing::Smoothing;
+use crate::techniques::Technique;
+use crate::{Coverage, LineInfo, Spectrum, StatementInfo};
+use std::collections::HashSet;
+
+/// How much running a candidate test is expected to sharpen the ranking.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestSuggestion {
+    pub name: String,
+    /// Chance the test fails, assuming a single fault distributed like the
+    /// current suspiciousness
+    pub failure_probability: f64,
+    /// Ranking entropy (in bits) expected after the test has run
+    pub expected_entropy: f64,
+    pub entropy_reduction: f64,
+}
+
+/// Suspiciousness normalized to a probability distribution over statements.
+/// Statements with infinite scores share all of the mass; if nothing scores
+/// above zero every statement is equally likely.
+pub fn fault_distribution(statements: &[StatementInfo]) -> Vec<f64> {
+    let infinite = statements
+        .iter()
+        .filter(|s| s.suspiciousness.is_infinite())
+        .count();
+    if infinite > 0 {
+        return statements
+            .iter()
+            .map(|s| match s.suspiciousness.is_infinite() {
+                true => 1.0 / infinite as f64,
+                false => 0.0,
+            })
+            .collect();
+    }
+    let weight = |s: &StatementInfo| match s.suspiciousness > 0.0 {
+        true => s.suspiciousness as f64,
+        false => 0.0,
+    };
+    let total = statements.iter().fold(0.0, |sum, s| sum + weight(s));
+    match total > 0.0 {
+        true => statements.iter().map(|s| weight(s) / total).collect(),
+        false => vec![1.0 / statements.len() as f64; statements.len()],
+    }
+}
+
+/// Shannon entropy of the fault distribution, in bits. It is highest when
+/// many statements are tied and zero when a single statement stands out.
+pub fn ranking_entropy(statements: &[StatementInfo]) -> f64 {
+    fault_distribution(statements)
+        .into_iter()
+        .filter(|p| *p > 0.0)
+        .fold(0.0, |h, p| h - p * p.log2())
+}
+
+/// Score tests that have not been run yet by the expected drop in ranking
+/// entropy, given the coverage each is expected to have. The outcome is
+/// unknown, so both are weighed: the test fails with the probability that
+/// the fault lies in what it covers. Best candidates come first.
+pub fn suggest_tests(
+    spectrum: &Spectrum,
+    candidates: &[(String, Vec<LineInfo>)],
+    technique: Technique,
+    smoothing: Smoothing,
+) -> Vec<TestSuggestion> {
+    let mut current = spectrum.clone();
+    current.score(technique, smoothing);
+    let entropy = ranking_entropy(&current.statements);
+    let distribution = fault_distribution(&current.statements);
+    let mut suggestions: Vec<TestSuggestion> = candidates
+        .iter()
+        .map(|(name, coverage)| {
+            let test = align_to_statements(&current.statements, coverage);
+            let failure_probability = test
+                .iter()
+                .zip(&distribution)
+                .filter(|(line, _)| line.coverage == Coverage::Covered)
+                .fold(0.0, |sum, (_, p)| sum + p);
+            let entropy_after = |is_passing: bool| {
+                let mut after = current.clone();
+                after.add_test(test.clone(), is_passing);
+                after.score(technique, smoothing);
+                ranking_entropy(&after.statements)
+            };
+            let expected_entropy = failure_probability * entropy_after(false)
+                + (1.0 - failure_probability) * entropy_after(true);
+            TestSuggestion {
+                name: name.clone(),
+                failure_probability,
+                expected_entropy,
+                entropy_reduction: entropy - expected_entropy,
+            }
+        })
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.entropy_reduction
+            .total_cmp(&a.entropy_reduction)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    suggestions
+}
+
+/// Lay a candidate's coverage out like the spectrum's statements; lines the
+/// candidate doesn't mention are not covered.
+fn align_to_statements(statements: &[StatementInfo], coverage: &[LineInfo]) -> Vec<LineInfo> {
+    let covered: HashSet<u64> = crate::covered_set(coverage);
+    statements
+        .iter()
+        .map(|s| {
+            let is_covered = covered.contains(&s.key());
+            LineInfo {
+                file: s.file,
+                line_number: s.line_number,
+                statement: s.statement().to_string(),
+                coverage: match is_covered {
+                    true => Coverage::Covered,
+                    false => Coverage::NotCovered,
+                },
+                execution_count: is_covered as u64,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_case(covered: &[u32]) -> Vec<LineInfo> {
+        (1..=4)
+            .map(|line_number| LineInfo {
+                file: 0,
+                line_number,
+                statement: format!("s{}", line_number),
+                coverage: match covered.contains(&line_number) {
+                    true => Coverage::Covered,
+                    false => Coverage::NotCovered,
+                },
+                execution_count: covered.contains(&line_number) as u64,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_suggest_tests_prefers_splitting_ties() {
+        // lines 1 and 2 are tied at the top
+        let spectrum = Spectrum::new(vec![test_case(&[3, 4])], vec![test_case(&[1, 2])]);
+        let candidates = vec![
+            ("both".to_string(), test_case(&[1, 2])),
+            ("split".to_string(), test_case(&[1])),
+            ("elsewhere".to_string(), test_case(&[4])),
+        ];
+        let suggestions =
+            suggest_tests(&spectrum, &candidates, Technique::Formula, Smoothing::None);
+        assert_eq!(suggestions[0].name, "split");
+        assert!((suggestions[0].failure_probability - 0.5).abs() < 1e-9);
+        assert!(suggestions[0].entropy_reduction > 0.0);
+        let both = suggestions.iter().find(|s| s.name == "both").unwrap();
+        assert!(both.entropy_reduction.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ranking_entropy() {
+        let mut spectrum = Spectrum::new(vec![test_case(&[3, 4])], vec![test_case(&[1, 2])]);
+        spectrum.score(Technique::Formula, Smoothing::None);
+        assert!((ranking_entropy(&spectrum.statements) - 1.0).abs() < 1e-9);
+    }
+}
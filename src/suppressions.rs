@@ -0,0 +1,212 @@
+use crate::StatementInfo;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Name of the suppression file looked for in the project root.
+pub const FILE_NAME: &str = ".fl-dstar-suppressions";
+
+/// A calendar date, ordered chronologically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Today in UTC.
+    pub fn today() -> Date {
+        let seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Date::from_days((seconds / 86400) as i64)
+    }
+
+    /// The date `days` after 1970-01-01 (Howard Hinnant's civil_from_days).
+    pub(crate) fn from_days(days: i64) -> Date {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (year_of_era + era * 400 + (month <= 2) as i64) as i32;
+        Date { year, month, day }
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Accepts `YYYY-MM-DD`.
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid date '{}' (expected YYYY-MM-DD)", s);
+        let parts: Vec<&str> = s.split('-').collect();
+        let [year, month, day] = parts.as_slice() else {
+            return Err(invalid());
+        };
+        let date = Date {
+            year: year.parse().map_err(|_| invalid())?,
+            month: month.parse().map_err(|_| invalid())?,
+            day: day.parse().map_err(|_| invalid())?,
+        };
+        match (1..=12).contains(&date.month) && (1..=31).contains(&date.day) {
+            true => Ok(date),
+            false => Err(invalid()),
+        }
+    }
+}
+
+/// A line triaged as innocent, hidden from reports until it expires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suppression {
+    pub path: String,
+    pub line_number: u32,
+    /// Last day the suppression applies
+    pub expires: Option<Date>,
+}
+
+impl Suppression {
+    pub fn is_active(&self, today: Date) -> bool {
+        self.expires.is_none_or(|expires| today <= expires)
+    }
+}
+
+/// Parse a suppression file: one `path:line` per line, optionally followed
+/// by the date it expires. `#` starts a comment, which is a good place for
+/// the reason.
+///
+/// ```text
+/// # parser init, not the bug (JIRA-123)
+/// src/parser.c:42
+/// src/lexer.c:7 2024-06-30  # until the lexer rewrite lands
+/// ```
+pub fn parse(text: &str) -> Result<Vec<Suppression>, String> {
+    let mut suppressions = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: String| format!("line {}: {}", i + 1, message);
+        let mut fields = line.split_whitespace();
+        let location = fields.next().unwrap_or("");
+        // rsplit, as Windows paths have a colon of their own
+        let (path, line_number) = location
+            .rsplit_once(':')
+            .and_then(|(path, n)| Some((path, n.parse().ok()?)))
+            .ok_or_else(|| error(format!("expected path:line, found '{}'", location)))?;
+        let expires = fields.next().map(str::parse).transpose().map_err(error)?;
+        if let Some(extra) = fields.next() {
+            return Err(error(format!("unexpected '{}'", extra)));
+        }
+        suppressions.push(Suppression {
+            path: path.replace('\\', "/"),
+            line_number,
+            expires,
+        });
+    }
+    Ok(suppressions)
+}
+
+/// Read `path`, or the suppression file in `root` when no path is given.
+/// A missing default file is the same as an empty one.
+pub fn load(path: Option<&Path>, root: Option<&Path>) -> Result<Vec<Suppression>, String> {
+    let (path, required) = match (path, root) {
+        (Some(path), _) => (path.to_path_buf(), true),
+        (None, Some(root)) => (root.join(FILE_NAME), false),
+        (None, None) => (Path::new(FILE_NAME).to_path_buf(), false),
+    };
+    if !required && !path.exists() {
+        return Ok(Vec::new());
+    }
+    let text =
+        crate::encoding::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Whether an active suppression covers `statement`.
+pub fn is_suppressed(
+    statement: &StatementInfo,
+    sources: &HashMap<u32, String>,
+    suppressions: &[Suppression],
+    today: Date,
+) -> bool {
+    suppressions.iter().any(|suppression| {
+        suppression.is_active(today)
+            && suppression.line_number == statement.line_number
+            && sources.get(&statement.file) == Some(&suppression.path)
+    })
+}
+
+/// `statements` without the ones an active suppression covers.
+pub fn filter(
+    statements: &[StatementInfo],
+    sources: &HashMap<u32, String>,
+    suppressions: &[Suppression],
+    today: Date,
+) -> Vec<StatementInfo> {
+    statements
+        .iter()
+        .filter(|statement| !is_suppressed(statement, sources, suppressions, today))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let suppressions =
+            parse("# known\nsrc/a.c:4\n\nC:\\src\\b.c:10 2024-06-30 # until the rewrite\n")
+                .unwrap();
+        assert_eq!(suppressions.len(), 2);
+        assert_eq!(suppressions[0].expires, None);
+        assert_eq!(suppressions[1].path, "C:/src/b.c");
+        assert_eq!(suppressions[1].line_number, 10);
+        let expires = suppressions[1].expires.unwrap();
+        assert_eq!(expires.to_string(), "2024-06-30");
+        assert!(suppressions[1].is_active(expires));
+        assert!(!suppressions[1].is_active("2024-07-01".parse().unwrap()));
+        assert!(parse("src/a.c").unwrap_err().starts_with("line 1:"));
+        assert!(parse("src/a.c:4 tomorrow").is_err());
+    }
+
+    #[test]
+    fn test_filter() {
+        let statements: Vec<StatementInfo> = [4, 5]
+            .iter()
+            .map(|&line| {
+                let mut statement = StatementInfo::new(line, "x();".to_string(), 1);
+                statement.file = 1;
+                statement
+            })
+            .collect();
+        let sources = HashMap::from([(1, "src/a.c".to_string())]);
+        let suppressions = parse("src/a.c:4\nsrc/b.c:5\n").unwrap();
+        let today = Date::today();
+        let kept = filter(&statements, &sources, &suppressions, today);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].line_number, 5);
+    }
+
+    #[test]
+    fn test_from_days() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(19_783).to_string(), "2024-03-01");
+        assert_eq!(Date::from_days(-1).to_string(), "1969-12-31");
+    }
+}
@@ -0,0 +1,240 @@
+use crate::formats::SourceCoverage;
+use crate::{Coverage, LineInfo};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Read an address trace: hexadecimal addresses (with or without `0x`)
+/// separated by whitespace, with `#` starting a comment.
+pub fn parse_addresses(text: &str) -> Result<Vec<u64>, String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(str::split_whitespace)
+        .map(|word| {
+            let digits = word.trim_start_matches("0x").trim_start_matches("0X");
+            u64::from_str_radix(digits, 16).map_err(|_| format!("invalid address '{}'", word))
+        })
+        .collect()
+}
+
+const SANCOV_MAGIC_64: u64 = 0xC0BF_FFFF_FFFF_FF64;
+const SANCOV_MAGIC_32: u64 = 0xC0BF_FFFF_FFFF_FF32;
+
+/// Decode a SanitizerCoverage `.sancov` dump: a little-endian magic number
+/// that gives the PC width, followed by the PCs. Returns `None` if the data
+/// is not a `.sancov` file.
+///
+/// The recorded PCs are return addresses of the coverage callbacks, so like
+/// `sancov` itself each one is moved back one byte into the call instruction
+/// before it is symbolized.
+pub fn parse_sancov(data: &[u8]) -> Option<Result<Vec<u64>, String>> {
+    let magic = u64::from_le_bytes(data.get(..8)?.try_into().ok()?);
+    let width = match magic {
+        SANCOV_MAGIC_64 => 8,
+        SANCOV_MAGIC_32 => 4,
+        _ => return None,
+    };
+    let body = &data[8..];
+    if !body.len().is_multiple_of(width) {
+        return Some(Err(format!(
+            "truncated .sancov file: {} bytes of {}-byte PCs",
+            body.len(),
+            width
+        )));
+    }
+    let pcs = body
+        .chunks_exact(width)
+        .map(|chunk| match width {
+            8 => u64::from_le_bytes(chunk.try_into().unwrap()),
+            _ => u32::from_le_bytes(chunk.try_into().unwrap()) as u64,
+        })
+        .map(|pc| pc.saturating_sub(1))
+        .collect();
+    Some(Ok(pcs))
+}
+
+/// Parse one line of addr2line output, `file:line` optionally followed by
+/// ` (discriminator N)`. Unknown locations (`??:0`, `file:?`) give `None`.
+fn parse_location(line: &str) -> Option<(String, u32)> {
+    let line = line.split(" (discriminator").next()?.trim();
+    let (file, number) = line.rsplit_once(':')?;
+    let number = number.parse::<u32>().ok()?;
+    if file == "??" || number == 0 {
+        return None;
+    }
+    Some((file.to_string(), number))
+}
+
+/// Resolve addresses to source locations with addr2line, which must be on
+/// the `PATH` (or named by the `ADDR2LINE` environment variable; llvm-addr2line
+/// works too). The binary needs debug info.
+pub fn addr2line(
+    binary: &std::path::Path,
+    addresses: &[u64],
+) -> std::io::Result<Vec<Option<(String, u32)>>> {
+    let program = std::env::var("ADDR2LINE").unwrap_or_else(|_| "addr2line".to_string());
+    let mut child = Command::new(program)
+        .arg("-e")
+        .arg(binary)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        for address in addresses {
+            writeln!(stdin, "0x{:x}", address)?;
+        }
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "addr2line exited with {}",
+            output.status
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(parse_location)
+        .collect())
+}
+
+/// Group resolved locations into per-file coverage. Every location counts as
+/// one execution of its line; the source text is filled in when the file can
+/// be read.
+pub fn coverage_from_locations(locations: &[Option<(String, u32)>]) -> Vec<SourceCoverage> {
+    coverage_from_counts(locations, &vec![1; locations.len()])
+}
+
+/// Like [`coverage_from_locations`], with `counts[i]` executions of
+/// `locations[i]`. Lines whose counts are all zero are not covered.
+pub fn coverage_from_counts(
+    locations: &[Option<(String, u32)>],
+    counts: &[u64],
+) -> Vec<SourceCoverage> {
+    let mut files: BTreeMap<&str, BTreeMap<u32, u64>> = BTreeMap::new();
+    for (location, count) in locations.iter().zip(counts) {
+        if let Some((file, line)) = location {
+            *files.entry(file).or_default().entry(*line).or_insert(0) += count;
+        }
+    }
+    files
+        .into_iter()
+        .map(|(file, lines)| {
+            let source_lines: Vec<String> = crate::encoding::read_to_string(file.as_ref())
+                .map(|text| text.lines().map(|l| l.trim().to_string()).collect())
+                .unwrap_or_default();
+            SourceCoverage {
+                source: Some(file.to_string()),
+                lines: lines
+                    .into_iter()
+                    .map(|(line_number, execution_count)| LineInfo {
+                        file: 0,
+                        line_number,
+                        statement: source_lines
+                            .get(line_number as usize - 1)
+                            .cloned()
+                            .unwrap_or_default(),
+                        coverage: match execution_count {
+                            0 => Coverage::NotCovered,
+                            _ => Coverage::Covered,
+                        },
+                        execution_count,
+                    })
+                    .collect(),
+                skipped: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+/// Read an address trace for one test, either a `.sancov` dump, counters
+/// dumped by a target (see [`crate::embedded`]) or a text list of
+/// addresses, and symbolize it against `binary`. `symbols`, from a linker
+/// map, resolves counters named by symbol.
+pub fn read_address_trace(
+    path: &std::path::Path,
+    binary: &std::path::Path,
+    symbols: Option<&HashMap<String, u64>>,
+) -> Result<Vec<SourceCoverage>, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let text = String::from_utf8_lossy(&data);
+    let (addresses, counts): (Vec<u64>, Vec<u64>) = match parse_sancov(&data) {
+        Some(pcs) => pcs?.into_iter().map(|pc| (pc, 1)).unzip(),
+        None => match crate::embedded::parse_counter_dump(&text) {
+            Some(counters) => crate::embedded::resolve(&counters?, symbols)?
+                .into_iter()
+                .unzip(),
+            None => parse_addresses(&text)?.into_iter().map(|a| (a, 1)).unzip(),
+        },
+    };
+    let locations = addr2line(binary, &addresses).map_err(|e| e.to_string())?;
+    Ok(coverage_from_counts(&locations, &counts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addresses() {
+        assert_eq!(
+            parse_addresses("0x401136\n401140 0X10 # comment 0xdead\n").unwrap(),
+            vec![0x401136, 0x401140, 0x10]
+        );
+        assert!(parse_addresses("0xzz").is_err());
+    }
+
+    #[test]
+    fn test_parse_sancov() {
+        let mut data = SANCOV_MAGIC_64.to_le_bytes().to_vec();
+        data.extend_from_slice(&0x1137u64.to_le_bytes());
+        data.extend_from_slice(&0x1143u64.to_le_bytes());
+        assert_eq!(parse_sancov(&data), Some(Ok(vec![0x1136, 0x1142])));
+
+        let mut data = SANCOV_MAGIC_32.to_le_bytes().to_vec();
+        data.extend_from_slice(&0x20u32.to_le_bytes());
+        assert_eq!(parse_sancov(&data), Some(Ok(vec![0x1f])));
+
+        data.push(0);
+        assert!(matches!(parse_sancov(&data), Some(Err(_))));
+        assert_eq!(parse_sancov(b"0x1129\n"), None);
+    }
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(
+            parse_location("/src/tcas.c:77 (discriminator 2)"),
+            Some(("/src/tcas.c".to_string(), 77))
+        );
+        assert_eq!(parse_location("??:0"), None);
+        assert_eq!(parse_location("/src/tcas.c:?"), None);
+    }
+
+    #[test]
+    fn test_align_tests() {
+        let locations = vec![
+            Some(("t.c".to_string(), 3)),
+            Some(("t.c".to_string(), 3)),
+            None,
+        ];
+        let first = coverage_from_locations(&locations).remove(0).lines;
+        assert_eq!(first[0].execution_count, 2);
+        let second = coverage_from_locations(&[Some(("t.c".to_string(), 5))])
+            .remove(0)
+            .lines;
+        let mut tests = vec![first, second];
+        crate::align_tests(&mut tests);
+        let coverage: Vec<Vec<(u32, bool)>> = tests
+            .iter()
+            .map(|t| {
+                t.iter()
+                    .map(|l| (l.line_number, l.coverage == Coverage::Covered))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            coverage,
+            vec![vec![(3, true), (5, false)], vec![(3, false), (5, true)]]
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use crate::{covered_set, LineInfo, StatementInfo};
+use std::collections::HashSet;
+
+/// How statements are ranked once the coverage of every test has been read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Technique {
+    /// Score each statement with a suspiciousness formula (D* by default)
+    Formula,
+    /// Renieris and Reiss' nearest neighbor: compare each failing test with
+    /// the most similar passing test
+    NearestNeighbor,
+    /// Statements covered by every failing test and by no passing test
+    Union,
+    /// Statements covered by every passing test and by no failing test
+    Intersection,
+    /// Barinel's Bayesian ranking of single- and double-fault candidates,
+    /// scoring each statement with its posterior probability of being faulty
+    BarinelBayes,
+}
+
+impl std::str::FromStr for Technique {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "formula" => Ok(Technique::Formula),
+            "nearest-neighbor" | "nn" => Ok(Technique::NearestNeighbor),
+            "union" => Ok(Technique::Union),
+            "intersection" => Ok(Technique::Intersection),
+            "barinel-bayes" | "barinel" => Ok(Technique::BarinelBayes),
+            _ => Err(format!(
+                "unknown technique '{}' (expected formula, nearest-neighbor, union, intersection or barinel-bayes)",
+                s
+            )),
+        }
+    }
+}
+
+/// Score statements with the nearest neighbor technique. For every failing
+/// test the passing test with the smallest Hamming distance between their
+/// coverage is chosen, and the statements covered only by the failing test
+/// are reported. A statement's score is the fraction of failing tests whose
+/// difference contains it.
+pub fn nearest_neighbor(
+    statements: &mut [StatementInfo],
+    passing: &[Vec<LineInfo>],
+    failing: &[Vec<LineInfo>],
+) {
+    let passing_sets: Vec<HashSet<u64>> = passing.iter().map(|t| covered_set(t)).collect();
+    let mut hits = vec![0u32; statements.len()];
+    for failing_test in failing {
+        let failing_set = covered_set(failing_test);
+        let nearest = passing_sets
+            .iter()
+            .min_by_key(|passing_set| failing_set.symmetric_difference(passing_set).count());
+        let empty = HashSet::new();
+        let nearest = nearest.unwrap_or(&empty);
+        for (i, statement) in statements.iter().enumerate() {
+            if failing_set.contains(&statement.key()) && !nearest.contains(&statement.key()) {
+                hits[i] += 1;
+            }
+        }
+    }
+    for (statement, hit) in statements.iter_mut().zip(hits) {
+        statement.suspiciousness = match failing.len() {
+            0 => 0.0,
+            n => hit as f32 / n as f32,
+        };
+    }
+}
+
+fn all_covered(tests: &[Vec<LineInfo>]) -> HashSet<u64> {
+    let mut sets = tests.iter().map(|t| covered_set(t));
+    let first = sets.next().unwrap_or_default();
+    sets.fold(first, |acc, set| &acc & &set)
+}
+
+fn any_covered(tests: &[Vec<LineInfo>]) -> HashSet<u64> {
+    tests
+        .iter()
+        .fold(HashSet::new(), |acc, t| &acc | &covered_set(t))
+}
+
+fn score_membership(statements: &mut [StatementInfo], members: &HashSet<u64>) {
+    for statement in statements.iter_mut() {
+        statement.suspiciousness = match members.contains(&statement.key()) {
+            true => 1.0,
+            false => 0.0,
+        };
+    }
+}
+
+/// Union model: flag statements executed by all failing tests that no
+/// passing test executed.
+pub fn union(
+    statements: &mut [StatementInfo],
+    passing: &[Vec<LineInfo>],
+    failing: &[Vec<LineInfo>],
+) {
+    let members = &all_covered(failing) - &any_covered(passing);
+    score_membership(statements, &members);
+}
+
+/// Intersection model: flag statements executed by all passing tests that no
+/// failing test executed, pointing at code the failing runs skipped.
+pub fn intersection(
+    statements: &mut [StatementInfo],
+    passing: &[Vec<LineInfo>],
+    failing: &[Vec<LineInfo>],
+) {
+    let members = &all_covered(passing) - &any_covered(failing);
+    score_membership(statements, &members);
+}
+
+/// Prior probability that any one statement is faulty, used to weigh
+/// double-fault candidates against single-fault ones.
+const BARINEL_PRIOR: f64 = 0.01;
+
+/// Double-fault candidates are only enumerated when at most this many
+/// statements could take part in one, to keep the search quadratic but small.
+const BARINEL_MAX_PAIR_COMPONENTS: usize = 500;
+
+/// Log-likelihood of the observed outcomes if exactly the statements in
+/// `candidate` are faulty and behave correctly with probability `health`.
+fn barinel_log_likelihood(
+    candidate: &[u64],
+    health: &[f64],
+    passing: &[HashSet<u64>],
+    failing: &[HashSet<u64>],
+) -> f64 {
+    let pass_probability = |test: &HashSet<u64>| -> f64 {
+        candidate
+            .iter()
+            .zip(health)
+            .filter(|(line, _)| test.contains(line))
+            .map(|(_, h)| h)
+            .product()
+    };
+    let passing: f64 = passing.iter().map(|test| pass_probability(test).ln()).sum();
+    let failing: f64 = failing
+        .iter()
+        .map(|test| (1.0 - pass_probability(test)).ln())
+        .sum();
+    passing + failing
+}
+
+/// Maximum likelihood of a candidate, with each statement's health found by
+/// coordinate-wise grid search.
+fn barinel_max_likelihood(
+    candidate: &[u64],
+    passing: &[HashSet<u64>],
+    failing: &[HashSet<u64>],
+) -> f64 {
+    let mut health = vec![0.5; candidate.len()];
+    let mut best = barinel_log_likelihood(candidate, &health, passing, failing);
+    for _ in 0..4 {
+        for i in 0..candidate.len() {
+            for step in 0..=100 {
+                let mut trial = health.clone();
+                trial[i] = step as f64 / 100.0;
+                let value = barinel_log_likelihood(candidate, &trial, passing, failing);
+                if value > best {
+                    best = value;
+                    health = trial;
+                }
+            }
+        }
+    }
+    best.exp()
+}
+
+/// Score statements with Barinel's Bayesian diagnosis. Candidates are the
+/// statements that explain every failing test on their own, plus pairs that
+/// only explain them together. Each candidate's posterior is its prior times
+/// the likelihood of the observations under maximum likelihood health
+/// estimates, and a statement's score is the total posterior of the
+/// candidates containing it, so scores are probabilities summing to one.
+pub fn barinel_bayes(
+    statements: &mut [StatementInfo],
+    passing: &[Vec<LineInfo>],
+    failing: &[Vec<LineInfo>],
+) {
+    let passing: Vec<HashSet<u64>> = passing.iter().map(|t| covered_set(t)).collect();
+    let failing: Vec<HashSet<u64>> = failing.iter().map(|t| covered_set(t)).collect();
+    let components: Vec<u64> = statements
+        .iter()
+        .map(|s| s.key())
+        .filter(|line| failing.iter().any(|test| test.contains(line)))
+        .collect();
+    let explains = |candidate: &[u64]| {
+        failing
+            .iter()
+            .all(|test| candidate.iter().any(|line| test.contains(line)))
+    };
+    let mut candidates: Vec<(Vec<u64>, f64)> = Vec::new();
+    let (singles, others): (Vec<u64>, Vec<u64>) =
+        components.iter().partition(|&&line| explains(&[line]));
+    for line in singles {
+        let likelihood = barinel_max_likelihood(&[line], &passing, &failing);
+        candidates.push((vec![line], BARINEL_PRIOR * likelihood));
+    }
+    if others.len() <= BARINEL_MAX_PAIR_COMPONENTS {
+        for (i, &first) in others.iter().enumerate() {
+            for &second in &others[i + 1..] {
+                let pair = [first, second];
+                if !explains(&pair) {
+                    continue;
+                }
+                let likelihood = barinel_max_likelihood(&pair, &passing, &failing);
+                candidates.push((pair.to_vec(), BARINEL_PRIOR * BARINEL_PRIOR * likelihood));
+            }
+        }
+    }
+    let total: f64 = candidates.iter().map(|(_, posterior)| posterior).sum();
+    for statement in statements.iter_mut() {
+        let mass: f64 = candidates
+            .iter()
+            .filter(|(candidate, _)| candidate.contains(&statement.key()))
+            .fold(0.0, |acc, (_, posterior)| acc + posterior);
+        statement.suspiciousness = match total > 0.0 {
+            true => (mass / total) as f32,
+            false => 0.0,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Coverage;
+
+    fn test_case(covered: &[u32]) -> Vec<LineInfo> {
+        (1..=4)
+            .map(|line_number| {
+                let is_covered = covered.contains(&line_number);
+                LineInfo {
+                    file: 0,
+                    line_number,
+                    statement: String::new(),
+                    coverage: if is_covered {
+                        Coverage::Covered
+                    } else {
+                        Coverage::NotCovered
+                    },
+                    execution_count: is_covered as u64,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nearest_neighbor() {
+        let mut statements: Vec<StatementInfo> = (1..=4)
+            .map(|n| StatementInfo::new(n, String::new(), 1))
+            .collect();
+        // the second passing test is closest to the failing one and differs only on line 3
+        let passing = vec![test_case(&[4]), test_case(&[1, 2])];
+        let failing = vec![test_case(&[1, 2, 3])];
+        nearest_neighbor(&mut statements, &passing, &failing);
+        let scores: Vec<f32> = statements.iter().map(|s| s.suspiciousness).collect();
+        assert_eq!(scores, vec![0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_barinel_bayes_single_fault() {
+        let mut statements: Vec<StatementInfo> = (1..=4)
+            .map(|n| StatementInfo::new(n, String::new(), 1))
+            .collect();
+        let passing = vec![test_case(&[1, 2])];
+        let failing = vec![test_case(&[1, 2, 3])];
+        barinel_bayes(&mut statements, &passing, &failing);
+        // lines 1 and 2 each have likelihood 0.25 (h = 0.5), line 3 has likelihood 1
+        let scores: Vec<f32> = statements.iter().map(|s| s.suspiciousness).collect();
+        assert!((scores[0] - 1.0 / 6.0).abs() < 1e-4);
+        assert!((scores[1] - 1.0 / 6.0).abs() < 1e-4);
+        assert!((scores[2] - 2.0 / 3.0).abs() < 1e-4);
+        assert_eq!(scores[3], 0.0);
+    }
+
+    #[test]
+    fn test_barinel_bayes_double_fault() {
+        let mut statements: Vec<StatementInfo> = (1..=4)
+            .map(|n| StatementInfo::new(n, String::new(), 2))
+            .collect();
+        // no single statement explains both failures, so only the pair {1, 3} remains
+        let passing = vec![test_case(&[4])];
+        let failing = vec![test_case(&[1]), test_case(&[3])];
+        barinel_bayes(&mut statements, &passing, &failing);
+        let scores: Vec<f32> = statements.iter().map(|s| s.suspiciousness).collect();
+        assert_eq!(scores, vec![1.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_union_and_intersection() {
+        let mut statements: Vec<StatementInfo> = (1..=4)
+            .map(|n| StatementInfo::new(n, String::new(), 2))
+            .collect();
+        let passing = vec![test_case(&[1, 4]), test_case(&[1, 2, 4])];
+        let failing = vec![test_case(&[1, 2, 3]), test_case(&[1, 3])];
+        union(&mut statements, &passing, &failing);
+        let scores: Vec<f32> = statements.iter().map(|s| s.suspiciousness).collect();
+        assert_eq!(scores, vec![0.0, 0.0, 1.0, 0.0]);
+        intersection(&mut statements, &passing, &failing);
+        let scores: Vec<f32> = statements.iter().map(|s| s.suspiciousness).collect();
+        assert_eq!(scores, vec![0.0, 0.0, 0.0, 1.0]);
+    }
+}
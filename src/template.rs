@@ -0,0 +1,312 @@
+use crate::json::Json;
+
+/// Render a report template against `context`. The syntax is the common
+/// subset of Tera and Jinja2, so simple templates work with either:
+///
+/// - `{{ statement.line_number }}` prints a value; `| escape` escapes it for
+///   HTML, `| safe` leaves it as is when `autoescape` is on, and `| upper` /
+///   `| lower` change its case
+/// - `{% for s in statements %}...{% endfor %}`, with `loop.index` (from 1),
+///   `loop.first` and `loop.last`
+/// - `{% if failing_tests %}...{% else %}...{% endif %}`, where `not` negates
+///   and null, false, 0, "" and empty lists are false
+/// - `{# comments #}`
+pub fn render(template: &str, context: &Json, autoescape: bool) -> Result<String, String> {
+    let tokens = tokenize(template)?;
+    let mut position = 0;
+    let nodes = parse(&tokens, &mut position, &[])?;
+    let mut out = String::new();
+    let mut scope = vec![("".to_string(), context.clone())];
+    render_nodes(&nodes, &mut scope, autoescape, &mut out)?;
+    Ok(out)
+}
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Expression(String),
+    Tag(String),
+}
+
+fn tokenize(template: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let closing = match rest[start..].chars().nth(1) {
+            Some('{') => "}}",
+            Some('%') => "%}",
+            Some('#') => "#}",
+            _ => {
+                tokens.push(Token::Text(rest[..start + 1].to_string()));
+                rest = &rest[start + 1..];
+                continue;
+            }
+        };
+        if start > 0 {
+            tokens.push(Token::Text(rest[..start].to_string()));
+        }
+        let body_start = start + 2;
+        let end = rest[body_start..]
+            .find(closing)
+            .ok_or_else(|| format!("unclosed '{}'", &rest[start..body_start]))?
+            + body_start;
+        let body = rest[body_start..end].trim().to_string();
+        match closing {
+            "}}" => tokens.push(Token::Expression(body)),
+            "%}" => tokens.push(Token::Tag(body)),
+            _ => {}
+        }
+        rest = &rest[end + 2..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest.to_string()));
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Value {
+        path: String,
+        filters: Vec<String>,
+    },
+    For {
+        variable: String,
+        path: String,
+        body: Vec<Node>,
+    },
+    If {
+        path: String,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// Parse nodes until one of the `until` tags, which is left unconsumed.
+fn parse(tokens: &[Token], position: &mut usize, until: &[&str]) -> Result<Vec<Node>, String> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.get(*position) {
+        match token {
+            Token::Text(text) => nodes.push(Node::Text(text.clone())),
+            Token::Expression(expression) => {
+                let mut parts = expression.split('|').map(|part| part.trim().to_string());
+                let path = parts.next().unwrap_or_default();
+                nodes.push(Node::Value {
+                    path,
+                    filters: parts.collect(),
+                });
+            }
+            Token::Tag(tag) => {
+                let words: Vec<&str> = tag.split_whitespace().collect();
+                match words.as_slice() {
+                    [word, ..] if until.contains(word) => return Ok(nodes),
+                    ["for", variable, "in", path] => {
+                        *position += 1;
+                        let body = parse(tokens, position, &["endfor"])?;
+                        expect_tag(tokens, *position, "endfor")?;
+                        nodes.push(Node::For {
+                            variable: variable.to_string(),
+                            path: path.to_string(),
+                            body,
+                        });
+                    }
+                    ["if", condition @ ..] => {
+                        let (negate, path) = match condition {
+                            ["not", path] => (true, path),
+                            [path] => (false, path),
+                            _ => return Err(format!("unsupported condition in {{% {} %}}", tag)),
+                        };
+                        *position += 1;
+                        let then = parse(tokens, position, &["else", "endif"])?;
+                        let otherwise = match tokens.get(*position) {
+                            Some(Token::Tag(tag)) if tag == "else" => {
+                                *position += 1;
+                                parse(tokens, position, &["endif"])?
+                            }
+                            _ => Vec::new(),
+                        };
+                        expect_tag(tokens, *position, "endif")?;
+                        nodes.push(Node::If {
+                            path: path.to_string(),
+                            negate,
+                            then,
+                            otherwise,
+                        });
+                    }
+                    _ => return Err(format!("unsupported tag {{% {} %}}", tag)),
+                }
+            }
+        }
+        *position += 1;
+    }
+    match until.last() {
+        Some(tag) => Err(format!("missing {{% {} %}}", tag)),
+        None => Ok(nodes),
+    }
+}
+
+fn expect_tag(tokens: &[Token], position: usize, tag: &str) -> Result<(), String> {
+    match tokens.get(position) {
+        Some(Token::Tag(found)) if found == tag => Ok(()),
+        _ => Err(format!("missing {{% {} %}}", tag)),
+    }
+}
+
+/// Look up a dotted path, innermost loop variable first.
+fn lookup(path: &str, scope: &[(String, Json)]) -> Option<Json> {
+    let mut parts = path.split('.');
+    let first = parts.next()?;
+    let mut value = scope
+        .iter()
+        .rev()
+        .find_map(|(name, value)| match name == first {
+            true => Some(value.clone()),
+            false => None,
+        })
+        .or_else(|| scope[0].1.get(first).cloned())?;
+    for part in parts {
+        value = match &value {
+            Json::Array(items) => items.get(part.parse::<usize>().ok()?)?.clone(),
+            _ => value.get(part)?.clone(),
+        };
+    }
+    Some(value)
+}
+
+fn is_truthy(value: &Option<Json>) -> bool {
+    match value {
+        None | Some(Json::Null) | Some(Json::Bool(false)) => false,
+        Some(Json::Number(n)) => *n != 0.0,
+        Some(Json::String(s)) => !s.is_empty(),
+        Some(Json::Array(items)) => !items.is_empty(),
+        Some(_) => true,
+    }
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn render_nodes(
+    nodes: &[Node],
+    scope: &mut Vec<(String, Json)>,
+    autoescape: bool,
+    out: &mut String,
+) -> Result<(), String> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value { path, filters } => {
+                let value =
+                    lookup(path, scope).ok_or_else(|| format!("unknown variable '{}'", path))?;
+                let mut text = match &value {
+                    Json::String(s) => s.clone(),
+                    Json::Null => String::new(),
+                    other => other.to_string(),
+                };
+                let mut escape = autoescape;
+                for filter in filters {
+                    match filter.as_str() {
+                        "escape" | "e" => {
+                            text = escape_html(&text);
+                            escape = false;
+                        }
+                        "safe" => escape = false,
+                        "upper" => text = text.to_uppercase(),
+                        "lower" => text = text.to_lowercase(),
+                        _ => return Err(format!("unknown filter '{}'", filter)),
+                    }
+                }
+                match escape {
+                    true => out.push_str(&escape_html(&text)),
+                    false => out.push_str(&text),
+                }
+            }
+            Node::For {
+                variable,
+                path,
+                body,
+            } => {
+                let items = match lookup(path, scope) {
+                    Some(Json::Array(items)) => items,
+                    Some(_) => return Err(format!("'{}' is not a list", path)),
+                    None => return Err(format!("unknown variable '{}'", path)),
+                };
+                for (i, item) in items.iter().enumerate() {
+                    let info = Json::Object(vec![
+                        ("index".to_string(), Json::from(i as u32 + 1)),
+                        ("first".to_string(), Json::from(i == 0)),
+                        ("last".to_string(), Json::from(i + 1 == items.len())),
+                    ]);
+                    scope.push(("loop".to_string(), info));
+                    scope.push((variable.clone(), item.clone()));
+                    let result = render_nodes(body, scope, autoescape, out);
+                    scope.truncate(scope.len() - 2);
+                    result?;
+                }
+            }
+            Node::If {
+                path,
+                negate,
+                then,
+                otherwise,
+            } => {
+                let branch = match is_truthy(&lookup(path, scope)) != *negate {
+                    true => then,
+                    false => otherwise,
+                };
+                render_nodes(branch, scope, autoescape, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> Json {
+        crate::json::parse(
+            r#"{"title": "a<b", "statements": [{"line": 4, "text": "b();"}, {"line": 2, "text": "x < y"}], "none": []}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let template = "{# heading #}<h1>{{ title }}</h1>\n\
+            {% for s in statements %}{{ loop.index }}:{{ s.line }} {{ s.text }}{% if not loop.last %}, {% endif %}{% endfor %}\n\
+            {% if none %}some{% else %}none{% endif %} {{ title | safe | upper }}";
+        assert_eq!(
+            render(template, &context(), true).unwrap(),
+            "<h1>a&lt;b</h1>\n1:4 b();, 2:2 x &lt; y\nnone A<B"
+        );
+        assert_eq!(
+            render("{{ statements.1.text }}", &context(), false).unwrap(),
+            "x < y"
+        );
+    }
+
+    #[test]
+    fn test_render_errors() {
+        assert!(render("{% for s in statements %}", &context(), false).is_err());
+        assert!(render("{{ missing }}", &context(), false).is_err());
+        assert!(render("{{ title | bogus }}", &context(), false).is_err());
+        assert!(render("{% endif %}", &context(), false).is_err());
+        assert!(render("{{ title", &context(), false).is_err());
+    }
+}
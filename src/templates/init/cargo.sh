@@ -0,0 +1,36 @@
+#!/bin/sh
+# Ranks the lines of a Cargo project most likely to be at fault, as written by
+# `fl_dstar init`. Builds the tests with -C instrument-coverage in
+# FL_BUILD_DIR, runs each on its own and files its coverage, exported by
+# llvm-cov, under FL_PASS_DIR or FL_FAIL_DIR by whether it passed. Needs the
+# llvm-tools component (rustup component add llvm-tools). Settings are in
+# fl_dstar.env.
+set -eu
+cd "$(dirname "$0")"
+. ./fl_dstar.env
+
+export RUSTFLAGS="${RUSTFLAGS:-} -C instrument-coverage"
+export CARGO_TARGET_DIR="$FL_BUILD_DIR"
+tools=$(echo "$(rustc --print sysroot)"/lib/rustlib/*/bin)
+profiles=$(mktemp -d)
+trap 'rm -rf "$profiles"' EXIT
+
+# the test executables, which llvm-cov reads the coverage mapping from
+objects=$(cargo test --tests --no-run 2>&1 | sed -n 's/.*Executable .*(\(.*\))$/-object \1/p')
+
+rm -rf "$FL_PASS_DIR" "$FL_FAIL_DIR"
+mkdir -p "$FL_PASS_DIR" "$FL_FAIL_DIR"
+cargo test --tests -q -- --list --format terse 2>/dev/null | sed -n 's/: test$//p' | while read -r test; do
+    rm -f "$profiles"/*
+    outcome=$FL_PASS_DIR
+    LLVM_PROFILE_FILE="$profiles/%p-%m.profraw" cargo test --tests -q -- --exact "$test" \
+        >/dev/null 2>&1 || outcome=$FL_FAIL_DIR
+    "$tools/llvm-profdata" merge -sparse "$profiles"/*.profraw -o "$profiles/test.profdata"
+    # shellcheck disable=SC2086
+    "$tools/llvm-cov" export -format=lcov -instr-profile="$profiles/test.profdata" $objects \
+        -ignore-filename-regex='/.cargo/registry|/rustc/' >"$outcome/$test.info"
+done
+
+# shellcheck disable=SC2086
+"$FL_DSTAR" "$FL_PASS_DIR" "$FL_FAIL_DIR" --format "$FL_FORMAT" --metric "$FL_METRIC" \
+    ${FL_MANIFEST:+--manifest "$FL_MANIFEST"} $FL_OPTIONS
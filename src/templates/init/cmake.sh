@@ -0,0 +1,32 @@
+#!/bin/sh
+# Ranks the lines of a CMake project most likely to be at fault, as written by
+# `fl_dstar init`. Builds with gcov instrumentation in FL_BUILD_DIR, runs each
+# test ctest knows of on its own and files its gcov output under FL_PASS_DIR
+# or FL_FAIL_DIR by whether it passed. Needs CMake 3.20 or later. Settings
+# are in fl_dstar.env.
+set -eu
+cd "$(dirname "$0")"
+. ./fl_dstar.env
+
+cmake -S . -B "$FL_BUILD_DIR" -DCMAKE_BUILD_TYPE=Debug \
+    -DCMAKE_C_FLAGS=--coverage -DCMAKE_CXX_FLAGS=--coverage \
+    -DCMAKE_EXE_LINKER_FLAGS=--coverage -DCMAKE_SHARED_LINKER_FLAGS=--coverage
+cmake --build "$FL_BUILD_DIR"
+
+rm -rf "$FL_PASS_DIR" "$FL_FAIL_DIR"
+mkdir -p "$FL_PASS_DIR" "$FL_FAIL_DIR"
+find "$FL_BUILD_DIR" -name '*.gcda' -exec rm -f {} +
+ctest --test-dir "$FL_BUILD_DIR" -N | sed -n 's/^ *Test *#[0-9]*: //p' | while read -r test; do
+    outcome=$FL_PASS_DIR
+    ctest --test-dir "$FL_BUILD_DIR" -R "^$test\$" >/dev/null 2>&1 || outcome=$FL_FAIL_DIR
+    # the files of every source the test ran go into one file per test
+    find "$FL_BUILD_DIR" -name '*.gcda' | while read -r data; do
+        (cd "$(dirname "$data")" && gcov "$(basename "$data")" >/dev/null &&
+            cat ./*.gcov && rm -f ./*.gcov) || true
+    done >"$outcome/$test.gcov"
+    find "$FL_BUILD_DIR" -name '*.gcda' -exec rm -f {} +
+done
+
+# shellcheck disable=SC2086
+"$FL_DSTAR" "$FL_PASS_DIR" "$FL_FAIL_DIR" --format "$FL_FORMAT" --metric "$FL_METRIC" \
+    ${FL_MANIFEST:+--manifest "$FL_MANIFEST"} $FL_OPTIONS
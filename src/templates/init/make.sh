@@ -0,0 +1,30 @@
+#!/bin/sh
+# Ranks the lines of a Make project most likely to be at fault, as written by
+# `fl_dstar init`. Builds with gcov instrumentation, runs each target in
+# FL_TESTS on its own and files its gcov output under FL_PASS_DIR or
+# FL_FAIL_DIR by whether the target succeeded. Settings are in fl_dstar.env.
+set -eu
+cd "$(dirname "$0")"
+. ./fl_dstar.env
+
+make clean
+make CFLAGS="${CFLAGS:-} -O0 --coverage" LDFLAGS="${LDFLAGS:-} --coverage"
+
+rm -rf "$FL_PASS_DIR" "$FL_FAIL_DIR"
+mkdir -p "$FL_PASS_DIR" "$FL_FAIL_DIR"
+find . -name '*.gcda' -exec rm -f {} +
+for test in $FL_TESTS; do
+    outcome=$FL_PASS_DIR
+    make "$test" >/dev/null 2>&1 || outcome=$FL_FAIL_DIR
+    # gcov runs next to each object, which is where Make compiled it from;
+    # the files of every source the test ran go into one file per test
+    find . -name '*.gcda' | while read -r data; do
+        (cd "$(dirname "$data")" && gcov "$(basename "$data")" >/dev/null &&
+            cat ./*.gcov && rm -f ./*.gcov) || true
+    done >"$outcome/$test.gcov"
+    find . -name '*.gcda' -exec rm -f {} +
+done
+
+# shellcheck disable=SC2086
+"$FL_DSTAR" "$FL_PASS_DIR" "$FL_FAIL_DIR" --format "$FL_FORMAT" --metric "$FL_METRIC" \
+    ${FL_MANIFEST:+--manifest "$FL_MANIFEST"} $FL_OPTIONS
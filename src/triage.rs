@@ -0,0 +1,60 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Fill a command template, replacing `{input}` with the fuzz input and
+/// `{coverage}` with the file the command should write coverage to.
+pub fn fill_template(template: &str, input: &Path, coverage: &Path) -> String {
+    template
+        .replace("{input}", &input.to_string_lossy())
+        .replace("{coverage}", &coverage.to_string_lossy())
+}
+
+/// Run a filled-in command through the shell and report whether the input
+/// crashed the program: any non-zero exit, including death by a signal or a
+/// sanitizer report, counts as a crash.
+pub fn run_crashes(command: &str) -> std::io::Result<bool> {
+    let mut shell = match cfg!(windows) {
+        true => {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        }
+        false => {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        }
+    };
+    let status = shell
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    Ok(!status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_template() {
+        assert_eq!(
+            fill_template(
+                "./fuzz {input} && gcov -t fuzz.c > {coverage}",
+                Path::new("corpus/a"),
+                Path::new("/tmp/a.gcov")
+            ),
+            "./fuzz corpus/a && gcov -t fuzz.c > /tmp/a.gcov"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_crashes() {
+        assert!(!run_crashes("true").unwrap());
+        assert!(run_crashes("exit 1").unwrap());
+        assert!(run_crashes("kill -SEGV $$").unwrap());
+    }
+}
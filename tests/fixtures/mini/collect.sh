@@ -0,0 +1,36 @@
+#!/bin/sh
+# Collects the fixture's coverage again: builds tcas.c with gcov
+# instrumentation, runs it on each line of tests.txt and files the gcov
+# output under pass/ or fail/ by whether it printed what the fixed program
+# prints. Needs gcc and gcov.
+set -eu
+cd "$(dirname "$0")"
+work=$(mktemp -d)
+trap 'rm -rf "$work"' EXIT
+cp tcas.c "$work"
+sed 's/Up_Separation > ALIM()/Up_Separation >= ALIM()/' tcas.c >"$work/fixed.c"
+(
+    cd "$work"
+    gcc -O0 --coverage -c tcas.c -o tcas.o
+    gcc --coverage tcas.o -o tcas
+    gcc -O0 fixed.c -o fixed
+)
+rm -rf pass fail lcov
+mkdir -p pass fail lcov/pass lcov/fail
+n=0
+while read -r args; do
+    n=$((n + 1))
+    name=$(printf 'test_%02d' "$n")
+    rm -f "$work/tcas.gcda"
+    # shellcheck disable=SC2086
+    got=$("$work/tcas" $args)
+    # shellcheck disable=SC2086
+    want=$("$work/fixed" $args)
+    (cd "$work" && gcov tcas.c >/dev/null)
+    outcome=pass
+    [ "$got" = "$want" ] || outcome=fail
+    cp "$work/tcas.c.gcov" "$outcome/$name.gcov"
+    # the same coverage as an lcov tracefile, for the lcov reader
+    cargo run --quiet -- convert --from gcov --to lcov "$outcome/$name.gcov" \
+        -o "lcov/$outcome/$name.info"
+done <tests.txt
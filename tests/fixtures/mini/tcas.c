@@ -0,0 +1,95 @@
+/* A cut-down tcas, the traffic collision avoidance program of the
+ * Siemens suite, with one seeded fault (see faults.csv). */
+#include <stdio.h>
+#include <stdlib.h>
+
+#define MAXALTDIFF 600
+#define MINSEP 300
+#define NOZCROSS 100
+
+#define UNRESOLVED 0
+#define UPWARD_RA 1
+#define DOWNWARD_RA 2
+
+int Cur_Vertical_Sep;
+int High_Confidence;
+int Two_of_Three_Reports_Valid;
+int Own_Tracked_Alt;
+int Other_Tracked_Alt;
+int Up_Separation;
+int Down_Separation;
+int Climb_Inhibit;
+
+int ALIM(void)
+{
+    return 400;
+}
+
+int Inhibit_Biased_Climb(void)
+{
+    return Climb_Inhibit ? Up_Separation + NOZCROSS : Up_Separation;
+}
+
+int Own_Below_Threat(void)
+{
+    return Own_Tracked_Alt < Other_Tracked_Alt;
+}
+
+int Own_Above_Threat(void)
+{
+    return Other_Tracked_Alt < Own_Tracked_Alt;
+}
+
+int Non_Crossing_Biased_Climb(void)
+{
+    int upward_preferred = Inhibit_Biased_Climb() > Down_Separation;
+    if (upward_preferred)
+        return !Own_Below_Threat() || !(Down_Separation >= ALIM());
+    else
+        return Own_Above_Threat() && Cur_Vertical_Sep >= MINSEP && Up_Separation >= ALIM();
+}
+
+int Non_Crossing_Biased_Descend(void)
+{
+    int upward_preferred = Inhibit_Biased_Climb() > Down_Separation;
+    if (upward_preferred)
+        return Own_Below_Threat() && Cur_Vertical_Sep >= MINSEP && Down_Separation >= ALIM();
+    else
+        return !Own_Above_Threat() || Up_Separation > ALIM();
+}
+
+int alt_sep_test(void)
+{
+    int enabled = High_Confidence && Cur_Vertical_Sep > MAXALTDIFF;
+    int intent_not_known = Two_of_Three_Reports_Valid;
+    int alt_sep = UNRESOLVED;
+    if (enabled && intent_not_known) {
+        int need_upward_RA = Non_Crossing_Biased_Climb() && Own_Below_Threat();
+        int need_downward_RA = Non_Crossing_Biased_Descend() && Own_Above_Threat();
+        if (need_upward_RA && need_downward_RA)
+            alt_sep = UNRESOLVED;
+        else if (need_upward_RA)
+            alt_sep = UPWARD_RA;
+        else if (need_downward_RA)
+            alt_sep = DOWNWARD_RA;
+    }
+    return alt_sep;
+}
+
+int main(int argc, char *argv[])
+{
+    if (argc != 9) {
+        fprintf(stderr, "usage: tcas SEP CONFIDENCE VALID OWN OTHER UP DOWN INHIBIT\n");
+        return 1;
+    }
+    Cur_Vertical_Sep = atoi(argv[1]);
+    High_Confidence = atoi(argv[2]);
+    Two_of_Three_Reports_Valid = atoi(argv[3]);
+    Own_Tracked_Alt = atoi(argv[4]);
+    Other_Tracked_Alt = atoi(argv[5]);
+    Up_Separation = atoi(argv[6]);
+    Down_Separation = atoi(argv[7]);
+    Climb_Inhibit = atoi(argv[8]);
+    printf("%d\n", alt_sep_test());
+    return 0;
+}
@@ -0,0 +1,173 @@
+//! End-to-end runs of the command line tool on `tests/fixtures/mini`, a
+//! cut-down tcas with one seeded fault and the gcov output of each of its
+//! tests, compared with the outputs kept in `tests/golden/mini`. After a
+//! change that is meant to alter the output, run the tests with
+//! `UPDATE_GOLDEN=1` to write the new outputs, and review the diff.
+#![cfg(feature = "cli")]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn fixture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mini")
+}
+
+/// The output of fl_dstar with `args`, run from the fixture's directory.
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_fl_dstar"))
+        .current_dir(fixture())
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "fl_dstar {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn check_golden(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden/mini")
+        .join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {}; run with UPDATE_GOLDEN=1", path.display(), e));
+    assert!(
+        expected == actual,
+        "the output differs from {}; run with UPDATE_GOLDEN=1 if that's intended\n\
+         expected:\n{}\nactual:\n{}",
+        path.display(),
+        expected,
+        actual
+    );
+}
+
+/// The seeded fault, as `faults.csv` gives it.
+fn fault_line() -> String {
+    let faults = std::fs::read_to_string(fixture().join("faults.csv")).unwrap();
+    let (_, line) = faults.lines().nth(1).unwrap().split_once(',').unwrap();
+    line.to_string()
+}
+
+#[test]
+fn test_formulas() {
+    let fault = fault_line();
+    for metric in ["dstar", "tarantula", "ochiai", "jaccard", "op2", "barinel"] {
+        let ranking = run(&["pass", "fail", "--metric", metric]);
+        check_golden(&format!("{}.csv", metric), &ranking);
+        // every formula puts the fault in the top two, tied with the other
+        // branch of the same test
+        let line = ranking
+            .lines()
+            .next()
+            .unwrap()
+            .split(',')
+            .position(|column| column == "line_number")
+            .unwrap();
+        let top: Vec<&str> = ranking
+            .lines()
+            .skip(1)
+            .take(2)
+            .map(|row| row.split(',').nth(line).unwrap())
+            .collect();
+        assert!(top.contains(&fault.as_str()), "{}: {:?}", metric, top);
+    }
+}
+
+#[test]
+fn test_formats_and_views() {
+    check_golden(
+        "lcov.csv",
+        &run(&["lcov/pass", "lcov/fail", "--format", "lcov"]),
+    );
+    let json = run(&["pass", "fail", "--output-format", "json"]);
+    check_golden("ranking.json", &json);
+    // one object per row of the CSV ranking
+    let rows = run(&["pass", "fail"]).lines().count() - 1;
+    let parsed = fl_dstar::json::parse(&json).unwrap();
+    assert_eq!(parsed.as_array().map(|rows| rows.len()), Some(rows));
+    check_golden(
+        "ranking.jsonl",
+        &run(&[
+            "pass",
+            "fail",
+            "--output-format",
+            "jsonl",
+            "--columns",
+            "rank,file,line,ef,ep,nf,np,suspiciousness",
+        ]),
+    );
+    check_golden(
+        "show.txt",
+        &run(&[
+            "show", "pass", "fail", "--top", "2", "--gap", "1", "--color", "never",
+        ]),
+    );
+}
+
+#[test]
+fn test_default_header() {
+    // scripts read the default ranking, so its columns only change on purpose
+    let ranking = run(&["pass", "fail"]);
+    let header = ranking.lines().next().unwrap();
+    check_golden("header.csv", &format!("{}\n", header));
+}
+
+#[test]
+fn test_closed_stdout() {
+    // as with `| head`, the reader goes before the ranking is written
+    for format in ["csv", "json"] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_fl_dstar"))
+            .current_dir(fixture())
+            .args(["pass", "fail", "--output-format", format])
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        drop(child.stdout.take());
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "{}: {:?}", format, output.status);
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    }
+}
+
+#[test]
+fn test_json_report_ignores_locale() {
+    // German writes 1.5 as "1,50", which --json must not
+    let path = std::env::temp_dir().join(format!("fl_dstar_report_{}.json", std::process::id()));
+    let report_path = path.to_str().unwrap();
+    run(&["pass", "fail", "--locale", "de_DE", "--json", report_path]);
+    let report = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let report = fl_dstar::json::parse(&report).unwrap();
+    let ranking = fl_dstar::json::parse(&run(&[
+        "pass",
+        "fail",
+        "--output-format",
+        "json",
+        "--schema-version",
+        "3",
+    ]))
+    .unwrap();
+    let statements = report.get("statements").and_then(|s| s.as_array()).unwrap();
+    let rows = ranking.as_array().unwrap();
+    assert_eq!(statements.len(), rows.len());
+    for (statement, row) in statements.iter().zip(rows) {
+        let field = |json: &fl_dstar::json::Json, key| json.get(key).unwrap().as_f64();
+        assert!(field(statement, "suspiciousness").is_some());
+        assert_eq!(
+            field(statement, "suspiciousness"),
+            field(row, "suspiciousness")
+        );
+        assert_eq!(field(statement, "exam"), field(row, "exam"));
+        let hash = statement.get("hash").unwrap().as_str().unwrap();
+        assert!(hash.len() == 16 && !hash.starts_with('#'), "{}", hash);
+    }
+}